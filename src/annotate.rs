@@ -0,0 +1,187 @@
+//! The URL and headers of a request as they were sent, with the parts which weren't typed in the
+//! editors marked by where they came from. This makes it clear why the request on the wire
+//! differs from the editors.
+
+use crate::environment::Environment;
+use crate::redact::{redact_headers, MASK};
+use crate::settings::Settings;
+use crate::Method;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Source {
+    /// Typed in the editors.
+    Typed,
+    /// The value of an environment variable.
+    Variable,
+    /// Added from the imported cookies.
+    Cookie,
+    /// The token added by the environment's OAuth2.
+    Auth,
+    /// Added or changed by the pre-send hook.
+    Hook,
+}
+
+pub struct Segment {
+    pub text: String,
+    pub source: Source,
+    /// Shown masked, for the values of secret variables and sensitive headers.
+    pub secret: bool,
+}
+
+impl Segment {
+    fn new(text: &str, source: Source) -> Self {
+        Segment {
+            text: text.to_string(),
+            source,
+            secret: false,
+        }
+    }
+
+    pub fn display(&self) -> &str {
+        if self.secret {
+            MASK
+        } else {
+            self.text.as_str()
+        }
+    }
+}
+
+pub type Line = Vec<Segment>;
+
+fn plain(line: &[Segment]) -> String {
+    line.iter().map(|segment| segment.text.as_str()).collect()
+}
+
+/// Substitute variables in a line as `substitute` does, keeping the values apart from the typed
+/// text around them.
+fn substitute_line(text: &str, environment: Option<&Environment>) -> Line {
+    let mut line = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let (name, end) = match after.find("}}") {
+            Some(end) => (after[..end].trim(), end),
+            None => break,
+        };
+        let value = environment.and_then(|environment| {
+            environment
+                .get(name)
+                .map(|value| (value, environment.is_secret(name)))
+        });
+        match value {
+            Some((value, secret)) => {
+                line.push(Segment::new(&rest[..start], Source::Typed));
+                line.push(Segment {
+                    text: value.to_string(),
+                    source: Source::Variable,
+                    secret,
+                });
+            }
+            // References to unknown variables are sent as they are.
+            None => line.push(Segment::new(&rest[..start + 2 + end + 2], Source::Typed)),
+        }
+        rest = &after[end + 2..];
+    }
+    line.push(Segment::new(rest, Source::Typed));
+    line.retain(|segment| !segment.text.is_empty());
+    line
+}
+
+/// A header line from a single source, with the name apart from the value so the value can be
+/// masked on its own.
+fn header_line(text: &str, source: Source) -> Line {
+    match text.find(':') {
+        Some(colon) => {
+            let value = text[colon + 1..].trim_start();
+            let name_end = text.len() - value.len();
+            vec![
+                Segment::new(&text[..name_end], source),
+                Segment::new(value, source),
+            ]
+        }
+        None => vec![Segment::new(text, source)],
+    }
+}
+
+/// Match the lines which were sent to the lines substituted from the editors. A line which isn't
+/// one of them is one of the `added` headers, like the cookie header from the jar, otherwise the
+/// hook added it.
+fn annotate_lines(substituted: Vec<Line>, added: &[(String, Source)], sent: &str) -> Vec<Line> {
+    let mut substituted: Vec<Option<Line>> = substituted.into_iter().map(Some).collect();
+    sent.lines()
+        .map(|sent_line| {
+            let found = substituted
+                .iter_mut()
+                .find(|line| line.as_ref().is_some_and(|line| plain(line) == sent_line));
+            match found.and_then(|line| line.take()) {
+                Some(line) => line,
+                None => {
+                    let source = added
+                        .iter()
+                        .find(|(line, _)| line == sent_line)
+                        .map_or(Source::Hook, |(_, source)| *source);
+                    header_line(sent_line, source)
+                }
+            }
+        })
+        .collect()
+}
+
+/// The request line and headers which were sent, one line each, annotated with where their parts
+/// came from. `added` are the headers added before the pre-send hook, with where they came from.
+/// Unless the settings
+/// include secrets, secret variables and the values of redacted headers which weren't typed are
+/// masked.
+#[allow(clippy::too_many_arguments)]
+pub fn annotate(
+    method: &Method,
+    url_template: &str,
+    headers_template: &str,
+    environment: Option<&Environment>,
+    added: &[(String, Source)],
+    url: &str,
+    headers: &str,
+    settings: &Settings,
+) -> Vec<Line> {
+    let method_str = method.as_str();
+    let url_line = substitute_line(url_template, environment);
+    let mut request_line = vec![Segment::new(
+        format!("{:} ", method_str).as_str(),
+        Source::Typed,
+    )];
+    let substituted_url = plain(&url_line);
+    if let Some(query) = url.strip_prefix(substituted_url.as_str()) {
+        // Query parameters from the params pane are appended to the URL.
+        request_line.extend(url_line);
+        if !query.is_empty() {
+            request_line.push(Segment::new(query, Source::Typed));
+        }
+    } else {
+        request_line.push(Segment::new(url, Source::Hook));
+    }
+
+    let substituted = headers_template
+        .lines()
+        .map(|line| substitute_line(line, environment))
+        .collect();
+    let mut lines = vec![request_line];
+    lines.extend(annotate_lines(substituted, added, headers));
+
+    for line in lines.iter_mut() {
+        let text = plain(line);
+        // The value of a redacted header starts after the colon.
+        let value_start = (redact_headers(text.as_str(), settings) != text)
+            .then(|| text.find(':').map(|colon| colon + 1))
+            .flatten();
+        let mut offset = 0;
+        for segment in line.iter_mut() {
+            if settings.include_secrets {
+                segment.secret = false;
+            } else if value_start.is_some_and(|start| offset >= start) {
+                segment.secret |= segment.source != Source::Typed;
+            }
+            offset += segment.text.len();
+        }
+    }
+    lines
+}
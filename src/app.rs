@@ -1,24 +1,80 @@
+use crate::annotate::{annotate, Line};
+use crate::assertion::{self, Assertion, Outcome};
+use crate::audit;
+use crate::capture::{self, Capture};
+use crate::command;
+use crate::content_type::{is_generic, sniff, CONTENT_TYPES};
+use crate::cookies::CookieJar;
+use crate::crypto;
+use crate::curl::{self, CurlRequest};
+use crate::download::Download;
+use crate::environment::{substitute, Environment, Environments};
+use crate::error::{Error, Result};
+use crate::external_editor;
+use crate::formatters::{self, format_body};
+use crate::frame_stats::FrameStats;
+use crate::guard::{confirmation_reason, host_warning};
+use crate::har;
+use crate::history::{History, HistoryEntry};
+use crate::hooks;
+use crate::http_file;
+use crate::insomnia;
+use crate::json_view::{format_bytes, key_paths, line_transform, path_at_line};
+use crate::metrics::is_metrics_content_type;
+use crate::oauth2;
+use crate::oidc::{self, Discovery};
+use crate::openapi;
+use crate::output::output_path;
 use crate::paragraph_with_state::ParagraphWithState;
 use crate::persistence::RequestCollection;
+use crate::prepare::{self, prepare_request, request_url};
+use crate::probe::{
+    capability_report, fetch, fetch_headers, origin, well_known_report, Gate, WELL_KNOWN_PATHS,
+};
+use crate::proxy::ProxyChoice;
+use crate::redact::{redact_headers, redact_values, secret_values};
+use crate::redirect;
+use crate::runner::{self, CollectionRun};
+use crate::script::format_key;
+use crate::session::Session;
+use crate::settings::{ResponseFocus, Settings};
+use crate::suggest;
 use std::fs::File;
+use std::future::Future;
 use std::io::Write;
+use std::path::Path;
+use std::pin::Pin;
 
-use crate::{default_key_binds, Method, Operation, Request, Response, WebRequest};
+use crate::{
+    clipboard, default_key_binds, json_path, BodyType, Method, Operation, Request, Response,
+    ScrollDirection, WebRequest,
+};
 use bytes::Bytes;
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 
-use crate::key_bind::KeyBind;
+use crate::key_bind::{self, get_hints, get_key_symbol, set_ascii, KeyBind};
+use crate::token_refresh::{expiry_label, refresh_token, token_expiry, TokenRefresh};
+use crate::ui::json_tree::JsonTreeState;
 use crate::ui::text_area::{EditCommand, EditState};
-use reqwest::header::HeaderValue;
-use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use crate::waterfall::Step;
+use crate::web_request_handler::{next_request_id, queue, web_request_handler};
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::StatusCode;
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::task::{Context, Waker};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tui::layout::Rect;
 use tui::widgets::ListState;
 
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum View {
     Request,
     Response,
+    /// The results of the last collection run.
+    Results,
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -26,101 +82,652 @@ pub enum Mode {
     Url,
     Method,
     RequestHeaders,
+    RequestParams,
     RequestBody,
     ResponseHeaders,
     ResponseBody,
+    Results,
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum Modal {
     Save,
     Requests,
+    InsertFile,
+    History,
+    /// Confirm how to save a response which contains secret values.
+    SaveResponseSecrets,
+    /// Confirm how to copy a response which contains secret values.
+    CopyResponseSecrets,
+    /// Confirm sending a request which matched a safety rule.
+    ConfirmSend,
+    Environments,
+    /// Input the path of an environments file to import.
+    ImportEnvironments,
+    /// Input the path of a session file to import.
+    ImportSession,
+    /// Input the path of a file to download the response to.
+    Download,
+    /// Input the timeout of the current request, or nothing to use the one in the settings.
+    Timeout,
+    /// Input the proxy of the current request, or nothing to use the one in the settings.
+    Proxy,
+    /// Input a method which isn't in the method selector, like `PROPFIND`.
+    CustomMethod,
+    /// Input the path or URL of an OpenAPI document to import requests from.
+    ImportOpenApi,
+    /// Input the path of an Insomnia export to import requests and environments from.
+    ImportInsomnia,
+    /// Input the path of a `.http` file to import requests from.
+    ImportHttpFile,
+    /// Input the start of the names of the saved requests to run, or nothing to run them all.
+    RunCollection,
+    /// Edit the assertions of the current request, one per line.
+    Assertions,
+    /// Edit the values captured from the responses of the current request, one per line.
+    Captures,
+    /// List the operations with their keys, to bind them to other keys.
+    KeyBinds,
+    /// Every operation and its keys, filling the screen.
+    Help,
+    /// Input a `Cookie` header, or the path of a `cookies.txt` file, to import cookies from.
+    ImportCookies,
+    /// Input the name of the variable to save the value under the cursor to.
+    ExtractVariable,
+    /// Input the text metric names in the preview must contain.
+    MetricsFilter,
+    /// Input the command the response body is piped through.
+    PostProcess,
+    /// Input the base URL to replace in the collection, and its replacement.
+    Retarget,
+    /// Input the value of a prompt variable before sending.
+    Prompt,
+    /// Input the names of the variables of the current request which are prompted for.
+    Prompts,
+    /// Input the passphrase for encrypted environment variables.
+    Unlock,
+    /// Input the OpenID Connect issuer to set up OAuth2 from.
+    OidcIssuer,
+    /// Input a curl command to fill the editors from.
+    ImportCurl,
+    /// Input part of a key path in the JSON response to go to.
+    GoToKey,
+    /// Show an error which the user should know about.
+    Error,
+    /// Show the outcome of an action, like where a file was exported to.
+    Notice,
     None,
 }
 
+/// The parts of a request which are sent, before environment variables are substituted.
+pub struct RequestTemplate {
+    pub method: Method,
+    pub url: String,
+    pub headers: String,
+    /// Query parameters, one `name=value` per line, appended to the URL once substituted.
+    pub params: String,
+    pub body: String,
+    pub body_type: BodyType,
+    pub bypass_proxy: bool,
+    pub timeout_secs: Option<u64>,
+    pub proxy: Option<String>,
+    pub post_process: Option<String>,
+    /// Write the response body to this file instead of showing it.
+    pub download: Option<String>,
+    pub assertions: Vec<Assertion>,
+    pub captures: Vec<Capture>,
+}
+
+impl From<&crate::persistence::Request> for RequestTemplate {
+    fn from(request: &crate::persistence::Request) -> Self {
+        RequestTemplate {
+            method: request.method.clone(),
+            url: request.url.clone(),
+            headers: request.headers_to_string(),
+            params: request.params_to_string(),
+            body: match request.body.clone() {
+                Some(body) if request.method.has_body() || request.send_body => body,
+                _ => "".to_string(),
+            },
+            body_type: request.body_type,
+            bypass_proxy: request.bypass_proxy,
+            timeout_secs: request.timeout_secs,
+            proxy: request.proxy.clone(),
+            post_process: request.post_process.clone(),
+            download: None,
+            assertions: request.assertions.clone(),
+            captures: request.captures.clone(),
+        }
+    }
+}
+
+impl RequestTemplate {
+    /// How long to wait for the whole response, from the request or else the settings.
+    pub fn timeout(&self, settings: &Settings) -> Option<Duration> {
+        self.timeout_secs
+            .map(Duration::from_secs)
+            .or_else(|| settings.request_timeout())
+    }
+}
+
+/// Editor state of the previously loaded request, kept so it can be swapped back in.
+struct AlternateRequest {
+    request_name: String,
+    method: Method,
+    url: EditState,
+    headers: EditState,
+    params: EditState,
+    body: EditState,
+    body_type: BodyType,
+    bypass_proxy: bool,
+    timeout_secs: Option<u64>,
+    proxy: Option<String>,
+    send_body: bool,
+    prompts: Vec<String>,
+    post_process: String,
+    assertions: Vec<Assertion>,
+    captures: Vec<Capture>,
+}
+
+/// A request open in a tab, with its response. The current tab's are kept in the app, and a
+/// request still receiving its response in another tab writes to that tab's.
+struct Tab {
+    request: AlternateRequest,
+    alternate_request: Option<AlternateRequest>,
+    response: Arc<Mutex<Option<Bytes>>>,
+    response_paragraph: Arc<Mutex<ParagraphWithState>>,
+    response_header_paragraph: Arc<Mutex<ParagraphWithState>>,
+    content_type: Arc<Mutex<String>>,
+    content_type_detected: Arc<AtomicBool>,
+    content_type_overridden: bool,
+    response_bookmarks: Vec<u16>,
+    response_tree: JsonTreeState,
+    download: Arc<Mutex<Option<Download>>>,
+    steps: Arc<Mutex<Vec<Step>>>,
+    sent_request: Arc<Mutex<Vec<Line>>>,
+    request_started: Instant,
+    status: Arc<AtomicU16>,
+    proxy: Arc<Mutex<Option<String>>>,
+    in_flight_timeout_secs: Option<u64>,
+    response_complete: Arc<AtomicBool>,
+    request_id: Arc<AtomicU64>,
+    handler_id: Arc<AtomicU64>,
+    in_flight: Arc<AtomicBool>,
+    assertion_outcomes: Arc<Mutex<Vec<Outcome>>>,
+}
+
+impl Tab {
+    /// An empty request without a response.
+    fn new() -> Self {
+        Tab {
+            request: AlternateRequest {
+                request_name: "".to_string(),
+                method: Method::GET,
+                url: EditState::new(""),
+                headers: EditState::new(""),
+                params: EditState::new(""),
+                body: EditState::new(""),
+                body_type: BodyType::default(),
+                bypass_proxy: false,
+                timeout_secs: None,
+                proxy: None,
+                send_body: false,
+                prompts: Vec::new(),
+                post_process: "".to_string(),
+                assertions: Vec::new(),
+                captures: Vec::new(),
+            },
+            alternate_request: None,
+            response: Arc::new(Mutex::new(None)),
+            response_paragraph: Arc::new(Mutex::new(
+                ParagraphWithState::new("".to_string(), true, false).with_cursor(),
+            )),
+            response_header_paragraph: Arc::new(Mutex::new(ParagraphWithState::new(
+                "".to_string(),
+                true,
+                false,
+            ))),
+            content_type: Arc::new(Mutex::new("".to_string())),
+            content_type_detected: Arc::new(AtomicBool::new(false)),
+            content_type_overridden: false,
+            response_bookmarks: Vec::new(),
+            response_tree: JsonTreeState::default(),
+            download: Arc::new(Mutex::new(None)),
+            steps: Arc::new(Mutex::new(Vec::new())),
+            sent_request: Arc::new(Mutex::new(Vec::new())),
+            request_started: Instant::now(),
+            status: Arc::new(AtomicU16::new(0)),
+            proxy: Arc::new(Mutex::new(None)),
+            in_flight_timeout_secs: None,
+            response_complete: Arc::new(AtomicBool::new(false)),
+            request_id: Arc::new(AtomicU64::new(0)),
+            handler_id: Arc::new(AtomicU64::new(0)),
+            in_flight: Arc::new(AtomicBool::new(false)),
+            assertion_outcomes: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+}
+
+/// The label of a tab, the name of its request, or its URL if it hasn't been saved.
+fn tab_label(request_name: &str, url: &str) -> String {
+    const MAX_LABEL_LEN: usize = 24;
+
+    let label = match (request_name, url) {
+        ("", "") => "New Request",
+        ("", url) => url
+            .split_once("://")
+            .map_or(url, |(_, without_scheme)| without_scheme),
+        (request_name, _) => request_name,
+    };
+    if label.chars().count() > MAX_LABEL_LEN {
+        let shortened: String = label.chars().take(MAX_LABEL_LEN - 1).collect();
+        return format!("{:}…", shortened);
+    }
+    label.to_string()
+}
+
+/// How long quitting waits for requests in progress to be written to the history.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
 /// App holds the state of the application
 pub struct App {
     pub url: EditState,
     pub mode: Mode,
     pub method: Method,
     pub headers: EditState,
+    pub params: EditState,
     pub body: EditState,
     pub sender: mpsc::Sender<WebRequest>,
     pub response: Arc<Mutex<Option<Bytes>>>,
     pub response_paragraph: Arc<Mutex<ParagraphWithState>>,
     pub response_header_paragraph: Arc<Mutex<ParagraphWithState>>,
+    /// Content type of the most recent response.
+    pub content_type: Arc<Mutex<String>>,
+    /// The content type was guessed from the body, as the response's didn't say what it was.
+    pub content_type_detected: Arc<AtomicBool>,
+    /// The content type was chosen by the user.
+    pub content_type_overridden: bool,
+    /// Show a parsed preview of the response body next to the raw body.
+    pub response_split: bool,
+    pub response_preview: ParagraphWithState,
+    /// Length of the response body the preview was rendered from.
+    pub response_preview_len: Option<usize>,
+    /// Scroll positions bookmarked in the response body, numbered from 1 in the order added.
+    pub response_bookmarks: Vec<u16>,
+    /// Show a JSON response body as a tree of collapsible nodes, instead of as text.
+    pub show_response_tree: bool,
+    pub response_tree: JsonTreeState,
     pub dirty: Arc<AtomicBool>,
     pub modal: Modal,
     pub view: View,
     pub request_name: String,
+    pub insert_file_path: String,
+    pub import_environments_path: String,
+    pub import_session_path: String,
+    pub import_curl: String,
+    pub import_cookies: String,
+    pub oidc_issuer: String,
+    pub download_path: String,
+    pub timeout_input: String,
+    /// Progress of the response being downloaded to a file, if it is.
+    pub download: Arc<Mutex<Option<Download>>>,
+    /// A discovery document fetched in the background, with the environment to set up from it.
+    oidc_discovery: Arc<Mutex<Option<(String, Discovery)>>>,
+    pub import_openapi_source: String,
+    /// Requests read from an OpenAPI document, to be added to the collection.
+    openapi_import: Arc<Mutex<Option<openapi::Import>>>,
+    pub import_insomnia_path: String,
+    pub import_http_path: String,
+    pub run_prefix: String,
+    /// The collection run in progress, or the last one.
+    pub collection_run: Arc<Mutex<CollectionRun>>,
+    /// Incremented for each collection run, so a run stops once another starts.
+    run_id: Arc<AtomicU64>,
+    pub results_selection_state: ListState,
+    pub metrics_filter: String,
+    /// The text typed to find a key path in the JSON response.
+    pub key_query: String,
+    /// The key paths in the JSON response, with the line each starts on.
+    key_paths: Vec<(String, usize)>,
+    /// The key paths matching the typed text, best first.
+    pub key_matches: Vec<(String, usize)>,
+    pub key_match_state: ListState,
+    /// The token expiry last drawn, so the screen is only redrawn when it changes.
+    expiry_drawn: Option<String>,
+    /// The base URL to replace in the collection, followed by its replacement.
+    pub retarget: String,
+    /// Value under the cursor in the response body, waiting for a variable name.
+    pub extract_value: String,
+    pub extract_variable: String,
+    /// Errors waiting to be shown, oldest first. Shared with the request tasks.
+    pub errors: Arc<Mutex<Vec<Error>>>,
+    /// Messages waiting to be shown, oldest first, like the errors.
+    pub notices: Vec<String>,
+    /// Set while a request is waiting for room in the request handler's queue.
+    pub handler_busy: Arc<AtomicBool>,
+    /// Cookies imported from a browser, which are sent with the requests to their site.
+    pub cookies: Arc<Mutex<CookieJar>>,
+    /// The requests made for the last send, when there was more than one, like a retry after a
+    /// token refresh.
+    pub steps: Arc<Mutex<Vec<Step>>>,
+    /// The URL and headers of the last request as they were sent, marked with where their parts
+    /// came from.
+    pub sent_request: Arc<Mutex<Vec<Line>>>,
+    /// Show the sent request in place of the response headers.
+    pub show_sent_request: bool,
+    /// When the request in flight was sent.
+    pub request_started: Instant,
+    /// Whole seconds of the elapsed time last drawn, so it is redrawn as it ticks over.
+    elapsed_drawn: u64,
+    /// Request tasks which may still be running, waited for before quitting so their responses
+    /// are written to the history.
+    tasks: Vec<JoinHandle<()>>,
+    /// The modal which was open when the error modal was shown, restored when it is dismissed.
+    modal_before_error: Modal,
+    /// The modal which was open when the notice modal was shown, restored when it is dismissed.
+    modal_before_notice: Modal,
     pub request_collection: RequestCollection,
     pub request_selection_state: ListState,
+    /// Number of requests visible in the requests modal, updated when it is rendered.
+    pub request_page_size: usize,
     pub key_binds: Vec<KeyBind>,
+    pub key_binds_selection_state: ListState,
+    /// The next keys pressed in the key binds modal are bound to the selected operation.
+    pub rebinding: bool,
+    /// Lines the help is scrolled down by.
+    pub help_scroll: u16,
+    /// Where each pane was last drawn, for finding the pane under the mouse.
+    pub pane_areas: Vec<(Mode, Rect)>,
     pub status: Arc<AtomicU16>,
+    /// The proxy the most recent request was sent through.
+    pub proxy: Arc<Mutex<Option<String>>>,
+    /// Send the current request directly instead of through an environment proxy.
+    pub bypass_proxy: bool,
+    /// Timeout of the current request, overriding the one in the settings.
+    pub timeout_secs: Option<u64>,
+    /// Timeout of the request in flight, in seconds.
+    pub in_flight_timeout_secs: Option<u64>,
+    /// Proxy of the current request, overriding the one in the settings.
+    pub request_proxy: Option<String>,
+    pub proxy_input: String,
+    pub method_input: String,
+    /// Send the body even with a method which conventionally has none, like GET.
+    pub send_body: bool,
+    pub body_type: BodyType,
+    /// Why the pending send needs to be confirmed.
+    pub confirm_reason: String,
+    /// The request waiting for confirmation before it is sent.
+    pending_send: Option<RequestTemplate>,
+    /// Prefix of the collection run waiting for the passphrase or confirmation before it starts.
+    pending_run: Option<String>,
+    /// A saved request sent from the list without loading it, with the values entered so far for
+    /// its prompt variables.
+    pending_saved: Option<(crate::persistence::Request, Environment)>,
+    /// Response text waiting to be copied once it is confirmed how to copy its secrets.
+    pending_copy: Option<String>,
+    /// Set by the request task when a response has been completely received.
+    response_complete: Arc<AtomicBool>,
+    /// Id of the most recently sent request. Response updates from any other request are dropped.
+    request_id: Arc<AtomicU64>,
+    /// Id the request handler knows the most recently sent request by, which is cancelled when
+    /// another is sent. Requests sent from other tabs carry on.
+    handler_id: Arc<AtomicU64>,
+    /// True while the most recently sent request is still receiving its response.
+    pub in_flight: Arc<AtomicBool>,
+    /// Keys captured while recording a macro, or None when not recording.
+    pub recording: Option<Vec<KeyEvent>>,
+    recorded_macro: Vec<KeyEvent>,
+    playing_macro: bool,
+    alternate_request: Option<AlternateRequest>,
+    /// Every open tab. The current tab's request and response are kept in the app instead.
+    tabs: Vec<Tab>,
+    pub current_tab: usize,
+    /// Set when the user asked to suspend to the shell. The main loop owns the terminal, so it
+    /// performs the suspend and clears this.
+    pub suspend_requested: bool,
+    /// Set when the user asked to edit the request body in their editor, which the main loop
+    /// gives the terminal to. It clears this and calls `edit_body_externally`.
+    pub external_edit_requested: bool,
+    /// Show render and input timings over the interface.
+    pub show_profiler: bool,
+    pub frame_stats: FrameStats,
+    /// Switch to the response view when the response is complete.
+    focus_on_completion: bool,
+    /// Variables of the current request which are prompted for when sending.
+    pub prompts: Vec<String>,
+    pub prompts_input: String,
+    /// Command the response body of the current request is piped through, empty for none.
+    pub post_process: String,
+    /// Checks of the response to the current request.
+    pub assertions: Vec<Assertion>,
+    pub assertions_input: String,
+    /// Outcomes of the assertions for the response shown.
+    pub assertion_outcomes: Arc<Mutex<Vec<Outcome>>>,
+    /// Values of the response to the current request set as variables.
+    pub captures: Vec<Capture>,
+    pub captures_input: String,
+    /// Values from the history for the URL or header value being typed, most recent first.
+    pub suggestions: Vec<String>,
+    /// Values entered for prompt variables during this session.
+    pub prompt_values: Environment,
+    /// The prompt variable currently being entered, and its value so far.
+    pub prompt_name: String,
+    pub prompt_value: String,
+    pub passphrase: String,
+    pub settings: Settings,
+    pub history: Arc<Mutex<History>>,
+    pub history_selection_state: ListState,
+    pub environments: Arc<Mutex<Environments>>,
+    pub environment_selection_state: ListState,
+    /// Previously focused view and mode pairs, most recent last, used to go back with Esc.
+    focus_stack: Vec<(View, Mode)>,
 }
 
 impl App {
     pub fn new(sender: mpsc::Sender<WebRequest>) -> Self {
+        let mut errors = Vec::new();
+        let settings = or_default(Settings::load(), &mut errors);
+        let request_collection = or_default(RequestCollection::load(), &mut errors);
+        let history = or_default(History::load(&settings), &mut errors);
+        let environments = or_default(Environments::load(), &mut errors);
+        set_ascii(settings.glyphs.use_ascii());
+        let mut key_binds = default_key_binds::default_key_binds();
+        if let Err(err) = key_bind::apply_overrides(&mut key_binds, &settings.key_binds) {
+            error!("{:}", err);
+            errors.push(err);
+        }
         App {
             url: EditState::new(""),
             headers: EditState::new(""),
+            params: EditState::new(""),
             body: EditState::new(""),
             mode: Mode::Url,
             method: Method::GET,
             sender,
             response: Arc::new(Mutex::new(None)),
-            response_paragraph: Arc::new(Mutex::new(ParagraphWithState::new(
-                "".to_string(),
-                true,
-                false,
-            ))),
+            response_paragraph: Arc::new(Mutex::new(
+                ParagraphWithState::new("".to_string(), true, false).with_cursor(),
+            )),
             dirty: Arc::new(AtomicBool::new(false)),
             response_header_paragraph: Arc::new(Mutex::new(ParagraphWithState::new(
                 "".to_string(),
                 true,
                 false,
             ))),
+            content_type: Arc::new(Mutex::new("".to_string())),
+            content_type_detected: Arc::new(AtomicBool::new(false)),
+            content_type_overridden: false,
+            response_split: false,
+            response_preview: ParagraphWithState::new("".to_string(), true, false),
+            response_preview_len: None,
+            show_response_tree: false,
+            response_tree: JsonTreeState::default(),
+            response_bookmarks: Vec::new(),
             modal: Modal::None,
             request_name: "".to_string(),
-            request_collection: RequestCollection::load(),
+            insert_file_path: "".to_string(),
+            import_environments_path: "".to_string(),
+            import_session_path: "".to_string(),
+            import_curl: "".to_string(),
+            import_cookies: "".to_string(),
+            oidc_issuer: "".to_string(),
+            download_path: "".to_string(),
+            timeout_input: "".to_string(),
+            download: Arc::new(Mutex::new(None)),
+            oidc_discovery: Arc::new(Mutex::new(None)),
+            import_openapi_source: "".to_string(),
+            openapi_import: Arc::new(Mutex::new(None)),
+            import_insomnia_path: "".to_string(),
+            import_http_path: "".to_string(),
+            run_prefix: "".to_string(),
+            collection_run: Arc::new(Mutex::new(CollectionRun::default())),
+            run_id: Arc::new(AtomicU64::new(0)),
+            results_selection_state: ListState::default(),
+            metrics_filter: "".to_string(),
+            key_query: "".to_string(),
+            key_paths: Vec::new(),
+            key_matches: Vec::new(),
+            key_match_state: ListState::default(),
+            retarget: "".to_string(),
+            expiry_drawn: None,
+            extract_value: "".to_string(),
+            extract_variable: "".to_string(),
+            errors: Arc::new(Mutex::new(errors)),
+            notices: Vec::new(),
+            handler_busy: Arc::new(AtomicBool::new(false)),
+            cookies: Arc::new(Mutex::new(CookieJar::default())),
+            steps: Arc::new(Mutex::new(Vec::new())),
+            sent_request: Arc::new(Mutex::new(Vec::new())),
+            show_sent_request: false,
+            request_started: Instant::now(),
+            elapsed_drawn: 0,
+            tasks: Vec::new(),
+            modal_before_error: Modal::None,
+            modal_before_notice: Modal::None,
+            request_collection,
             request_selection_state: ListState::default(),
+            request_page_size: 10,
             view: View::Request,
-            key_binds: default_key_binds::default_key_binds(),
+            key_binds,
+            key_binds_selection_state: ListState::default(),
+            rebinding: false,
+            help_scroll: 0,
+            pane_areas: Vec::new(),
             status: Arc::new(AtomicU16::new(0)),
+            proxy: Arc::new(Mutex::new(None)),
+            bypass_proxy: false,
+            timeout_secs: None,
+            in_flight_timeout_secs: None,
+            request_proxy: None,
+            proxy_input: "".to_string(),
+            method_input: "".to_string(),
+            send_body: false,
+            body_type: BodyType::Raw,
+            confirm_reason: "".to_string(),
+            pending_send: None,
+            pending_run: None,
+            pending_saved: None,
+            pending_copy: None,
+            response_complete: Arc::new(AtomicBool::new(false)),
+            request_id: Arc::new(AtomicU64::new(0)),
+            handler_id: Arc::new(AtomicU64::new(0)),
+            in_flight: Arc::new(AtomicBool::new(false)),
+            recording: None,
+            recorded_macro: Vec::new(),
+            playing_macro: false,
+            alternate_request: None,
+            tabs: vec![Tab::new()],
+            current_tab: 0,
+            suspend_requested: false,
+            external_edit_requested: false,
+            show_profiler: false,
+            frame_stats: FrameStats::default(),
+            focus_on_completion: false,
+            prompts: Vec::new(),
+            prompts_input: "".to_string(),
+            assertions: Vec::new(),
+            assertions_input: "".to_string(),
+            assertion_outcomes: Arc::new(Mutex::new(Vec::new())),
+            captures: Vec::new(),
+            captures_input: "".to_string(),
+            post_process: "".to_string(),
+            suggestions: Vec::new(),
+            prompt_values: Environment::default(),
+            prompt_name: "".to_string(),
+            prompt_value: "".to_string(),
+            passphrase: "".to_string(),
+            history: Arc::new(Mutex::new(history)),
+            history_selection_state: ListState::default(),
+            environments: Arc::new(Mutex::new(environments)),
+            environment_selection_state: ListState::default(),
+            settings,
+            focus_stack: Vec::new(),
         }
     }
 }
 
 impl App {
-    fn next_method(&mut self) {
-        static METHODS: [Method; 5] = [
-            Method::GET,
-            Method::POST,
-            Method::PUT,
-            Method::DELETE,
-            Method::PATCH,
-        ];
+    fn next_method(&mut self, previous: bool) {
+        const METHODS: [Method; 7] = Method::CYCLE;
 
         let mut index = METHODS
             .iter()
             .position(|method| method == &self.method)
             .unwrap_or(0usize);
 
-        index += 1;
+        if previous {
+            if index > 0 {
+                index -= 1;
+            } else {
+                index = METHODS.len() - 1;
+            }
+        } else {
+            index += 1;
+        }
 
         if index < METHODS.len() {
-            self.method = METHODS[index];
+            self.method = METHODS[index].clone();
         } else {
-            self.method = METHODS[0usize];
+            self.method = METHODS[0usize].clone();
+        }
+    }
+
+    /// Find the pane which is spatially next to the current one in the direction of the
+    /// operation. The layout is the method and url on top, with the body above the headers
+    /// below them.
+    fn neighbor_mode(&self, operation: Operation) -> Option<Mode> {
+        let (body, headers) = match self.view {
+            View::Request => (Mode::RequestBody, Mode::RequestHeaders),
+            View::Response => (Mode::ResponseBody, Mode::ResponseHeaders),
+            View::Results => (Mode::Results, Mode::Results),
+        };
+        match (self.mode, operation) {
+            (Mode::Url, Operation::FocusLeft) => Some(Mode::Method),
+            (Mode::Method, Operation::FocusRight) => Some(Mode::Url),
+            (Mode::Url | Mode::Method, Operation::FocusDown) => Some(body),
+            (mode, Operation::FocusUp) if mode == body => Some(Mode::Url),
+            (mode, Operation::FocusDown) if mode == body => Some(headers),
+            (mode, Operation::FocusUp) if mode == headers => Some(body),
+            (Mode::RequestHeaders, Operation::FocusRight) => Some(Mode::RequestParams),
+            (Mode::RequestParams, Operation::FocusLeft) => Some(Mode::RequestHeaders),
+            (Mode::RequestParams, Operation::FocusUp) => Some(Mode::RequestBody),
+            _ => None,
         }
     }
 
     pub fn next_mode(&mut self, previous: bool) {
-        static REQUEST_MODES: [Mode; 3] = [Mode::Url, Mode::RequestBody, Mode::RequestHeaders];
+        static REQUEST_MODES: [Mode; 4] = [
+            Mode::Url,
+            Mode::RequestBody,
+            Mode::RequestHeaders,
+            Mode::RequestParams,
+        ];
         static RESPONSE_MODES: [Mode; 3] = [Mode::Url, Mode::ResponseBody, Mode::ResponseHeaders];
-        let modes = match self.view {
+        static RESULTS_MODES: [Mode; 2] = [Mode::Url, Mode::Results];
+        let modes: &[Mode] = match self.view {
             View::Request => &REQUEST_MODES,
             View::Response => &RESPONSE_MODES,
+            View::Results => &RESULTS_MODES,
         };
         let mut index = modes
             .iter()
@@ -166,6 +773,589 @@ impl App {
         }
     }
 
+    /// Operations which are relevant to the given mode, with a short label for the help bar.
+    fn mode_operations(mode: Mode) -> &'static [(&'static str, Operation)] {
+        match mode {
+            Mode::Url => &[
+                ("Method", Operation::NextMethod),
+                ("Proxy", Operation::ToggleProxyBypass),
+                ("Timeout", Operation::SetTimeout),
+                ("Set Proxy", Operation::SetProxy),
+                ("Prompts", Operation::EditPrompts),
+                ("Options", Operation::ProbeOptions),
+                ("Well Known", Operation::ProbeWellKnown),
+                ("Focus", Operation::FocusLeft),
+                ("Focus", Operation::FocusDown),
+            ],
+            Mode::Method => &[
+                ("Method", Operation::NextMethod),
+                ("Send", Operation::SendRequest),
+            ],
+            Mode::RequestBody => &[
+                ("Send", Operation::SendRequest),
+                ("Insert File", Operation::InsertFile),
+                ("Dup", Operation::DuplicateLine),
+                ("Line", Operation::MoveLineUp),
+                ("Line", Operation::MoveLineDown),
+                ("Comment", Operation::ToggleComment),
+                ("Editor", Operation::EditBodyExternally),
+                ("Send Body", Operation::ToggleSendBody),
+                ("Body Type", Operation::NextBodyType),
+            ],
+            Mode::RequestHeaders | Mode::RequestParams => &[
+                ("Send", Operation::SendRequest),
+                ("Dup", Operation::DuplicateLine),
+                ("Line", Operation::MoveLineUp),
+                ("Line", Operation::MoveLineDown),
+            ],
+            Mode::ResponseBody => &[
+                ("Send", Operation::SendRequest),
+                ("Save", Operation::SaveResponse),
+                ("Split", Operation::ToggleResponseSplit),
+                ("Export", Operation::ExportSession),
+                ("Process", Operation::PostProcess),
+                ("Download", Operation::Download),
+                ("HAR", Operation::ExportHar),
+                ("Copy", Operation::CopyResponse),
+            ],
+            Mode::ResponseHeaders => &[
+                ("Send", Operation::SendRequest),
+                ("Sent Request", Operation::ToggleSentRequest),
+                ("Assertions", Operation::EditAssertions),
+                ("Captures", Operation::EditCaptures),
+            ],
+            Mode::Results => &[
+                ("Run", Operation::RunCollection),
+                ("Request", Operation::GotoRequestView),
+                ("Response", Operation::GotoResponseView),
+            ],
+        }
+    }
+
+    /// The terminal was resized. Wrapped text is recalculated on the next render, which also
+    /// clamps scroll positions to the new size.
+    pub fn handle_resize(&mut self) {
+        self.response_paragraph.lock().unwrap().invalidate_cache();
+        self.response_header_paragraph
+            .lock()
+            .unwrap()
+            .invalidate_cache();
+        self.response_preview.invalidate_cache();
+        self.dirty.store(true, Ordering::SeqCst);
+    }
+
+    /// Title for the terminal window, so separate sessions can be told apart.
+    pub fn window_title(&self) -> String {
+        let mut title = "rester".to_string();
+        if !self.request_name.is_empty() {
+            title.push_str(" — ");
+            title.push_str(self.request_name.as_str());
+        }
+        if let Some(environment) = self.environments.lock().unwrap().active() {
+            title.push_str(format!(" [{:}]", environment.name).as_str());
+        }
+        title
+    }
+
+    /// A warning when the current request goes to a host the active environment doesn't expect.
+    pub fn host_warning(&self) -> Option<String> {
+        let environments = self.environments.lock().unwrap();
+        let environment = environments.active();
+        let url = substitute(
+            substitute(self.url.as_str(), Some(&self.prompt_values)).as_str(),
+            environment,
+        );
+        host_warning(url.as_str(), environment)
+    }
+
+    /// When the managed token of the active environment expires, if it is known.
+    pub fn token_expiry_label(&self) -> Option<String> {
+        self.environments
+            .lock()
+            .unwrap()
+            .active()
+            .and_then(token_expiry)
+            .map(expiry_label)
+    }
+
+    /// A one line hint for the operations available in the current mode.
+    pub fn mode_hints(&self) -> String {
+        let hints = get_hints(Self::mode_operations(self.mode), &self.key_binds);
+        if self.mode == Mode::Url {
+            format!("Send {:} {:}", get_key_symbol(KeyCode::Enter), hints)
+        } else {
+            hints
+        }
+    }
+
+    /// The multi-line editor for the current mode, if the mode has one.
+    fn focused_editor(&mut self) -> Option<&mut EditState> {
+        if self.modal != Modal::None {
+            return None;
+        }
+        match self.mode {
+            Mode::RequestBody => Some(&mut self.body),
+            Mode::RequestHeaders => Some(&mut self.headers),
+            Mode::RequestParams => Some(&mut self.params),
+            _ => None,
+        }
+    }
+
+    fn editor(&mut self, mode: Mode) -> Option<&mut EditState> {
+        match mode {
+            Mode::Url => Some(&mut self.url),
+            Mode::RequestBody => Some(&mut self.body),
+            Mode::RequestHeaders => Some(&mut self.headers),
+            Mode::RequestParams => Some(&mut self.params),
+            _ => None,
+        }
+    }
+
+    /// Clicking a pane focuses it, and moves the cursor of an editor to where it was clicked. The
+    /// wheel scrolls the response. The mouse is ignored while a modal is open.
+    pub fn handle_mouse(&mut self, mouse: MouseEvent) {
+        const WHEEL_LINES: usize = 3;
+
+        if self.modal != Modal::None {
+            return;
+        }
+        let (mode, area) = match self.pane_areas.iter().find(|(_, area)| {
+            (area.left()..area.right()).contains(&mouse.column)
+                && (area.top()..area.bottom()).contains(&mouse.row)
+        }) {
+            Some(pane) => *pane,
+            None => return,
+        };
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let focus = (self.view, self.mode);
+                self.mode = mode;
+                if focus != (self.view, self.mode) {
+                    self.push_focus(focus);
+                }
+                // Inside the borders.
+                let inner = Rect::new(
+                    area.x + 1,
+                    area.y + 1,
+                    area.width.saturating_sub(2),
+                    area.height.saturating_sub(2),
+                );
+                if let (Some(editor), true, true) = (
+                    self.editor(mode),
+                    (inner.left()..inner.right()).contains(&mouse.column),
+                    (inner.top()..inner.bottom()).contains(&mouse.row),
+                ) {
+                    editor.move_to(
+                        inner.width,
+                        inner.height,
+                        mouse.column - inner.x,
+                        mouse.row - inner.y,
+                    );
+                }
+                self.update_suggestions();
+            }
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown
+                if mode == Mode::ResponseBody && self.show_response_tree =>
+            {
+                let code = match mouse.kind {
+                    MouseEventKind::ScrollUp => KeyCode::Up,
+                    _ => KeyCode::Down,
+                };
+                for _ in 0..WHEEL_LINES {
+                    self.response_tree
+                        .handle_input(KeyEvent::new(code, KeyModifiers::NONE));
+                }
+            }
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                let paragraph = match mode {
+                    Mode::ResponseBody => &self.response_paragraph,
+                    Mode::ResponseHeaders => &self.response_header_paragraph,
+                    _ => return,
+                };
+                let mut paragraph = paragraph.lock().unwrap();
+                for _ in 0..WHEEL_LINES {
+                    paragraph.scroll_view(match mouse.kind {
+                        MouseEventKind::ScrollUp => ScrollDirection::Up,
+                        _ => ScrollDirection::Down,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn save_response(&self, redact: bool) {
+        let resp = self.response_paragraph.lock();
+        let para = &*resp.unwrap();
+
+        let filename = output_path(self.url.as_str(), "txt");
+
+        let contents = if redact {
+            redact_values(para.as_str(), &self.response_secrets())
+        } else {
+            para.as_str().to_string()
+        };
+
+        let res = File::create(filename).and_then(|mut file| file.write_all(contents.as_bytes()));
+        if let Err(err) = res {
+            self.report(err.into());
+        }
+    }
+
+    /// The secret values sent with the request and those of the active environment, which
+    /// shouldn't leave the application in its response.
+    fn response_secrets(&self) -> Vec<String> {
+        let environments = self.environments.lock().unwrap();
+        secret_values(self.headers.as_str(), environments.active(), &self.settings)
+    }
+
+    /// Whether the text contains any of the request's secret values, unless the settings include
+    /// secrets.
+    fn contains_secrets(&self, text: &str) -> bool {
+        !self.settings.include_secrets
+            && self
+                .response_secrets()
+                .iter()
+                .any(|secret| text.contains(secret.as_str()))
+    }
+
+    /// Copy the selected lines of the response, or else the whole body, first asking how to copy
+    /// it if it contains secrets.
+    fn copy_response(&mut self) {
+        if self.modal != Modal::None {
+            return;
+        }
+        let text = {
+            let mut paragraph = self.response_paragraph.lock().unwrap();
+            // Lines selected from the mark set with v, or else the whole body.
+            match paragraph.take_selection() {
+                Some(selection) => selection,
+                None if paragraph.is_empty() => {
+                    info!("There is no response to copy");
+                    return;
+                }
+                None => paragraph.as_str().to_string(),
+            }
+        };
+        if self.contains_secrets(text.as_str()) {
+            self.pending_copy = Some(text);
+            self.modal = Modal::CopyResponseSecrets;
+        } else {
+            clipboard::copy(text.as_str());
+        }
+    }
+
+    /// Send the current request, asking for confirmation first if it matches a safety rule.
+    fn send_request(&mut self) {
+        if self.modal != Modal::None {
+            return;
+        }
+        self.pending_saved = None;
+        if let Some(name) = self.next_unset_prompt(&self.prompts, &self.prompt_values) {
+            self.prompt_name = name;
+            self.prompt_value.clear();
+            self.modal = Modal::Prompt;
+            return;
+        }
+        self.send_template(self.current_template());
+    }
+
+    /// Send a saved request without loading it into the editor, first asking for its prompt
+    /// variables like `send_request` does.
+    fn send_saved(&mut self, request: crate::persistence::Request, values: Environment) {
+        if let Some(name) = self.next_unset_prompt(&request.prompts, &values) {
+            self.prompt_name = name;
+            self.prompt_value.clear();
+            self.pending_saved = Some((request, values));
+            self.modal = Modal::Prompt;
+            return;
+        }
+        let mut template = RequestTemplate::from(&request);
+        template.url = substitute(template.url.as_str(), Some(&values));
+        template.headers = substitute(template.headers.as_str(), Some(&values));
+        template.params = substitute(template.params.as_str(), Some(&values));
+        template.body = substitute(template.body.as_str(), Some(&values));
+        self.send_template(template);
+    }
+
+    /// The request in the editors, with prompt values applied. Prompt values don't change while
+    /// sending, so they are applied up front.
+    fn current_template(&self) -> RequestTemplate {
+        RequestTemplate {
+            method: self.method.clone(),
+            url: substitute(self.url.as_str(), Some(&self.prompt_values)),
+            headers: substitute(self.headers.as_str(), Some(&self.prompt_values)),
+            params: substitute(self.params.as_str(), Some(&self.prompt_values)),
+            body: if self.body_skipped() {
+                "".to_string()
+            } else {
+                substitute(self.body.as_str(), Some(&self.prompt_values))
+            },
+            body_type: self.body_type,
+            bypass_proxy: self.bypass_proxy,
+            timeout_secs: self.timeout_secs,
+            proxy: self.request_proxy.clone(),
+            post_process: Some(self.post_process.trim().to_string())
+                .filter(|command| !command.is_empty()),
+            download: None,
+            assertions: self.assertions.clone(),
+            captures: self.captures.clone(),
+        }
+    }
+
+    /// Send a request, asking for confirmation first if it matches a safety rule.
+    fn send_template(&mut self, template: RequestTemplate) {
+        let environments = self.environments.lock().unwrap();
+        let environment = environments.active();
+        if environment.is_some_and(Environment::needs_passphrase) {
+            drop(environments);
+            self.pending_send = Some(template);
+            self.pending_run = None;
+            self.passphrase.clear();
+            self.modal = Modal::Unlock;
+            return;
+        }
+        let url = request_url(&template, environment);
+        let reason =
+            confirmation_reason(&template.method, url.as_str(), &self.settings, environment);
+        drop(environments);
+        match reason {
+            Some(reason) => {
+                self.confirm_reason = reason;
+                self.pending_send = Some(template);
+                self.pending_run = None;
+                self.modal = Modal::ConfirmSend;
+            }
+            None => self.send_template_confirmed(template),
+        }
+    }
+
+    /// The body isn't sent, because the method conventionally has none and sending it anyway
+    /// hasn't been turned on.
+    pub fn body_skipped(&self) -> bool {
+        !self.method.has_body() && !self.send_body
+    }
+
+    /// The first of the prompt variables without a value.
+    fn next_unset_prompt(&self, prompts: &[String], values: &Environment) -> Option<String> {
+        let environments = self.environments.lock().unwrap();
+        let environment = environments.active();
+        prompts
+            .iter()
+            .find(|name| {
+                values.get(name).is_none()
+                    && environment
+                        .and_then(|environment| environment.get(name))
+                        .is_none()
+            })
+            .cloned()
+    }
+
+    fn handle_prompt_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                self.modal = Modal::None;
+                // Continue sending, this will prompt for any remaining variables.
+                match self.pending_saved.take() {
+                    Some((request, mut values)) => {
+                        values.set(self.prompt_name.as_str(), self.prompt_value.as_str());
+                        self.send_saved(request, values);
+                    }
+                    None => {
+                        self.prompt_values
+                            .set(self.prompt_name.as_str(), self.prompt_value.as_str());
+                        self.send_request();
+                    }
+                }
+            }
+            KeyCode::Char(c) => {
+                self.prompt_value.push(c);
+            }
+            KeyCode::Backspace => {
+                self.prompt_value.pop();
+            }
+            _ => {}
+        };
+    }
+
+    /// Once unlocked, the variables are decrypted, any written in plain text are encrypted, and
+    /// the request waiting on them is sent.
+    fn handle_unlock_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                let passphrase = std::mem::take(&mut self.passphrase);
+                let mut environments = self.environments.lock().unwrap();
+                let unlocked =
+                    crypto::unlock_values(passphrase.as_str(), environments.encrypted_sample())
+                        .map_err(Error::from)
+                        .and_then(|_| environments.decrypt_variables())
+                        .and_then(|_| environments.save());
+                drop(environments);
+                match unlocked {
+                    Ok(()) => {
+                        self.modal = Modal::None;
+                        if let Some(template) = self.pending_send.take() {
+                            self.send_template(template);
+                        } else if let Some(prefix) = self.pending_run.take() {
+                            self.run_collection(prefix);
+                        }
+                    }
+                    Err(err) => self.report(err),
+                }
+            }
+            KeyCode::Char(c) => self.passphrase.push(c),
+            KeyCode::Backspace => {
+                self.passphrase.pop();
+            }
+            _ => {}
+        };
+    }
+
+    fn send_template_confirmed(&mut self, template: RequestTemplate) {
+        if self.settings.offline {
+            self.serve_offline(&template);
+        } else {
+            self.make_request(template);
+        }
+        match self.settings.response_focus {
+            ResponseFocus::Immediate => self.set_view(View::Response),
+            ResponseFocus::OnCompletion => self.focus_on_completion = true,
+            ResponseFocus::Stay => {}
+        }
+    }
+
+    /// Apply state changes from background tasks. This is called regularly by the main loop.
+    pub fn tick(&mut self) {
+        if self.modal != Modal::Error && !self.errors.lock().unwrap().is_empty() {
+            self.modal_before_error = self.modal;
+            self.modal = Modal::Error;
+        } else if !matches!(self.modal, Modal::Error | Modal::Notice) && !self.notices.is_empty() {
+            self.modal_before_notice = self.modal;
+            self.modal = Modal::Notice;
+        }
+        if self.response_complete.swap(false, Ordering::SeqCst) && self.focus_on_completion {
+            self.focus_on_completion = false;
+            if self.modal == Modal::None {
+                self.push_focus((self.view, self.mode));
+                self.set_view(View::Response);
+            }
+            self.dirty.store(true, Ordering::SeqCst);
+        }
+        let discovery = self.oidc_discovery.lock().unwrap().take();
+        if let Some((environment, discovery)) = discovery {
+            self.apply_oidc_discovery(environment.as_str(), &discovery);
+        }
+        let import = self.openapi_import.lock().unwrap().take();
+        if let Some(import) = import {
+            self.apply_openapi_import(import);
+        }
+        let expiry = self.token_expiry_label();
+        if expiry != self.expiry_drawn {
+            self.expiry_drawn = expiry;
+            self.dirty.store(true, Ordering::SeqCst);
+        }
+        if self.in_flight.load(Ordering::SeqCst) {
+            let elapsed = self.request_started.elapsed().as_secs();
+            if elapsed != self.elapsed_drawn {
+                self.elapsed_drawn = elapsed;
+                self.dirty.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// Display the most recent response for the current request from the history instead of
+    /// sending it.
+    fn serve_offline(&mut self, template: &RequestTemplate) {
+        // Built as it is when sending, so it matches the URL recorded in the history.
+        let url = request_url(template, self.environments.lock().unwrap().active());
+        let entry = self
+            .history
+            .lock()
+            .unwrap()
+            .find_response(&template.method, url.as_str())
+            .cloned();
+        match entry {
+            Some(entry) => self.display_history_entry(&entry),
+            None => {
+                self.reset();
+                self.status.store(0, Ordering::SeqCst);
+                self.response_paragraph
+                    .lock()
+                    .unwrap()
+                    .set_value("Offline: no cached response for this request.".to_string());
+            }
+        }
+    }
+
+    /// Show a response from the history in the response view.
+    fn display_history_entry(&mut self, entry: &HistoryEntry) {
+        self.reset();
+        self.status.store(entry.status, Ordering::SeqCst);
+        *self.proxy.lock().unwrap() = entry.proxy.clone();
+        self.response_header_paragraph
+            .lock()
+            .unwrap()
+            .set_value(entry.response_headers.clone());
+
+        let body = entry.response_body.clone().unwrap_or_default();
+        // The history doesn't keep the content type, so it is guessed from the body.
+        let content_type = sniff(body.as_str());
+        let body = match content_type {
+            Some(content_type) => format_body(content_type, body.as_bytes(), &self.settings),
+            None => body,
+        };
+        let mut response_paragraph = self.response_paragraph.lock().unwrap();
+        response_paragraph.set_value(body);
+        if let Some(content_type) = content_type {
+            *self.content_type.lock().unwrap() = content_type.to_string();
+            response_paragraph.set_line_transform(line_transform(content_type, &self.settings));
+        }
+        drop(response_paragraph);
+        self.dirty.store(true, Ordering::SeqCst);
+    }
+
+    fn handle_confirm_send_input(&mut self, key: KeyEvent) {
+        if key.code == KeyCode::Char('y') {
+            self.modal = Modal::None;
+            if let Some(template) = self.pending_send.take() {
+                self.send_template_confirmed(template);
+            } else if let Some(prefix) = self.pending_run.take() {
+                self.start_collection_run(prefix, true);
+            }
+        }
+    }
+
+    fn handle_save_response_secrets_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('r') => {
+                self.save_response(true);
+                self.modal = Modal::None;
+            }
+            KeyCode::Char('s') => {
+                self.save_response(false);
+                self.modal = Modal::None;
+            }
+            _ => {}
+        };
+    }
+
+    fn handle_copy_response_secrets_input(&mut self, key: KeyEvent) {
+        let redact = match key.code {
+            KeyCode::Char('r') => true,
+            KeyCode::Char('c') => false,
+            _ => return,
+        };
+        if let Some(text) = self.pending_copy.take() {
+            match redact {
+                true => {
+                    clipboard::copy(redact_values(text.as_str(), &self.response_secrets()).as_str())
+                }
+                false => clipboard::copy(text.as_str()),
+            }
+        }
+        self.modal = Modal::None;
+    }
+
     fn handle_operation(&mut self, operation: Operation) -> bool {
         match operation {
             Operation::GotoUrl => {
@@ -179,6 +1369,10 @@ impl App {
                 self.set_view(View::Request);
                 self.mode = Mode::RequestHeaders;
             }
+            Operation::GotoRequestParams => {
+                self.set_view(View::Request);
+                self.mode = Mode::RequestParams;
+            }
             Operation::GotoResponseBody => {
                 self.set_view(View::Response);
                 self.mode = Mode::ResponseBody;
@@ -188,7 +1382,7 @@ impl App {
                 self.mode = Mode::ResponseHeaders
             }
             Operation::NextMethod => {
-                self.next_method();
+                self.next_method(false);
             }
             Operation::LoadRequest => {
                 if self.modal == Modal::None {
@@ -202,21 +1396,17 @@ impl App {
                 }
             }
             Operation::SaveResponse => {
-                let resp = self.response_paragraph.lock();
-                let para = &*resp.unwrap();
-
-                let url = self.url.as_str().to_string();
-                let url = url.replace("://", "_");
-                let url = url.replace("/", "_");
-                let url = url.replace(":", "_");
-                let mut filename = sanitize_filename::sanitize(url);
-                filename.push_str(".txt");
-
-                let file = File::create(filename);
-                if let Ok(mut file) = file {
-                    if let Err(err) = file.write_all(para.as_str().as_bytes()) {
-                        error!("Error writing file {:?}", err);
-                    }
+                if self.modal != Modal::None {
+                    return false;
+                }
+                let contains_secrets = {
+                    let body = self.response_paragraph.lock().unwrap().as_str().to_string();
+                    self.contains_secrets(body.as_str())
+                };
+                if contains_secrets {
+                    self.modal = Modal::SaveResponseSecrets;
+                } else {
+                    self.save_response(false);
                 }
             }
             Operation::GotoRequestView => {
@@ -226,277 +1416,2928 @@ impl App {
                 self.set_view(View::Response);
             }
             Operation::SendRequest => {
-                self.make_request();
-                self.set_view(View::Response);
+                self.send_request();
             }
-            Operation::Quit => {
-                return true;
+            Operation::InsertFile => {
+                if self.modal == Modal::None {
+                    self.modal = Modal::InsertFile;
+                }
             }
-        };
-        false
-    }
-
-    pub fn handle_input(&mut self, key: KeyEvent) -> bool {
-        info!("Handling {:?}", key);
-        let key_bind = self
-            .key_binds
-            .iter()
-            .find(|key_bind| key_bind.key == key.code && key.modifiers == key_bind.modifiers);
-
-        if let Some(key_bind) = key_bind {
-            let operation = key_bind.operation.clone();
-            return self.handle_operation(operation);
-        }
-
-        if key.modifiers.contains(KeyModifiers::CONTROL)
-            || key.modifiers.contains(KeyModifiers::ALT)
-        {
-            return false;
-        }
-        match key.code {
-            KeyCode::Esc => {
-                return if self.modal == Modal::None {
-                    false
-                } else {
-                    self.modal = Modal::None;
-                    false
+            Operation::DuplicateLine => {
+                if let Some(editor) = self.focused_editor() {
+                    editor.handle_command(EditCommand::DuplicateLine);
                 }
             }
-            _ => {}
-        }
-        match self.modal {
-            Modal::Save => self.handle_save_input(key),
-            Modal::Requests => self.handle_request_input(key),
-            Modal::None => match self.mode {
-                Mode::Url => self.handle_url_input(key),
+            Operation::MoveLineUp => {
+                if let Some(editor) = self.focused_editor() {
+                    editor.handle_command(EditCommand::MoveLineUp);
+                }
+            }
+            Operation::MoveLineDown => {
+                if let Some(editor) = self.focused_editor() {
+                    editor.handle_command(EditCommand::MoveLineDown);
+                }
+            }
+            Operation::ToggleComment => {
+                if let Some(editor) = self.focused_editor() {
+                    editor.handle_command(EditCommand::ToggleComment);
+                }
+            }
+            Operation::FocusLeft
+            | Operation::FocusRight
+            | Operation::FocusUp
+            | Operation::FocusDown => {
+                if let Some(mode) = self.neighbor_mode(operation) {
+                    self.mode = mode;
+                }
+            }
+            Operation::NextMode => {
+                self.next_mode(false);
+            }
+            Operation::PreviousMode => {
+                self.next_mode(true);
+            }
+            Operation::ShowHistory => {
+                if self.modal == Modal::None {
+                    self.modal = Modal::History;
+                    let len = self.history.lock().unwrap().entries.len();
+                    // Newest entries are at the end.
+                    self.history_selection_state
+                        .select(Some(len.saturating_sub(1)));
+                }
+            }
+            Operation::ToggleProxyBypass => {
+                self.bypass_proxy = !self.bypass_proxy;
+            }
+            Operation::ToggleSendBody => {
+                self.send_body = !self.send_body;
+            }
+            Operation::NextBodyType => {
+                self.body_type = self.body_type.next();
+            }
+            Operation::Download => {
+                if self.modal == Modal::None {
+                    self.modal = Modal::Download;
+                }
+            }
+            Operation::SetTimeout => {
+                if self.modal == Modal::None {
+                    self.timeout_input = self
+                        .timeout_secs
+                        .map(|timeout| timeout.to_string())
+                        .unwrap_or_default();
+                    self.modal = Modal::Timeout;
+                }
+            }
+            Operation::EditPrompts => {
+                if self.modal == Modal::None {
+                    self.prompts_input = self.prompts.join(", ");
+                    self.modal = Modal::Prompts;
+                }
+            }
+            Operation::SetProxy => {
+                if self.modal == Modal::None {
+                    self.proxy_input = self.request_proxy.clone().unwrap_or_default();
+                    self.modal = Modal::Proxy;
+                }
+            }
+            Operation::ImportOpenApi => {
+                if self.modal == Modal::None {
+                    self.modal = Modal::ImportOpenApi;
+                }
+            }
+            Operation::ImportInsomnia => {
+                if self.modal == Modal::None {
+                    self.modal = Modal::ImportInsomnia;
+                }
+            }
+            Operation::RunCollection => {
+                if self.modal == Modal::None {
+                    self.modal = Modal::RunCollection;
+                }
+            }
+            Operation::GotoResultsView => {
+                self.set_view(View::Results);
+                self.mode = Mode::Results;
+            }
+            Operation::EditAssertions => {
+                if self.modal == Modal::None {
+                    self.assertions_input = assertion::to_string(&self.assertions);
+                    if !self.assertions_input.is_empty() {
+                        self.assertions_input.push('\n');
+                    }
+                    self.modal = Modal::Assertions;
+                }
+            }
+            Operation::EditCaptures => {
+                if self.modal == Modal::None {
+                    self.captures_input = capture::to_string(&self.captures);
+                    if !self.captures_input.is_empty() {
+                        self.captures_input.push('\n');
+                    }
+                    self.modal = Modal::Captures;
+                }
+            }
+            Operation::EditKeyBinds => {
+                if self.modal == Modal::None {
+                    self.key_binds_selection_state.select(Some(0));
+                    self.modal = Modal::KeyBinds;
+                }
+            }
+            Operation::ShowHelp => match self.modal {
+                Modal::None => {
+                    self.help_scroll = 0;
+                    self.modal = Modal::Help;
+                }
+                Modal::Help => self.modal = Modal::None,
+                _ => {}
+            },
+            Operation::ProbeOptions => self.probe_options(),
+            Operation::ProbeWellKnown => self.probe_well_known(),
+            Operation::SetupOidc => {
+                if self.modal == Modal::None {
+                    self.modal = Modal::OidcIssuer;
+                }
+            }
+            Operation::SelectEnvironment => {
+                if self.modal == Modal::None {
+                    self.modal = Modal::Environments;
+                    // The first item is for no environment.
+                    let environments = self.environments.lock().unwrap();
+                    let active = environments.active.as_ref().and_then(|active| {
+                        environments
+                            .environments
+                            .iter()
+                            .position(|environment| &environment.name == active)
+                    });
+                    drop(environments);
+                    self.environment_selection_state
+                        .select(Some(active.map_or(0, |index| index + 1)));
+                }
+            }
+            Operation::ExportAuditLog => match audit::export() {
+                Ok(filename) => self.notify(format!("Exported audit log to {:}", filename)),
+                Err(err) => self.report(err),
+            },
+            Operation::ToggleOffline => {
+                self.settings.offline = !self.settings.offline;
+                if let Err(err) = self.settings.save() {
+                    self.report(err);
+                }
+            }
+            Operation::ToggleResponseSplit => {
+                self.response_split = !self.response_split;
+            }
+            Operation::ToggleSentRequest => {
+                self.show_sent_request = !self.show_sent_request;
+            }
+            Operation::RecordMacro => match self.recording.take() {
+                Some(keys) => {
+                    info!("Recorded macro of {:} keys", keys.len());
+                    self.recorded_macro = keys;
+                }
+                None => self.recording = Some(Vec::new()),
+            },
+            Operation::PlayMacro => return self.play_macro(),
+            Operation::Suspend => self.suspend_requested = true,
+            Operation::CopyResponse => self.copy_response(),
+            Operation::Paste => match clipboard::paste() {
+                Ok(text) => {
+                    if !self.paste(text.as_str()) {
+                        info!("Focus the URL or an editor to paste into it");
+                    }
+                }
+                Err(err) => self.report(err),
+            },
+            Operation::EditBodyExternally => {
+                if self.modal == Modal::None {
+                    self.set_view(View::Request);
+                    self.mode = Mode::RequestBody;
+                    self.external_edit_requested = true;
+                }
+            }
+            Operation::ToggleProfiler => self.show_profiler = !self.show_profiler,
+            Operation::ExportSession => match self.export_session() {
+                Ok(filename) => self.notify(format!("Exported session to {:}", filename)),
+                Err(err) => self.report(err),
+            },
+            Operation::ExportHar => {
+                let last = self.history.lock().unwrap().entries.last().cloned();
+                match last {
+                    Some(entry) => {
+                        match har::export(std::slice::from_ref(&entry), entry.url.as_str()) {
+                            Ok(filename) => self.notify(format!("Exported HAR to {:}", filename)),
+                            Err(err) => self.report(err),
+                        }
+                    }
+                    None => {
+                        self.report(Error::Parse("There are no exchanges to export".to_string()))
+                    }
+                }
+            }
+            Operation::PostProcess => {
+                if self.modal == Modal::None {
+                    self.modal = Modal::PostProcess;
+                }
+            }
+            Operation::ImportCurl => {
+                if self.modal == Modal::None {
+                    self.modal = Modal::ImportCurl;
+                }
+            }
+            Operation::ImportSession => {
+                if self.modal == Modal::None {
+                    self.modal = Modal::ImportSession;
+                }
+            }
+            Operation::ImportCookies => {
+                if self.modal == Modal::None {
+                    self.modal = Modal::ImportCookies;
+                }
+            }
+            Operation::AlternateRequest => {
+                if let Some(alternate) = self.alternate_request.take() {
+                    self.reset();
+                    let current = self.take_editor();
+                    self.restore_editor(alternate);
+                    self.alternate_request = Some(current);
+                }
+            }
+            Operation::NewTab => {
+                if self.modal == Modal::None {
+                    self.tabs.insert(self.current_tab + 1, Tab::new());
+                    self.switch_tab(self.current_tab + 1);
+                    self.set_view(View::Request);
+                    self.mode = Mode::Url;
+                }
+            }
+            Operation::CloseTab => {
+                if self.modal == Modal::None && self.tabs.len() > 1 {
+                    let closing = self.current_tab;
+                    let next = if closing + 1 < self.tabs.len() {
+                        closing + 1
+                    } else {
+                        closing - 1
+                    };
+                    self.switch_tab(next);
+                    self.tabs.remove(closing);
+                    if next > closing {
+                        self.current_tab -= 1;
+                    }
+                }
+            }
+            Operation::NextTab => {
+                if self.modal == Modal::None {
+                    self.switch_tab((self.current_tab + 1) % self.tabs.len());
+                }
+            }
+            Operation::PreviousTab => {
+                if self.modal == Modal::None {
+                    self.switch_tab((self.current_tab + self.tabs.len() - 1) % self.tabs.len());
+                }
+            }
+            Operation::Quit => {
+                return true;
+            }
+        };
+        false
+    }
+
+    /// The labels of the open tabs, in order.
+    pub fn tab_labels(&self) -> Vec<String> {
+        self.tabs
+            .iter()
+            .enumerate()
+            .map(|(index, tab)| {
+                if index == self.current_tab {
+                    tab_label(self.request_name.as_str(), self.url.as_str())
+                } else {
+                    tab_label(tab.request.request_name.as_str(), tab.request.url.as_str())
+                }
+            })
+            .collect()
+    }
+
+    /// Keep the current request and response in their tab, and show those of another.
+    fn switch_tab(&mut self, index: usize) {
+        if index == self.current_tab || index >= self.tabs.len() {
+            return;
+        }
+        let mut tabs = std::mem::take(&mut self.tabs);
+        self.swap_tab(&mut tabs[self.current_tab]);
+        self.swap_tab(&mut tabs[index]);
+        self.tabs = tabs;
+        self.current_tab = index;
+        self.response_preview_len = None;
+        self.focus_on_completion = false;
+        self.dirty.store(true, Ordering::SeqCst);
+    }
+
+    /// Exchange the request and response in the app with those of the tab.
+    fn swap_tab(&mut self, tab: &mut Tab) {
+        let request = self.take_editor();
+        self.restore_editor(std::mem::replace(&mut tab.request, request));
+        std::mem::swap(&mut self.alternate_request, &mut tab.alternate_request);
+        std::mem::swap(&mut self.response, &mut tab.response);
+        std::mem::swap(&mut self.response_paragraph, &mut tab.response_paragraph);
+        std::mem::swap(
+            &mut self.response_header_paragraph,
+            &mut tab.response_header_paragraph,
+        );
+        std::mem::swap(&mut self.content_type, &mut tab.content_type);
+        std::mem::swap(
+            &mut self.content_type_detected,
+            &mut tab.content_type_detected,
+        );
+        std::mem::swap(
+            &mut self.content_type_overridden,
+            &mut tab.content_type_overridden,
+        );
+        std::mem::swap(&mut self.response_bookmarks, &mut tab.response_bookmarks);
+        std::mem::swap(&mut self.response_tree, &mut tab.response_tree);
+        std::mem::swap(&mut self.download, &mut tab.download);
+        std::mem::swap(&mut self.steps, &mut tab.steps);
+        std::mem::swap(&mut self.sent_request, &mut tab.sent_request);
+        std::mem::swap(&mut self.request_started, &mut tab.request_started);
+        std::mem::swap(&mut self.status, &mut tab.status);
+        std::mem::swap(&mut self.proxy, &mut tab.proxy);
+        std::mem::swap(
+            &mut self.in_flight_timeout_secs,
+            &mut tab.in_flight_timeout_secs,
+        );
+        std::mem::swap(&mut self.response_complete, &mut tab.response_complete);
+        std::mem::swap(&mut self.request_id, &mut tab.request_id);
+        std::mem::swap(&mut self.handler_id, &mut tab.handler_id);
+        std::mem::swap(&mut self.in_flight, &mut tab.in_flight);
+        std::mem::swap(&mut self.assertion_outcomes, &mut tab.assertion_outcomes);
+    }
+
+    pub fn handle_input(&mut self, key: KeyEvent) -> bool {
+        // The passphrase must not end up in a macro. Keys aren't logged, since they include
+        // everything typed into the editors.
+        if self.modal != Modal::Unlock {
+            self.record_key(key);
+        }
+        if key.code == KeyCode::Esc && key.modifiers.is_empty() {
+            // Going back is not recorded, otherwise Esc would bounce between two panes.
+            if !self.dispatch_escape(key) {
+                self.go_back();
+            }
+            return false;
+        }
+        let focus = (self.view, self.mode);
+        let quit = self.dispatch_input(key);
+        if focus != (self.view, self.mode) {
+            self.push_focus(focus);
+        }
+        self.update_suggestions();
+        quit
+    }
+
+    /// Suggest values from the history for the URL, or the header value, being typed. Suggestions
+    /// are only made with the cursor at the end of the line.
+    fn update_suggestions(&mut self) {
+        let history = self.history.clone();
+        let history = history.lock().unwrap();
+        self.suggestions = match (self.modal, self.mode) {
+            (Modal::None, Mode::Url) if self.url.at_line_end() => {
+                suggest::urls(&history, self.url.line_before_cursor())
+            }
+            (Modal::None, Mode::RequestHeaders) if self.headers.at_line_end() => {
+                suggest::header_values(&history, self.headers.line_before_cursor(), &self.settings)
+            }
+            _ => Vec::new(),
+        };
+    }
+
+    /// Complete the URL or header value with the first suggestion.
+    fn accept_suggestion(&mut self) {
+        let suggestion = match self.suggestions.first() {
+            Some(suggestion) => suggestion.clone(),
+            None => return,
+        };
+        let editor = match self.mode {
+            Mode::Url => &mut self.url,
+            Mode::RequestHeaders => &mut self.headers,
+            _ => return,
+        };
+        let line = editor.line_before_cursor();
+        let typed = match self.mode {
+            Mode::RequestHeaders => line
+                .split_once(':')
+                .map_or("", |(_, value)| value.trim_start()),
+            _ => line,
+        };
+        if let Some(rest) = suggestion.strip_prefix(typed) {
+            editor.insert_str(rest);
+        }
+    }
+
+    /// Capture a key for the macro being recorded. The keys which control recording and playback
+    /// are left out, so playing a macro can't start another recording or replay itself.
+    fn record_key(&mut self, key: KeyEvent) {
+        if self.playing_macro {
+            return;
+        }
+        let controls_macro = self.key_binds.iter().any(|key_bind| {
+            matches!(
+                key_bind.operation,
+                Operation::RecordMacro | Operation::PlayMacro
+            ) && key_bind.key == key.code
+                && key_bind.modifiers == key.modifiers
+        });
+        if let (Some(keys), false) = (self.recording.as_mut(), controls_macro) {
+            keys.push(key);
+        }
+    }
+
+    /// Replay the last recorded macro as if its keys had been typed.
+    fn play_macro(&mut self) -> bool {
+        if self.playing_macro || self.recording.is_some() {
+            return false;
+        }
+        self.playing_macro = true;
+        let keys = self.recorded_macro.clone();
+        let mut quit = false;
+        for key in keys {
+            if self.handle_input(key) {
+                quit = true;
+                break;
+            }
+        }
+        self.playing_macro = false;
+        quit
+    }
+
+    /// Put a request in the editor, keeping the one it replaces as the alternate request.
+    fn load_request(&mut self, request: crate::persistence::Request) {
+        self.alternate_request = Some(self.take_editor());
+
+        self.url.set_value(request.url.clone());
+        self.method = request.method.clone();
+        self.bypass_proxy = request.bypass_proxy;
+        self.timeout_secs = request.timeout_secs;
+        self.request_proxy = request.proxy.clone();
+        self.send_body = request.send_body;
+        self.body_type = request.body_type;
+        self.prompts = request.prompts.clone();
+        self.post_process = request.post_process.clone().unwrap_or_default();
+        self.assertions = request.assertions.clone();
+        self.captures = request.captures.clone();
+        self.prompt_values = Environment::default();
+        self.request_name = request.key.clone();
+        if let Some(body) = &request.body {
+            self.body.set_value(body.clone());
+        }
+
+        self.headers.set_value(request.headers_to_string());
+        self.params.set_value(request.params_to_string());
+    }
+
+    /// Write the request, active environment, and response to a file which can be shared.
+    fn export_session(&self) -> Result<String> {
+        let mut builder = crate::persistence::RequestBuilder::new(self.request_name.as_str());
+        builder.url(self.url.as_str());
+        builder.method(self.method.clone());
+        builder.headers(redact_headers(self.headers.as_str(), &self.settings).as_str());
+        builder.params(self.params.as_str());
+        builder.body(self.body.as_str());
+        builder.bypass_proxy(self.bypass_proxy);
+        builder.timeout_secs(self.timeout_secs);
+        builder.proxy(self.request_proxy.clone());
+        builder.send_body(self.send_body);
+        builder.body_type(self.body_type);
+        builder.prompts(&self.prompts);
+        builder.post_process(self.post_process.as_str());
+        builder.assertions(&self.assertions);
+        builder.captures(&self.captures);
+
+        let template = self.current_template();
+        let (environment, headers, url, body) = {
+            let environments = self.environments.lock().unwrap();
+            let environment = environments.active();
+            (
+                environment.map(|environment| {
+                    if self.settings.include_secrets {
+                        environment.clone()
+                    } else {
+                        environment.without_secrets()
+                    }
+                }),
+                substitute(template.headers.as_str(), environment),
+                request_url(&template, environment),
+                substitute(template.body.as_str(), environment),
+            )
+        };
+        let secrets = if self.settings.include_secrets {
+            Vec::new()
+        } else {
+            self.response_secrets()
+        };
+        let status = self.status.load(Ordering::SeqCst);
+        let response_body = self.response_paragraph.lock().unwrap().as_str().to_string();
+        let exchange = HistoryEntry {
+            timestamp: crate::history::now(),
+            method: self.method.clone(),
+            url: redact_values(url.as_str(), &secrets),
+            headers: redact_headers(headers.as_str(), &self.settings),
+            body: redact_values(body.as_str(), &secrets),
+            status,
+            response_headers: redact_headers(
+                self.response_header_paragraph.lock().unwrap().as_str(),
+                &self.settings,
+            ),
+            response_body: (status != 0).then(|| redact_values(response_body.as_str(), &secrets)),
+            proxy: self.proxy.lock().unwrap().clone(),
+            wait_ms: None,
+            elapsed_ms: None,
+        };
+        Session::new(builder.build(), environment, exchange).export()
+    }
+
+    fn import_session(&mut self, path: &str) -> Result<()> {
+        let session = Session::import(Path::new(path))?;
+        if let Some(environment) = session.environment {
+            let mut environments = self.environments.lock().unwrap();
+            environments.set_active(Some(environment.name.clone()));
+            environments.add(environment);
+            environments.save()?;
+        }
+        self.load_request(session.request);
+        self.display_history_entry(&session.exchange);
+        Ok(())
+    }
+
+    /// Take the request out of the editor, leaving it empty.
+    fn take_editor(&mut self) -> AlternateRequest {
+        self.prompt_values = Environment::default();
+        AlternateRequest {
+            request_name: std::mem::take(&mut self.request_name),
+            method: self.method.clone(),
+            url: std::mem::replace(&mut self.url, EditState::new("")),
+            headers: std::mem::replace(&mut self.headers, EditState::new("")),
+            params: std::mem::replace(&mut self.params, EditState::new("")),
+            body: std::mem::replace(&mut self.body, EditState::new("")),
+            bypass_proxy: self.bypass_proxy,
+            timeout_secs: self.timeout_secs,
+            proxy: self.request_proxy.clone(),
+            send_body: self.send_body,
+            body_type: self.body_type,
+            prompts: std::mem::take(&mut self.prompts),
+            post_process: std::mem::take(&mut self.post_process),
+            assertions: std::mem::take(&mut self.assertions),
+            captures: std::mem::take(&mut self.captures),
+        }
+    }
+
+    fn restore_editor(&mut self, request: AlternateRequest) {
+        self.request_name = request.request_name;
+        self.method = request.method;
+        self.url = request.url;
+        self.headers = request.headers;
+        self.params = request.params;
+        self.body = request.body;
+        self.bypass_proxy = request.bypass_proxy;
+        self.timeout_secs = request.timeout_secs;
+        self.request_proxy = request.proxy;
+        self.send_body = request.send_body;
+        self.body_type = request.body_type;
+        self.prompts = request.prompts;
+        self.post_process = request.post_process;
+        self.assertions = request.assertions;
+        self.captures = request.captures;
+    }
+
+    fn push_focus(&mut self, focus: (View, Mode)) {
+        const MAX_FOCUS_HISTORY: usize = 32;
+
+        if self.focus_stack.last() != Some(&focus) {
+            self.focus_stack.push(focus);
+        }
+        if self.focus_stack.len() > MAX_FOCUS_HISTORY {
+            self.focus_stack.remove(0);
+        }
+    }
+
+    /// Give Esc to the focused pane, or to the key binds being edited, to cancel what is in
+    /// progress, like a search. Returns whether it was used.
+    fn dispatch_escape(&mut self, key: KeyEvent) -> bool {
+        if self.rebinding {
+            self.rebinding = false;
+            return true;
+        }
+        if self.modal != Modal::None {
+            return false;
+        }
+        match self.mode {
+            Mode::ResponseBody if !self.show_response_tree => self
+                .response_paragraph
+                .lock()
+                .unwrap()
+                .handle_search_input(key),
+            Mode::ResponseHeaders => self
+                .response_header_paragraph
+                .lock()
+                .unwrap()
+                .handle_search_input(key),
+            _ => false,
+        }
+    }
+
+    /// Close the open modal, or if there is none return to the previously focused pane.
+    fn go_back(&mut self) {
+        if self.modal == Modal::Error {
+            self.dismiss_error();
+            return;
+        }
+        if self.modal == Modal::Notice {
+            self.dismiss_notice();
+            return;
+        }
+        if self.modal != Modal::None {
+            self.close_modal();
+            return;
+        }
+        if let Some((view, mode)) = self.focus_stack.pop() {
+            self.view = view;
+            self.mode = mode;
+        }
+    }
+
+    /// Close the open modal without finishing it. What was typed into it, and anything waiting
+    /// on it, like a request to confirm, is dropped so it starts afresh the next time.
+    fn close_modal(&mut self) {
+        match self.modal {
+            Modal::InsertFile => self.insert_file_path.clear(),
+            Modal::ImportEnvironments => self.import_environments_path.clear(),
+            Modal::ImportSession => self.import_session_path.clear(),
+            Modal::ImportCurl => self.import_curl.clear(),
+            Modal::ImportCookies => self.import_cookies.clear(),
+            Modal::ImportOpenApi => self.import_openapi_source.clear(),
+            Modal::ImportInsomnia => self.import_insomnia_path.clear(),
+            Modal::ImportHttpFile => self.import_http_path.clear(),
+            Modal::OidcIssuer => self.oidc_issuer.clear(),
+            Modal::Download => self.download_path.clear(),
+            Modal::Timeout => self.timeout_input.clear(),
+            Modal::Proxy => self.proxy_input.clear(),
+            Modal::CustomMethod => self.method_input.clear(),
+            Modal::Prompts => self.prompts_input.clear(),
+            Modal::Assertions => self.assertions_input.clear(),
+            Modal::Captures => self.captures_input.clear(),
+            Modal::RunCollection => self.run_prefix.clear(),
+            Modal::Retarget => self.retarget.clear(),
+            Modal::ExtractVariable => {
+                self.extract_value.clear();
+                self.extract_variable.clear();
+            }
+            Modal::GoToKey => {
+                self.key_query.clear();
+                self.key_paths.clear();
+                self.key_matches.clear();
+            }
+            Modal::CopyResponseSecrets => self.pending_copy = None,
+            Modal::Prompt | Modal::Unlock | Modal::ConfirmSend => {
+                self.prompt_name.clear();
+                self.prompt_value.clear();
+                self.passphrase.clear();
+                self.confirm_reason.clear();
+                self.pending_send = None;
+                self.pending_run = None;
+                self.pending_saved = None;
+            }
+            _ => {}
+        }
+        self.modal = Modal::None;
+    }
+
+    fn dispatch_input(&mut self, key: KeyEvent) -> bool {
+        // The keys being bound mustn't run the operation they are bound to now.
+        if self.rebinding {
+            self.rebind(key);
+            return false;
+        }
+        let key_bind = self
+            .key_binds
+            .iter()
+            .find(|key_bind| key_bind.key == key.code && key.modifiers == key_bind.modifiers);
+
+        if let Some(key_bind) = key_bind {
+            let operation = key_bind.operation;
+            return self.handle_operation(operation);
+        }
+
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            || key.modifiers.contains(KeyModifiers::ALT)
+        {
+            return false;
+        }
+        match self.modal {
+            Modal::Save => self.handle_save_input(key),
+            Modal::Requests => self.handle_request_input(key),
+            Modal::InsertFile => self.handle_insert_file_input(key),
+            Modal::ImportEnvironments => self.handle_import_environments_input(key),
+            Modal::ImportSession => self.handle_import_session_input(key),
+            Modal::ImportCurl => self.handle_import_curl_input(key),
+            Modal::ImportCookies => self.handle_import_cookies_input(key),
+            Modal::ExtractVariable => self.handle_extract_variable_input(key),
+            Modal::MetricsFilter => self.handle_metrics_filter_input(key),
+            Modal::PostProcess => self.handle_post_process_input(key),
+            Modal::Retarget => self.handle_retarget_input(key),
+            Modal::Error => {
+                if key.code == KeyCode::Enter {
+                    self.dismiss_error();
+                }
+            }
+            Modal::Notice => {
+                if key.code == KeyCode::Enter {
+                    self.dismiss_notice();
+                }
+            }
+            Modal::History => self.handle_history_input(key),
+            Modal::SaveResponseSecrets => self.handle_save_response_secrets_input(key),
+            Modal::CopyResponseSecrets => self.handle_copy_response_secrets_input(key),
+            Modal::ConfirmSend => self.handle_confirm_send_input(key),
+            Modal::Environments => self.handle_environments_input(key),
+            Modal::Prompt => self.handle_prompt_input(key),
+            Modal::Unlock => self.handle_unlock_input(key),
+            Modal::OidcIssuer => self.handle_oidc_issuer_input(key),
+            Modal::Download => self.handle_download_input(key),
+            Modal::Timeout => self.handle_timeout_input(key),
+            Modal::Proxy => self.handle_proxy_input(key),
+            Modal::CustomMethod => self.handle_custom_method_input(key),
+            Modal::ImportOpenApi => self.handle_import_openapi_input(key),
+            Modal::ImportInsomnia => self.handle_import_insomnia_input(key),
+            Modal::ImportHttpFile => self.handle_import_http_input(key),
+            Modal::RunCollection => self.handle_run_collection_input(key),
+            Modal::Assertions => self.handle_assertions_input(key),
+            Modal::Captures => self.handle_captures_input(key),
+            Modal::Prompts => self.handle_prompts_input(key),
+            Modal::KeyBinds => self.handle_key_binds_input(key),
+            Modal::Help => self.handle_help_input(key),
+            Modal::GoToKey => self.handle_go_to_key_input(key),
+            Modal::None => match self.mode {
+                Mode::Url => self.handle_url_input(key),
+                Mode::Method => self.handle_method_input(key),
                 Mode::RequestHeaders => self.handle_request_headers_input(key),
+                Mode::RequestParams => self.handle_request_params_input(key),
                 Mode::RequestBody => self.handle_request_body_input(key),
-                Mode::ResponseBody => self.response_paragraph.lock().unwrap().handle_input(key),
+                Mode::ResponseBody => self.handle_response_body_input(key),
                 Mode::ResponseHeaders => self
                     .response_header_paragraph
                     .lock()
                     .unwrap()
                     .handle_input(key),
-                _ => {}
+                Mode::Results => self.handle_results_input(key),
             },
         }
-        false
+        false
+    }
+
+    fn save_request(&mut self) {
+        if self.url.is_empty() || self.request_name.is_empty() {
+            return;
+        }
+        let mut builder = crate::persistence::RequestBuilder::new(self.request_name.as_str());
+        builder.url(self.url.as_str());
+        builder.method(self.method.clone());
+        builder.headers(self.headers.as_str());
+        builder.params(self.params.as_str());
+        builder.body(self.body.as_str());
+        builder.bypass_proxy(self.bypass_proxy);
+        builder.timeout_secs(self.timeout_secs);
+        builder.proxy(self.request_proxy.clone());
+        builder.send_body(self.send_body);
+        builder.body_type(self.body_type);
+        builder.prompts(&self.prompts);
+        builder.post_process(self.post_process.as_str());
+        builder.assertions(&self.assertions);
+        builder.captures(&self.captures);
+        self.request_collection.add_request(builder.build());
+        if let Err(err) = self.request_collection.save() {
+            self.report(err);
+        }
+        self.modal = Modal::None;
+    }
+
+    fn handle_save_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => self.save_request(),
+            KeyCode::Char(c) => {
+                self.request_name.push(c);
+            }
+            KeyCode::Backspace => {
+                self.request_name.pop();
+            }
+            _ => {}
+        };
+    }
+
+    fn handle_insert_file_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                if self.insert_file_path.is_empty() {
+                    return;
+                }
+                let path = std::mem::take(&mut self.insert_file_path);
+                // Paths copied from Windows Explorer are quoted.
+                let path = path.trim().trim_matches('"').to_string();
+                match std::fs::read_to_string(&path) {
+                    // The editor works with `\n` line endings. Line endings can be converted
+                    // when sending with the `line_endings` setting.
+                    Ok(contents) => self
+                        .body
+                        .insert_str(contents.replace("\r\n", "\n").as_str()),
+                    Err(err) => {
+                        self.report(err.into());
+                        return;
+                    }
+                }
+                self.set_view(View::Request);
+                self.mode = Mode::RequestBody;
+                self.modal = Modal::None;
+            }
+            KeyCode::Char(c) => {
+                self.insert_file_path.push(c);
+            }
+            KeyCode::Backspace => {
+                self.insert_file_path.pop();
+            }
+            _ => {}
+        };
+    }
+
+    fn handle_request_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                let index = self.request_selection_state.selected().unwrap_or(0);
+                if index >= self.request_collection.requests.len() {
+                    return;
+                }
+
+                self.reset();
+                self.load_request(self.request_collection.requests[index].clone());
+                self.modal = Modal::None;
+            }
+            KeyCode::Up | KeyCode::Down | KeyCode::PageUp | KeyCode::PageDown
+                if self.request_collection.requests.is_empty() => {}
+            KeyCode::Up => self
+                .request_selection_state
+                .select(Some(Self::list_previous(
+                    self.request_collection.requests.len(),
+                    self.request_selection_state.selected().unwrap_or(0),
+                ))),
+            KeyCode::Down => self.request_selection_state.select(Some(Self::list_next(
+                self.request_collection.requests.len(),
+                self.request_selection_state.selected().unwrap_or(0),
+            ))),
+            KeyCode::PageUp => {
+                let selected = self.request_selection_state.selected().unwrap_or(0);
+                self.request_selection_state
+                    .select(Some(selected.saturating_sub(self.request_page_size)));
+            }
+            KeyCode::PageDown => {
+                let selected = self.request_selection_state.selected().unwrap_or(0);
+                let last = self.request_collection.requests.len() - 1;
+                self.request_selection_state
+                    .select(Some((selected + self.request_page_size).min(last)));
+            }
+            KeyCode::Char('s') => {
+                let index = self.request_selection_state.selected().unwrap_or(0);
+                if let Some(request) = self.request_collection.requests.get(index) {
+                    let request = request.clone();
+                    self.modal = Modal::None;
+                    self.send_saved(request, Environment::default());
+                }
+            }
+            KeyCode::Char('r') => self.modal = Modal::Retarget,
+            KeyCode::Char('i') => self.modal = Modal::ImportHttpFile,
+            KeyCode::Char('e') => match http_file::export(&self.request_collection.requests) {
+                Ok(filename) => self.notify(format!("Exported the requests to {:}", filename)),
+                Err(err) => self.report(err),
+            },
+            KeyCode::Home => self.request_selection_state.select(Some(0)),
+            KeyCode::End => self.request_selection_state.select(Some(
+                self.request_collection.requests.len().saturating_sub(1),
+            )),
+            KeyCode::Delete => {
+                if let Some(index) = self.request_selection_state.selected() {
+                    self.request_collection.remove_request(index);
+                    if let Err(err) = self.request_collection.save() {
+                        self.report(err);
+                    }
+                    if index > 0 {
+                        self.request_selection_state.select(Some(index - 1));
+                    }
+                    if self.request_collection.requests.is_empty() {
+                        self.modal = Modal::None;
+                    }
+                }
+            }
+            _ => {}
+        };
+    }
+
+    fn handle_import_http_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                let path = std::mem::take(&mut self.import_http_path);
+                let path = path.trim().trim_matches('"');
+                let prefix = Path::new(path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let imported = std::fs::read_to_string(path)
+                    .map_err(Error::from)
+                    .and_then(|text| http_file::parse(text.as_str(), prefix.as_str()));
+                match imported {
+                    Ok(requests) => {
+                        info!("Imported {:} requests from {:}", requests.len(), path);
+                        for request in requests {
+                            self.request_collection.add_request(request);
+                        }
+                        if let Err(err) = self.request_collection.save() {
+                            self.report(err);
+                        }
+                    }
+                    Err(err) => self.report(err),
+                }
+                self.modal = if self.request_collection.requests.is_empty() {
+                    Modal::None
+                } else {
+                    Modal::Requests
+                };
+            }
+            KeyCode::Char(c) => self.import_http_path.push(c),
+            KeyCode::Backspace => {
+                self.import_http_path.pop();
+            }
+            _ => {}
+        };
+    }
+
+    fn handle_run_collection_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                self.modal = Modal::None;
+                self.run_collection(self.run_prefix.clone());
+            }
+            KeyCode::Char(c) => self.run_prefix.push(c),
+            KeyCode::Backspace => {
+                self.run_prefix.pop();
+            }
+            _ => {}
+        };
+    }
+
+    fn handle_results_input(&mut self, key: KeyEvent) {
+        let len = self.collection_run.lock().unwrap().results.len();
+        let selected = self.results_selection_state.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Enter => {
+                let entry = self
+                    .collection_run
+                    .lock()
+                    .unwrap()
+                    .results
+                    .get(selected)
+                    .and_then(|result| result.entry.clone());
+                if let Some(entry) = entry {
+                    self.display_history_entry(&entry);
+                    self.set_view(View::Response);
+                    self.mode = Mode::ResponseBody;
+                }
+            }
+            KeyCode::Char('r') => {
+                let prefix = self.collection_run.lock().unwrap().prefix.clone();
+                self.run_collection(prefix);
+            }
+            KeyCode::Up | KeyCode::Down if len == 0 => {}
+            KeyCode::Up => self
+                .results_selection_state
+                .select(Some(Self::list_previous(len, selected))),
+            KeyCode::Down => self
+                .results_selection_state
+                .select(Some(Self::list_next(len, selected))),
+            KeyCode::Home => self.results_selection_state.select(Some(0)),
+            KeyCode::End => self
+                .results_selection_state
+                .select(Some(len.saturating_sub(1))),
+            _ => {}
+        }
+    }
+
+    /// Run the saved requests whose names start with `prefix`, after the same checks as sending
+    /// from the editor. Nothing is sent while offline, the passphrase is asked for when the
+    /// environment has encrypted variables, and the run is confirmed once up front when any of
+    /// its requests match a safety rule.
+    fn run_collection(&mut self, prefix: String) {
+        if self.settings.offline {
+            self.report(Error::Refused(
+                "Collection runs send requests, so they can't be run while offline".to_string(),
+            ));
+            return;
+        }
+        let environments = self.environments.lock().unwrap();
+        let environment = environments.active();
+        if environment.is_some_and(Environment::needs_passphrase) {
+            drop(environments);
+            self.pending_run = Some(prefix);
+            self.pending_send = None;
+            self.passphrase.clear();
+            self.modal = Modal::Unlock;
+            return;
+        }
+        let requests = runner::select(&self.request_collection.requests, prefix.as_str());
+        let reasons: Vec<String> = requests
+            .iter()
+            .filter_map(|request| {
+                let url = request_url(&RequestTemplate::from(*request), environment);
+                confirmation_reason(&request.method, url.as_str(), &self.settings, environment)
+            })
+            .collect();
+        drop(environments);
+        match reasons.first() {
+            Some(reason) => {
+                self.confirm_reason = format!(
+                    "{:} of the {:} requests match a safety rule, like {:}",
+                    reasons.len(),
+                    requests.len(),
+                    reason
+                );
+                self.pending_run = Some(prefix);
+                self.pending_send = None;
+                self.modal = Modal::ConfirmSend;
+            }
+            None => self.start_collection_run(prefix, false),
+        }
+    }
+
+    /// Send the saved requests whose names start with `prefix` one after another in the
+    /// background, with the active environment, and show their results as they finish. Requests
+    /// matching a safety rule are only sent once the run has been `confirmed`.
+    fn start_collection_run(&mut self, prefix: String, confirmed: bool) {
+        let requests: Vec<crate::persistence::Request> =
+            runner::select(&self.request_collection.requests, prefix.as_str())
+                .into_iter()
+                .cloned()
+                .collect();
+        if requests.is_empty() {
+            self.report(Error::Parse(format!(
+                "No saved request starts with {:}",
+                prefix
+            )));
+            return;
+        }
+        let mut environment = self
+            .environments
+            .lock()
+            .unwrap()
+            .active()
+            .cloned()
+            .unwrap_or_default();
+        *self.collection_run.lock().unwrap() = CollectionRun {
+            prefix,
+            total: requests.len(),
+            ..Default::default()
+        };
+        self.results_selection_state.select(Some(0));
+        self.set_view(View::Results);
+        self.mode = Mode::Results;
+
+        let run_id = self.run_id.clone();
+        let id = self.run_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let collection_run = self.collection_run.clone();
+        let history = self.history.clone();
+        let settings = self.settings.clone();
+        let errors = self.errors.clone();
+        let dirty = self.dirty.clone();
+        self.spawn_task(async move {
+            // The run has its own handler, so sending from the editor doesn't cancel it.
+            let (sender, receiver) = mpsc::channel(10);
+            let handler = web_request_handler(receiver);
+            for request in requests {
+                if run_id.load(Ordering::SeqCst) != id {
+                    break;
+                }
+                let result =
+                    runner::run_one(&sender, &request, &mut environment, &settings, confirmed)
+                        .await;
+                if let Some(entry) = &result.entry {
+                    record_history(&history, entry.clone(), &settings, &errors).await;
+                }
+                if run_id.load(Ordering::SeqCst) == id {
+                    collection_run.lock().unwrap().results.push(result);
+                    dirty.store(true, Ordering::SeqCst);
+                }
+            }
+            if run_id.load(Ordering::SeqCst) == id {
+                collection_run.lock().unwrap().done = true;
+                dirty.store(true, Ordering::SeqCst);
+            }
+            let _ = queue(&sender, WebRequest::Shutdown, &AtomicBool::new(false)).await;
+            let _ = handler.await;
+        });
+    }
+
+    fn handle_history_input(&mut self, key: KeyEvent) {
+        if key.code == KeyCode::Enter {
+            let selected = self.history_selection_state.selected().unwrap_or(0);
+            let entry = self.history.lock().unwrap().entries.get(selected).cloned();
+            if let Some(entry) = entry {
+                self.display_history_entry(&entry);
+                self.modal = Modal::None;
+                self.set_view(View::Response);
+                self.mode = Mode::ResponseBody;
+            }
+            return;
+        }
+
+        let mut history = self.history.lock().unwrap();
+        let len = history.entries.len();
+        let selected = self.history_selection_state.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Up | KeyCode::Down if len == 0 => {}
+            KeyCode::Up => self
+                .history_selection_state
+                .select(Some(Self::list_previous(len, selected))),
+            KeyCode::Down => self
+                .history_selection_state
+                .select(Some(Self::list_next(len, selected))),
+            KeyCode::Delete => {
+                history.remove(selected);
+                if let Err(err) = history.save() {
+                    self.report(err);
+                }
+                self.history_selection_state
+                    .select(Some(selected.min(history.entries.len().saturating_sub(1))));
+            }
+            KeyCode::Char('r') => {
+                history.remove_response(selected);
+                if let Err(err) = history.save() {
+                    self.report(err);
+                }
+            }
+            KeyCode::Char('p') => {
+                let removed = history.prune(&self.settings);
+                info!("Pruned {:} history entries", removed);
+                if let Err(err) = history.save() {
+                    self.report(err);
+                }
+                self.history_selection_state
+                    .select(Some(history.entries.len().saturating_sub(1)));
+            }
+            KeyCode::Char('h') => {
+                let exported = har::export(&history.entries, "history");
+                drop(history);
+                match exported {
+                    Ok(filename) => {
+                        self.notify(format!("Exported the history as HAR to {:}", filename))
+                    }
+                    Err(err) => self.report(err),
+                }
+            }
+            KeyCode::Char('c') => {
+                history.clear();
+                if let Err(err) = history.save() {
+                    self.report(err);
+                }
+                self.history_selection_state.select(Some(0));
+            }
+            _ => {}
+        };
+    }
+
+    fn handle_environments_input(&mut self, key: KeyEvent) {
+        let mut environments = self.environments.lock().unwrap();
+        // The first item is for no environment.
+        let len = environments.environments.len() + 1;
+        let selected = self.environment_selection_state.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Up => self
+                .environment_selection_state
+                .select(Some(Self::list_previous(len, selected))),
+            KeyCode::Down => self
+                .environment_selection_state
+                .select(Some(Self::list_next(len, selected))),
+            KeyCode::Enter => {
+                let name = if selected == 0 {
+                    None
+                } else {
+                    environments
+                        .environments
+                        .get(selected - 1)
+                        .map(|environment| environment.name.clone())
+                };
+                environments.set_active(name);
+                if let Err(err) = environments.save() {
+                    self.report(err);
+                }
+                self.modal = Modal::None;
+            }
+            KeyCode::Char('x') => {
+                let exported = environments.export(self.settings.include_secrets);
+                drop(environments);
+                match exported {
+                    Ok(filename) => self.notify(format!("Exported environments to {:}", filename)),
+                    Err(err) => self.report(err),
+                }
+            }
+            KeyCode::Char('i') => self.modal = Modal::ImportEnvironments,
+            _ => {}
+        };
+    }
+
+    fn handle_extract_variable_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                let name = std::mem::take(&mut self.extract_variable);
+                let name = name.trim();
+                if name.is_empty() {
+                    return;
+                }
+                let mut environments = self.environments.lock().unwrap();
+                if let Some(environment) = environments.active_mut() {
+                    environment.set(name, self.extract_value.as_str());
+                    if let Err(err) = environments.save() {
+                        self.report(err);
+                    }
+                }
+                self.modal = Modal::None;
+            }
+            KeyCode::Char(c) => self.extract_variable.push(c),
+            KeyCode::Backspace => {
+                self.extract_variable.pop();
+            }
+            _ => {}
+        };
+    }
+
+    fn handle_import_environments_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                let path = std::mem::take(&mut self.import_environments_path);
+                let path = path.trim().trim_matches('"');
+                let mut environments = self.environments.lock().unwrap();
+                match environments.import(path) {
+                    Ok(count) => {
+                        info!("Imported {:} environments from {:}", count, path);
+                        if let Err(err) = environments.save() {
+                            self.report(err);
+                        }
+                    }
+                    Err(err) => self.report(err),
+                }
+                self.modal = Modal::Environments;
+            }
+            KeyCode::Char(c) => self.import_environments_path.push(c),
+            KeyCode::Backspace => {
+                self.import_environments_path.pop();
+            }
+            _ => {}
+        };
+    }
+
+    fn handle_post_process_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => self.modal = Modal::None,
+            KeyCode::Char(c) => self.post_process.push(c),
+            KeyCode::Backspace => {
+                self.post_process.pop();
+            }
+            _ => {}
+        };
+    }
+
+    /// The base URL to replace and its replacement, once both have been typed.
+    pub fn retarget_bases(&self) -> Option<(&str, &str)> {
+        let mut parts = self.retarget.split_whitespace();
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(from), Some(to), None) => Some((from, to)),
+            _ => None,
+        }
+    }
+
+    /// The changes are shown as the bases are typed, and only made on enter.
+    fn handle_retarget_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                let (from, to) = match self.retarget_bases() {
+                    Some((from, to)) => (from.to_string(), to.to_string()),
+                    None => return,
+                };
+                let count = self.request_collection.retarget(from.as_str(), to.as_str());
+                info!("Retargeted {:} requests from {:} to {:}", count, from, to);
+                if count > 0 {
+                    if let Err(err) = self.request_collection.save() {
+                        self.report(err);
+                    }
+                }
+                self.retarget.clear();
+                self.modal = Modal::Requests;
+            }
+            KeyCode::Char(c) => self.retarget.push(c),
+            KeyCode::Backspace => {
+                self.retarget.pop();
+            }
+            _ => {}
+        };
+    }
+
+    /// The preview is updated as the filter is typed.
+    fn update_key_matches(&mut self) {
+        self.key_matches = suggest::key_paths(&self.key_paths, self.key_query.as_str());
+        self.key_match_state
+            .select((!self.key_matches.is_empty()).then_some(0));
+    }
+
+    /// The matching key paths are updated as the text is typed, and the selected one is gone to.
+    fn handle_go_to_key_input(&mut self, key: KeyEvent) {
+        let selected = self.key_match_state.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Enter => {
+                self.modal = Modal::None;
+                if let Some((_, line)) = self.key_matches.get(selected) {
+                    self.response_paragraph.lock().unwrap().go_to_line(*line);
+                }
+                self.key_paths.clear();
+                self.key_matches.clear();
+            }
+            KeyCode::Up => self
+                .key_match_state
+                .select(Some(selected.saturating_sub(1))),
+            KeyCode::Down => self.key_match_state.select(Some(
+                (selected + 1).min(self.key_matches.len().saturating_sub(1)),
+            )),
+            KeyCode::Char(c) => {
+                self.key_query.push(c);
+                self.update_key_matches();
+            }
+            KeyCode::Backspace => {
+                self.key_query.pop();
+                self.update_key_matches();
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_metrics_filter_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => self.modal = Modal::None,
+            KeyCode::Char(c) => self.metrics_filter.push(c),
+            KeyCode::Backspace => {
+                self.metrics_filter.pop();
+            }
+            _ => {}
+        };
+        self.response_split = true;
+        self.response_preview_len = None;
+    }
+
+    /// Cookies from a header are for the host of the URL being edited. Importing nothing clears
+    /// the cookies.
+    fn handle_import_cookies_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                let input = std::mem::take(&mut self.import_cookies);
+                let input = input.trim();
+                self.modal = Modal::None;
+                let mut cookies = self.cookies.lock().unwrap();
+                if input.is_empty() {
+                    cookies.clear();
+                    info!("Cleared the imported cookies");
+                    return;
+                }
+                // Paths copied from Windows Explorer are quoted.
+                let path = Path::new(input.trim_matches('"'));
+                let text = if path.is_file() {
+                    match std::fs::read_to_string(path) {
+                        Ok(text) => text,
+                        Err(err) => {
+                            drop(cookies);
+                            self.report(err.into());
+                            return;
+                        }
+                    }
+                } else {
+                    input.to_string()
+                };
+                let url = substitute(
+                    self.url.as_str(),
+                    self.environments.lock().unwrap().active(),
+                );
+                let imported = cookies.import(text.as_str(), url.as_str());
+                drop(cookies);
+                match imported {
+                    Ok(count) => info!("Imported {:} cookies", count),
+                    Err(err) => self.report(err),
+                }
+            }
+            KeyCode::Char(c) => self.import_cookies.push(c),
+            KeyCode::Backspace => {
+                self.import_cookies.pop();
+            }
+            _ => {}
+        };
+    }
+
+    fn handle_import_session_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                let path = std::mem::take(&mut self.import_session_path);
+                self.modal = Modal::None;
+                if let Err(err) = self.import_session(path.trim().trim_matches('"')) {
+                    self.report(err);
+                }
+            }
+            KeyCode::Char(c) => self.import_session_path.push(c),
+            KeyCode::Backspace => {
+                self.import_session_path.pop();
+            }
+            _ => {}
+        };
+    }
+
+    /// A pasted command arrives as keys, so a line ending with `\` continues on the next line
+    /// rather than importing.
+    fn handle_import_curl_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter if self.import_curl.trim_end().ends_with('\\') => {
+                self.import_curl.push('\n')
+            }
+            KeyCode::Enter => {
+                let command = std::mem::take(&mut self.import_curl);
+                self.modal = Modal::None;
+                match curl::parse(command.as_str()) {
+                    Ok(request) => self.import_curl_request(request),
+                    Err(err) => self.report(err),
+                }
+            }
+            KeyCode::Char(c) => self.import_curl.push(c),
+            KeyCode::Backspace => {
+                self.import_curl.pop();
+            }
+            _ => {}
+        };
+    }
+
+    /// Fill the editors from a curl command, keeping the request which was in them as the
+    /// alternate request.
+    fn import_curl_request(&mut self, request: CurlRequest) {
+        self.alternate_request = Some(self.take_editor());
+        self.method = request.method;
+        self.url.set_value(request.url);
+        self.headers.set_value(request.headers);
+        self.body.set_value(request.body);
+        self.body_type = request.body_type;
+        self.request_proxy = request.proxy;
+        self.set_view(View::Request);
+        self.mode = Mode::Url;
+    }
+
+    fn handle_response_body_input(&mut self, key: KeyEvent) {
+        const MAX_BOOKMARKS: usize = 9;
+
+        if self.show_response_tree {
+            match key.code {
+                KeyCode::Char('J') => self.show_response_tree = false,
+                KeyCode::Char('p') => {
+                    if let Some(path) = self.response_tree.selected_path() {
+                        clipboard::copy(path.as_str());
+                    }
+                }
+                _ => self.response_tree.handle_input(key),
+            }
+            return;
+        }
+
+        let mut paragraph = self.response_paragraph.lock().unwrap();
+        if paragraph.handle_search_input(key) {
+            return;
+        }
+        match key.code {
+            KeyCode::Char('b') => {
+                if !self.response_bookmarks.contains(&paragraph.scroll)
+                    && self.response_bookmarks.len() < MAX_BOOKMARKS
+                {
+                    self.response_bookmarks.push(paragraph.scroll);
+                }
+            }
+            KeyCode::Char(c @ '1'..='9') => {
+                let index = c as usize - '1' as usize;
+                if let Some(scroll) = self.response_bookmarks.get(index) {
+                    paragraph.scroll = *scroll;
+                    paragraph.follow = false;
+                }
+            }
+            // Jump to the nearest bookmark below the current position, wrapping to the top.
+            KeyCode::Char('n') => {
+                let mut sorted = self.response_bookmarks.clone();
+                sorted.sort_unstable();
+                if let Some(scroll) = sorted
+                    .iter()
+                    .find(|scroll| **scroll > paragraph.scroll)
+                    .or_else(|| sorted.first())
+                {
+                    paragraph.scroll = *scroll;
+                    paragraph.follow = false;
+                }
+            }
+            KeyCode::Char('p') => match path_at_line(paragraph.as_str(), paragraph.cursor()) {
+                Some(path) => clipboard::copy(json_path::format(&path).as_str()),
+                None => info!("No JSON value on line {:}", paragraph.cursor()),
+            },
+            KeyCode::Char('t') | KeyCode::Char('u') => {
+                if key.code == KeyCode::Char('t') {
+                    self.settings.humanize_timestamps = !self.settings.humanize_timestamps;
+                } else {
+                    self.settings.number_hints = !self.settings.number_hints;
+                }
+                if let Err(err) = self.settings.save() {
+                    self.report(err);
+                }
+                let content_type = self.content_type.lock().unwrap();
+                paragraph.set_line_transform(line_transform(&content_type, &self.settings));
+            }
+            KeyCode::Char('x') => {
+                let value = path_at_line(paragraph.as_str(), paragraph.cursor()).and_then(|path| {
+                    let document: serde_json::Value =
+                        serde_json::from_str(paragraph.as_str()).ok()?;
+                    json_path::get(&document, json_path::format(&path).as_str())
+                        .map(json_path::value_to_string)
+                });
+                match value {
+                    Some(value) if self.environments.lock().unwrap().active().is_some() => {
+                        self.extract_value = value;
+                        self.modal = Modal::ExtractVariable;
+                    }
+                    Some(_) => error!("Select an environment to save the value to"),
+                    None => info!("No JSON value on line {:}", paragraph.cursor()),
+                }
+            }
+            KeyCode::Char('F') => paragraph.toggle_follow(),
+            KeyCode::Char('J') => {
+                self.response_tree.update(paragraph.as_str());
+                if self.response_tree.is_json() {
+                    self.show_response_tree = true;
+                } else {
+                    info!("The response isn't JSON");
+                }
+            }
+            KeyCode::Char('g') => {
+                self.key_paths = key_paths(paragraph.as_str());
+                drop(paragraph);
+                if self.key_paths.is_empty() {
+                    info!("No JSON keys in the response");
+                    return;
+                }
+                self.key_query.clear();
+                self.update_key_matches();
+                self.modal = Modal::GoToKey;
+            }
+            KeyCode::Char('c') => {
+                drop(paragraph);
+                self.cycle_content_type();
+            }
+            KeyCode::Char('f') => {
+                if is_metrics_content_type(&self.content_type.lock().unwrap()) {
+                    self.modal = Modal::MetricsFilter;
+                }
+            }
+            _ => paragraph.handle_input(key),
+        }
+    }
+
+    /// Show the response as the next of the known content types, for when it was detected
+    /// wrongly or not at all.
+    fn cycle_content_type(&mut self) {
+        let mut content_type = self.content_type.lock().unwrap();
+        let next = CONTENT_TYPES
+            .iter()
+            .position(|item| content_type.starts_with(item))
+            .map_or(0, |index| (index + 1) % CONTENT_TYPES.len());
+        *content_type = CONTENT_TYPES[next].to_string();
+        self.content_type_overridden = true;
+
+        let mut paragraph = self.response_paragraph.lock().unwrap();
+        let formatted = formatters::find(&content_type, &self.settings)
+            .and_then(|formatter| (formatter.format)(paragraph.as_str().as_bytes()));
+        if let Some(formatted) = formatted {
+            paragraph.set_value(formatted);
+        }
+        paragraph.set_line_transform(line_transform(&content_type, &self.settings));
+        self.response_preview_len = None;
+    }
+
+    fn dismiss_error(&mut self) {
+        let mut errors = self.errors.lock().unwrap();
+        if !errors.is_empty() {
+            errors.remove(0);
+        }
+        if errors.is_empty() {
+            self.modal = self.modal_before_error;
+        }
+    }
+
+    fn dismiss_notice(&mut self) {
+        if !self.notices.is_empty() {
+            self.notices.remove(0);
+        }
+        if self.notices.is_empty() {
+            self.modal = self.modal_before_notice;
+        }
+    }
+
+    /// Tell the user about the outcome of an action, once any errors have been shown.
+    fn notify(&mut self, message: String) {
+        info!("{:}", message);
+        self.notices.push(message);
+    }
+
+    /// Insert text from the clipboard, or pasted into the terminal, into the URL or the focused
+    /// editor. Newlines are left out of the URL, so pasting one doesn't send the request. Returns
+    /// false when neither is focused.
+    pub fn paste(&mut self, text: &str) -> bool {
+        if self.modal != Modal::None {
+            return false;
+        }
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let text = match self.mode {
+            Mode::Url => text.lines().map(str::trim).collect(),
+            _ => text,
+        };
+        match self.editor(self.mode) {
+            Some(editor) => editor.insert_str(text.as_str()),
+            None => return false,
+        }
+        self.update_suggestions();
+        true
+    }
+
+    /// Replace the request body with what it was edited to in the user's editor. This runs the
+    /// editor, so the terminal must have been given up to it.
+    pub fn edit_body_externally(&mut self) {
+        match external_editor::edit(self.body.as_str()) {
+            Ok(body) => {
+                // Editors usually end the file with a newline, which the body didn't have.
+                let body = match self.body.as_str().ends_with('\n') {
+                    true => body,
+                    false => body.trim_end_matches(['\r', '\n']).to_string(),
+                };
+                if body != self.body.as_str() {
+                    self.body.set_value(body);
+                }
+            }
+            Err(err) => self.report(err),
+        }
+        self.dirty.store(true, Ordering::SeqCst);
+    }
+
+    /// Show an error to the user.
+    pub fn report(&self, err: Error) {
+        report(&self.errors, err);
+    }
+
+    fn handle_url_input(&mut self, event: KeyEvent) {
+        if event.code == KeyCode::Enter {
+            self.send_request();
+            return;
+        }
+        match event.code {
+            KeyCode::Right if self.url.at_line_end() && !self.suggestions.is_empty() => {
+                self.accept_suggestion()
+            }
+            KeyCode::Right => self.url.handle_command(EditCommand::ForwardCursor),
+            KeyCode::Left => self.url.handle_command(EditCommand::BackwardCursor),
+            KeyCode::Backspace => self.url.handle_command(EditCommand::BackwardDelete),
+            KeyCode::Delete => self.url.handle_command(EditCommand::ForwardDelete),
+            KeyCode::Char(c) => self.url.handle_command(EditCommand::InsertCharacter(c)),
+            _ => {}
+        };
+    }
+
+    fn handle_method_input(&mut self, event: KeyEvent) {
+        match event.code {
+            KeyCode::Up => self.next_method(true),
+            KeyCode::Down | KeyCode::Char(' ') => self.next_method(false),
+            KeyCode::Enter => self.send_request(),
+            // Typing a name starts entering a custom method.
+            KeyCode::Char(c) if c.is_ascii_alphabetic() => {
+                self.method_input = c.to_ascii_uppercase().to_string();
+                self.modal = Modal::CustomMethod;
+            }
+            _ => {}
+        };
+    }
+
+    fn handle_request_body_input(&mut self, event: KeyEvent) {
+        match event.code {
+            KeyCode::Right => self.body.handle_command(EditCommand::ForwardCursor),
+            KeyCode::Left => self.body.handle_command(EditCommand::BackwardCursor),
+            KeyCode::Backspace => self.body.handle_command(EditCommand::BackwardDelete),
+            KeyCode::Delete => self.body.handle_command(EditCommand::ForwardDelete),
+            KeyCode::Char(c) => self.body.handle_command(EditCommand::InsertCharacter(c)),
+            KeyCode::Enter => {
+                self.body.handle_command(EditCommand::InsertCharacter('\n'));
+            }
+            KeyCode::Up => self.body.handle_command(EditCommand::UpCursor),
+            KeyCode::Down => self.body.handle_command(EditCommand::DownCursor),
+            _ => {}
+        };
+    }
+
+    fn handle_request_headers_input(&mut self, event: KeyEvent) {
+        match event.code {
+            KeyCode::Right if self.headers.at_line_end() && !self.suggestions.is_empty() => {
+                self.accept_suggestion()
+            }
+            KeyCode::Right => self.headers.handle_command(EditCommand::ForwardCursor),
+            KeyCode::Left => self.headers.handle_command(EditCommand::BackwardCursor),
+            KeyCode::Backspace => self.headers.handle_command(EditCommand::BackwardDelete),
+            KeyCode::Delete => self.headers.handle_command(EditCommand::ForwardDelete),
+            KeyCode::Char(c) => self.headers.handle_command(EditCommand::InsertCharacter(c)),
+            KeyCode::Enter => {
+                self.headers
+                    .handle_command(EditCommand::InsertCharacter('\n'));
+            }
+            KeyCode::Up => self.headers.handle_command(EditCommand::UpCursor),
+            KeyCode::Down => self.headers.handle_command(EditCommand::DownCursor),
+            _ => {}
+        };
+    }
+
+    fn handle_request_params_input(&mut self, event: KeyEvent) {
+        match event.code {
+            KeyCode::Right => self.params.handle_command(EditCommand::ForwardCursor),
+            KeyCode::Left => self.params.handle_command(EditCommand::BackwardCursor),
+            KeyCode::Backspace => self.params.handle_command(EditCommand::BackwardDelete),
+            KeyCode::Delete => self.params.handle_command(EditCommand::ForwardDelete),
+            KeyCode::Char(c) => self.params.handle_command(EditCommand::InsertCharacter(c)),
+            KeyCode::Enter => {
+                self.params
+                    .handle_command(EditCommand::InsertCharacter('\n'));
+            }
+            KeyCode::Up => self.params.handle_command(EditCommand::UpCursor),
+            KeyCode::Down => self.params.handle_command(EditCommand::DownCursor),
+            _ => {}
+        };
+    }
+
+    fn reset(&mut self) {
+        {
+            let mut response_paragraph = self.response_paragraph.lock().unwrap();
+            response_paragraph.reset();
+            response_paragraph.set_line_transform(None);
+            response_paragraph.follow = self.settings.follow_streams;
+        }
+        self.response_header_paragraph.lock().unwrap().reset();
+        *self.response.lock().unwrap() = None;
+        *self.proxy.lock().unwrap() = None;
+        self.status.store(0, Ordering::SeqCst);
+        self.content_type.lock().unwrap().clear();
+        self.content_type_detected.store(false, Ordering::SeqCst);
+        self.content_type_overridden = false;
+        self.response_preview_len = None;
+        self.response_bookmarks.clear();
+        self.response_tree.reset();
+        self.steps.lock().unwrap().clear();
+        self.sent_request.lock().unwrap().clear();
+        self.assertion_outcomes.lock().unwrap().clear();
+        *self.download.lock().unwrap() = None;
+        // Detach any request still streaming so it can't write into the cleared panes.
+        self.request_id.fetch_add(1, Ordering::SeqCst);
+        self.in_flight.store(false, Ordering::SeqCst);
+    }
+
+    /// Stop the request handler, cancelling any response still streaming, then wait a short time
+    /// for the request tasks to write the responses they completed to the history.
+    pub async fn shutdown(&mut self, handler: JoinHandle<()>) {
+        self.request_id.fetch_add(1, Ordering::SeqCst);
+        let tasks = std::mem::take(&mut self.tasks);
+        let sender = self.sender.clone();
+        let finished = tokio::time::timeout(SHUTDOWN_TIMEOUT, async move {
+            if sender.send(WebRequest::Shutdown).await.is_ok() {
+                let _ = handler.await;
+            }
+            for task in tasks {
+                let _ = task.await;
+            }
+        })
+        .await;
+        if finished.is_err() {
+            error!("Timed out waiting for requests to finish before quitting");
+        }
+    }
+
+    /// Run a request task, keeping its handle to wait for when quitting. The handles of tasks
+    /// which have already finished are let go.
+    fn spawn_task(&mut self, task: impl Future<Output = ()> + Send + 'static) {
+        self.tasks.retain_mut(|task| !is_finished(task));
+        self.tasks.push(tokio::spawn(task));
+    }
+
+    /// Send OPTIONS to the current URL and report the methods and CORS it allows.
+    fn probe_options(&mut self) {
+        let template = self.current_template();
+        let (url, headers) = {
+            let environments = self.environments.lock().unwrap();
+            let environment = environments.active();
+            (
+                substitute(template.url.as_str(), environment),
+                substitute(template.headers.as_str(), environment),
+            )
+        };
+        let mut headers = self.cookies.lock().unwrap().add_to(headers, url.as_str());
+        let has_origin = headers.lines().any(|line| {
+            line.split_once(':')
+                .is_some_and(|(name, _)| name.trim().eq_ignore_ascii_case("origin"))
+        });
+        // With an origin, the request is a CORS preflight for the current method.
+        if has_origin {
+            let method = template.method.as_str();
+            headers.push_str(format!("\nAccess-Control-Request-Method: {:}", method).as_str());
+        }
+        let gate = self.probe_gate();
+        let sender = self.sender.clone();
+        let busy = self.handler_busy.clone();
+        let proxy = self.proxy_choice(&template);
+        self.run_probe(async move {
+            let (status, response_headers) = fetch_headers(
+                &gate,
+                &sender,
+                &busy,
+                Method::OPTIONS,
+                url.clone(),
+                headers,
+                proxy,
+            )
+            .await?;
+            let report = capability_report(url.as_str(), status, &response_headers, has_origin);
+            Ok((status.as_u16(), format!("{:?}", response_headers), report))
+        });
+    }
+
+    /// GET the standard paths which describe a service from the host of the current URL, and
+    /// list which respond.
+    fn probe_well_known(&mut self) {
+        let template = self.current_template();
+        let (url, headers) = {
+            let environments = self.environments.lock().unwrap();
+            let environment = environments.active();
+            (
+                substitute(template.url.as_str(), environment),
+                substitute(template.headers.as_str(), environment),
+            )
+        };
+        let origin = match origin(url.as_str()) {
+            Ok(origin) => origin,
+            Err(err) => {
+                self.report(err);
+                return;
+            }
+        };
+        let gate = self.probe_gate();
+        let sender = self.sender.clone();
+        let busy = self.handler_busy.clone();
+        let cookies = self.cookies.clone();
+        let proxy = self.proxy_choice(&template);
+        self.run_probe(async move {
+            let mut results = Vec::new();
+            for path in WELL_KNOWN_PATHS {
+                let url = format!("{:}{:}", origin, path);
+                let headers = cookies
+                    .lock()
+                    .unwrap()
+                    .add_to(headers.clone(), url.as_str());
+                let res = fetch_headers(
+                    &gate,
+                    &sender,
+                    &busy,
+                    Method::GET,
+                    url,
+                    headers,
+                    proxy.clone(),
+                )
+                .await;
+                results.push((path, res));
+            }
+            Ok((
+                0,
+                "".to_string(),
+                well_known_report(origin.as_str(), &results),
+            ))
+        });
+    }
+
+    fn handle_oidc_issuer_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                let issuer = std::mem::take(&mut self.oidc_issuer);
+                self.modal = Modal::None;
+                if !issuer.trim().is_empty() {
+                    self.discover_oidc(issuer.trim());
+                }
+            }
+            KeyCode::Char(c) => self.oidc_issuer.push(c),
+            KeyCode::Backspace => {
+                self.oidc_issuer.pop();
+            }
+            _ => {}
+        };
+    }
+
+    /// Send the request, writing the response body to the entered path as it arrives rather than
+    /// showing it.
+    fn handle_download_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                let path = std::mem::take(&mut self.download_path);
+                self.modal = Modal::None;
+                if !path.trim().is_empty() {
+                    let mut template = self.current_template();
+                    template.download = Some(path.trim().to_string());
+                    self.send_template(template);
+                }
+            }
+            KeyCode::Char(c) => self.download_path.push(c),
+            KeyCode::Backspace => {
+                self.download_path.pop();
+            }
+            _ => {}
+        };
+    }
+
+    /// Enter starts a new assertion, or on an empty line finishes editing.
+    fn handle_assertions_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter
+                if self.assertions_input.is_empty() || self.assertions_input.ends_with('\n') =>
+            {
+                match assertion::parse(self.assertions_input.as_str()) {
+                    Ok(assertions) => {
+                        self.assertions = assertions;
+                        self.modal = Modal::None;
+                    }
+                    Err(err) => self.report(err),
+                }
+            }
+            KeyCode::Enter => self.assertions_input.push('\n'),
+            KeyCode::Char(c) => self.assertions_input.push(c),
+            KeyCode::Backspace => {
+                self.assertions_input.pop();
+            }
+            _ => {}
+        };
+    }
+
+    /// Enter starts a new capture, or on an empty line finishes editing.
+    fn handle_captures_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter
+                if self.captures_input.is_empty() || self.captures_input.ends_with('\n') =>
+            {
+                match capture::parse(self.captures_input.as_str()) {
+                    Ok(captures) => {
+                        self.captures = captures;
+                        self.modal = Modal::None;
+                    }
+                    Err(err) => self.report(err),
+                }
+            }
+            KeyCode::Enter => self.captures_input.push('\n'),
+            KeyCode::Char(c) => self.captures_input.push(c),
+            KeyCode::Backspace => {
+                self.captures_input.pop();
+            }
+            _ => {}
+        };
+    }
+
+    fn handle_help_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Up => self.help_scroll = self.help_scroll.saturating_sub(1),
+            KeyCode::Down => self.help_scroll = self.help_scroll.saturating_add(1),
+            KeyCode::PageUp => self.help_scroll = self.help_scroll.saturating_sub(10),
+            KeyCode::PageDown => self.help_scroll = self.help_scroll.saturating_add(10),
+            KeyCode::Home => self.help_scroll = 0,
+            _ => {}
+        }
+    }
+
+    fn handle_key_binds_input(&mut self, key: KeyEvent) {
+        let len = self.key_binds.len();
+        let selected = self.key_binds_selection_state.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Enter => self.rebinding = true,
+            KeyCode::Delete => {
+                let operation = self.key_binds[selected].operation;
+                let default = default_key_binds::default_key_binds()
+                    .into_iter()
+                    .find(|key_bind| key_bind.operation == operation);
+                if let Some(default) = default {
+                    self.set_key_bind(selected, default.modifiers, default.key);
+                }
+            }
+            KeyCode::Up => self
+                .key_binds_selection_state
+                .select(Some(Self::list_previous(len, selected))),
+            KeyCode::Down => self
+                .key_binds_selection_state
+                .select(Some(Self::list_next(len, selected))),
+            KeyCode::Home => self.key_binds_selection_state.select(Some(0)),
+            KeyCode::End => self
+                .key_binds_selection_state
+                .select(Some(len.saturating_sub(1))),
+            _ => {}
+        }
     }
 
-    fn save_request(&mut self) {
-        if self.url.is_empty() || self.request_name.is_empty() {
+    /// Bind the keys pressed to the operation selected in the key binds modal, unless they are
+    /// bound to another operation or needed for typing.
+    fn rebind(&mut self, key: KeyEvent) {
+        self.rebinding = false;
+        if key_bind::is_typing_key(key.modifiers, key.code) {
+            self.report(Error::Parse(format!(
+                "{:} is used for typing, bind keys with Ctrl or Alt, or a function key",
+                format_key(key)
+            )));
             return;
         }
-        let mut builder = crate::persistence::RequestBuilder::new(self.request_name.as_str());
-        builder.url(self.url.as_str());
-        builder.method(self.method);
-        builder.headers(self.headers.as_str());
-        builder.body(self.body.as_str());
-        self.request_collection.add_request(builder.build());
-        self.request_collection.save();
-        // TODO: Need to implement some error handling here.
-        self.modal = Modal::None;
+        let selected = self.key_binds_selection_state.selected().unwrap_or(0);
+        self.set_key_bind(selected, key.modifiers, key.code);
     }
 
-    fn handle_save_input(&mut self, key: KeyEvent) {
+    /// Change the keys of a key bind, unless they are bound to another operation. The change is
+    /// kept in the settings unless they are the default keys.
+    fn set_key_bind(&mut self, index: usize, modifiers: KeyModifiers, key: KeyCode) {
+        if let Some(other) = key_bind::bound_to(&self.key_binds, modifiers, key) {
+            if other != self.key_binds[index].operation {
+                self.report(Error::Parse(format!(
+                    "{:} is already bound to {:}",
+                    format_key(KeyEvent::new(key, modifiers)),
+                    key_bind::operation_name(other)
+                )));
+                return;
+            }
+        }
+        let key_bind = &mut self.key_binds[index];
+        key_bind.modifiers = modifiers;
+        key_bind.key = key;
+        let name = key_bind::operation_key(key_bind.operation);
+        let is_default = default_key_binds::default_key_binds()
+            .iter()
+            .any(|default| {
+                default.operation == key_bind.operation
+                    && default.modifiers == modifiers
+                    && default.key == key
+            });
+        if is_default {
+            self.settings.key_binds.remove(&name);
+        } else {
+            self.settings
+                .key_binds
+                .insert(name, format_key(KeyEvent::new(key, modifiers)));
+        }
+        if let Err(err) = self.settings.save() {
+            self.report(err);
+        }
+    }
+
+    fn handle_timeout_input(&mut self, key: KeyEvent) {
         match key.code {
-            KeyCode::Enter => self.save_request(),
-            KeyCode::Char(c) => {
-                self.request_name.push(c);
+            KeyCode::Enter => {
+                let input = std::mem::take(&mut self.timeout_input);
+                self.modal = Modal::None;
+                if input.trim().is_empty() {
+                    self.timeout_secs = None;
+                    return;
+                }
+                match input.trim().parse::<u64>() {
+                    Ok(timeout) if timeout > 0 => self.timeout_secs = Some(timeout),
+                    _ => self.report(Error::Parse(format!(
+                        "The timeout must be a number of seconds, not {:}",
+                        input.trim()
+                    ))),
+                }
             }
+            KeyCode::Char(c) => self.timeout_input.push(c),
             KeyCode::Backspace => {
-                self.request_name.pop();
+                self.timeout_input.pop();
             }
             _ => {}
         };
     }
 
-    fn handle_request_input(&mut self, key: KeyEvent) {
+    /// The names are separated by commas or spaces, and are kept in the order given.
+    fn handle_prompts_input(&mut self, key: KeyEvent) {
         match key.code {
             KeyCode::Enter => {
-                let index = self.request_selection_state.selected().unwrap_or(0);
-
-                self.reset();
-                let request = &self.request_collection.requests[index];
-
-                self.url.set_value(request.url.clone());
-                self.method = request.method;
-                self.request_name = request.key.clone();
-                if let Some(body) = &request.body {
-                    self.body.set_value(body.clone());
+                let input = std::mem::take(&mut self.prompts_input);
+                self.modal = Modal::None;
+                self.prompts.clear();
+                for name in input.split(|c: char| c == ',' || c.is_whitespace()) {
+                    if !name.is_empty() && !self.prompts.iter().any(|prompt| prompt == name) {
+                        self.prompts.push(name.to_string());
+                    }
                 }
+            }
+            KeyCode::Char(c) => self.prompts_input.push(c),
+            KeyCode::Backspace => {
+                self.prompts_input.pop();
+            }
+            _ => {}
+        };
+    }
 
-                self.headers.set_value(request.headers_to_string());
-
+    fn handle_proxy_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                let input = std::mem::take(&mut self.proxy_input);
                 self.modal = Modal::None;
+                self.request_proxy = (!input.trim().is_empty()).then(|| input.trim().to_string());
             }
-            KeyCode::Up => self
-                .request_selection_state
-                .select(Some(Self::list_previous(
-                    self.request_collection.requests.len(),
-                    self.request_selection_state.selected().unwrap_or(0),
-                ))),
-            KeyCode::Down => self.request_selection_state.select(Some(Self::list_next(
-                self.request_collection.requests.len(),
-                self.request_selection_state.selected().unwrap_or(0),
-            ))),
-            KeyCode::Delete => {
-                if let Some(index) = self.request_selection_state.selected() {
-                    self.request_collection.remove_request(index);
-                    self.request_collection.save();
-                    if index > 0 {
-                        self.request_selection_state.select(Some(index - 1));
-                    }
-                    if self.request_collection.requests.len() == 0 {
-                        self.modal = Modal::None;
-                    }
-                }
+            KeyCode::Char(c) => self.proxy_input.push(c),
+            KeyCode::Backspace => {
+                self.proxy_input.pop();
             }
             _ => {}
         };
     }
 
-    fn handle_url_input(&mut self, event: KeyEvent) {
-        if event.code == KeyCode::Enter {
-            self.make_request();
-            self.set_view(View::Response);
-            return;
-        }
-        match event.code {
-            KeyCode::Right => self.url.handle_command(EditCommand::ForwardCursor),
-            KeyCode::Left => self.url.handle_command(EditCommand::BackwardCursor),
-            KeyCode::Backspace => self.url.handle_command(EditCommand::BackwardDelete),
-            KeyCode::Delete => self.url.handle_command(EditCommand::ForwardDelete),
-            KeyCode::Char(c) => self.url.handle_command(EditCommand::InsertCharacter(c)),
+    fn handle_custom_method_input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                let input = std::mem::take(&mut self.method_input);
+                self.modal = Modal::None;
+                if input.trim().is_empty() {
+                    return;
+                }
+                match reqwest::Method::from_bytes(input.trim().as_bytes()) {
+                    Ok(_) => self.method = Method::parse(input.as_str()),
+                    Err(_) => self.report(Error::Parse(format!(
+                        "{:} isn't a valid method",
+                        input.trim()
+                    ))),
+                }
+            }
+            KeyCode::Char(c) => self.method_input.push(c.to_ascii_uppercase()),
+            KeyCode::Backspace => {
+                self.method_input.pop();
+            }
             _ => {}
         };
     }
 
-    fn handle_request_body_input(&mut self, event: KeyEvent) {
-        match event.code {
-            KeyCode::Right => self.body.handle_command(EditCommand::ForwardCursor),
-            KeyCode::Left => self.body.handle_command(EditCommand::BackwardCursor),
-            KeyCode::Backspace => self.body.handle_command(EditCommand::BackwardDelete),
-            KeyCode::Delete => self.body.handle_command(EditCommand::ForwardDelete),
-            KeyCode::Char(c) => self.body.handle_command(EditCommand::InsertCharacter(c)),
+    /// The proxy to send a request through, from the request or the settings.
+    fn proxy_choice(&self, template: &RequestTemplate) -> ProxyChoice {
+        let environments = self.environments.lock().unwrap();
+        prepare::proxy_choice(template, environments.active(), &self.settings)
+    }
+
+    /// Fetch the discovery document of an issuer, which is then used to set up OAuth2 in the
+    /// active environment.
+    fn discover_oidc(&mut self, issuer: &str) {
+        let environment = match self.environments.lock().unwrap().active() {
+            Some(environment) => environment.name.clone(),
+            None => {
+                self.report(Error::Parse(
+                    "Select an environment to set up OAuth2 in".to_string(),
+                ));
+                return;
+            }
+        };
+        let url = oidc::discovery_url(issuer);
+        let gate = self.probe_gate();
+        let sender = self.sender.clone();
+        let busy = self.handler_busy.clone();
+        let discovered = self.oidc_discovery.clone();
+        let dirty = self.dirty.clone();
+        let proxy = self.proxy_choice(&self.current_template());
+        self.run_probe(async move {
+            let (status, body) = fetch(&gate, &sender, &busy, url.clone(), proxy).await?;
+            if !status.is_success() {
+                return Err(Error::Parse(format!(
+                    "Fetching the discovery document {:} returned {:}",
+                    url, status
+                )));
+            }
+            let discovery: Discovery = serde_json::from_slice(&body)?;
+            let report = oidc::report(environment.as_str(), &discovery);
+            *discovered.lock().unwrap() = Some((environment, discovery));
+            dirty.store(true, Ordering::SeqCst);
+            Ok((status.as_u16(), "".to_string(), report))
+        });
+    }
+
+    /// Set up OAuth2 in an environment, and save the request which fetches its token.
+    fn apply_oidc_discovery(&mut self, environment: &str, discovery: &Discovery) {
+        {
+            let mut environments = self.environments.lock().unwrap();
+            match environments
+                .environments
+                .iter_mut()
+                .find(|existing| existing.name == environment)
+            {
+                Some(existing) => oidc::configure(existing, discovery),
+                None => return,
+            }
+            if let Err(err) = environments.save() {
+                drop(environments);
+                self.report(err);
+            }
+        }
+        self.request_collection
+            .add_request(oidc::token_request(environment));
+        if let Err(err) = self.request_collection.save() {
+            self.report(err);
+        }
+    }
+
+    fn handle_import_openapi_input(&mut self, key: KeyEvent) {
+        match key.code {
             KeyCode::Enter => {
-                self.body.handle_command(EditCommand::InsertCharacter('\n'));
+                let source = std::mem::take(&mut self.import_openapi_source);
+                self.modal = Modal::None;
+                let source = source.trim().trim_matches('"');
+                if !source.is_empty() {
+                    self.import_openapi(source.to_string());
+                }
+            }
+            KeyCode::Char(c) => self.import_openapi_source.push(c),
+            KeyCode::Backspace => {
+                self.import_openapi_source.pop();
             }
-            KeyCode::Up => self.body.handle_command(EditCommand::UpCursor),
-            KeyCode::Down => self.body.handle_command(EditCommand::DownCursor),
             _ => {}
         };
     }
 
-    fn handle_request_headers_input(&mut self, event: KeyEvent) {
-        match event.code {
-            KeyCode::Right => self.headers.handle_command(EditCommand::ForwardCursor),
-            KeyCode::Left => self.headers.handle_command(EditCommand::BackwardCursor),
-            KeyCode::Backspace => self.headers.handle_command(EditCommand::BackwardDelete),
-            KeyCode::Delete => self.headers.handle_command(EditCommand::ForwardDelete),
-            KeyCode::Char(c) => self.headers.handle_command(EditCommand::InsertCharacter(c)),
+    /// Read an OpenAPI document from a file or URL in the background, which is then imported as
+    /// a saved request for each operation.
+    fn import_openapi(&mut self, source: String) {
+        let gate = self.probe_gate();
+        let sender = self.sender.clone();
+        let busy = self.handler_busy.clone();
+        let imported = self.openapi_import.clone();
+        let dirty = self.dirty.clone();
+        let proxy = self.proxy_choice(&self.current_template());
+        self.run_probe(async move {
+            let (status, text) = if source.starts_with("http://") || source.starts_with("https://")
+            {
+                let (status, body) = fetch(&gate, &sender, &busy, source.clone(), proxy).await?;
+                if !status.is_success() {
+                    return Err(Error::Parse(format!(
+                        "Fetching the OpenAPI document {:} returned {:}",
+                        source, status
+                    )));
+                }
+                (status.as_u16(), String::from_utf8_lossy(&body).to_string())
+            } else {
+                (0, tokio::fs::read_to_string(source.as_str()).await?)
+            };
+            let import = openapi::import(&openapi::parse(text.as_str())?, Some(source.as_str()))?;
+            let report = openapi::report(&import);
+            *imported.lock().unwrap() = Some(import);
+            dirty.store(true, Ordering::SeqCst);
+            Ok((status, "".to_string(), report))
+        });
+    }
+
+    /// Save the imported requests, and set the base URL in the active environment if it isn't
+    /// set yet.
+    fn apply_openapi_import(&mut self, import: openapi::Import) {
+        if let Some(server) = &import.server {
+            let mut environments = self.environments.lock().unwrap();
+            let updated = match environments.active_mut() {
+                Some(environment) if environment.get(openapi::BASE_URL).is_none() => {
+                    environment.set(openapi::BASE_URL, server.as_str());
+                    true
+                }
+                _ => false,
+            };
+            if updated {
+                if let Err(err) = environments.save() {
+                    drop(environments);
+                    self.report(err);
+                }
+            }
+        }
+        for request in import.requests {
+            self.request_collection.add_request(request);
+        }
+        if let Err(err) = self.request_collection.save() {
+            self.report(err);
+        }
+    }
+
+    fn handle_import_insomnia_input(&mut self, key: KeyEvent) {
+        match key.code {
             KeyCode::Enter => {
-                self.headers
-                    .handle_command(EditCommand::InsertCharacter('\n'));
+                let path = std::mem::take(&mut self.import_insomnia_path);
+                self.modal = Modal::None;
+                let path = path.trim().trim_matches('"');
+                if !path.is_empty() {
+                    let imported = std::fs::read_to_string(path)
+                        .map_err(Error::from)
+                        .and_then(|text| insomnia::import(text.as_str()));
+                    match imported {
+                        Ok(import) => self.apply_insomnia_import(import),
+                        Err(err) => self.report(err),
+                    }
+                }
+            }
+            KeyCode::Char(c) => self.import_insomnia_path.push(c),
+            KeyCode::Backspace => {
+                self.import_insomnia_path.pop();
             }
-            KeyCode::Up => self.headers.handle_command(EditCommand::UpCursor),
-            KeyCode::Down => self.headers.handle_command(EditCommand::DownCursor),
             _ => {}
         };
     }
 
-    fn reset(&mut self) {
-        self.response_paragraph.lock().unwrap().reset();
-        self.response_header_paragraph.lock().unwrap().reset();
-        *self.response.lock().unwrap() = None;
+    /// Save the imported requests and environments, merging environments into existing ones with
+    /// the same name, and show what was imported.
+    fn apply_insomnia_import(&mut self, import: insomnia::Import) {
+        let report = insomnia::report(&import);
+        if !import.environments.is_empty() {
+            let mut environments = self.environments.lock().unwrap();
+            for environment in import.environments {
+                environments.add(environment);
+            }
+            if let Err(err) = environments.save() {
+                drop(environments);
+                self.report(err);
+            }
+        }
+        for request in import.requests {
+            self.request_collection.add_request(request);
+        }
+        if let Err(err) = self.request_collection.save() {
+            self.report(err);
+        }
+        self.run_probe(async move { Ok((0, "".to_string(), report)) });
+    }
+
+    /// The rules a probe's requests are held to, with the active environment.
+    fn probe_gate(&self) -> Gate {
+        Gate::new(&self.settings, self.environments.lock().unwrap().active())
+    }
+
+    /// Run a probe in the background, then show its report in the response body in place of a
+    /// response. The probe gives the status and headers to show, and the report.
+    fn run_probe<F>(&mut self, probe: F)
+    where
+        F: Future<Output = Result<(u16, String, String)>> + Send + 'static,
+    {
+        self.reset();
+        let request_id = self.request_id.clone();
+        let id = self.request_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let in_flight = self.in_flight.clone();
+        self.in_flight.store(true, Ordering::SeqCst);
+        self.request_started = Instant::now();
+        self.elapsed_drawn = 0;
+        let status = self.status.clone();
+        let res_paragraph = self.response_paragraph.clone();
+        let response_header_paragraph = self.response_header_paragraph.clone();
+        let errors = self.errors.clone();
+        let dirty = self.dirty.clone();
+        self.set_view(View::Response);
+        self.mode = Mode::ResponseBody;
+
+        self.spawn_task(async move {
+            let res = probe.await;
+            if request_id.load(Ordering::SeqCst) != id {
+                return;
+            }
+            match res {
+                Ok((probe_status, headers, report)) => {
+                    status.store(probe_status, Ordering::SeqCst);
+                    let headers = jsonxf::pretty_print(headers.as_str()).unwrap_or(headers);
+                    response_header_paragraph.lock().unwrap().set_value(headers);
+                    res_paragraph.lock().unwrap().set_value(report);
+                }
+                Err(err) => report(&errors, err),
+            }
+            in_flight.store(false, Ordering::SeqCst);
+            dirty.store(true, Ordering::SeqCst);
+        });
     }
 
-    pub fn make_request(&mut self) {
+    pub fn make_request(&mut self, template: RequestTemplate) {
         self.reset();
         let sender = self.sender.clone();
-        let method = self.method;
-        let url = String::from(self.url.as_str());
+        let download = template.download.clone();
+        self.in_flight_timeout_secs = template
+            .timeout(&self.settings)
+            .map(|timeout| timeout.as_secs());
+        *self.download.lock().unwrap() = download.as_deref().map(Download::new);
+        let app_download = self.download.clone();
         let response = self.response.clone();
         let res_paragraph = self.response_paragraph.clone();
-        let headers = String::from(self.headers.as_str());
-        let body = String::from(self.body.as_str());
         let dirty = self.dirty.clone();
         let response_header_paragraph = self.response_header_paragraph.clone();
         let app_status = self.status.clone();
+        let post_process_command = template.post_process.clone();
+        let assertions = template.assertions.clone();
+        let assertion_outcomes = self.assertion_outcomes.clone();
+        let captures = template.captures.clone();
+        let app_proxy = self.proxy.clone();
+        let history = self.history.clone();
+        let settings = self.settings.clone();
+        let environments = self.environments.clone();
+        let response_complete = self.response_complete.clone();
+        let app_content_type = self.content_type.clone();
+        let content_type_detected = self.content_type_detected.clone();
+        let errors = self.errors.clone();
+        let handler_busy = self.handler_busy.clone();
+        let cookies = self.cookies.clone();
+        let steps = self.steps.clone();
+        let sent_request = self.sent_request.clone();
+        self.response_complete.store(false, Ordering::SeqCst);
+        let request_id = self.request_id.clone();
+        let id = self.request_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let handler_id = self.handler_id.clone();
+        let in_flight = self.in_flight.clone();
+        self.in_flight.store(true, Ordering::SeqCst);
+        self.request_started = Instant::now();
+        self.elapsed_drawn = 0;
+        let refresh = self
+            .environments
+            .lock()
+            .unwrap()
+            .active()
+            .and_then(|environment| {
+                if environment.oauth2.is_some() {
+                    return Some(Refresher::OAuth2);
+                }
+                let refresh = environment.token_refresh.clone()?;
+                self.request_collection
+                    .requests
+                    .iter()
+                    .find(|request| request.key == refresh.request)
+                    .cloned()
+                    .map(|request| Refresher::Request(refresh, Box::new(request)))
+            });
 
-        tokio::spawn(async move {
-            let (tx, mut rx) = mpsc::channel(10);
-            // This isn't the most elegant solution, but we just send a cancel before the operation
-            // and this breaks us out of the previous request one was still streaming a body. This
-            // would be especially common for an SSE stream.
-            sender.send(WebRequest::Cancel).await.unwrap();
-            sender
-                .send(WebRequest::Request(Request {
-                    method,
-                    url,
-                    headers,
+        self.spawn_task(async move {
+            // A newer request may have been sent while this one was streaming, in which case its
+            // response belongs in the history only and must not touch the panes.
+            let is_current = move || request_id.load(Ordering::SeqCst) == id;
+            let mut refreshed = false;
+            let mut attempts = 0;
+            let started = Instant::now();
+            loop {
+                // Refresh a token which is about to expire before it is rejected.
+                if let (Some(refresh), false) = (&refresh, refreshed) {
+                    let window = settings.token_refresh_window_secs;
+                    let expiring = match refresh {
+                        // There may be no token yet, which is fetched before the first request.
+                        Refresher::OAuth2 => environments
+                            .lock()
+                            .unwrap()
+                            .active()
+                            .is_some_and(|environment| oauth2::needs_token(environment, window)),
+                        Refresher::Request(..) => {
+                            window > 0
+                                && environments
+                                    .lock()
+                                    .unwrap()
+                                    .active()
+                                    .and_then(token_expiry)
+                                    .is_some_and(|expiry| expiry <= crate::history::now() + window)
+                        }
+                    };
+                    if expiring {
+                        refreshed = true;
+                        info!("Token expiring, refreshing with {:}", refresh.name());
+                        let steps = is_current().then(|| (steps.as_ref(), started));
+                        refresh_step(
+                            &sender,
+                            &handler_busy,
+                            refresh,
+                            &environments,
+                            &settings,
+                            steps,
+                        )
+                        .await;
+                        dirty.store(true, Ordering::SeqCst);
+                    }
+                }
+                // The environment is copied, since the pre-send hook is waited for while preparing.
+                let environment = environments.lock().unwrap().active().cloned();
+                let prepared =
+                    prepare_request(&template, environment.as_ref(), &settings, Some(&cookies))
+                        .await;
+                let prepared = match prepared {
+                    Ok(prepared) => prepared,
+                    Err(err) => {
+                        if is_current() {
+                            report(&errors, err);
+                        }
+                        break;
+                    }
+                };
+                if is_current() {
+                    *sent_request.lock().unwrap() = annotate(
+                        &prepared.method,
+                        template.url.as_str(),
+                        template.headers.as_str(),
+                        environment.as_ref(),
+                        &prepared.added,
+                        prepared.url.as_str(),
+                        prepared.headers.as_str(),
+                        &settings,
+                    );
+                }
+                let mut entry = prepared.entry;
+
+                let (tx, mut rx) = mpsc::channel(10);
+                let sent_id = next_request_id();
+                let request = WebRequest::Request(Box::new(Request {
+                    id: sent_id,
+                    method: prepared.method,
+                    url: prepared.url,
+                    headers: prepared.headers,
                     resp: tx,
-                    body,
-                }))
-                .await
-                .unwrap();
+                    body: prepared.body,
+                    multipart: prepared.multipart,
+                    body_file: prepared.body_file,
+                    download: download.clone(),
+                    proxy: prepared.proxy,
+                    timeout: prepared.timeout,
+                    redirects: settings.redirects,
+                }));
+                let step_start = Instant::now();
+                // Sending again cancels the request this tab sent before, which may still be
+                // streaming its body, like an SSE stream.
+                let previous = match is_current() {
+                    true => handler_id.swap(sent_id, Ordering::SeqCst),
+                    false => 0,
+                };
+                let queued = match previous {
+                    0 => queue(&sender, request, &handler_busy).await,
+                    previous => {
+                        match queue(&sender, WebRequest::Cancel(previous), &handler_busy).await {
+                            Ok(()) => queue(&sender, request, &handler_busy).await,
+                            Err(err) => Err(err),
+                        }
+                    }
+                };
+                if let Err(err) = queued {
+                    if is_current() {
+                        report(&errors, err);
+                    }
+                    break;
+                }
 
-            let mut content_type = "text/plain".to_string();
+                let mut content_type = "text/plain".to_string();
+                let mut received: Vec<u8> = Vec::new();
+                let mut redirect_chain = Vec::new();
+                let mut received_headers = HeaderMap::new();
+                let mut cancelled = false;
 
-            loop {
-                let res = rx.recv().await;
-
-                match res {
-                    Some(Response::Status(status)) => {
-                        app_status.store(status.as_u16(), Ordering::SeqCst);
-                    }
-                    Some(Response::Headers(res)) => {
-                        let header_string = jsonxf::pretty_print(format!("{:?}", res).as_str());
-                        content_type = res
-                            .get("content-type")
-                            .unwrap_or(&HeaderValue::from_str(content_type.as_str()).unwrap())
-                            .to_str()
-                            .unwrap_or("text/plain")
-                            .to_string();
-                        if let Ok(header_string) = header_string {
-                            response_header_paragraph
-                                .lock()
-                                .unwrap()
-                                .set_value(header_string);
+                loop {
+                    let res = rx.recv().await;
+
+                    match res {
+                        Some(Response::Proxy(proxy)) => {
+                            entry.proxy = proxy.clone();
+                            if is_current() {
+                                *app_proxy.lock().unwrap() = proxy;
+                            }
                         }
-                    }
-                    Some(Response::Body(res)) => {
-                        let mut response_bytes = response.lock().unwrap();
-
-                        let decoded_string = String::from_utf8_lossy(&res);
-                        let pretty_json = jsonxf::pretty_print(decoded_string.to_string().as_str());
-                        info!("Decoded {:}", decoded_string);
-                        let final_string = if content_type.contains("json") {
-                            info!("IS JSON");
-                            if let Ok(pretty_json) = pretty_json {
-                                pretty_json
-                            } else {
-                                decoded_string.to_string()
+                        Some(Response::Redirects(redirects)) => {
+                            redirect_chain = redirects;
+                        }
+                        Some(Response::Status(status)) => {
+                            if is_current() {
+                                app_status.store(status.as_u16(), Ordering::SeqCst);
+                            }
+                            entry.status = status.as_u16();
+                            entry.wait_ms = Some(step_start.elapsed().as_millis() as u64);
+                        }
+                        Some(Response::Headers(res)) => {
+                            let header_string = jsonxf::pretty_print(format!("{:?}", res).as_str())
+                                .map(|headers| {
+                                    format!("{:}{:}", redirect::describe(&redirect_chain), headers)
+                                });
+                            content_type = res
+                                .get("content-type")
+                                .unwrap_or(&HeaderValue::from_str(content_type.as_str()).unwrap())
+                                .to_str()
+                                .unwrap_or("text/plain")
+                                .to_string();
+                            if is_current() {
+                                *app_content_type.lock().unwrap() = content_type.clone();
+                                res_paragraph
+                                    .lock()
+                                    .unwrap()
+                                    .set_line_transform(line_transform(&content_type, &settings));
+                            }
+                            received_headers = res.clone();
+                            if let Ok(header_string) = header_string {
+                                entry.response_headers =
+                                    redact_headers(header_string.as_str(), &settings);
+                                if is_current() {
+                                    response_header_paragraph
+                                        .lock()
+                                        .unwrap()
+                                        .set_value(header_string);
+                                }
+                            }
+                        }
+                        Some(Response::Body(res)) => {
+                            received.extend_from_slice(&res);
+                            if !is_current() {
+                                continue;
+                            }
+                            let mut response_bytes = response.lock().unwrap();
+
+                            let decoded_string = String::from_utf8_lossy(&res);
+                            // The type is guessed from the first chunk.
+                            if received.len() == res.len() && is_generic(&content_type) {
+                                if let Some(detected) = sniff(&decoded_string) {
+                                    content_type = detected.to_string();
+                                    *app_content_type.lock().unwrap() = content_type.clone();
+                                    content_type_detected.store(true, Ordering::SeqCst);
+                                    res_paragraph.lock().unwrap().set_line_transform(
+                                        line_transform(&content_type, &settings),
+                                    );
+                                }
+                            }
+                            let mut paragraph = res_paragraph.lock().unwrap();
+                            // Formatters needing the whole body are run once it has all been
+                            // received, so until then the chunks are shown as they are.
+                            match formatters::find(&content_type, &settings) {
+                                Some(formatter) if !formatter.whole_body => paragraph.append_value(
+                                    (formatter.format)(&res)
+                                        .unwrap_or_else(|| decoded_string.to_string()),
+                                ),
+                                _ => paragraph.append_value(decoded_string.to_string()),
+                            }
+                            drop(paragraph);
+
+                            *response_bytes = Some(res);
+                            dirty.store(true, Ordering::SeqCst);
+                        }
+                        Some(Response::Progress(bytes)) => {
+                            if is_current() {
+                                if let Some(download) = app_download.lock().unwrap().as_mut() {
+                                    download.bytes = bytes;
+                                }
+                                dirty.store(true, Ordering::SeqCst);
+                            }
+                        }
+                        Some(Response::Failure(err)) => {
+                            if is_current() {
+                                report(&errors, err);
                             }
-                        } else {
-                            decoded_string.to_string()
-                        };
-                        // let final_string = if let Ok(pretty_json) = pretty_json {
-                        //     pretty_json
-                        // } else {
-                        //     decoded_string.to_string()
-                        // };
-                        // let final_string = decoded_string.to_string();
-
-                        *response_bytes = Some(res);
-                        res_paragraph.lock().unwrap().append_value(final_string);
+                            break;
+                        }
+                        Some(Response::Cancelled) => {
+                            cancelled = true;
+                            break;
+                        }
+                        None => {
+                            break;
+                        }
+                    };
+                }
+
+                entry.elapsed_ms = Some(step_start.elapsed().as_millis() as u64);
+                if is_current() {
+                    let label = if attempts == 0 { "request" } else { "retry" };
+                    steps.lock().unwrap().push(Step::finished(
+                        label,
+                        started,
+                        step_start,
+                        entry.status,
+                    ));
+                }
+
+                audit::record_sent(
+                    &entry,
+                    environment.map(|environment| environment.name),
+                    &settings,
+                );
+                // The body was cut off, so it isn't kept in the history as if it were the whole
+                // response.
+                if cancelled {
+                    info!("A request was cancelled before its response was complete");
+                    if is_current() {
+                        res_paragraph
+                            .lock()
+                            .unwrap()
+                            .append_value("\n[Cancelled, the response is incomplete]".to_string());
                         dirty.store(true, Ordering::SeqCst);
                     }
-                    _ => {
-                        break;
+                    break;
+                }
+
+                let status = entry.status;
+                if status != 0 {
+                    entry.response_body = Some(String::from_utf8_lossy(&received).to_string());
+                }
+                if status != 0 && download.is_none() && is_current() {
+                    if let Some(formatter) = formatters::find(&content_type, &settings)
+                        .filter(|formatter| formatter.whole_body)
+                    {
+                        if let Some(formatted) = (formatter.format)(&received) {
+                            res_paragraph.lock().unwrap().set_value(formatted);
+                            dirty.store(true, Ordering::SeqCst);
+                        }
+                    }
+                }
+                if !assertions.is_empty() && status != 0 && is_current() {
+                    let outcomes =
+                        assertion::evaluate(&assertions, status, &received_headers, &received);
+                    let mut headers = response_header_paragraph.lock().unwrap();
+                    let described =
+                        format!("{:}{:}", assertion::describe(&outcomes), headers.as_str());
+                    headers.set_value(described);
+                    *assertion_outcomes.lock().unwrap() = outcomes;
+                    dirty.store(true, Ordering::SeqCst);
+                }
+                // The variables are set even when a newer request is shown, since later requests
+                // may need them.
+                if !captures.is_empty() && status != 0 {
+                    let mut environments = environments.lock().unwrap();
+                    match environments.active_mut() {
+                        Some(environment) => {
+                            let missing = capture::apply(&captures, status, &received, environment);
+                            if !missing.is_empty() {
+                                report(&errors, capture::missing_error(&missing));
+                            }
+                            if let Err(err) = environments.save() {
+                                report(&errors, err);
+                            }
+                        }
+                        None => report(
+                            &errors,
+                            Error::Parse(
+                                "There is no active environment to capture variables into"
+                                    .to_string(),
+                            ),
+                        ),
+                    }
+                    dirty.store(true, Ordering::SeqCst);
+                }
+                if let (Some(path), true) = (&download, is_current()) {
+                    if let Some(finished) = app_download.lock().unwrap().take() {
+                        if status != 0 {
+                            res_paragraph.lock().unwrap().set_value(format!(
+                                "Saved {:} to {:}",
+                                format_bytes(finished.bytes as f64),
+                                path
+                            ));
+                        }
+                    }
+                    dirty.store(true, Ordering::SeqCst);
+                }
+                if let Some(hook) = &settings.post_receive_hook {
+                    if let Err(err) = hooks::post_receive(hook, &entry).await {
+                        report(&errors, err);
+                    }
+                }
+                // The history keeps the response as it was received.
+                if let (Some(post_process), true) = (
+                    &post_process_command,
+                    status != 0 && download.is_none() && is_current(),
+                ) {
+                    match command::run(post_process, &received).await {
+                        Ok(output) if is_current() => {
+                            res_paragraph.lock().unwrap().set_value(output);
+                            dirty.store(true, Ordering::SeqCst);
+                        }
+                        Ok(_) => {}
+                        Err(err) => report(&errors, err),
                     }
+                }
+                record_history(&history, entry, &settings, &errors).await;
+
+                attempts += 1;
+                if status != StatusCode::UNAUTHORIZED.as_u16() || refreshed {
+                    break;
+                }
+                let refresh = match &refresh {
+                    Some(refresh) => refresh,
+                    None => break,
                 };
+                refreshed = true;
+                info!("Unauthorized, refreshing token with {:}", refresh.name());
+                let refresh_steps = is_current().then(|| (steps.as_ref(), started));
+                let updated = refresh_step(
+                    &sender,
+                    &handler_busy,
+                    refresh,
+                    &environments,
+                    &settings,
+                    refresh_steps,
+                )
+                .await;
+                dirty.store(true, Ordering::SeqCst);
+                if !updated {
+                    break;
+                }
+
+                if is_current() {
+                    res_paragraph.lock().unwrap().reset();
+                    response_header_paragraph.lock().unwrap().reset();
+                    *response.lock().unwrap() = None;
+                    dirty.store(true, Ordering::SeqCst);
+                }
+            }
+            if is_current() {
+                in_flight.store(false, Ordering::SeqCst);
+                response_complete.store(true, Ordering::SeqCst);
+                dirty.store(true, Ordering::SeqCst);
             }
         });
     }
 }
+
+/// How the token of the active environment is renewed.
+enum Refresher {
+    /// Send a saved request and take the token from its response.
+    Request(TokenRefresh, Box<crate::persistence::Request>),
+    /// Fetch a token from the environment's OAuth2 token endpoint.
+    OAuth2,
+}
+
+impl Refresher {
+    fn name(&self) -> &str {
+        match self {
+            Refresher::Request(refresh, _) => refresh.request.as_str(),
+            Refresher::OAuth2 => "OAuth2",
+        }
+    }
+}
+
+/// Refresh the token of the active environment, adding the refresh to `steps` when they are
+/// given. Returns true if the token was updated.
+async fn refresh_step(
+    sender: &mpsc::Sender<WebRequest>,
+    busy: &AtomicBool,
+    refresh: &Refresher,
+    environments: &Arc<Mutex<Environments>>,
+    settings: &Settings,
+    steps: Option<(&Mutex<Vec<Step>>, Instant)>,
+) -> bool {
+    let refresh_start = Instant::now();
+    let (status, updated) = match refresh {
+        Refresher::Request(refresh, request) => {
+            refresh_token(sender, busy, refresh, request, environments, settings).await
+        }
+        Refresher::OAuth2 => oauth2::fetch_token(sender, busy, environments, settings).await,
+    };
+    if let Some((steps, started)) = steps {
+        steps.lock().unwrap().push(Step::finished(
+            format!("refresh {:}", refresh.name()).as_str(),
+            started,
+            refresh_start,
+            status,
+        ));
+    }
+    updated
+}
+
+/// Add an entry to the history and save it, writing the file on a blocking thread once the
+/// history is unlocked.
+async fn record_history(
+    history: &Mutex<History>,
+    entry: HistoryEntry,
+    settings: &Settings,
+    errors: &Mutex<Vec<Error>>,
+) {
+    let snapshot = {
+        let mut history = history.lock().unwrap();
+        history.add(entry, settings);
+        history.snapshot()
+    };
+    let saved = match snapshot {
+        Ok(snapshot) => tokio::task::spawn_blocking(move || snapshot.write())
+            .await
+            .unwrap_or(Err(Error::Handler("Saving the history failed"))),
+        Err(err) => Err(err),
+    };
+    if let Err(err) = saved {
+        report(errors, err);
+    }
+}
+
+/// Whether a task has finished, without waiting for it.
+fn is_finished(task: &mut JoinHandle<()>) -> bool {
+    let mut context = Context::from_waker(Waker::noop());
+    Pin::new(task).poll(&mut context).is_ready()
+}
+
+fn report(errors: &Mutex<Vec<Error>>, err: Error) {
+    error!("{:}", err);
+    errors.lock().unwrap().push(err);
+}
+
+/// The loaded value, or the default when loading failed. The error is kept to show the user.
+fn or_default<T: Default>(res: Result<T>, errors: &mut Vec<Error>) -> T {
+    res.unwrap_or_else(|err| {
+        error!("{:}", err);
+        errors.push(err);
+        T::default()
+    })
+}
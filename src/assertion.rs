@@ -0,0 +1,211 @@
+//! Checks of a response kept with a saved request, like its status or a value in its JSON body,
+//! which are evaluated each time it is sent. They are edited one per line:
+//!
+//! ```text
+//! status 201
+//! header Content-Type: application/json
+//! json $.items[0].id == 42
+//! json $.name contains Smith
+//! ```
+
+use crate::error::{Error, Result};
+use crate::json_path;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Assertion {
+    Status {
+        status: u16,
+    },
+    /// The header is present with this value, ignoring the case of its name.
+    Header {
+        name: String,
+        value: String,
+    },
+    /// The value at the JSONPath is this, with strings compared unquoted.
+    JsonEquals {
+        path: String,
+        value: String,
+    },
+    /// The string at the JSONPath contains this, or the array there has it as an element.
+    JsonContains {
+        path: String,
+        value: String,
+    },
+}
+
+/// The outcome of an assertion for a response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub assertion: String,
+    pub passed: bool,
+    /// What the response had instead, when the assertion failed.
+    pub actual: Option<String>,
+}
+
+impl std::fmt::Display for Assertion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Assertion::Status { status } => write!(f, "status {:}", status),
+            Assertion::Header { name, value } => write!(f, "header {:}: {:}", name, value),
+            Assertion::JsonEquals { path, value } => write!(f, "json {:} == {:}", path, value),
+            Assertion::JsonContains { path, value } => {
+                write!(f, "json {:} contains {:}", path, value)
+            }
+        }
+    }
+}
+
+/// Parse assertions, one per line. Blank lines are skipped.
+pub fn parse(text: &str) -> Result<Vec<Assertion>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(parse_line)
+        .collect()
+}
+
+fn parse_line(line: &str) -> Result<Assertion> {
+    let invalid = || {
+        Error::Parse(format!(
+            "{:} isn't an assertion like status 200, header Name: value, json $.path == value \
+             or json $.path contains value",
+            line
+        ))
+    };
+    let (kind, rest) = line.split_once(' ').ok_or_else(invalid)?;
+    let rest = rest.trim();
+    match kind {
+        "status" => Ok(Assertion::Status {
+            status: rest.parse().map_err(|_| invalid())?,
+        }),
+        "header" => {
+            let (name, value) = rest.split_once(':').ok_or_else(invalid)?;
+            Ok(Assertion::Header {
+                name: name.trim().to_string(),
+                value: value.trim().to_string(),
+            })
+        }
+        "json" => {
+            if let Some((path, value)) = rest.split_once(" == ") {
+                json_path::parse(path).ok_or_else(invalid)?;
+                Ok(Assertion::JsonEquals {
+                    path: path.trim().to_string(),
+                    value: value.trim().to_string(),
+                })
+            } else if let Some((path, value)) = rest.split_once(" contains ") {
+                json_path::parse(path).ok_or_else(invalid)?;
+                Ok(Assertion::JsonContains {
+                    path: path.trim().to_string(),
+                    value: value.trim().to_string(),
+                })
+            } else {
+                Err(invalid())
+            }
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Assertions as they are edited, one per line.
+pub fn to_string(assertions: &[Assertion]) -> String {
+    assertions
+        .iter()
+        .map(|assertion| assertion.to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Evaluate the assertions for a response.
+pub fn evaluate(
+    assertions: &[Assertion],
+    status: u16,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Vec<Outcome> {
+    let document: Option<Value> = serde_json::from_slice(body).ok();
+    assertions
+        .iter()
+        .map(|assertion| {
+            let actual = match assertion {
+                Assertion::Status { status: expected } => {
+                    (status != *expected).then(|| status.to_string())
+                }
+                Assertion::Header { name, value } => {
+                    let actual = headers
+                        .get_all(name.as_str())
+                        .iter()
+                        .filter_map(|actual| actual.to_str().ok())
+                        .collect::<Vec<&str>>();
+                    match actual.as_slice() {
+                        [] => Some("missing".to_string()),
+                        actual if actual.contains(&value.as_str()) => None,
+                        actual => Some(actual.join(", ")),
+                    }
+                }
+                Assertion::JsonEquals { path, value } => match json_value(&document, path) {
+                    Ok(actual) if json_path::value_to_string(actual) == *value => None,
+                    Ok(actual) => Some(json_path::value_to_string(actual)),
+                    Err(missing) => Some(missing),
+                },
+                Assertion::JsonContains { path, value } => match json_value(&document, path) {
+                    Ok(actual) if contains(actual, value) => None,
+                    Ok(actual) => Some(json_path::value_to_string(actual)),
+                    Err(missing) => Some(missing),
+                },
+            };
+            Outcome {
+                assertion: assertion.to_string(),
+                passed: actual.is_none(),
+                actual,
+            }
+        })
+        .collect()
+}
+
+fn json_value<'a>(
+    document: &'a Option<Value>,
+    path: &str,
+) -> std::result::Result<&'a Value, String> {
+    let document = document
+        .as_ref()
+        .ok_or_else(|| "the body isn't JSON".to_string())?;
+    json_path::get(document, path).ok_or_else(|| "missing".to_string())
+}
+
+fn contains(actual: &Value, value: &str) -> bool {
+    match actual {
+        Value::String(text) => text.contains(value),
+        Value::Array(items) => items
+            .iter()
+            .any(|item| json_path::value_to_string(item) == value),
+        Value::Object(fields) => fields.contains_key(value),
+        actual => json_path::value_to_string(actual).contains(value),
+    }
+}
+
+/// The outcomes as lines to show above the response headers, or nothing if there were none.
+pub fn describe(outcomes: &[Outcome]) -> String {
+    if outcomes.is_empty() {
+        return "".to_string();
+    }
+    let passed = outcomes.iter().filter(|outcome| outcome.passed).count();
+    let mut description = format!("Assertions ({:}/{:} passed):\n", passed, outcomes.len());
+    for outcome in outcomes {
+        description.push_str(describe_outcome(outcome).as_str());
+        description.push('\n');
+    }
+    description.push('\n');
+    description
+}
+
+/// Like `FAIL status 200 (was 404)`.
+pub fn describe_outcome(outcome: &Outcome) -> String {
+    match &outcome.actual {
+        Some(actual) => format!("FAIL {:} (was {:})", outcome.assertion, actual),
+        None => format!("PASS {:}", outcome.assertion),
+    }
+}
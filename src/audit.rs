@@ -0,0 +1,94 @@
+use crate::error::Result;
+use crate::history::{now, HistoryEntry};
+use crate::output::output_path;
+use crate::redact::redact_url;
+use crate::settings::Settings;
+use crate::{crypto, paths, Method};
+
+use serde::{Deserialize, Serialize};
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+/// A record of a request which was sent. Records are appended to the audit log one JSON object
+/// per line, and are never modified afterwards.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuditRecord {
+    /// Seconds since the unix epoch when the request was sent.
+    pub timestamp: u64,
+    pub user: String,
+    pub method: Method,
+    pub url: String,
+    pub environment: Option<String>,
+    /// Zero when no response was received.
+    pub status: u16,
+}
+
+pub fn current_user() -> String {
+    env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Append a record of a request which was sent, if the audit log is enabled.
+pub fn record_sent(entry: &HistoryEntry, environment: Option<String>, settings: &Settings) {
+    if settings.audit_log {
+        append(
+            AuditRecord {
+                timestamp: entry.timestamp,
+                user: current_user(),
+                method: entry.method.clone(),
+                url: entry.url.clone(),
+                environment,
+                status: entry.status,
+            },
+            settings,
+        );
+    }
+}
+
+/// Append a record to the audit log. Secrets in the URL are masked, like they are in the history.
+pub fn append(mut record: AuditRecord, settings: &Settings) {
+    record.url = redact_url(record.url.as_str(), settings);
+    let line = match serde_json::to_string(&record) {
+        Ok(line) => line,
+        Err(err) => {
+            error!("Error serializing audit record {:?}", err);
+            return;
+        }
+    };
+    let path = &paths::get().audit;
+    // An encrypted file can't be appended to, so it's written again with the record added.
+    if crypto::encrypts_writes() {
+        let res = match path.exists() {
+            true => crypto::read(path),
+            false => Ok(Vec::new()),
+        }
+        .and_then(|mut contents| {
+            contents.extend_from_slice(format!("{:}\n", line).as_bytes());
+            crypto::write(path, &contents)
+        });
+        if let Err(err) = res {
+            error!("Error writing audit log {:?}", err);
+        }
+        return;
+    }
+    let file = OpenOptions::new().create(true).append(true).open(path);
+    match file {
+        Ok(mut file) => {
+            if let Err(err) = writeln!(file, "{:}", line) {
+                error!("Error writing audit log {:?}", err);
+            }
+        }
+        Err(err) => error!("Error opening audit log {:?}", err),
+    }
+}
+
+/// Copy the audit log to a timestamped export file, decrypted. Returns the name of the exported
+/// file.
+pub fn export() -> Result<String> {
+    let path = output_path(format!("audit-export-{:}", now()).as_str(), "jsonl");
+    fs::write(&path, crypto::read(&paths::get().audit)?)?;
+    Ok(path.display().to_string())
+}
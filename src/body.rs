@@ -0,0 +1,216 @@
+use crate::query;
+use crate::settings::{FinalNewline, LineEndings, Settings};
+
+/// Apply the send-time transformations from the settings to a request body.
+pub fn prepare_body(body: &str, settings: &Settings) -> String {
+    let mut body = if settings.strip_body_comments {
+        strip_json_comments(body)
+    } else {
+        body.to_string()
+    };
+
+    if settings.trim_trailing_whitespace {
+        body = trim_trailing_whitespace(body.as_str());
+    }
+
+    body = match settings.final_newline {
+        FinalNewline::Preserve => body,
+        FinalNewline::Ensure => {
+            if body.is_empty() || body.ends_with('\n') {
+                body
+            } else {
+                body + "\n"
+            }
+        }
+        FinalNewline::Strip => body.trim_end_matches(&['\r', '\n'][..]).to_string(),
+    };
+
+    match settings.line_endings {
+        LineEndings::Preserve => body,
+        LineEndings::Lf => body.replace("\r\n", "\n"),
+        LineEndings::Crlf => body.replace("\r\n", "\n").replace('\n', "\r\n"),
+    }
+}
+
+/// Remove trailing spaces and tabs from every line, keeping the original line endings.
+pub fn trim_trailing_whitespace(body: &str) -> String {
+    body.split_inclusive('\n')
+        .map(|line| {
+            let (content, ending) = match line.strip_suffix("\r\n") {
+                Some(content) => (content, "\r\n"),
+                None => match line.strip_suffix('\n') {
+                    Some(content) => (content, "\n"),
+                    None => (line, ""),
+                },
+            };
+            format!("{:}{:}", content.trim_end_matches(&[' ', '\t'][..]), ending)
+        })
+        .collect()
+}
+
+/// Remove `//` line comments and `/* */` block comments from a JSONC document. Comment markers
+/// inside of strings are left alone.
+pub fn strip_json_comments(body: &str) -> String {
+    let mut res = String::with_capacity(body.len());
+    let mut chars = body.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            res.push(c);
+            match c {
+                '\\' => {
+                    if let Some(escaped) = chars.next() {
+                        res.push(escaped);
+                    }
+                }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                res.push(c);
+            }
+            ('/', Some('/')) => {
+                // Keep the newline so line numbers in errors still make sense.
+                for skipped in chars.by_ref() {
+                    if skipped == '\n' {
+                        res.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for skipped in chars.by_ref() {
+                    if skipped == '\n' {
+                        res.push('\n');
+                    }
+                    if previous == '*' && skipped == '/' {
+                        break;
+                    }
+                    previous = skipped;
+                }
+            }
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
+/// The path of a file to send as a raw body, given as the whole body like `@/path/to/file`, as
+/// with curl's `--data-binary`. The file is streamed when the request is sent, so it can be
+/// binary or too large to edit.
+pub fn file_reference(body: &str) -> Option<&str> {
+    let path = body.trim().strip_prefix('@')?.trim();
+    (!path.is_empty() && !path.contains('\n')).then_some(path)
+}
+
+/// A part of a multipart form body.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Part {
+    Text {
+        name: String,
+        value: String,
+    },
+    /// A file, which is read when the request is sent.
+    File {
+        name: String,
+        path: String,
+        content_type: Option<String>,
+    },
+}
+
+/// The parts of a multipart body, one `name=value` per line. As with curl's `-F`, a value like
+/// `@path` sends a file, and `@path;type=image/png` gives its content type.
+pub fn multipart_parts(body: &str) -> Vec<Part> {
+    query::parse(body)
+        .into_iter()
+        .map(|(name, value)| match value.strip_prefix('@') {
+            Some(file) => {
+                let (path, content_type) = match file.split_once(";type=") {
+                    Some((path, content_type)) => (path, Some(content_type.trim().to_string())),
+                    None => (file, None),
+                };
+                Part::File {
+                    name,
+                    path: path.trim().to_string(),
+                    content_type,
+                }
+            }
+            None => Part::Text { name, value },
+        })
+        .collect()
+}
+
+/// Encode `name=value` lines as an `application/x-www-form-urlencoded` body, adding the
+/// `Content-Type` header unless there is one.
+pub fn encode_form(headers: String, body: &str) -> (String, String) {
+    let has_content_type = headers.lines().any(|line| {
+        line.split_once(':')
+            .is_some_and(|(name, _)| name.trim().eq_ignore_ascii_case("content-type"))
+    });
+    let headers = if has_content_type {
+        headers
+    } else if headers.trim().is_empty() {
+        "Content-Type: application/x-www-form-urlencoded".to_string()
+    } else {
+        format!(
+            "{:}\nContent-Type: application/x-www-form-urlencoded",
+            headers.trim_end()
+        )
+    };
+    (headers, query::encode_params(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_a_form() {
+        assert_eq!(
+            encode_form("".to_string(), "a=1 2\nb=&"),
+            (
+                "Content-Type: application/x-www-form-urlencoded".to_string(),
+                "a=1%202&b=%26".to_string()
+            )
+        );
+        assert_eq!(
+            encode_form("Accept: */*\n".to_string(), "a=1").0,
+            "Accept: */*\nContent-Type: application/x-www-form-urlencoded"
+        );
+        assert_eq!(
+            encode_form("content-type: text/plain".to_string(), "a=1").0,
+            "content-type: text/plain"
+        );
+    }
+
+    #[test]
+    fn multipart_parts_with_files() {
+        assert_eq!(
+            multipart_parts("title = a\nfile=@/tmp/a.png;type=image/png\nraw=@ b.txt"),
+            vec![
+                Part::Text {
+                    name: "title".to_string(),
+                    value: "a".to_string()
+                },
+                Part::File {
+                    name: "file".to_string(),
+                    path: "/tmp/a.png".to_string(),
+                    content_type: Some("image/png".to_string())
+                },
+                Part::File {
+                    name: "raw".to_string(),
+                    path: "b.txt".to_string(),
+                    content_type: None
+                },
+            ]
+        );
+    }
+}
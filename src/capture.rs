@@ -0,0 +1,105 @@
+//! Values captured from the JSON body of a response into variables of the active environment, so
+//! a login request can set the token the following requests send. They are edited one per line:
+//!
+//! ```text
+//! auth_token = $.token
+//! user_id = $.user.id
+//! ```
+
+use crate::environment::Environment;
+use crate::error::{Error, Result};
+use crate::json_path;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Capture {
+    /// Name of the variable set.
+    pub variable: String,
+    /// JSONPath of the value in the response body.
+    pub path: String,
+}
+
+impl std::fmt::Display for Capture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:} = {:}", self.variable, self.path)
+    }
+}
+
+/// Parse captures, one per line. Blank lines are skipped.
+pub fn parse(text: &str) -> Result<Vec<Capture>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(parse_line)
+        .collect()
+}
+
+fn parse_line(line: &str) -> Result<Capture> {
+    let invalid = || {
+        Error::Parse(format!(
+            "{:} isn't a capture like auth_token = $.token",
+            line
+        ))
+    };
+    let (variable, path) = line.split_once('=').ok_or_else(invalid)?;
+    let (variable, path) = (variable.trim(), path.trim());
+    if variable.is_empty() || variable.contains(['{', '}']) {
+        return Err(invalid());
+    }
+    json_path::parse(path).ok_or_else(invalid)?;
+    Ok(Capture {
+        variable: variable.to_string(),
+        path: path.to_string(),
+    })
+}
+
+/// Captures as they are edited, one per line.
+pub fn to_string(captures: &[Capture]) -> String {
+    captures
+        .iter()
+        .map(|capture| capture.to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Set the variables captured from a response in the environment. Nothing is captured from an
+/// error response, so a failed login doesn't replace a token. Returns the captures whose values
+/// weren't in the body.
+pub fn apply<'a>(
+    captures: &'a [Capture],
+    status: u16,
+    body: &[u8],
+    environment: &mut Environment,
+) -> Vec<&'a Capture> {
+    if status >= 400 {
+        return Vec::new();
+    }
+    let document: Option<Value> = serde_json::from_slice(body).ok();
+    let mut missing = Vec::new();
+    for capture in captures {
+        match document
+            .as_ref()
+            .and_then(|document| json_path::get(document, capture.path.as_str()))
+        {
+            Some(value) => environment.set(
+                capture.variable.as_str(),
+                json_path::value_to_string(value).as_str(),
+            ),
+            None => missing.push(capture),
+        }
+    }
+    missing
+}
+
+/// Describes the captures which weren't found in a response.
+pub fn missing_error(missing: &[&Capture]) -> Error {
+    let paths: Vec<&str> = missing
+        .iter()
+        .map(|capture| capture.path.as_str())
+        .collect();
+    Error::Parse(format!(
+        "Nothing was captured at {:} in the response",
+        paths.join(", ")
+    ))
+}
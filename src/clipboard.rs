@@ -0,0 +1,57 @@
+//! The system clipboard. Without a desktop clipboard, like over SSH, copying goes through the
+//! terminal with the OSC 52 escape sequence, which some terminals need to be enabled for, and
+//! text is pasted with the terminal's own paste.
+
+use crate::error::{Error, Result};
+
+use arboard::Clipboard;
+use std::io::{self, Write};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+/// The desktop clipboard, kept open while the program runs because on X11 the copied text is
+/// lost once the clipboard it was copied with is closed.
+static CLIPBOARD: OnceLock<Option<Mutex<Clipboard>>> = OnceLock::new();
+
+fn clipboard() -> Option<MutexGuard<'static, Clipboard>> {
+    CLIPBOARD
+        .get_or_init(|| match Clipboard::new() {
+            Ok(clipboard) => Some(Mutex::new(clipboard)),
+            Err(err) => {
+                info!(
+                    "No desktop clipboard, copying through the terminal {:?}",
+                    err
+                );
+                None
+            }
+        })
+        .as_ref()
+        .map(|clipboard| clipboard.lock().unwrap())
+}
+
+pub fn copy(text: &str) {
+    if let Some(mut clipboard) = clipboard() {
+        match clipboard.set_text(text) {
+            Ok(()) => return,
+            Err(err) => error!("Error copying to the desktop clipboard {:?}", err),
+        }
+    }
+    let mut stdout = io::stdout();
+    let res =
+        write!(stdout, "\x1b]52;c;{:}\x07", base64::encode(text)).and_then(|_| stdout.flush());
+    if let Err(err) = res {
+        error!("Error copying to the clipboard {:?}", err);
+    }
+}
+
+/// The text on the desktop clipboard.
+pub fn paste() -> Result<String> {
+    let mut clipboard = clipboard().ok_or_else(|| {
+        Error::Clipboard(
+            "There is no desktop clipboard to paste from, paste with the terminal instead"
+                .to_string(),
+        )
+    })?;
+    clipboard
+        .get_text()
+        .map_err(|err| Error::Clipboard(format!("Couldn't paste: {:}", err)))
+}
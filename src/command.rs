@@ -0,0 +1,52 @@
+//! Running external commands, like the post-processor of a response or the request hooks.
+
+use crate::error::{Error, Result};
+
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Run `command` with the shell, writing `input` to it. Returns the command's output, or what it
+/// wrote to stderr if it failed.
+pub async fn run(command: &str, input: &[u8]) -> Result<String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let mut child = shell
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    // The input is written while the output is read, so a command producing a lot of output
+    // can't block waiting for it to be read.
+    let mut stdin = child.stdin.take();
+    let input = input.to_vec();
+    let writer = tokio::spawn(async move {
+        if let Some(stdin) = stdin.as_mut() {
+            // The command may exit without reading all of its input, which isn't an error.
+            let _ = stdin.write_all(&input).await;
+        }
+    });
+    let output = child.wait_with_output().await?;
+    let _ = writer.await;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Command(format!(
+            "`{:}` failed ({:}): {:}",
+            command,
+            output.status,
+            stderr.trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
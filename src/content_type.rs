@@ -0,0 +1,92 @@
+//! Working out how to show a response whose content type doesn't say what it contains.
+
+use crate::metrics::{is_metrics, METRICS_CONTENT_TYPE};
+
+/// The types a response can be shown as, in the order they are cycled through.
+pub const CONTENT_TYPES: [&str; 6] = [
+    "application/json",
+    "application/xml",
+    "text/html",
+    "text/csv",
+    METRICS_CONTENT_TYPE,
+    "text/plain",
+];
+
+/// Content types which say nothing about the format of the body.
+pub fn is_generic(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim();
+    matches!(essence, "" | "text/plain" | "application/octet-stream")
+}
+
+/// Guess the type of a body from its start, which may be only the first chunk received.
+pub fn sniff(body: &str) -> Option<&'static str> {
+    let body = body.trim_start_matches('\u{feff}').trim();
+    let lower: String = body.chars().take(256).collect::<String>().to_lowercase();
+
+    if body.starts_with('{') || body.starts_with('[') {
+        // A body streamed in chunks can be cut off part way through.
+        match serde_json::from_str::<serde_json::Value>(body) {
+            Ok(_) => return Some("application/json"),
+            Err(err) if err.is_eof() => return Some("application/json"),
+            Err(_) => {}
+        }
+    }
+    if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+        return Some("text/html");
+    }
+    if lower.starts_with("<?xml") || (body.starts_with('<') && body.contains("</")) {
+        return Some("application/xml");
+    }
+    if is_metrics(body) {
+        return Some(METRICS_CONTENT_TYPE);
+    }
+    if looks_like_csv(body) {
+        return Some("text/csv");
+    }
+    None
+}
+
+/// At least two lines, all with the same number of commas outside of quotes.
+fn looks_like_csv(body: &str) -> bool {
+    const SAMPLE_LINES: usize = 10;
+
+    let mut counts = body
+        .lines()
+        .take(SAMPLE_LINES)
+        .map(|line| split_csv_line(line).len() - 1);
+    let first = match counts.next() {
+        Some(first) if first > 0 => first,
+        _ => return false,
+    };
+    let mut others = counts.peekable();
+    others.peek().is_some() && others.all(|count| count == first)
+}
+
+/// Split a line of CSV into its fields, removing the quotes around quoted fields.
+pub fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+/// A short name for a content type, like `json`.
+pub fn short_name(content_type: &str) -> &str {
+    let essence = content_type.split(';').next().unwrap_or("").trim();
+    let subtype = essence.rsplit('/').next().unwrap_or(essence);
+    match subtype {
+        "plain" => "text",
+        subtype => subtype,
+    }
+}
@@ -0,0 +1,162 @@
+//! Cookies imported from a browser, so a signed in session can be reused. They are sent with
+//! requests to the site they belong to until the app exits.
+
+use crate::error::{Error, Result};
+use crate::history::now;
+use reqwest::Url;
+
+pub struct Cookie {
+    pub domain: String,
+    /// Also send the cookie to subdomains of the domain.
+    pub include_subdomains: bool,
+    pub path: String,
+    /// Only send the cookie over https.
+    pub secure: bool,
+    pub name: String,
+    pub value: String,
+}
+
+impl Cookie {
+    fn matches(&self, url: &Url) -> bool {
+        let host = match url.host_str() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return false,
+        };
+        let domain_matches = host == self.domain
+            || (self.include_subdomains && host.ends_with(format!(".{:}", self.domain).as_str()));
+        let path = self.path.trim_end_matches('/');
+        let path_matches = url
+            .path()
+            .strip_prefix(path)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+        domain_matches && path_matches && (!self.secure || url.scheme() == "https")
+    }
+}
+
+#[derive(Default)]
+pub struct CookieJar {
+    pub cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    /// Import the cookies in a Netscape `cookies.txt` file, or in a `Cookie` header copied from a
+    /// browser, which are for the host of `url`. Returns the number of cookies imported.
+    pub fn import(&mut self, text: &str, url: &str) -> Result<usize> {
+        let cookies = if text.lines().any(|line| line.contains('\t')) {
+            parse_cookies_txt(text)
+        } else {
+            let url = Url::parse(url).map_err(|err| {
+                Error::Parse(format!(
+                    "Cookies from a header are for the host of the request URL, which is invalid: \
+                     {:}",
+                    err
+                ))
+            })?;
+            parse_cookie_header(text, &url)
+        };
+        if cookies.is_empty() {
+            return Err(Error::Parse("No cookies found to import".to_string()));
+        }
+        let count = cookies.len();
+        for cookie in cookies {
+            self.add(cookie);
+        }
+        Ok(count)
+    }
+
+    /// Add a cookie, replacing one with the same name for the same domain and path.
+    fn add(&mut self, cookie: Cookie) {
+        self.cookies.retain(|existing| {
+            existing.domain != cookie.domain
+                || existing.path != cookie.path
+                || existing.name != cookie.name
+        });
+        self.cookies.push(cookie);
+    }
+
+    pub fn clear(&mut self) {
+        self.cookies.clear();
+    }
+
+    /// Add a `Cookie` header with the cookies for the URL to the headers, unless they already have
+    /// one.
+    pub fn add_to(&self, headers: String, url: &str) -> String {
+        let has_cookie = headers.lines().any(|line| {
+            line.split_once(':')
+                .is_some_and(|(name, _)| name.trim().eq_ignore_ascii_case("cookie"))
+        });
+        match self.header(url) {
+            Some(cookie) if !has_cookie && headers.trim().is_empty() => {
+                format!("Cookie: {:}", cookie)
+            }
+            Some(cookie) if !has_cookie => format!("{:}\nCookie: {:}", headers, cookie),
+            _ => headers,
+        }
+    }
+
+    /// The value of the `Cookie` header for a request to the URL, if any cookies are for it.
+    fn header(&self, url: &str) -> Option<String> {
+        let url = Url::parse(url).ok()?;
+        let pairs: Vec<String> = self
+            .cookies
+            .iter()
+            .filter(|cookie| cookie.matches(&url))
+            .map(|cookie| format!("{:}={:}", cookie.name, cookie.value))
+            .collect();
+        (!pairs.is_empty()).then(|| pairs.join("; "))
+    }
+}
+
+/// Parse a header like `Cookie: session=abc; theme=dark`, the `Cookie:` is optional.
+fn parse_cookie_header(text: &str, url: &Url) -> Vec<Cookie> {
+    let text = text.trim();
+    let text = match text.split_once(':') {
+        Some((name, value)) if name.trim().eq_ignore_ascii_case("cookie") => value,
+        _ => text,
+    };
+    let domain = url.host_str().unwrap_or("").to_ascii_lowercase();
+    text.split(';')
+        .filter_map(|pair| pair.split_once('='))
+        .filter(|(name, _)| !name.trim().is_empty())
+        .map(|(name, value)| Cookie {
+            domain: domain.clone(),
+            include_subdomains: false,
+            path: "/".to_string(),
+            secure: false,
+            name: name.trim().to_string(),
+            value: value.trim().to_string(),
+        })
+        .collect()
+}
+
+/// Parse the tab separated lines of a `cookies.txt` file, skipping cookies which have expired.
+fn parse_cookies_txt(text: &str) -> Vec<Cookie> {
+    let now = now();
+    text.lines()
+        .filter_map(|line| {
+            // Curl marks HttpOnly cookies with a prefix, which would otherwise be a comment.
+            let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+            if line.starts_with('#') {
+                return None;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields[..] {
+                [domain, include_subdomains, path, secure, expires, name, value] => {
+                    let expires: u64 = expires.parse().unwrap_or(0);
+                    if expires != 0 && expires < now {
+                        return None;
+                    }
+                    Some(Cookie {
+                        domain: domain.trim_start_matches('.').to_ascii_lowercase(),
+                        include_subdomains: include_subdomains.eq_ignore_ascii_case("TRUE"),
+                        path: path.to_string(),
+                        secure: secure.eq_ignore_ascii_case("TRUE"),
+                        name: name.to_string(),
+                        value: value.to_string(),
+                    })
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,286 @@
+//! Optional encryption at rest for the files rester persists. Files are encrypted with
+//! ChaCha20-Poly1305 using a key derived from a passphrase with Argon2.
+//!
+//! The encrypted format is the magic prefix, followed by the salt, the nonce, and then the
+//! ciphertext. Individually encrypted values, like environment variables, are stored in the same
+//! format, base64 encoded after a prefix.
+
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+use crate::paths;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const MAGIC: &[u8] = b"RESTER-ENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const VALUE_PREFIX: &str = "enc:";
+
+static CIPHER: OnceLock<Cipher> = OnceLock::new();
+
+struct Cipher {
+    passphrase: String,
+    /// Salt and key used when writing files. Derived once, because derivation is slow.
+    salt: [u8; SALT_LEN],
+    key: Key,
+    encrypt_writes: bool,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> io::Result<Key> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| io::Error::other(format!("Unable to derive the key: {:}", err)))?;
+    Ok(key)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+impl Cipher {
+    fn new(passphrase: &str, encrypt_writes: bool) -> io::Result<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Ok(Cipher {
+            passphrase: passphrase.to_string(),
+            key: derive_key(passphrase, &salt)?,
+            salt,
+            encrypt_writes,
+        })
+    }
+
+    fn encrypt(&self, contents: &[u8]) -> io::Result<Vec<u8>> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = ChaCha20Poly1305::new(&self.key)
+            .encrypt(&nonce, contents)
+            .map_err(|_| invalid_data("Unable to encrypt"))?;
+
+        let mut res = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+        res.extend_from_slice(MAGIC);
+        res.extend_from_slice(&self.salt);
+        res.extend_from_slice(&nonce);
+        res.extend_from_slice(&ciphertext);
+        Ok(res)
+    }
+
+    fn decrypt(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let data = &data[MAGIC.len()..];
+        if data.len() < SALT_LEN + NONCE_LEN {
+            return Err(invalid_data("Encrypted file is truncated"));
+        }
+        let (salt, data) = data.split_at(SALT_LEN);
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+
+        let key = if salt == self.salt {
+            self.key
+        } else {
+            derive_key(self.passphrase.as_str(), salt)?
+        };
+        ChaCha20Poly1305::new(&key)
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| invalid_data("Unable to decrypt, the passphrase may be incorrect"))
+    }
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Check if any of the data files are currently encrypted.
+pub fn has_encrypted_files() -> bool {
+    paths::get().data_files().iter().any(|path| {
+        fs::read(path)
+            .map(|data| is_encrypted(&data))
+            .unwrap_or(false)
+    })
+}
+
+/// Provide the passphrase for this session. When `encrypt_writes` is false encrypted files can
+/// still be read, but will be written back in plain text.
+pub fn unlock(passphrase: &str, encrypt_writes: bool) -> io::Result<()> {
+    if CIPHER
+        .set(Cipher::new(passphrase, encrypt_writes)?)
+        .is_err()
+    {
+        error!("Encryption was already unlocked");
+    }
+    Ok(())
+}
+
+/// Provide the passphrase part way through the session, for individually encrypted values. Files
+/// are still written in plain text. When a `sample` encrypted value is given the passphrase must
+/// decrypt it.
+pub fn unlock_values(passphrase: &str, sample: Option<&str>) -> io::Result<()> {
+    let cipher = Cipher::new(passphrase, false)?;
+    if let Some(sample) = sample {
+        decrypt_value_with(&cipher, sample)?;
+    }
+    if CIPHER.set(cipher).is_err() {
+        error!("Encryption was already unlocked");
+    }
+    Ok(())
+}
+
+/// A passphrase has been given this session.
+pub fn is_unlocked() -> bool {
+    CIPHER.get().is_some()
+}
+
+/// Files are encrypted when they are written.
+pub fn encrypts_writes() -> bool {
+    CIPHER.get().is_some_and(|cipher| cipher.encrypt_writes)
+}
+
+pub fn is_encrypted_value(value: &str) -> bool {
+    value.starts_with(VALUE_PREFIX)
+}
+
+/// Encrypt a single value, for storing in a file which is otherwise plain text.
+pub fn encrypt_value(value: &str) -> io::Result<String> {
+    let cipher = CIPHER
+        .get()
+        .ok_or_else(|| invalid_data("No passphrase was provided to encrypt with"))?;
+    let data = cipher.encrypt(value.as_bytes())?;
+    Ok(format!("{:}{:}", VALUE_PREFIX, base64::encode(data)))
+}
+
+pub fn decrypt_value(value: &str) -> io::Result<String> {
+    match CIPHER.get() {
+        Some(cipher) => decrypt_value_with(cipher, value),
+        None => Err(invalid_data(
+            "Value is encrypted and no passphrase was provided",
+        )),
+    }
+}
+
+fn decrypt_value_with(cipher: &Cipher, value: &str) -> io::Result<String> {
+    let data = value
+        .strip_prefix(VALUE_PREFIX)
+        .and_then(|encoded| base64::decode(encoded).ok())
+        .filter(|data| is_encrypted(data))
+        .ok_or_else(|| invalid_data("Encrypted value is malformed"))?;
+    String::from_utf8(cipher.decrypt(&data)?)
+        .map_err(|_| invalid_data("Encrypted value is not valid text"))
+}
+
+/// Verify the passphrase can decrypt all of the data files.
+pub fn verify() -> io::Result<()> {
+    for path in paths::get().data_files() {
+        if path.exists() {
+            read(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Rewrite the data files so they match the current setting, encrypting plain text protected
+/// files when encryption is enabled and decrypting files when it has been disabled, or when they
+/// aren't protected.
+pub fn rewrite_protected_files() -> io::Result<()> {
+    for path in paths::get().data_files() {
+        if path.exists() {
+            let contents = read(path)?;
+            write(path, &contents)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read a file, decrypting it if it is encrypted.
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    let data = fs::read(path)?;
+    if !is_encrypted(&data) {
+        return Ok(data);
+    }
+    match CIPHER.get() {
+        Some(cipher) => cipher.decrypt(&data),
+        None => Err(invalid_data(
+            "File is encrypted and no passphrase was provided",
+        )),
+    }
+}
+
+/// Write a file, encrypting it if encryption at rest is enabled and it is one of the protected
+/// files.
+pub fn write(path: &Path, contents: &[u8]) -> io::Result<()> {
+    match CIPHER.get() {
+        Some(cipher) if cipher.encrypt_writes && paths::get().protected_files().contains(&path) => {
+            write_replacing(path, &cipher.encrypt(contents)?)
+        }
+        _ => write_replacing(path, contents),
+    }
+}
+
+/// Write to a temporary file which is then renamed over the file, so a crash part way through
+/// leaves the old contents instead of a truncated file which can't be decrypted.
+fn write_replacing(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    let mut file = File::create(&temporary)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let cipher = Cipher::new("correct horse", true).unwrap();
+        let encrypted = cipher.encrypt(b"secret contents").unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), b"secret contents");
+        // A new cipher has its own salt, so the key is derived again from the file's salt.
+        let other = Cipher::new("correct horse", false).unwrap();
+        assert_eq!(other.decrypt(&encrypted).unwrap(), b"secret contents");
+    }
+
+    #[test]
+    fn wrong_passphrase() {
+        let encrypted = Cipher::new("correct horse", true)
+            .unwrap()
+            .encrypt(b"secret contents")
+            .unwrap();
+        let err = Cipher::new("wrong horse", true)
+            .unwrap()
+            .decrypt(&encrypted)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn truncated() {
+        let cipher = Cipher::new("correct horse", true).unwrap();
+        assert!(cipher.decrypt(MAGIC).is_err());
+    }
+
+    #[test]
+    fn value_round_trip() {
+        let cipher = Cipher::new("correct horse", true).unwrap();
+        let encrypted = format!(
+            "{:}{:}",
+            VALUE_PREFIX,
+            base64::encode(cipher.encrypt(b"token").unwrap())
+        );
+        assert!(is_encrypted_value(encrypted.as_str()));
+        assert_eq!(
+            decrypt_value_with(&cipher, encrypted.as_str()).unwrap(),
+            "token"
+        );
+        assert!(decrypt_value_with(
+            &Cipher::new("wrong horse", true).unwrap(),
+            encrypted.as_str()
+        )
+        .is_err());
+        assert!(decrypt_value_with(&cipher, "enc:not base64!").is_err());
+    }
+}
@@ -0,0 +1,336 @@
+//! Importing a request from a `curl` command, the form most API documentation gives its examples
+//! in.
+
+use crate::error::{Error, Result};
+use crate::query::encode;
+use crate::{BodyType, Method};
+
+/// The parts of a request given by a curl command.
+pub struct CurlRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: String,
+    pub body: String,
+    pub body_type: BodyType,
+    pub proxy: Option<String>,
+}
+
+/// Options which take a value that isn't part of the request, so the value isn't mistaken for the
+/// URL.
+const IGNORED_WITH_VALUE: [&str; 12] = [
+    "-o",
+    "--output",
+    "-m",
+    "--max-time",
+    "--connect-timeout",
+    "-w",
+    "--write-out",
+    "--retry",
+    "-c",
+    "--cookie-jar",
+    "--cacert",
+    "--resolve",
+];
+
+/// Split a command line into arguments as a POSIX shell would, handling quotes, backslash escapes
+/// and lines continued with a backslash.
+fn split_args(command: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err(Error::Parse("Unterminated ' quote".to_string())),
+                    }
+                }
+            }
+            '"' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // Only these characters are escaped within double quotes.
+                        Some('\\') if matches!(chars.peek(), Some('"' | '\\' | '$' | '`')) => {
+                            arg.push(chars.next().unwrap_or('\\'))
+                        }
+                        Some('\\') if chars.peek() == Some(&'\n') => {
+                            chars.next();
+                        }
+                        Some(c) => arg.push(c),
+                        None => return Err(Error::Parse("Unterminated \" quote".to_string())),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some('\n') | None => {}
+                Some(c) => current.get_or_insert_with(String::new).push(c),
+            },
+            c if c.is_whitespace() => {
+                if let Some(arg) = current.take() {
+                    args.push(arg);
+                }
+            }
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(current);
+    Ok(args)
+}
+
+/// The value of an option, either attached like `-XPOST` and `--request=POST` or the next
+/// argument.
+fn option_value(
+    arg: &str,
+    attached: Option<&str>,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<String> {
+    match attached {
+        Some(value) => Ok(value.to_string()),
+        None => args
+            .next()
+            .ok_or_else(|| Error::Parse(format!("{:} needs a value", arg))),
+    }
+}
+
+/// Data given with `@file` is read from the file, as curl does.
+fn data_value(value: String) -> Result<String> {
+    match value.strip_prefix('@') {
+        Some(path) => Ok(std::fs::read_to_string(path)?),
+        None => Ok(value),
+    }
+}
+
+fn has_header(headers: &[String], name: &str) -> bool {
+    headers.iter().any(|header| {
+        header
+            .split_once(':')
+            .is_some_and(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+    })
+}
+
+/// Parse a curl command, which can be pasted across several lines continued with `\`. Options
+/// which don't change the request, like `-s` and `-k`, are ignored.
+pub fn parse(command: &str) -> Result<CurlRequest> {
+    let mut args = split_args(command.trim())?.into_iter().peekable();
+    if args.peek().map(String::as_str) == Some("curl") {
+        args.next();
+    }
+
+    let mut method = None;
+    let mut url = None;
+    let mut headers: Vec<String> = Vec::new();
+    let mut data: Vec<String> = Vec::new();
+    // Parts of a multipart form, in the `name=value` or `name=@file` form they are edited in.
+    let mut form: Vec<String> = Vec::new();
+    let mut get = false;
+    let mut head = false;
+    let mut json = false;
+    let mut proxy = None;
+
+    while let Some(arg) = args.next() {
+        // Long options can have their value attached with `=`, short ones directly.
+        let (name, attached) = if arg.starts_with("--") {
+            match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg.clone(), None),
+            }
+        } else if arg.starts_with('-') && arg.chars().count() > 2 {
+            let split = arg
+                .char_indices()
+                .nth(2)
+                .map_or(arg.len(), |(index, _)| index);
+            (arg[..split].to_string(), Some(arg[split..].to_string()))
+        } else {
+            (arg.clone(), None)
+        };
+        let attached = attached.as_deref();
+
+        match name.as_str() {
+            "-X" | "--request" => {
+                method = Some(Method::parse(&option_value(&name, attached, &mut args)?))
+            }
+            "-H" | "--header" => headers.push(option_value(&name, attached, &mut args)?),
+            "-d" | "--data" | "--data-ascii" | "--data-binary" => {
+                let value = option_value(&name, attached, &mut args)?;
+                data.push(data_value(value)?);
+            }
+            "--data-raw" => data.push(option_value(&name, attached, &mut args)?),
+            "--data-urlencode" => {
+                let value = option_value(&name, attached, &mut args)?;
+                data.push(match value.split_once('=') {
+                    Some((name, value)) => format!("{:}={:}", name, encode(value)),
+                    None => encode(value.as_str()),
+                });
+            }
+            "--json" => {
+                let value = option_value(&name, attached, &mut args)?;
+                data.push(data_value(value)?);
+                json = true;
+            }
+            "-u" | "--user" => {
+                let credentials = option_value(&name, attached, &mut args)?;
+                headers.push(format!(
+                    "Authorization: Basic {:}",
+                    base64::encode(credentials)
+                ));
+            }
+            "-A" | "--user-agent" => headers.push(format!(
+                "User-Agent: {:}",
+                option_value(&name, attached, &mut args)?
+            )),
+            "-e" | "--referer" => headers.push(format!(
+                "Referer: {:}",
+                option_value(&name, attached, &mut args)?
+            )),
+            "-b" | "--cookie" => {
+                let cookie = option_value(&name, attached, &mut args)?;
+                // Without an `=` the value is a file to read cookies from.
+                if cookie.contains('=') {
+                    headers.push(format!("Cookie: {:}", cookie));
+                }
+            }
+            "-G" | "--get" => get = true,
+            "-I" | "--head" => head = true,
+            "-x" | "--proxy" => proxy = Some(option_value(&name, attached, &mut args)?),
+            "--url" => url = Some(option_value(&name, attached, &mut args)?),
+            "-F" | "--form" => form.push(option_value(&name, attached, &mut args)?),
+            name if IGNORED_WITH_VALUE.contains(&name) => {
+                option_value(name, attached, &mut args)?;
+            }
+            name if name.starts_with('-') => {}
+            _ => url = Some(arg),
+        }
+    }
+
+    let mut url = url.ok_or_else(|| Error::Parse("The curl command has no URL".to_string()))?;
+    if !form.is_empty() && !data.is_empty() {
+        return Err(Error::Parse(
+            "A curl command can't send both -F and -d data".to_string(),
+        ));
+    }
+    let body_type = if form.is_empty() {
+        BodyType::Raw
+    } else {
+        BodyType::Multipart
+    };
+    let mut body = if form.is_empty() {
+        data.join("&")
+    } else {
+        form.join("\n")
+    };
+    if get && body_type.is_raw() && !body.is_empty() {
+        url.push(if url.contains('?') { '&' } else { '?' });
+        url.push_str(body.as_str());
+        body.clear();
+    }
+    if json {
+        if !has_header(&headers, "Content-Type") {
+            headers.push("Content-Type: application/json".to_string());
+        }
+        if !has_header(&headers, "Accept") {
+            headers.push("Accept: application/json".to_string());
+        }
+    } else if body_type.is_raw() && !body.is_empty() && !has_header(&headers, "Content-Type") {
+        // Curl sends data as a form unless told otherwise.
+        headers.push("Content-Type: application/x-www-form-urlencoded".to_string());
+    }
+    let method = match method {
+        Some(method) => method,
+        None if head => Method::HEAD,
+        None if get => Method::GET,
+        None if !body.is_empty() => Method::POST,
+        None => Method::GET,
+    };
+
+    Ok(CurlRequest {
+        method,
+        url,
+        headers: headers.join("\n"),
+        body,
+        body_type,
+        proxy,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_command_across_lines() {
+        let request = parse(
+            "curl -X PUT 'https://example.com/items/1' \\\n  -H 'Content-Type: application/json' \\\n  --data-raw '{\"name\": \"a b\"}'",
+        )
+        .unwrap();
+        assert_eq!(request.method, Method::PUT);
+        assert_eq!(request.url, "https://example.com/items/1");
+        assert_eq!(request.headers, "Content-Type: application/json");
+        assert_eq!(request.body, "{\"name\": \"a b\"}");
+        assert!(request.body_type.is_raw());
+    }
+
+    #[test]
+    fn data_is_posted_as_a_form() {
+        let request = parse("curl https://example.com -d a=1 --data-urlencode 'b=x y'").unwrap();
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.body, "a=1&b=x%20y");
+        assert_eq!(
+            request.headers,
+            "Content-Type: application/x-www-form-urlencoded"
+        );
+    }
+
+    #[test]
+    fn get_adds_data_to_the_url() {
+        let request = parse("curl -G https://example.com/search?x=1 -d q=a").unwrap();
+        assert_eq!(request.method, Method::GET);
+        assert_eq!(request.url, "https://example.com/search?x=1&q=a");
+        assert!(request.body.is_empty());
+    }
+
+    #[test]
+    fn attached_values_and_ignored_options() {
+        let request = parse(
+            "curl -s -k -XDELETE --url=https://example.com/a -o out.txt -uuser:pass -x http://proxy:8080",
+        )
+        .unwrap();
+        assert_eq!(request.method, Method::DELETE);
+        assert_eq!(request.url, "https://example.com/a");
+        assert_eq!(request.headers, "Authorization: Basic dXNlcjpwYXNz");
+        assert_eq!(request.proxy.as_deref(), Some("http://proxy:8080"));
+    }
+
+    #[test]
+    fn json_adds_headers() {
+        let request =
+            parse("curl --json '{}' -H 'accept: text/plain' https://example.com").unwrap();
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(
+            request.headers,
+            "accept: text/plain\nContent-Type: application/json"
+        );
+    }
+
+    #[test]
+    fn form_parts() {
+        let request = parse("curl -F name=a -F 'file=@b.txt' https://example.com").unwrap();
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.body, "name=a\nfile=@b.txt");
+        assert_eq!(request.body_type, BodyType::Multipart);
+        assert!(request.headers.is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse("curl -s").is_err());
+        assert!(parse("curl 'https://example.com").is_err());
+        assert!(parse("curl -F a=1 -d b=2 https://example.com").is_err());
+        assert!(parse("curl https://example.com -H").is_err());
+    }
+}
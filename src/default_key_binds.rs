@@ -19,6 +19,11 @@ pub fn default_key_binds() -> Vec<KeyBind> {
             modifiers: KeyModifiers::CONTROL,
             key: KeyCode::Char('h'),
         },
+        KeyBind {
+            operation: Operation::GotoRequestParams,
+            modifiers: KeyModifiers::CONTROL,
+            key: KeyCode::Char('g'),
+        },
         KeyBind {
             operation: Operation::GotoResponseBody,
             modifiers: KeyModifiers::CONTROL,
@@ -59,11 +64,290 @@ pub fn default_key_binds() -> Vec<KeyBind> {
             modifiers: KeyModifiers::CONTROL,
             key: KeyCode::Char('q'),
         },
+        // Windows Terminal uses Alt+Enter to toggle full screen.
         KeyBind {
             operation: Operation::SendRequest,
-            modifiers: KeyModifiers::ALT,
+            modifiers: if cfg!(windows) {
+                KeyModifiers::CONTROL
+            } else {
+                KeyModifiers::ALT
+            },
             key: KeyCode::Enter,
         },
+        KeyBind {
+            operation: Operation::InsertFile,
+            modifiers: KeyModifiers::CONTROL,
+            key: KeyCode::Char('o'),
+        },
+        KeyBind {
+            operation: Operation::DuplicateLine,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('d'),
+        },
+        // Windows Terminal uses Alt with the arrow keys to move between panes.
+        KeyBind {
+            operation: Operation::MoveLineUp,
+            modifiers: KeyModifiers::ALT,
+            key: if cfg!(windows) {
+                KeyCode::Char('k')
+            } else {
+                KeyCode::Up
+            },
+        },
+        KeyBind {
+            operation: Operation::MoveLineDown,
+            modifiers: KeyModifiers::ALT,
+            key: if cfg!(windows) {
+                KeyCode::Char('j')
+            } else {
+                KeyCode::Down
+            },
+        },
+        KeyBind {
+            operation: Operation::ToggleComment,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('/'),
+        },
+        KeyBind {
+            operation: Operation::FocusLeft,
+            modifiers: KeyModifiers::CONTROL,
+            key: KeyCode::Left,
+        },
+        KeyBind {
+            operation: Operation::FocusRight,
+            modifiers: KeyModifiers::CONTROL,
+            key: KeyCode::Right,
+        },
+        KeyBind {
+            operation: Operation::FocusUp,
+            modifiers: KeyModifiers::CONTROL,
+            key: KeyCode::Up,
+        },
+        KeyBind {
+            operation: Operation::FocusDown,
+            modifiers: KeyModifiers::CONTROL,
+            key: KeyCode::Down,
+        },
+        KeyBind {
+            operation: Operation::NextMode,
+            modifiers: KeyModifiers::NONE,
+            key: KeyCode::Tab,
+        },
+        KeyBind {
+            operation: Operation::PreviousMode,
+            modifiers: KeyModifiers::SHIFT,
+            key: KeyCode::BackTab,
+        },
+        KeyBind {
+            operation: Operation::ShowHistory,
+            modifiers: KeyModifiers::CONTROL,
+            key: KeyCode::Char('y'),
+        },
+        KeyBind {
+            operation: Operation::ToggleProxyBypass,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('p'),
+        },
+        KeyBind {
+            operation: Operation::SelectEnvironment,
+            modifiers: KeyModifiers::CONTROL,
+            key: KeyCode::Char('e'),
+        },
+        KeyBind {
+            operation: Operation::ExportAuditLog,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('a'),
+        },
+        KeyBind {
+            operation: Operation::ToggleOffline,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('o'),
+        },
+        KeyBind {
+            operation: Operation::ToggleResponseSplit,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('v'),
+        },
+        KeyBind {
+            operation: Operation::RecordMacro,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('r'),
+        },
+        KeyBind {
+            operation: Operation::PlayMacro,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('m'),
+        },
+        KeyBind {
+            operation: Operation::AlternateRequest,
+            modifiers: KeyModifiers::CONTROL,
+            key: KeyCode::Char('t'),
+        },
+        KeyBind {
+            operation: Operation::Suspend,
+            modifiers: KeyModifiers::CONTROL,
+            key: KeyCode::Char('z'),
+        },
+        KeyBind {
+            operation: Operation::ToggleProfiler,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('f'),
+        },
+        KeyBind {
+            operation: Operation::ExportSession,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('x'),
+        },
+        KeyBind {
+            operation: Operation::PostProcess,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('c'),
+        },
+        KeyBind {
+            operation: Operation::ImportSession,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('i'),
+        },
+        KeyBind {
+            operation: Operation::ImportCurl,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('u'),
+        },
+        KeyBind {
+            operation: Operation::ImportCookies,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('g'),
+        },
+        KeyBind {
+            operation: Operation::ToggleSendBody,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('b'),
+        },
+        KeyBind {
+            operation: Operation::NextBodyType,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('y'),
+        },
+        KeyBind {
+            operation: Operation::Download,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('z'),
+        },
+        KeyBind {
+            operation: Operation::SetTimeout,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('n'),
+        },
+        KeyBind {
+            operation: Operation::SetProxy,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('h'),
+        },
+        KeyBind {
+            operation: Operation::ImportOpenApi,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('q'),
+        },
+        KeyBind {
+            operation: Operation::ExportHar,
+            modifiers: KeyModifiers::CONTROL,
+            key: KeyCode::Char('x'),
+        },
+        KeyBind {
+            operation: Operation::ImportInsomnia,
+            modifiers: KeyModifiers::CONTROL,
+            key: KeyCode::Char('k'),
+        },
+        KeyBind {
+            operation: Operation::RunCollection,
+            modifiers: KeyModifiers::CONTROL,
+            key: KeyCode::Char('f'),
+        },
+        KeyBind {
+            operation: Operation::GotoResultsView,
+            modifiers: KeyModifiers::CONTROL,
+            key: KeyCode::Char('v'),
+        },
+        KeyBind {
+            operation: Operation::EditAssertions,
+            modifiers: KeyModifiers::CONTROL,
+            key: KeyCode::Char('d'),
+        },
+        KeyBind {
+            operation: Operation::EditCaptures,
+            modifiers: KeyModifiers::CONTROL,
+            key: KeyCode::Char('l'),
+        },
+        KeyBind {
+            operation: Operation::EditKeyBinds,
+            modifiers: KeyModifiers::NONE,
+            key: KeyCode::F(2),
+        },
+        KeyBind {
+            operation: Operation::ShowHelp,
+            modifiers: KeyModifiers::NONE,
+            key: KeyCode::F(1),
+        },
+        KeyBind {
+            operation: Operation::NewTab,
+            modifiers: KeyModifiers::NONE,
+            key: KeyCode::F(3),
+        },
+        KeyBind {
+            operation: Operation::CloseTab,
+            modifiers: KeyModifiers::NONE,
+            key: KeyCode::F(4),
+        },
+        KeyBind {
+            operation: Operation::NextTab,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Right,
+        },
+        KeyBind {
+            operation: Operation::PreviousTab,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Left,
+        },
+        KeyBind {
+            operation: Operation::EditBodyExternally,
+            modifiers: KeyModifiers::NONE,
+            key: KeyCode::F(5),
+        },
+        KeyBind {
+            operation: Operation::CopyResponse,
+            modifiers: KeyModifiers::NONE,
+            key: KeyCode::F(6),
+        },
+        KeyBind {
+            operation: Operation::Paste,
+            modifiers: KeyModifiers::NONE,
+            key: KeyCode::F(7),
+        },
+        KeyBind {
+            operation: Operation::EditPrompts,
+            modifiers: KeyModifiers::NONE,
+            key: KeyCode::F(8),
+        },
+        KeyBind {
+            operation: Operation::ProbeOptions,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('l'),
+        },
+        KeyBind {
+            operation: Operation::ProbeWellKnown,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('w'),
+        },
+        KeyBind {
+            operation: Operation::SetupOidc,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('t'),
+        },
+        KeyBind {
+            operation: Operation::ToggleSentRequest,
+            modifiers: KeyModifiers::ALT,
+            key: KeyCode::Char('e'),
+        },
         KeyBind {
             operation: Operation::Quit,
             modifiers: KeyModifiers::CONTROL,
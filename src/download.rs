@@ -0,0 +1,32 @@
+//! Downloading a response body straight to a file, for bodies too large to show.
+
+use crate::json_view::format_bytes;
+use std::time::Instant;
+
+/// Progress of a response body being written to a file.
+pub struct Download {
+    pub path: String,
+    pub bytes: u64,
+    pub started: Instant,
+}
+
+impl Download {
+    pub fn new(path: &str) -> Self {
+        Download {
+            path: path.to_string(),
+            bytes: 0,
+            started: Instant::now(),
+        }
+    }
+
+    /// Like `Downloading to out.bin: 12.5 MiB at 3.1 MiB/s`.
+    pub fn label(&self) -> String {
+        let seconds = self.started.elapsed().as_secs_f64().max(0.001);
+        format!(
+            "Downloading to {:}: {:} at {:}/s",
+            self.path,
+            format_bytes(self.bytes as f64),
+            format_bytes(self.bytes as f64 / seconds)
+        )
+    }
+}
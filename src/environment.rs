@@ -0,0 +1,355 @@
+use crate::error::Result;
+use crate::history::now;
+use crate::oauth2::{self, OAuth2};
+use crate::output::output_path;
+use crate::persistence::KeyValuePair;
+use crate::token_refresh::TokenRefresh;
+use crate::{crypto, paths};
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Environment {
+    pub name: String,
+    /// Mutating requests need to be confirmed while a read only environment is active.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub read_only: bool,
+    /// Host patterns, like `*.staging.example.com`, the environment's requests are expected to
+    /// go to. A warning is shown for a request to any other host.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<String>,
+    #[serde(default)]
+    pub variables: Vec<KeyValuePair>,
+    /// Refresh a token and retry when a request is rejected as unauthorized.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_refresh: Option<TokenRefresh>,
+    /// Fetch OAuth2 tokens, cached in the environment's variables, and send them as a Bearer
+    /// token. This takes the place of the token refresh.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oauth2: Option<OAuth2>,
+    /// Names of variables holding secrets, which are left out when exporting.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<String>,
+    /// Names of variables whose values are encrypted in the environments file. The passphrase is
+    /// asked for the first time one is needed. They are also treated as secrets.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub encrypted: Vec<String>,
+}
+
+impl Environment {
+    /// Secrets are the variables listed as secret, and the tokens maintained by a token refresh or
+    /// OAuth2.
+    pub fn is_secret(&self, name: &str) -> bool {
+        self.secrets.iter().any(|secret| secret == name)
+            || self.encrypted.iter().any(|encrypted| encrypted == name)
+            || self
+                .token_refresh
+                .as_ref()
+                .is_some_and(|refresh| refresh.variable == name)
+            || (self.oauth2.is_some() && oauth2::is_token_variable(name))
+    }
+
+    /// A copy of the environment with the values of its secrets blanked.
+    pub fn without_secrets(&self) -> Environment {
+        let mut res = self.clone();
+        for variable in res.variables.iter_mut() {
+            if self.is_secret(variable.key.as_str()) {
+                variable.value.clear();
+            }
+        }
+        res
+    }
+
+    /// Copy the variables of another environment into this one. Empty values, like the secrets
+    /// blanked in an export, don't overwrite values which are already set.
+    fn merge(&mut self, other: Environment) {
+        for variable in other.variables {
+            if variable.value.is_empty() && self.get(variable.key.as_str()).is_some() {
+                continue;
+            }
+            self.set(variable.key.as_str(), variable.value.as_str());
+        }
+        for secret in other.secrets {
+            if !self.secrets.contains(&secret) {
+                self.secrets.push(secret);
+            }
+        }
+        for encrypted in other.encrypted {
+            if !self.encrypted.contains(&encrypted) {
+                self.encrypted.push(encrypted);
+            }
+        }
+        self.read_only = other.read_only;
+        if !other.hosts.is_empty() {
+            self.hosts = other.hosts;
+        }
+        if other.token_refresh.is_some() {
+            self.token_refresh = other.token_refresh;
+        }
+        if other.oauth2.is_some() {
+            self.oauth2 = other.oauth2;
+        }
+    }
+
+    /// The passphrase is needed to decrypt the encrypted variables, or to encrypt ones which were
+    /// written in plain text.
+    pub fn needs_passphrase(&self) -> bool {
+        !self.encrypted.is_empty() && !crypto::is_unlocked()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.variables
+            .iter()
+            .find(|variable| variable.key == name)
+            .map(|variable| variable.value.as_str())
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) {
+        match self
+            .variables
+            .iter_mut()
+            .find(|variable| variable.key == name)
+        {
+            Some(variable) => variable.value = value.to_string(),
+            None => self.variables.push(KeyValuePair {
+                key: name.to_string(),
+                value: value.to_string(),
+            }),
+        }
+    }
+}
+
+/// Replace `{{name}}` references with the values of variables. References to unknown variables
+/// are left as they are.
+pub fn substitute(text: &str, environment: Option<&Environment>) -> String {
+    let environment = match environment {
+        Some(environment) => environment,
+        None => return text.to_string(),
+    };
+
+    let mut res = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        res.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let name = after[..end].trim();
+                match environment.get(name) {
+                    Some(value) => res.push_str(value),
+                    None => res.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                res.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    res.push_str(rest);
+    res
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Environments {
+    /// Name of the active environment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active: Option<String>,
+    #[serde(default)]
+    pub environments: Vec<Environment>,
+}
+
+impl Environments {
+    pub fn active(&self) -> Option<&Environment> {
+        let active = self.active.as_ref()?;
+        self.environments
+            .iter()
+            .find(|environment| &environment.name == active)
+    }
+
+    pub fn active_mut(&mut self) -> Option<&mut Environment> {
+        let active = self.active.clone()?;
+        self.environments
+            .iter_mut()
+            .find(|environment| environment.name == active)
+    }
+
+    pub fn set_active(&mut self, name: Option<String>) {
+        self.active = name;
+    }
+
+    /// Write the environments to a standalone file which can be shared. Secret values are blanked
+    /// unless `include_secrets` is set. Returns the name of the file.
+    pub fn export(&self, include_secrets: bool) -> Result<String> {
+        let environments: Vec<Environment> = self
+            .environments
+            .iter()
+            .map(|environment| {
+                if include_secrets {
+                    environment.clone()
+                } else {
+                    environment.without_secrets()
+                }
+            })
+            .collect();
+        let exported = Environments {
+            active: None,
+            environments,
+        };
+        let path = output_path(format!("environments-export-{:}", now()).as_str(), "json");
+        fs::write(&path, serde_json::to_string_pretty(&exported)?)?;
+        Ok(path.display().to_string())
+    }
+
+    /// Merge the environments from an exported file into these ones. Returns the number of
+    /// environments imported.
+    pub fn import(&mut self, path: &str) -> Result<usize> {
+        let imported: Environments = serde_json::from_slice(&fs::read(path)?)?;
+        let count = imported.environments.len();
+        for environment in imported.environments {
+            self.add(environment);
+        }
+        self.decrypt_variables()?;
+        Ok(count)
+    }
+
+    /// Add an environment, merging it into an existing one with the same name.
+    pub fn add(&mut self, environment: Environment) {
+        match self
+            .environments
+            .iter_mut()
+            .find(|existing| existing.name == environment.name)
+        {
+            Some(existing) => existing.merge(environment),
+            None => self.environments.push(environment),
+        }
+    }
+
+    /// An encrypted value, for checking a passphrase can decrypt it.
+    pub fn encrypted_sample(&self) -> Option<&str> {
+        self.environments.iter().find_map(|environment| {
+            environment
+                .variables
+                .iter()
+                .filter(|variable| environment.encrypted.contains(&variable.key))
+                .map(|variable| variable.value.as_str())
+                .find(|value| crypto::is_encrypted_value(value))
+        })
+    }
+
+    /// Decrypt the values of the encrypted variables, once the passphrase has been given. Values
+    /// which can't be decrypted are left as they are.
+    pub fn decrypt_variables(&mut self) -> Result<()> {
+        if !crypto::is_unlocked() {
+            return Ok(());
+        }
+        let mut res = Ok(());
+        for environment in self.environments.iter_mut() {
+            for variable in environment.variables.iter_mut() {
+                if !environment.encrypted.contains(&variable.key)
+                    || !crypto::is_encrypted_value(variable.value.as_str())
+                {
+                    continue;
+                }
+                match crypto::decrypt_value(variable.value.as_str()) {
+                    Ok(value) => variable.value = value,
+                    Err(err) => {
+                        error!("Error decrypting {:} {:}", variable.key, err);
+                        if res.is_ok() {
+                            res = Err(err.into());
+                        }
+                    }
+                }
+            }
+        }
+        res
+    }
+
+    /// Encrypted variables are written encrypted once the passphrase has been given, until then
+    /// they are written as they were read.
+    pub fn save(&self) -> Result<()> {
+        let mut environments = Environments {
+            active: self.active.clone(),
+            environments: self.environments.clone(),
+        };
+        if crypto::is_unlocked() {
+            for environment in environments.environments.iter_mut() {
+                for variable in environment.variables.iter_mut() {
+                    if environment.encrypted.contains(&variable.key)
+                        && !crypto::is_encrypted_value(variable.value.as_str())
+                    {
+                        variable.value = crypto::encrypt_value(variable.value.as_str())?;
+                    }
+                }
+            }
+        }
+        let serialized = serde_json::to_string_pretty(&environments)?;
+        crypto::write(&paths::get().environments, serialized.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn load() -> Result<Self> {
+        if !paths::get().environments.exists() {
+            return Ok(Self::default());
+        }
+        let contents = crypto::read(&paths::get().environments)?;
+        let mut environments: Self = serde_json::from_slice(&contents)?;
+        // Values which can't be decrypted are sent as they are, rather than losing the file.
+        let _ = environments.decrypt_variables();
+        Ok(environments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn environment(variables: &[(&str, &str)]) -> Environment {
+        let mut environment = Environment::default();
+        for (name, value) in variables {
+            environment.set(name, value);
+        }
+        environment
+    }
+
+    #[test]
+    fn substitutes_variables() {
+        let environment = environment(&[("host", "example.com"), ("id", "7")]);
+        assert_eq!(
+            substitute("https://{{host}}/items/{{ id }}", Some(&environment)),
+            "https://example.com/items/7"
+        );
+    }
+
+    #[test]
+    fn leaves_unset_variables() {
+        let environment = environment(&[("host", "example.com")]);
+        assert_eq!(
+            substitute("https://{{host}}/items/{{id}}", Some(&environment)),
+            "https://example.com/items/{{id}}"
+        );
+        assert_eq!(substitute("{{host}}", None), "{{host}}");
+        assert_eq!(substitute("{{host", Some(&environment)), "{{host");
+    }
+
+    #[test]
+    fn prompt_values_before_environment() {
+        // Prompt values are substituted first, then the environment fills in the rest.
+        let prompts = environment(&[("id", "42")]);
+        let environment = environment(&[("host", "example.com"), ("id", "7")]);
+        let url = substitute("https://{{host}}/items/{{id}}", Some(&prompts));
+        assert_eq!(url, "https://{{host}}/items/42");
+        assert_eq!(
+            substitute(url.as_str(), Some(&environment)),
+            "https://example.com/items/42"
+        );
+    }
+
+    #[test]
+    fn values_are_not_substituted_again() {
+        let environment = environment(&[("a", "{{b}}"), ("b", "x")]);
+        assert_eq!(substitute("{{a}}", Some(&environment)), "{{b}}");
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+/// Errors from reading and writing files, and from sending requests.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Serde(serde_json::Error),
+    Network(reqwest::Error),
+    /// Input which couldn't be understood, like a malformed file.
+    Parse(String),
+    /// The request handler couldn't take a request.
+    Handler(&'static str),
+    /// An external command failed.
+    Command(String),
+    /// The system clipboard couldn't be used.
+    Clipboard(String),
+    /// A safety rule stopped the request from being sent.
+    Refused(String),
+    /// Something asked for by name isn't there, or isn't set, like a saved request named on the
+    /// command line.
+    NotFound(String),
+    /// The response wasn't complete within the configured timeout.
+    Timeout(Duration),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "File error: {:}", err),
+            Error::Serde(err) => write!(f, "Invalid JSON: {:}", err),
+            Error::Network(err) => write!(f, "Request failed: {:}", err),
+            Error::Parse(message) => write!(f, "{:}", message),
+            Error::Handler(message) => write!(f, "{:}", message),
+            Error::Command(message) => write!(f, "{:}", message),
+            Error::Clipboard(message) => write!(f, "{:}", message),
+            Error::Refused(message) => write!(f, "{:}", message),
+            Error::NotFound(message) => write!(f, "{:}", message),
+            Error::Timeout(timeout) => write!(
+                f,
+                "The request timed out after {:}s without a complete response. To wait longer, raise \
+                 request_timeout_secs in the settings.",
+                timeout.as_secs()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Serde(err)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Network(err)
+    }
+}
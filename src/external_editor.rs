@@ -0,0 +1,72 @@
+//! Editing text in the user's own editor, named by `$VISUAL` or `$EDITOR`, which is a lot quicker
+//! for composing a large body than the built in editor. The text is written to a temporary file,
+//! and read back once the editor exits.
+
+use crate::content_type::sniff;
+use crate::error::{Error, Result};
+
+use std::fs;
+use std::process::Command;
+
+/// The editor used when neither `$VISUAL` nor `$EDITOR` is set.
+const DEFAULT_EDITOR: &str = if cfg!(windows) { "notepad" } else { "vi" };
+
+/// The editor to run, which may include arguments like `code --wait`.
+pub fn editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string())
+}
+
+/// Open the text in the editor, waiting for it to exit. Returns the text as it was saved. The
+/// terminal must be given up to the editor while it runs.
+pub fn edit(text: &str) -> Result<String> {
+    let path = std::env::temp_dir().join(format!(
+        "rester-body-{:}.{:}",
+        std::process::id(),
+        extension(text)
+    ));
+    fs::write(&path, text)?;
+    let res = run(editor().as_str(), &path);
+    let edited = res.and_then(|_| Ok(fs::read_to_string(&path)?));
+    let _ = fs::remove_file(&path);
+    edited
+}
+
+/// The extension of the temporary file, so the editor highlights the body.
+fn extension(text: &str) -> &'static str {
+    match sniff(text) {
+        Some("application/json") => "json",
+        Some("application/xml") => "xml",
+        Some("text/html") => "html",
+        _ if text.trim().is_empty() => "json",
+        _ => "txt",
+    }
+}
+
+fn run(editor: &str, path: &std::path::Path) -> Result<()> {
+    // The editor is run by the shell, so arguments in $EDITOR work as they would there.
+    let status = if cfg!(windows) {
+        Command::new("cmd")
+            .arg("/C")
+            .arg(format!("{:} \"{:}\"", editor, path.display()))
+            .status()
+    } else {
+        Command::new("sh")
+            .arg("-c")
+            .arg(format!("{:} \"$1\"", editor))
+            .arg("sh")
+            .arg(path)
+            .status()
+    }
+    .map_err(|err| Error::Command(format!("Couldn't run `{:}`: {:}", editor, err)))?;
+    if !status.success() {
+        return Err(Error::Command(format!(
+            "`{:}` failed ({:}), so the body wasn't changed",
+            editor, status
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,440 @@
+//! Formatters which turn a response body into readable text, chosen by the content type of the
+//! response. Adding a format only needs a new entry in `FORMATTERS`. The settings can put some
+//! formatters ahead of the others, and disable formatters which shouldn't be used.
+
+use crate::guard::glob_match;
+use crate::preview::csv_table;
+use crate::settings::Settings;
+use serde_json::{Map, Number, Value};
+
+pub struct Formatter {
+    pub name: &'static str,
+    /// Content type patterns, like `*+json`, matched against the type without its parameters.
+    pub patterns: &'static [&'static str],
+    /// Formatters which need the whole body are given it once the response is complete, others
+    /// are given each chunk on its own as it arrives.
+    pub whole_body: bool,
+    /// Returns `None` when the body isn't in the format.
+    pub format: fn(&[u8]) -> Option<String>,
+}
+
+/// The formatters, tried in this order unless the settings give them a priority.
+pub static FORMATTERS: [Formatter; 7] = [
+    Formatter {
+        name: "json",
+        patterns: &["*json*"],
+        whole_body: false,
+        format: format_json,
+    },
+    Formatter {
+        name: "xml",
+        patterns: &["*xml*"],
+        whole_body: true,
+        format: format_xml,
+    },
+    Formatter {
+        name: "html",
+        patterns: &["text/html"],
+        whole_body: true,
+        format: format_html,
+    },
+    Formatter {
+        name: "csv",
+        patterns: &["text/csv"],
+        whole_body: true,
+        format: format_csv,
+    },
+    Formatter {
+        name: "msgpack",
+        patterns: &["*msgpack*"],
+        whole_body: true,
+        format: format_msgpack,
+    },
+    Formatter {
+        name: "protobuf",
+        patterns: &["*protobuf*"],
+        whole_body: true,
+        format: format_protobuf,
+    },
+    Formatter {
+        name: "hex",
+        patterns: &[
+            "application/octet-stream",
+            "application/pdf",
+            "application/zip",
+            "image/*",
+            "audio/*",
+            "video/*",
+        ],
+        whole_body: true,
+        format: format_hex,
+    },
+];
+
+/// The formatter for a content type, taking the priority and disabled formatters in the settings
+/// into account.
+pub fn find(content_type: &str, settings: &Settings) -> Option<&'static Formatter> {
+    let essence = content_type.split(';').next().unwrap_or("").trim();
+    let mut formatters: Vec<&'static Formatter> = FORMATTERS
+        .iter()
+        .filter(|formatter| {
+            !settings
+                .disabled_formatters
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(formatter.name))
+        })
+        .collect();
+    // Sorting is stable, so formatters without a priority keep their order after the others.
+    formatters.sort_by_key(|formatter| {
+        settings
+            .formatter_priority
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(formatter.name))
+            .unwrap_or(usize::MAX)
+    });
+    formatters.into_iter().find(|formatter| {
+        formatter
+            .patterns
+            .iter()
+            .any(|pattern| glob_match(pattern, essence))
+    })
+}
+
+/// Format a whole body, falling back to the text of the body when no formatter handles it.
+pub fn format_body(content_type: &str, body: &[u8], settings: &Settings) -> String {
+    find(content_type, settings)
+        .and_then(|formatter| (formatter.format)(body))
+        .unwrap_or_else(|| String::from_utf8_lossy(body).to_string())
+}
+
+fn format_json(body: &[u8]) -> Option<String> {
+    jsonxf::pretty_print(String::from_utf8_lossy(body).as_ref()).ok()
+}
+
+fn format_csv(body: &[u8]) -> Option<String> {
+    std::str::from_utf8(body).ok().map(csv_table)
+}
+
+fn format_xml(body: &[u8]) -> Option<String> {
+    indent_markup(std::str::from_utf8(body).ok()?, &[], &[])
+}
+
+fn format_html(body: &[u8]) -> Option<String> {
+    const VOID_ELEMENTS: [&str; 14] = [
+        "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param",
+        "source", "track", "wbr",
+    ];
+    const RAW_TEXT_ELEMENTS: [&str; 2] = ["script", "style"];
+    indent_markup(
+        std::str::from_utf8(body).ok()?,
+        &VOID_ELEMENTS,
+        &RAW_TEXT_ELEMENTS,
+    )
+}
+
+fn tag_name(tag: &str) -> String {
+    tag.trim_start_matches(['<', '/'])
+        .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .next()
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+fn push_line(res: &mut String, depth: usize, line: &str) {
+    res.push_str("  ".repeat(depth).as_str());
+    res.push_str(line);
+    res.push('\n');
+}
+
+/// Put each tag of an XML or HTML document on its own line, indented by depth. An element which
+/// only contains text is kept on one line. Void elements have no closing tag, and the content of
+/// raw text elements is kept as it is.
+fn indent_markup(text: &str, void_elements: &[&str], raw_text_elements: &[&str]) -> Option<String> {
+    let mut rest = text.trim_start_matches('\u{feff}').trim();
+    if !rest.starts_with('<') {
+        return None;
+    }
+    let mut res = String::new();
+    let mut depth = 0usize;
+    while !rest.is_empty() {
+        if !rest.starts_with('<') {
+            let end = rest.find('<').unwrap_or(rest.len());
+            for line in rest[..end].lines().map(str::trim) {
+                if !line.is_empty() {
+                    push_line(&mut res, depth, line);
+                }
+            }
+            rest = &rest[end..];
+            continue;
+        }
+
+        let end_marker = if rest.starts_with("<!--") {
+            "-->"
+        } else if rest.starts_with("<![CDATA[") {
+            "]]>"
+        } else {
+            ">"
+        };
+        let end = rest.find(end_marker)? + end_marker.len();
+        let tag = &rest[..end];
+        rest = &rest[end..];
+        let name = tag_name(tag);
+
+        if tag.starts_with("</") {
+            depth = depth.saturating_sub(1);
+            push_line(&mut res, depth, tag);
+        } else if tag.starts_with("<?")
+            || tag.starts_with("<!")
+            || tag.ends_with("/>")
+            || void_elements.contains(&name.as_str())
+        {
+            push_line(&mut res, depth, tag);
+        } else if raw_text_elements.contains(&name.as_str()) {
+            let close = rest
+                .to_ascii_lowercase()
+                .find(format!("</{:}", name).as_str())
+                .unwrap_or(rest.len());
+            push_line(&mut res, depth, tag);
+            for line in rest[..close].lines().filter(|line| !line.trim().is_empty()) {
+                push_line(&mut res, depth + 1, line.trim_end());
+            }
+            rest = &rest[close..];
+            depth += 1;
+        } else {
+            let text_end = rest.find('<').unwrap_or(rest.len());
+            let after_text = &rest[text_end..];
+            match after_text.find('>') {
+                Some(close_end)
+                    if after_text.starts_with("</")
+                        && tag_name(&after_text[..close_end]) == name =>
+                {
+                    let line = format!(
+                        "{:}{:}{:}",
+                        tag,
+                        rest[..text_end].trim(),
+                        &after_text[..close_end + 1]
+                    );
+                    push_line(&mut res, depth, line.as_str());
+                    rest = &after_text[close_end + 1..];
+                }
+                _ => {
+                    push_line(&mut res, depth, tag);
+                    depth += 1;
+                }
+            }
+        }
+        rest = rest.trim_start();
+    }
+    Some(res)
+}
+
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if bytes.len() < len {
+        return None;
+    }
+    let (taken, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Some(taken)
+}
+
+fn take_uint(bytes: &mut &[u8], len: usize) -> Option<u64> {
+    Some(
+        take(bytes, len)?
+            .iter()
+            .fold(0, |value, byte| (value << 8) | *byte as u64),
+    )
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Decode one MessagePack value. Binary data and extension types are shown as hex.
+fn msgpack_value(bytes: &mut &[u8]) -> Option<Value> {
+    let marker = take_uint(bytes, 1)? as u8;
+    let value = match marker {
+        0x00..=0x7f => Value::from(marker),
+        0x80..=0x8f => msgpack_map(bytes, (marker & 0x0f) as usize)?,
+        0x90..=0x9f => msgpack_array(bytes, (marker & 0x0f) as usize)?,
+        0xa0..=0xbf => msgpack_str(bytes, (marker & 0x1f) as usize)?,
+        0xc0 => Value::Null,
+        0xc2 => Value::Bool(false),
+        0xc3 => Value::Bool(true),
+        0xc4..=0xc6 => {
+            let len = take_uint(bytes, 1 << (marker - 0xc4))? as usize;
+            Value::String(hex(take(bytes, len)?))
+        }
+        0xc7..=0xc9 => {
+            let len = take_uint(bytes, 1 << (marker - 0xc7))? as usize;
+            msgpack_ext(bytes, len)?
+        }
+        0xca => Value::from(f32::from_bits(take_uint(bytes, 4)? as u32) as f64),
+        0xcb => Value::from(f64::from_bits(take_uint(bytes, 8)?)),
+        0xcc..=0xcf => Value::from(take_uint(bytes, 1 << (marker - 0xcc))?),
+        0xd0..=0xd3 => {
+            let len = 1 << (marker - 0xd0);
+            let value = take_uint(bytes, len)?;
+            // Sign extend from the width of the value.
+            let shift = 64 - len * 8;
+            Value::from(((value << shift) as i64) >> shift)
+        }
+        0xd4..=0xd8 => msgpack_ext(bytes, 1 << (marker - 0xd4))?,
+        0xd9..=0xdb => {
+            let len = take_uint(bytes, 1 << (marker - 0xd9))? as usize;
+            msgpack_str(bytes, len)?
+        }
+        0xdc | 0xdd => {
+            let len = take_uint(bytes, if marker == 0xdc { 2 } else { 4 })? as usize;
+            msgpack_array(bytes, len)?
+        }
+        0xde | 0xdf => {
+            let len = take_uint(bytes, if marker == 0xde { 2 } else { 4 })? as usize;
+            msgpack_map(bytes, len)?
+        }
+        0xe0..=0xff => Value::from(marker as i8),
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn msgpack_str(bytes: &mut &[u8], len: usize) -> Option<Value> {
+    Some(Value::String(
+        String::from_utf8_lossy(take(bytes, len)?).to_string(),
+    ))
+}
+
+fn msgpack_ext(bytes: &mut &[u8], len: usize) -> Option<Value> {
+    let ext_type = take_uint(bytes, 1)? as i8;
+    let mut map = Map::new();
+    map.insert("ext".to_string(), Value::Number(Number::from(ext_type)));
+    map.insert("data".to_string(), Value::String(hex(take(bytes, len)?)));
+    Some(Value::Object(map))
+}
+
+fn msgpack_array(bytes: &mut &[u8], len: usize) -> Option<Value> {
+    // The length comes from the body, so it can't be trusted to size the array.
+    let mut items = Vec::new();
+    for _ in 0..len {
+        items.push(msgpack_value(bytes)?);
+    }
+    Some(Value::Array(items))
+}
+
+fn msgpack_map(bytes: &mut &[u8], len: usize) -> Option<Value> {
+    let mut map = Map::new();
+    for _ in 0..len {
+        let key = match msgpack_value(bytes)? {
+            Value::String(key) => key,
+            key => key.to_string(),
+        };
+        map.insert(key, msgpack_value(bytes)?);
+    }
+    Some(Value::Object(map))
+}
+
+/// MessagePack shown as JSON. A body with several values shows each of them in turn.
+fn format_msgpack(body: &[u8]) -> Option<String> {
+    let mut bytes = body;
+    let mut values = Vec::new();
+    while !bytes.is_empty() {
+        values.push(serde_json::to_string_pretty(&msgpack_value(&mut bytes)?).ok()?);
+    }
+    (!values.is_empty()).then(|| values.join("\n"))
+}
+
+fn varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = take_uint(bytes, 1)?;
+        value |= (byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Decode the fields of a protobuf message without its schema, like `protoc --decode_raw`.
+/// Length delimited fields are shown as text when they are, otherwise as a nested message if
+/// they parse as one, otherwise as hex.
+fn protobuf_fields(mut bytes: &[u8], depth: usize, res: &mut String) -> Option<()> {
+    while !bytes.is_empty() {
+        let key = varint(&mut bytes)?;
+        let field = key >> 3;
+        if field == 0 {
+            return None;
+        }
+        let line = match key & 7 {
+            0 => format!("{:}: {:}", field, varint(&mut bytes)?),
+            1 => format!(
+                "{:}: 0x{:016x}",
+                field,
+                take_uint(&mut bytes, 8)?.swap_bytes()
+            ),
+            2 => {
+                let len = varint(&mut bytes)? as usize;
+                let data = take(&mut bytes, len)?;
+                match std::str::from_utf8(data) {
+                    Ok(text) if !text.chars().any(|c| c.is_control() && c != '\n') => {
+                        format!("{:}: {:?}", field, text)
+                    }
+                    _ => {
+                        let mut nested = String::new();
+                        if !data.is_empty()
+                            && protobuf_fields(data, depth + 1, &mut nested).is_some()
+                        {
+                            push_line(res, depth, format!("{:} {{", field).as_str());
+                            res.push_str(nested.as_str());
+                            "}".to_string()
+                        } else {
+                            format!("{:}: 0x{:}", field, hex(data))
+                        }
+                    }
+                }
+            }
+            5 => format!(
+                "{:}: 0x{:08x}",
+                field,
+                (take_uint(&mut bytes, 4)? as u32).swap_bytes()
+            ),
+            // Groups are deprecated and not decoded.
+            _ => return None,
+        };
+        push_line(res, depth, line.as_str());
+    }
+    Some(())
+}
+
+fn format_protobuf(body: &[u8]) -> Option<String> {
+    let mut res = String::new();
+    protobuf_fields(body, 0, &mut res)?;
+    Some(res)
+}
+
+/// Lines of 16 bytes, like `00000010  68 65 6c 6c 6f  |hello|`. Only the start of a large body
+/// is shown.
+fn format_hex(body: &[u8]) -> Option<String> {
+    const LIMIT: usize = 64 * 1024;
+
+    let mut res = String::new();
+    for (index, chunk) in body[..body.len().min(LIMIT)].chunks(16).enumerate() {
+        let bytes: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let text: String = chunk
+            .iter()
+            .map(|byte| {
+                if byte.is_ascii_graphic() || *byte == b' ' {
+                    *byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        res.push_str(
+            format!("{:08x}  {:<47}  |{:}|\n", index * 16, bytes.join(" "), text).as_str(),
+        );
+    }
+    if body.len() > LIMIT {
+        res.push_str(format!("... {:} more bytes\n", body.len() - LIMIT).as_str());
+    }
+    Some(res)
+}
@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+/// Timings of recent frames, shown in the debug overlay.
+#[derive(Default, Debug)]
+pub struct FrameStats {
+    /// Time taken to draw the last frame.
+    pub render: Duration,
+    /// Time from reading the last input until the frame showing its effect was drawn.
+    pub input_latency: Duration,
+    pub frames: u64,
+    /// Frames which took longer to draw than the poll interval.
+    pub dropped: u64,
+}
+
+impl FrameStats {
+    pub fn record_render(&mut self, duration: Duration, budget: Duration) {
+        self.render = duration;
+        self.frames += 1;
+        if duration > budget {
+            self.dropped += 1;
+        }
+    }
+
+    pub fn record_input(&mut self, latency: Duration) {
+        self.input_latency = latency;
+    }
+
+    pub fn lines(&self) -> String {
+        format!(
+            "render {:.1?}\ninput {:.1?}\ndropped {:}/{:}",
+            self.render, self.input_latency, self.dropped, self.frames
+        )
+    }
+}
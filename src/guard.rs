@@ -0,0 +1,196 @@
+use crate::environment::Environment;
+use crate::settings::Settings;
+use crate::Method;
+use reqwest::Url;
+
+/// Match text against a pattern where `*` matches any sequence of characters and `?` matches a
+/// single character. Matching is case insensitive.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last star in the pattern, and the text position it was tried at.
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Determine if sending a request needs to be confirmed first. Returns the reason confirmation
+/// is required.
+pub fn confirmation_reason(
+    method: &Method,
+    url: &str,
+    settings: &Settings,
+    environment: Option<&Environment>,
+) -> Option<String> {
+    if !method.is_mutating() {
+        return None;
+    }
+    let method_str = method.as_str();
+
+    if let Some(environment) = environment {
+        if environment.read_only {
+            return Some(format!(
+                "{:} while the read only environment {:} is active",
+                method_str, environment.name
+            ));
+        }
+    }
+
+    let host = Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_string()))?;
+
+    settings
+        .confirm_hosts
+        .iter()
+        .find(|pattern| glob_match(pattern, host.as_str()))
+        .map(|pattern| format!("{:} to {:} matches {:}", method_str, host, pattern))
+}
+
+/// The request was checked before the pre-send hook ran, so when the hook changes where it goes
+/// the new request is checked again. Returns the reason when it needs a confirmation the original
+/// request didn't, since there's no way to ask for it once the hook has run.
+pub fn changed_request_reason(
+    (method, url): (&Method, &str),
+    (changed_method, changed_url): (&Method, &str),
+    settings: &Settings,
+    environment: Option<&Environment>,
+) -> Option<String> {
+    if changed_method == method && changed_url == url {
+        return None;
+    }
+    let reason = confirmation_reason(changed_method, changed_url, settings, environment)?;
+    match confirmation_reason(method, url, settings, environment) {
+        Some(original) if original == reason => None,
+        _ => Some(reason),
+    }
+}
+
+/// Check the host of a request is one the environment expects, to catch a production URL being
+/// sent with a staging environment. Returns a warning if it isn't.
+pub fn host_warning(url: &str, environment: Option<&Environment>) -> Option<String> {
+    let environment = environment.filter(|environment| !environment.hosts.is_empty())?;
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    if environment
+        .hosts
+        .iter()
+        .any(|pattern| glob_match(pattern, host))
+    {
+        return None;
+    }
+    Some(format!(
+        "{:} is not a host of the {:} environment, which expects {:}",
+        host,
+        environment.name,
+        environment.hosts.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(confirm_hosts: &[&str]) -> Settings {
+        Settings {
+            confirm_hosts: confirm_hosts.iter().map(|host| host.to_string()).collect(),
+            ..Settings::default()
+        }
+    }
+
+    fn environment(read_only: bool, hosts: &[&str]) -> Environment {
+        Environment {
+            name: "production".to_string(),
+            read_only,
+            hosts: hosts.iter().map(|host| host.to_string()).collect(),
+            ..Environment::default()
+        }
+    }
+
+    #[test]
+    fn glob_match_wildcards() {
+        assert!(glob_match("*prod*", "api.prod.example.com"));
+        assert!(glob_match("*.example.com", "api.example.com"));
+        assert!(!glob_match("*.example.com", "example.com"));
+        assert!(glob_match("api-?.example.com", "api-1.example.com"));
+        assert!(!glob_match("api-?.example.com", "api-10.example.com"));
+        assert!(glob_match("API.EXAMPLE.COM", "api.example.com"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("", "api.example.com"));
+    }
+
+    #[test]
+    fn confirmation_reason_for_hosts() {
+        let settings = settings(&["*prod*"]);
+        let url = "https://api.prod.example.com/items";
+        assert_eq!(
+            confirmation_reason(&Method::DELETE, url, &settings, None).as_deref(),
+            Some("DELETE to api.prod.example.com matches *prod*")
+        );
+        assert_eq!(
+            confirmation_reason(&Method::GET, url, &settings, None),
+            None
+        );
+        assert_eq!(
+            confirmation_reason(
+                &Method::POST,
+                "https://api.staging.example.com/items",
+                &settings,
+                None
+            ),
+            None
+        );
+        assert_eq!(
+            confirmation_reason(&Method::POST, "not a url", &settings, None),
+            None
+        );
+    }
+
+    #[test]
+    fn confirmation_reason_for_read_only_environment() {
+        let settings = settings(&[]);
+        let url = "https://api.example.com/items";
+        let read_only = environment(true, &[]);
+        assert_eq!(
+            confirmation_reason(&Method::PUT, url, &settings, Some(&read_only)).as_deref(),
+            Some("PUT while the read only environment production is active")
+        );
+        assert_eq!(
+            confirmation_reason(&Method::HEAD, url, &settings, Some(&read_only)),
+            None
+        );
+        let writable = environment(false, &[]);
+        assert_eq!(
+            confirmation_reason(&Method::PUT, url, &settings, Some(&writable)),
+            None
+        );
+    }
+
+    #[test]
+    fn host_warning_for_unexpected_host() {
+        let environment = environment(false, &["*.staging.example.com"]);
+        assert_eq!(
+            host_warning("https://api.staging.example.com/", Some(&environment)),
+            None
+        );
+        assert!(host_warning("https://api.example.com/", Some(&environment)).is_some());
+        assert_eq!(host_warning("https://api.example.com/", None), None);
+    }
+}
@@ -0,0 +1,338 @@
+//! Exporting exchanges from the history as a HAR (HTTP Archive) file, which browsers and most
+//! HTTP tools can open. Headers are written as they were kept in the history, so the redacted
+//! ones stay masked.
+
+use crate::error::Result;
+use crate::history::HistoryEntry;
+use crate::output::output_path;
+use reqwest::{StatusCode, Url};
+use serde::Serialize;
+use std::fs;
+
+#[derive(Serialize)]
+struct Har {
+    log: Log,
+}
+
+#[derive(Serialize)]
+struct Log {
+    version: &'static str,
+    creator: Creator,
+    entries: Vec<Entry>,
+}
+
+#[derive(Serialize)]
+struct Creator {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    started_date_time: String,
+    /// Milliseconds from sending the request until the response ended.
+    time: u64,
+    request: HarRequest,
+    response: HarResponse,
+    cache: Cache,
+    timings: Timings,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarRequest {
+    method: String,
+    url: String,
+    http_version: &'static str,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    query_string: Vec<NameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_data: Option<PostData>,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarResponse {
+    status: u16,
+    status_text: String,
+    http_version: &'static str,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    content: Content,
+    #[serde(rename = "redirectURL")]
+    redirect_url: String,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Serialize)]
+struct NameValue {
+    name: String,
+    value: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PostData {
+    mime_type: String,
+    text: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Content {
+    size: i64,
+    mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Cache {}
+
+/// Phases of the exchange in milliseconds. Connecting is counted as waiting, -1 is not known.
+#[derive(Serialize)]
+struct Timings {
+    send: i64,
+    wait: i64,
+    receive: i64,
+}
+
+/// Write the entries to a new HAR file named after `name`. Returns the name of the file.
+pub fn export(entries: &[HistoryEntry], name: &str) -> Result<String> {
+    let har = Har {
+        log: Log {
+            version: "1.2",
+            creator: Creator {
+                name: "rester",
+                version: env!("CARGO_PKG_VERSION"),
+            },
+            entries: entries.iter().map(entry).collect(),
+        },
+    };
+    let path = output_path(format!("har-{:}", name).as_str(), "har");
+    fs::write(&path, serde_json::to_string_pretty(&har)?)?;
+    Ok(path.display().to_string())
+}
+
+fn entry(exchange: &HistoryEntry) -> Entry {
+    let request_headers = request_headers(exchange.headers.as_str());
+    let response_headers = response_headers(exchange.response_headers.as_str());
+    let header = |headers: &[NameValue], name: &str| {
+        headers
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case(name))
+            .map(|header| header.value.clone())
+            .unwrap_or_default()
+    };
+
+    let query_string = Url::parse(exchange.url.as_str())
+        .map(|url| {
+            url.query_pairs()
+                .map(|(name, value)| NameValue {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let post_data = (!exchange.body.is_empty()).then(|| PostData {
+        mime_type: header(&request_headers, "Content-Type"),
+        text: exchange.body.clone(),
+    });
+    let content = Content {
+        size: exchange
+            .response_body
+            .as_ref()
+            .map_or(-1, |body| body.len() as i64),
+        mime_type: header(&response_headers, "Content-Type"),
+        text: exchange.response_body.clone(),
+    };
+    let elapsed = exchange.elapsed_ms.map_or(-1, |elapsed| elapsed as i64);
+    let wait = exchange.wait_ms.map_or(-1, |wait| wait as i64);
+
+    Entry {
+        started_date_time: iso8601(exchange.timestamp),
+        time: elapsed.max(0) as u64,
+        request: HarRequest {
+            method: exchange.method.as_str().to_string(),
+            url: exchange.url.clone(),
+            http_version: "HTTP/1.1",
+            cookies: Vec::new(),
+            query_string,
+            post_data,
+            headers_size: -1,
+            body_size: exchange.body.len() as i64,
+            headers: request_headers,
+        },
+        response: HarResponse {
+            status: exchange.status,
+            status_text: StatusCode::from_u16(exchange.status)
+                .ok()
+                .and_then(|status| status.canonical_reason())
+                .unwrap_or("")
+                .to_string(),
+            http_version: "HTTP/1.1",
+            cookies: Vec::new(),
+            redirect_url: header(&response_headers, "Location"),
+            content,
+            headers_size: -1,
+            body_size: -1,
+            headers: response_headers,
+        },
+        cache: Cache {},
+        timings: Timings {
+            send: 0,
+            wait,
+            receive: if elapsed < 0 || wait < 0 {
+                -1
+            } else {
+                elapsed - wait
+            },
+        },
+    }
+}
+
+/// Headers of a request, written as `Name: value` lines.
+fn request_headers(headers: &str) -> Vec<NameValue> {
+    headers
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| NameValue {
+            name: name.trim().to_string(),
+            value: value.trim().to_string(),
+        })
+        .collect()
+}
+
+/// Headers of a response, kept as a formatted map with a `"name": "value"` line each. Other
+/// lines, like the redirects listed above the headers, are skipped.
+fn response_headers(headers: &str) -> Vec<NameValue> {
+    headers
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim().trim_end_matches(',');
+            let (name, value) = line.split_once("\": ")?;
+            let name = name.strip_prefix('"')?;
+            let value = value.trim();
+            // Only the enclosing quotes, a value can end with an escaped quote.
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value);
+            Some(NameValue {
+                name: name.to_string(),
+                value: value.replace("\\\"", "\"").replace("\\\\", "\\"),
+            })
+        })
+        .collect()
+}
+
+/// A time in seconds since the Unix epoch, like `2024-01-01T12:00:00Z`.
+fn iso8601(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let seconds = timestamp % 86400;
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// The date in the proleptic Gregorian calendar of a number of days since the Unix epoch.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Method;
+
+    fn exchange() -> HistoryEntry {
+        HistoryEntry {
+            timestamp: 1_700_000_000,
+            method: Method::POST,
+            url: "https://example.com/items?a=1&b=x%20y".to_string(),
+            headers: "Content-Type: application/json\nAuthorization: ********".to_string(),
+            body: "{}".to_string(),
+            status: 201,
+            response_headers:
+                "{\n  \"content-type\": \"text/plain\",\n  \"location\": \"/items/1\"\n}"
+                    .to_string(),
+            response_body: Some("created".to_string()),
+            proxy: None,
+            wait_ms: Some(40),
+            elapsed_ms: Some(50),
+        }
+    }
+
+    #[test]
+    fn writes_an_entry() {
+        let entry = serde_json::to_value(entry(&exchange())).unwrap();
+        assert_eq!(entry["startedDateTime"], "2023-11-14T22:13:20Z");
+        assert_eq!(entry["time"], 50);
+        assert_eq!(entry["request"]["method"], "POST");
+        assert_eq!(entry["request"]["headers"][1]["value"], "********");
+        assert_eq!(entry["request"]["queryString"][1]["value"], "x y");
+        assert_eq!(entry["request"]["postData"]["mimeType"], "application/json");
+        assert_eq!(entry["response"]["status"], 201);
+        assert_eq!(entry["response"]["statusText"], "Created");
+        assert_eq!(entry["response"]["redirectURL"], "/items/1");
+        assert_eq!(entry["response"]["content"]["mimeType"], "text/plain");
+        assert_eq!(entry["response"]["content"]["text"], "created");
+        assert_eq!(entry["timings"]["receive"], 10);
+    }
+
+    #[test]
+    fn unknown_timings() {
+        let mut exchange = exchange();
+        exchange.body.clear();
+        exchange.wait_ms = None;
+        exchange.elapsed_ms = None;
+        exchange.response_body = None;
+        let entry = serde_json::to_value(entry(&exchange)).unwrap();
+        assert_eq!(entry["time"], 0);
+        assert_eq!(entry["timings"]["wait"], -1);
+        assert_eq!(entry["timings"]["receive"], -1);
+        assert_eq!(entry["response"]["content"]["size"], -1);
+        assert!(entry["request"].get("postData").is_none());
+    }
+
+    #[test]
+    fn response_headers_skip_other_lines() {
+        let headers = response_headers("Redirected from /a\n{\n  \"x-quote\": \"a \\\"b\\\"\"\n}");
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[0].name, "x-quote");
+        assert_eq!(headers[0].value, "a \"b\"");
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(iso8601(0), "1970-01-01T00:00:00Z");
+        assert_eq!(iso8601(951_782_400), "2000-02-29T00:00:00Z");
+    }
+}
@@ -0,0 +1,153 @@
+//! Finding the tokens of JSON and XML response bodies to colour them. Each line is tokenized on
+//! its own, which suits formatted bodies with a value or tag per line.
+
+use std::ops::Range;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Token {
+    Key,
+    String,
+    Number,
+    /// `true`, `false` and `null`.
+    Literal,
+    /// The name and brackets of an XML tag.
+    Tag,
+    Attribute,
+    Comment,
+    /// A note added by a line transform, like how long ago a timestamp was.
+    Annotation,
+}
+
+/// The tokens of a line, as byte ranges in order. Text which isn't part of a token is left out.
+pub type Tokens = Vec<(Range<usize>, Token)>;
+
+pub type Highlighter = fn(&str) -> Tokens;
+
+/// The highlighter for a content type, if it is one which is highlighted.
+pub fn highlighter(content_type: &str) -> Option<Highlighter> {
+    if content_type.contains("json") {
+        Some(json_tokens)
+    } else if content_type.contains("xml") || content_type.contains("html") {
+        Some(markup_tokens)
+    } else {
+        None
+    }
+}
+
+/// The index after the string starting at `start`, which is after its closing quote unless the
+/// line ends first.
+fn string_end(bytes: &[u8], start: usize, quote: u8) -> usize {
+    let mut escaped = false;
+    for (index, byte) in bytes.iter().enumerate().skip(start + 1) {
+        match *byte {
+            _ if escaped => escaped = false,
+            b'\\' if quote == b'"' => escaped = true,
+            byte if byte == quote => return index + 1,
+            _ => {}
+        }
+    }
+    bytes.len()
+}
+
+/// The index of the first byte from `start` which doesn't match.
+fn run_end(bytes: &[u8], start: usize, matches: impl Fn(u8) -> bool) -> usize {
+    bytes[start..]
+        .iter()
+        .position(|byte| !matches(*byte))
+        .map_or(bytes.len(), |position| start + position)
+}
+
+/// Tokens of a line of formatted JSON. A string followed by a colon is a key.
+pub fn json_tokens(line: &str) -> Tokens {
+    let bytes = line.as_bytes();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        let start = index;
+        match bytes[index] {
+            b'"' => {
+                index = string_end(bytes, start, b'"');
+                let token = if line[index..].trim_start().starts_with(':') {
+                    Token::Key
+                } else {
+                    Token::String
+                };
+                tokens.push((start..index, token));
+            }
+            b'-' | b'0'..=b'9' => {
+                index = run_end(bytes, start + 1, |byte| {
+                    matches!(byte, b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-')
+                });
+                tokens.push((start..index, Token::Number));
+            }
+            // Parentheses only appear in JSON within strings, so they start an annotation.
+            b'(' => {
+                tokens.push((start..bytes.len(), Token::Annotation));
+                break;
+            }
+            byte if byte.is_ascii_alphabetic() => {
+                index = run_end(bytes, start, |byte| byte.is_ascii_alphabetic());
+                if matches!(&line[start..index], "true" | "false" | "null") {
+                    tokens.push((start..index, Token::Literal));
+                }
+            }
+            _ => index += 1,
+        }
+    }
+    tokens
+}
+
+/// Tokens of a line of XML or HTML. Text between tags isn't highlighted.
+pub fn markup_tokens(line: &str) -> Tokens {
+    let bytes = line.as_bytes();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        let start = index;
+        if bytes[index] != b'<' {
+            index += 1;
+            continue;
+        }
+        if line[index..].starts_with("<!--") {
+            index = line[index..]
+                .find("-->")
+                .map_or(bytes.len(), |end| index + end + 3);
+            tokens.push((start..index, Token::Comment));
+            continue;
+        }
+
+        index = run_end(bytes, start + 1, |byte| matches!(byte, b'/' | b'?' | b'!'));
+        index = run_end(bytes, index, |byte| {
+            !byte.is_ascii_whitespace() && !matches!(byte, b'>' | b'/')
+        });
+        tokens.push((start..index, Token::Tag));
+        while index < bytes.len() {
+            let start = index;
+            match bytes[index] {
+                b'>' => {
+                    index += 1;
+                    tokens.push((start..index, Token::Tag));
+                    break;
+                }
+                b'/' | b'?' if bytes.get(index + 1) == Some(&b'>') => {
+                    index += 2;
+                    tokens.push((start..index, Token::Tag));
+                    break;
+                }
+                quote @ (b'"' | b'\'') => {
+                    index = string_end(bytes, start, quote);
+                    tokens.push((start..index, Token::String));
+                }
+                byte if byte.is_ascii_whitespace() || byte == b'=' => index += 1,
+                _ => {
+                    index = run_end(bytes, start, |byte| {
+                        !byte.is_ascii_whitespace() && !matches!(byte, b'=' | b'>' | b'/')
+                    })
+                    .max(start + 1);
+                    tokens.push((start..index, Token::Attribute));
+                }
+            }
+        }
+    }
+    tokens
+}
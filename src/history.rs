@@ -0,0 +1,200 @@
+use crate::error::Result;
+use crate::settings::Settings;
+use crate::{crypto, paths, Method};
+
+use serde::{Deserialize, Serialize};
+
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+    /// Seconds since the unix epoch when the request was sent.
+    pub timestamp: u64,
+    pub method: Method,
+    pub url: String,
+    pub headers: String,
+    pub body: String,
+    /// Zero when no response was received.
+    pub status: u16,
+    pub response_headers: String,
+    /// The response body, this is `None` once it has been deleted from the history.
+    pub response_body: Option<String>,
+    /// The proxy the request was sent through.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// Milliseconds from sending the request until the response started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_ms: Option<u64>,
+    /// Milliseconds from sending the request until the response ended.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elapsed_ms: Option<u64>,
+}
+
+impl HistoryEntry {
+    /// Approximate size of the entry on disk.
+    pub fn size(&self) -> usize {
+        serde_json::to_vec(self).map_or(0, |serialized| serialized.len())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct History {
+    /// Entries ordered from oldest to newest.
+    pub entries: Vec<HistoryEntry>,
+    /// Approximate size of the entries on disk, kept up to date as they change so it can be
+    /// shown without serializing the whole history.
+    #[serde(skip)]
+    disk_usage: usize,
+    /// Counts the snapshots taken, so an older one isn't written over a newer one.
+    #[serde(skip)]
+    generation: u64,
+}
+
+/// The entries serialized for saving. It's taken while the history is locked, and written after
+/// the lock is released, so a large history doesn't hold up everything else waiting on the lock.
+pub struct Snapshot {
+    generation: u64,
+    serialized: String,
+}
+
+impl Snapshot {
+    /// Write the snapshot to the history file, unless a newer one has already been written.
+    pub fn write(self) -> Result<()> {
+        static WRITTEN: Mutex<u64> = Mutex::new(0);
+        let mut written = WRITTEN.lock().unwrap();
+        if *written > self.generation {
+            return Ok(());
+        }
+        crypto::write(&paths::get().history, self.serialized.as_bytes())?;
+        *written = self.generation;
+        Ok(())
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// Format the time elapsed since a timestamp in a short human readable form.
+pub fn format_age(timestamp: u64) -> String {
+    let elapsed = now().saturating_sub(timestamp);
+    match elapsed {
+        0..=59 => format!("{:}s ago", elapsed),
+        60..=3599 => format!("{:}m ago", elapsed / 60),
+        3600..=86399 => format!("{:}h ago", elapsed / 3600),
+        _ => format!("{:}d ago", elapsed / 86400),
+    }
+}
+
+impl History {
+    pub fn add(&mut self, entry: HistoryEntry, settings: &Settings) {
+        self.disk_usage += entry.size();
+        self.entries.push(entry);
+        self.prune(settings);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.entries.len() {
+            let entry = self.entries.remove(index);
+            self.disk_usage -= entry.size();
+        }
+    }
+
+    /// Delete the stored response body of an entry while keeping the request.
+    pub fn remove_response(&mut self, index: usize) {
+        if let Some(entry) = self.entries.get_mut(index) {
+            let before = entry.size();
+            entry.response_body = None;
+            self.disk_usage = self.disk_usage - before + entry.size();
+        }
+    }
+
+    /// The most recent entry for a request which received a response.
+    pub fn find_response(&self, method: &Method, url: &str) -> Option<&HistoryEntry> {
+        self.entries.iter().rev().find(|entry| {
+            &entry.method == method && entry.url == url && entry.response_body.is_some()
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.disk_usage = 0;
+    }
+
+    pub fn disk_usage(&self) -> usize {
+        self.disk_usage
+    }
+
+    /// Remove entries which fall outside of the retention policy. Returns the number of entries
+    /// which were removed.
+    pub fn prune(&mut self, settings: &Settings) -> usize {
+        let before = self.entries.len();
+
+        if let Some(max_age_days) = settings.history_max_age_days {
+            let cutoff = now().saturating_sub(max_age_days * 24 * 60 * 60);
+            let mut removed = 0;
+            self.entries.retain(|entry| {
+                let keep = entry.timestamp >= cutoff;
+                if !keep {
+                    removed += entry.size();
+                }
+                keep
+            });
+            self.disk_usage -= removed;
+        }
+
+        if let Some(max_entries) = settings.history_max_entries {
+            if self.entries.len() > max_entries {
+                let removed: usize = self
+                    .entries
+                    .drain(0..self.entries.len() - max_entries)
+                    .map(|entry| entry.size())
+                    .sum();
+                self.disk_usage -= removed;
+            }
+        }
+
+        if let Some(max_bytes) = settings.history_max_bytes {
+            let mut remove = 0;
+            for entry in &self.entries {
+                if self.disk_usage <= max_bytes {
+                    break;
+                }
+                self.disk_usage -= entry.size();
+                remove += 1;
+            }
+            self.entries.drain(0..remove);
+        }
+
+        before - self.entries.len()
+    }
+
+    /// Take a snapshot of the entries to write once the history is unlocked.
+    pub fn snapshot(&mut self) -> Result<Snapshot> {
+        self.generation += 1;
+        Ok(Snapshot {
+            generation: self.generation,
+            serialized: serde_json::to_string(&self.entries)?,
+        })
+    }
+
+    pub fn save(&mut self) -> Result<()> {
+        self.snapshot()?.write()
+    }
+
+    pub fn load(settings: &Settings) -> Result<Self> {
+        let mut history = Self::default();
+        if paths::get().history.exists() {
+            let contents = crypto::read(&paths::get().history)?;
+            history.entries = serde_json::from_slice(&contents)?;
+        }
+        history.disk_usage = history.entries.iter().map(HistoryEntry::size).sum();
+        if history.prune(settings) > 0 {
+            history.save()?;
+        }
+        Ok(history)
+    }
+}
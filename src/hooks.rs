@@ -0,0 +1,43 @@
+//! Commands run before each request is sent and after its response is received, so tools like
+//! request signers and log shippers can be plugged in.
+
+use crate::command;
+use crate::error::{Error, Result};
+use crate::history::HistoryEntry;
+use crate::Method;
+
+use serde::{Deserialize, Serialize};
+
+/// A request as given to the pre-send hook, with the environment's variables substituted.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HookRequest {
+    pub method: Method,
+    pub url: String,
+    /// Headers in the `Name: value` form, one per line.
+    pub headers: String,
+    pub body: String,
+}
+
+/// Pass the request to the pre-send hook as JSON. If the hook writes a request back, in the same
+/// form, that request is sent instead, which allows it to add a signature for example.
+pub async fn pre_send(hook: &str, request: HookRequest) -> Result<HookRequest> {
+    let output = command::run(hook, &serde_json::to_vec(&request)?).await?;
+    if output.trim().is_empty() {
+        return Ok(request);
+    }
+    Ok(serde_json::from_str(output.as_str())?)
+}
+
+/// The error for a request the pre-send hook changed into one which needs confirmation.
+pub fn refusal(reason: String) -> Error {
+    Error::Refused(format!(
+        "The request changed by the pre-send hook wasn't sent, since it needs confirmation: {:}",
+        reason
+    ))
+}
+
+/// Pass the exchange to the post-receive hook as JSON, in the form it is kept in the history.
+pub async fn post_receive(hook: &str, entry: &HistoryEntry) -> Result<()> {
+    command::run(hook, &serde_json::to_vec(entry)?).await?;
+    Ok(())
+}
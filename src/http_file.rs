@@ -0,0 +1,329 @@
+//! Reading and writing the `.http` request files of VS Code's REST Client and JetBrains' HTTP
+//! client. Requests are separated by `###` lines, and each is a request line like
+//! `POST https://example.com/items`, headers, a blank line, then the body. Both use `{{name}}`
+//! for variables, so the references are kept as they are.
+
+use crate::body::{self, Part};
+use crate::error::{Error, Result};
+use crate::output::output_path;
+use crate::persistence::{Request, RequestBuilder};
+use crate::query;
+use crate::{BodyType, Method};
+use std::fs;
+
+/// Separates the parts of the multipart bodies which are written.
+const BOUNDARY: &str = "----rester-boundary";
+
+/// The requests in a `.http` file, named after the `###` or by a `# @name` comment. Requests
+/// without a name are named after `prefix`, like the name of the file, and their method and URL.
+pub fn parse(text: &str, prefix: &str) -> Result<Vec<Request>> {
+    let mut requests = Vec::new();
+    let mut name = None;
+    let mut block = Vec::new();
+    for line in text.lines() {
+        if let Some(separator) = line.trim_start().strip_prefix("###") {
+            if let Some(request) = request(&block, name.take(), prefix) {
+                requests.push(request);
+            }
+            block.clear();
+            let separator = separator.trim();
+            name = (!separator.is_empty()).then(|| separator.to_string());
+        } else {
+            block.push(line);
+        }
+    }
+    if let Some(request) = request(&block, name, prefix) {
+        requests.push(request);
+    }
+    if requests.is_empty() {
+        return Err(Error::Parse(
+            "There are no requests in the file".to_string(),
+        ));
+    }
+    Ok(requests)
+}
+
+/// A comment line, which can hold a name like `# @name items`.
+fn comment(line: &str) -> Option<&str> {
+    let line = line.trim();
+    line.strip_prefix('#')
+        .or_else(|| line.strip_prefix("//"))
+        .map(str::trim)
+}
+
+fn request(lines: &[&str], name: Option<String>, prefix: &str) -> Option<Request> {
+    let mut name = name;
+    let mut lines = lines.iter().copied().peekable();
+    // Before the request line there can be comments, and file variables like `@host = ...`,
+    // which aren't imported.
+    let request_line = loop {
+        let line = lines.next()?;
+        if let Some(comment) = comment(line) {
+            if let Some(value) = comment.strip_prefix("@name") {
+                name = Some(value.trim().to_string());
+            }
+        } else if !line.trim().is_empty() && !line.trim_start().starts_with('@') {
+            break line.trim();
+        }
+    };
+
+    // The request line is `METHOD URL HTTP/1.1`, where the method and version can be left out.
+    let mut parts: Vec<&str> = request_line.split_whitespace().collect();
+    if parts.len() > 1 && parts.last().is_some_and(|last| last.starts_with("HTTP/")) {
+        parts.pop();
+    }
+    let (method, url) = match parts.as_slice() {
+        [url] => (Method::GET, url.to_string()),
+        [method, rest @ ..] => (Method::parse(method), rest.join(" ")),
+        [] => return None,
+    };
+    // The query can continue on the following lines, each starting with `?` or `&`.
+    let mut url = url;
+    while let Some(line) = lines.peek() {
+        let line = line.trim();
+        if line.starts_with('?') || line.starts_with('&') {
+            url.push_str(line);
+            lines.next();
+        } else {
+            break;
+        }
+    }
+
+    let mut headers = Vec::new();
+    for line in lines.by_ref() {
+        if line.trim().is_empty() {
+            break;
+        }
+        if comment(line).is_none() {
+            headers.push(line.trim());
+        }
+    }
+    let body: Vec<&str> = lines.collect();
+    let body = body.join("\n");
+    let body = body.trim_end();
+    // A body of `< path` is read from the file.
+    let body = match body.trim().strip_prefix('<') {
+        Some(path) if !path.contains('\n') => format!("@{:}", path.trim()),
+        _ => body.to_string(),
+    };
+
+    let key = name.unwrap_or_else(|| format!("{:}: {:} {:}", prefix, method.as_str(), url));
+    let mut builder = RequestBuilder::new(key.as_str());
+    builder.url(url.as_str());
+    builder.headers(headers.join("\n").as_str());
+    if !body.is_empty() {
+        builder.body(body.as_str());
+        builder.send_body(matches!(method, Method::GET | Method::HEAD));
+    }
+    builder.method(method);
+    Some(builder.build())
+}
+
+/// Write the requests to a new `.http` file. Returns the name of the file.
+pub fn export(requests: &[Request]) -> Result<String> {
+    let text: Vec<String> = requests.iter().map(write).collect();
+    let path = output_path("requests", "http");
+    fs::write(&path, text.join("\n"))?;
+    Ok(path.display().to_string())
+}
+
+/// A request in the `.http` format, starting with its `###` separator. Parameters are written
+/// as they are, so the references to variables still work.
+fn write(request: &Request) -> String {
+    let mut res = format!("### {:}\n", request.key);
+    let params = request.params_to_string();
+    let query: Vec<&str> = params.lines().collect();
+    let url = if query.is_empty() {
+        request.url.clone()
+    } else {
+        let separator = if request.url.contains('?') { "&" } else { "?" };
+        format!("{:}{:}{:}", request.url, separator, query.join("&"))
+    };
+    res.push_str(format!("{:} {:}\n", request.method.as_str(), url).as_str());
+
+    let headers = request.headers.as_deref().unwrap_or_default();
+    let has_content_type = headers
+        .iter()
+        .any(|header| header.key.eq_ignore_ascii_case("content-type"));
+    for header in headers {
+        res.push_str(format!("{:}: {:}\n", header.key, header.value).as_str());
+    }
+
+    let body = request.body.as_deref().unwrap_or("");
+    if body.trim().is_empty() {
+        return res;
+    }
+    let body = match request.body_type {
+        BodyType::Raw => match body::file_reference(body) {
+            Some(path) => format!("< {:}", path),
+            None => body.to_string(),
+        },
+        BodyType::Form => {
+            if !has_content_type {
+                res.push_str("Content-Type: application/x-www-form-urlencoded\n");
+            }
+            let fields: Vec<String> = query::parse(body)
+                .into_iter()
+                .map(|(name, value)| format!("{:}={:}", name, value))
+                .collect();
+            fields.join("\n&")
+        }
+        BodyType::Multipart => {
+            res.push_str(
+                format!(
+                    "Content-Type: multipart/form-data; boundary={:}\n",
+                    BOUNDARY
+                )
+                .as_str(),
+            );
+            multipart(body)
+        }
+    };
+    res.push_str(format!("\n{:}\n", body).as_str());
+    res
+}
+
+/// A multipart body with its files read by `< path`.
+fn multipart(body: &str) -> String {
+    let mut res = String::new();
+    for part in body::multipart_parts(body) {
+        res.push_str(format!("--{:}\n", BOUNDARY).as_str());
+        match part {
+            Part::Text { name, value } => res.push_str(
+                format!(
+                    "Content-Disposition: form-data; name=\"{:}\"\n\n{:}\n",
+                    name, value
+                )
+                .as_str(),
+            ),
+            Part::File {
+                name,
+                path,
+                content_type,
+            } => {
+                let filename = std::path::Path::new(path.as_str())
+                    .file_name()
+                    .map(|filename| filename.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                res.push_str(
+                    format!(
+                        "Content-Disposition: form-data; name=\"{:}\"; filename=\"{:}\"\n",
+                        name, filename
+                    )
+                    .as_str(),
+                );
+                if let Some(content_type) = content_type {
+                    res.push_str(format!("Content-Type: {:}\n", content_type).as_str());
+                }
+                res.push_str(format!("\n< {:}\n", path).as_str());
+            }
+        }
+    }
+    res.push_str(format!("--{:}--", BOUNDARY).as_str());
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::RequestTemplate;
+
+    const FILE: &str = "@host = https://example.com
+
+# @name list
+GET {{host}}/items
+    ?page=1
+    &size=10
+Accept: application/json
+# a comment
+
+### Create item
+POST {{host}}/items HTTP/1.1
+Content-Type: application/json
+
+{
+  \"name\": \"a\"
+}
+
+###
+https://example.com/upload
+
+< ./data.bin
+";
+
+    #[test]
+    fn parses_requests() {
+        let requests = parse(FILE, "api").unwrap();
+        assert_eq!(requests.len(), 3);
+
+        assert_eq!(requests[0].key, "list");
+        let list = RequestTemplate::from(&requests[0]);
+        assert_eq!(list.method, Method::GET);
+        assert_eq!(list.url, "{{host}}/items?page=1&size=10");
+        assert_eq!(list.headers, "Accept:application/json");
+        assert!(list.body.is_empty());
+
+        assert_eq!(requests[1].key, "Create item");
+        let create = RequestTemplate::from(&requests[1]);
+        assert_eq!(create.method, Method::POST);
+        assert_eq!(create.url, "{{host}}/items");
+        assert_eq!(create.body, "{\n  \"name\": \"a\"\n}");
+
+        assert_eq!(requests[2].key, "api: GET https://example.com/upload");
+        assert_eq!(requests[2].body.as_deref(), Some("@./data.bin"));
+    }
+
+    #[test]
+    fn no_requests() {
+        assert!(parse("# only a comment\n###\n", "api").is_err());
+    }
+
+    #[test]
+    fn writes_requests() {
+        let mut builder = RequestBuilder::new("Login");
+        builder.method(Method::POST);
+        builder.url("{{host}}/login?next=/");
+        builder.params("a={{a}}");
+        builder.body("user=me\npass={{pass}}");
+        builder.body_type(BodyType::Form);
+        assert_eq!(
+            write(&builder.build()),
+            "### Login\nPOST {{host}}/login?next=/&a={{a}}\n\
+             Content-Type: application/x-www-form-urlencoded\n\nuser=me\n&pass={{pass}}\n"
+        );
+
+        let mut builder = RequestBuilder::new("Upload");
+        builder.method(Method::PUT);
+        builder.url("https://example.com/upload");
+        builder.body("title=a\nfile=@/tmp/a.png;type=image/png");
+        builder.body_type(BodyType::Multipart);
+        assert_eq!(
+            write(&builder.build()),
+            "### Upload\nPUT https://example.com/upload\n\
+             Content-Type: multipart/form-data; boundary=----rester-boundary\n\n\
+             ------rester-boundary\nContent-Disposition: form-data; name=\"title\"\n\na\n\
+             ------rester-boundary\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"a.png\"\n\
+             Content-Type: image/png\n\n< /tmp/a.png\n------rester-boundary--\n"
+        );
+    }
+
+    #[test]
+    fn written_requests_parse_the_same() {
+        let requests = parse(FILE, "api").unwrap();
+        let text: Vec<String> = requests.iter().map(write).collect();
+        let parsed = parse(text.join("\n").as_str(), "api").unwrap();
+        assert_eq!(parsed.len(), requests.len());
+        for (parsed, request) in parsed.iter().zip(requests.iter()) {
+            assert_eq!(parsed.key, request.key);
+            let (parsed, request) = (
+                RequestTemplate::from(parsed),
+                RequestTemplate::from(request),
+            );
+            assert_eq!(parsed.method, request.method);
+            assert_eq!(parsed.url, request.url);
+            assert_eq!(parsed.headers, request.headers);
+            assert_eq!(parsed.body, request.body);
+        }
+    }
+}
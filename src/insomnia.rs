@@ -0,0 +1,459 @@
+//! Importing the requests and environments of an Insomnia export. Requests are named after their
+//! workspace and folders, and the sub environments become environments with the variables of
+//! their base environment. Insomnia's `{{ _.name }}` references become `{{name}}`.
+
+use crate::environment::Environment;
+use crate::error::{Error, Result};
+use crate::persistence::{KeyValuePair, Request, RequestBuilder};
+use crate::{BodyType, Method};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// The requests and environments of an export.
+pub struct Import {
+    pub requests: Vec<Request>,
+    pub environments: Vec<Environment>,
+    /// Requests whose authentication couldn't be converted to a header.
+    pub skipped_auth: Vec<String>,
+}
+
+/// The requests and environments of an Insomnia export, in its JSON format version 4.
+pub fn import(text: &str) -> Result<Import> {
+    let export: Value = serde_json::from_str(text)?;
+    if export.get("_type").and_then(Value::as_str) != Some("export") {
+        return Err(Error::Parse(
+            "The file isn't an Insomnia export".to_string(),
+        ));
+    }
+    let resources = export
+        .get("resources")
+        .and_then(Value::as_array)
+        .ok_or_else(|| Error::Parse("The Insomnia export has no resources".to_string()))?;
+    let by_id: HashMap<&str, &Value> = resources
+        .iter()
+        .filter_map(|resource| Some((field(resource, "_id")?, resource)))
+        .collect();
+
+    let mut requests = Vec::new();
+    let mut skipped_auth = Vec::new();
+    for resource in resources {
+        if field(resource, "_type") == Some("request") {
+            let (request, auth_converted) = request(resource, &by_id);
+            if !auth_converted {
+                skipped_auth.push(request.key.clone());
+            }
+            requests.push(request);
+        }
+    }
+
+    Ok(Import {
+        requests,
+        environments: environments(resources, &by_id),
+        skipped_auth,
+    })
+}
+
+/// A summary of an import, shown in place of a response.
+pub fn report(import: &Import) -> String {
+    let mut res = format!(
+        "Imported {:} requests and {:} environments from Insomnia\n\n",
+        import.requests.len(),
+        import.environments.len()
+    );
+    for request in &import.requests {
+        res.push_str(format!("{:}\n", request.key).as_str());
+    }
+    if !import.environments.is_empty() {
+        res.push_str("\nEnvironments:\n");
+        for environment in &import.environments {
+            res.push_str(
+                format!(
+                    "{:} ({:} variables)\n",
+                    environment.name,
+                    environment.variables.len()
+                )
+                .as_str(),
+            );
+        }
+    }
+    if !import.skipped_auth.is_empty() {
+        res.push_str("\nThe authentication of these requests wasn't imported:\n");
+        for key in &import.skipped_auth {
+            res.push_str(format!("{:}\n", key).as_str());
+        }
+    }
+    res
+}
+
+fn field<'a>(resource: &'a Value, name: &str) -> Option<&'a str> {
+    resource.get(name).and_then(Value::as_str)
+}
+
+/// The names of the workspace and folders a resource is in, outermost first.
+fn ancestors<'a>(resource: &'a Value, by_id: &HashMap<&str, &'a Value>) -> Vec<&'a str> {
+    let mut names = Vec::new();
+    let mut parent = field(resource, "parentId");
+    // Bounded in case the parents form a loop.
+    while let Some(resource) = parent.and_then(|id| by_id.get(id)) {
+        if names.len() > by_id.len() {
+            break;
+        }
+        names.push(field(resource, "name").unwrap_or(""));
+        parent = field(resource, "parentId");
+    }
+    names.reverse();
+    names
+}
+
+/// A request, and whether its authentication, if it has any, was converted to a header.
+fn request(resource: &Value, by_id: &HashMap<&str, &Value>) -> (Request, bool) {
+    let mut path = ancestors(resource, by_id);
+    let name = field(resource, "name").unwrap_or("Request");
+    let key = match path.len() {
+        0 => name.to_string(),
+        _ => {
+            let workspace = path.remove(0);
+            path.push(name);
+            format!("{:}: {:}", workspace, path.join(" / "))
+        }
+    };
+    let mut builder = RequestBuilder::new(key.as_str());
+    builder.url(references(field(resource, "url").unwrap_or("")).as_str());
+    let method = Method::parse(field(resource, "method").unwrap_or("GET"));
+
+    let mut headers = enabled(resource.get("headers"))
+        .map(|(name, value)| format!("{:}: {:}", name, value))
+        .collect::<Vec<_>>();
+    let params = enabled(resource.get("parameters"))
+        .map(|(name, value)| format!("{:}={:}", name, value))
+        .collect::<Vec<_>>();
+
+    let auth_converted = match resource.get("authentication") {
+        Some(auth) if auth.get("disabled").and_then(Value::as_bool) != Some(true) => {
+            match authorization(auth) {
+                Some(Some(header)) => {
+                    headers.push(header);
+                    true
+                }
+                Some(None) => true,
+                None => false,
+            }
+        }
+        _ => true,
+    };
+
+    if let Some(body) = resource.get("body") {
+        let mime_type = field(body, "mimeType").unwrap_or("");
+        let has_content_type = headers
+            .iter()
+            .any(|header| header.to_lowercase().starts_with("content-type:"));
+        let (body_type, text) = match mime_type {
+            "application/x-www-form-urlencoded" => (BodyType::Form, form(body)),
+            "multipart/form-data" => {
+                // The content type is set with its boundary when sending.
+                headers.retain(|header| !header.to_lowercase().starts_with("content-type:"));
+                (BodyType::Multipart, form(body))
+            }
+            // GraphQL bodies are kept as the JSON Insomnia sends.
+            "application/graphql" => {
+                if !has_content_type {
+                    headers.push("Content-Type: application/json".to_string());
+                }
+                (BodyType::Raw, references(field(body, "text").unwrap_or("")))
+            }
+            _ => {
+                if !has_content_type && !mime_type.is_empty() {
+                    headers.push(format!("Content-Type: {:}", mime_type));
+                }
+                (BodyType::Raw, references(field(body, "text").unwrap_or("")))
+            }
+        };
+        if !text.is_empty() {
+            builder.body(text.as_str());
+            builder.body_type(body_type);
+            builder.send_body(matches!(method, Method::GET | Method::HEAD));
+        }
+    }
+
+    builder.method(method);
+    builder.headers(headers.join("\n").as_str());
+    builder.params(params.join("\n").as_str());
+    (builder.build(), auth_converted)
+}
+
+/// The enabled `name` and `value` pairs of a list like the headers of a request.
+fn enabled(pairs: Option<&Value>) -> impl Iterator<Item = (String, String)> + '_ {
+    pairs
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|pair| pair.get("disabled").and_then(Value::as_bool) != Some(true))
+        .filter_map(|pair| {
+            let name = field(pair, "name").filter(|name| !name.is_empty())?;
+            Some((
+                references(name),
+                references(field(pair, "value").unwrap_or("")),
+            ))
+        })
+}
+
+/// Form fields as `name=value` lines, with files as `@path`.
+fn form(body: &Value) -> String {
+    body.get("params")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|param| param.get("disabled").and_then(Value::as_bool) != Some(true))
+        .filter_map(|param| {
+            let name = references(field(param, "name").filter(|name| !name.is_empty())?);
+            Some(match field(param, "type") {
+                Some("file") => format!("{:}=@{:}", name, field(param, "fileName").unwrap_or("")),
+                _ => format!(
+                    "{:}={:}",
+                    name,
+                    references(field(param, "value").unwrap_or(""))
+                ),
+            })
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The Authorization header for Basic and Bearer authentication, nothing for none, or `None` for
+/// authentication which can't be sent as a header.
+fn authorization(auth: &Value) -> Option<Option<String>> {
+    match field(auth, "type") {
+        None | Some("none") => Some(None),
+        Some("basic") => {
+            let credentials = format!(
+                "{:}:{:}",
+                field(auth, "username").unwrap_or(""),
+                field(auth, "password").unwrap_or("")
+            );
+            // The credentials are encoded when importing, so they can't be variables.
+            if credentials.contains("{{") {
+                return None;
+            }
+            Some(Some(format!(
+                "Authorization: Basic {:}",
+                base64::encode(credentials)
+            )))
+        }
+        Some("bearer") => {
+            let prefix = field(auth, "prefix").filter(|prefix| !prefix.is_empty());
+            Some(Some(format!(
+                "Authorization: {:} {:}",
+                prefix.unwrap_or("Bearer"),
+                references(field(auth, "token").unwrap_or(""))
+            )))
+        }
+        _ => None,
+    }
+}
+
+/// The sub environments with the variables of their base environment, or the base environments
+/// themselves when they have none.
+fn environments(resources: &[Value], by_id: &HashMap<&str, &Value>) -> Vec<Environment> {
+    let of_type = |resource: &&Value| field(resource, "_type") == Some("environment");
+    let is_base = |resource: &Value| {
+        field(resource, "parentId")
+            .and_then(|id| by_id.get(id))
+            .and_then(|parent| field(parent, "_type"))
+            != Some("environment")
+    };
+
+    let mut res = Vec::new();
+    for base in resources
+        .iter()
+        .filter(of_type)
+        .filter(|base| is_base(base))
+    {
+        let base_id = field(base, "_id");
+        let subs: Vec<&Value> = resources
+            .iter()
+            .filter(of_type)
+            .filter(|sub| base_id.is_some() && field(sub, "parentId") == base_id)
+            .collect();
+        if subs.is_empty() {
+            let name = ancestors(base, by_id)
+                .first()
+                .copied()
+                .or_else(|| field(base, "name"))
+                .unwrap_or("Insomnia")
+                .to_string();
+            res.push(environment(name, &[base]));
+        }
+        for sub in subs {
+            let name = field(sub, "name").unwrap_or("Insomnia").to_string();
+            res.push(environment(name, &[base, sub]));
+        }
+    }
+    res
+}
+
+/// An environment with the variables of each of the Insomnia environments, later ones overriding
+/// earlier ones.
+fn environment(name: String, layers: &[&Value]) -> Environment {
+    let mut environment = Environment {
+        name,
+        ..Default::default()
+    };
+    for layer in layers {
+        let mut variables = Vec::new();
+        if let Some(data) = layer.get("data").and_then(Value::as_object) {
+            flatten("", data, &mut variables);
+        }
+        for variable in variables {
+            environment.set(variable.key.as_str(), variable.value.as_str());
+        }
+    }
+    environment
+}
+
+/// Nested objects become variables with dotted names, like `{{ _.api.host }}` refers to.
+fn flatten(prefix: &str, data: &Map<String, Value>, variables: &mut Vec<KeyValuePair>) {
+    for (name, value) in data {
+        let key = format!("{:}{:}", prefix, name);
+        match value {
+            Value::Object(nested) => flatten(format!("{:}.", key).as_str(), nested, variables),
+            Value::String(text) => variables.push(KeyValuePair {
+                key,
+                value: references(text),
+            }),
+            value => variables.push(KeyValuePair {
+                key,
+                value: value.to_string(),
+            }),
+        }
+    }
+}
+
+/// Rewrite Insomnia's `{{ _.name }}` references as `{{name}}`. Template tags like
+/// `{% response %}` are left as they are.
+fn references(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        res.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let name = after[..end].trim();
+                let name = name.strip_prefix("_.").unwrap_or(name);
+                res.push_str(format!("{{{{{:}}}}}", name).as_str());
+                rest = &after[end + 2..];
+            }
+            None => {
+                res.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    res.push_str(rest);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::RequestTemplate;
+
+    const EXPORT: &str = r#"{
+        "_type": "export",
+        "resources": [
+            {"_id": "wrk_1", "_type": "workspace", "name": "Shop"},
+            {"_id": "fld_1", "_type": "request_group", "parentId": "wrk_1", "name": "Items"},
+            {
+                "_id": "req_1", "_type": "request", "parentId": "fld_1", "name": "Create",
+                "method": "POST", "url": "{{ _.host }}/items",
+                "headers": [
+                    {"name": "Accept", "value": "application/json"},
+                    {"name": "X-Old", "value": "1", "disabled": true}
+                ],
+                "parameters": [{"name": "dry", "value": "{{_.dry}}"}],
+                "authentication": {"type": "bearer", "token": "{{ _.token }}"},
+                "body": {"mimeType": "application/json", "text": "{\"id\": 1}"}
+            },
+            {
+                "_id": "req_2", "_type": "request", "parentId": "wrk_1", "name": "Upload",
+                "method": "PUT", "url": "https://example.com/upload",
+                "headers": [{"name": "Content-Type", "value": "multipart/form-data"}],
+                "authentication": {"type": "digest", "username": "a"},
+                "body": {
+                    "mimeType": "multipart/form-data",
+                    "params": [
+                        {"name": "title", "value": "a"},
+                        {"name": "file", "type": "file", "fileName": "/tmp/a.txt"}
+                    ]
+                }
+            },
+            {"_id": "env_1", "_type": "environment", "parentId": "wrk_1", "name": "Base",
+                "data": {"host": "https://example.com", "api": {"version": 2}}},
+            {"_id": "env_2", "_type": "environment", "parentId": "env_1", "name": "Staging",
+                "data": {"host": "https://staging.example.com"}}
+        ]
+    }"#;
+
+    #[test]
+    fn imports_requests() {
+        let import = import(EXPORT).unwrap();
+        assert_eq!(import.requests.len(), 2);
+
+        let create = &import.requests[0];
+        assert_eq!(create.key, "Shop: Items / Create");
+        let template = RequestTemplate::from(create);
+        assert_eq!(template.method, Method::POST);
+        assert_eq!(template.url, "{{host}}/items");
+        assert_eq!(
+            template.headers,
+            "Accept:application/json\nAuthorization:Bearer {{token}}\nContent-Type:application/json"
+        );
+        assert_eq!(template.params, "dry={{dry}}");
+        assert_eq!(template.body, "{\"id\": 1}");
+
+        let upload = &import.requests[1];
+        assert_eq!(upload.key, "Shop: Upload");
+        let template = RequestTemplate::from(upload);
+        assert_eq!(template.body_type, BodyType::Multipart);
+        assert_eq!(template.body, "title=a\nfile=@/tmp/a.txt");
+        assert!(template.headers.is_empty());
+        assert_eq!(import.skipped_auth, vec!["Shop: Upload".to_string()]);
+    }
+
+    #[test]
+    fn sub_environments_include_the_base() {
+        let import = import(EXPORT).unwrap();
+        assert_eq!(import.environments.len(), 1);
+        let staging = &import.environments[0];
+        assert_eq!(staging.name, "Staging");
+        assert_eq!(staging.get("host"), Some("https://staging.example.com"));
+        assert_eq!(staging.get("api.version"), Some("2"));
+    }
+
+    #[test]
+    fn basic_auth_with_variables_is_skipped() {
+        let auth =
+            serde_json::json!({"type": "basic", "username": "{{ _.user }}", "password": "p"});
+        assert_eq!(authorization(&auth), None);
+        let auth = serde_json::json!({"type": "basic", "username": "user", "password": "pass"});
+        assert_eq!(
+            authorization(&auth),
+            Some(Some("Authorization: Basic dXNlcjpwYXNz".to_string()))
+        );
+    }
+
+    #[test]
+    fn rewrites_references() {
+        assert_eq!(
+            references("{{ _.host }}/{{id}}/{% response 'body' %}/{{ open"),
+            "{{host}}/{{id}}/{% response 'body' %}/{{ open"
+        );
+    }
+
+    #[test]
+    fn not_an_export() {
+        assert!(import("{\"_type\": \"workspace\"}").is_err());
+        assert!(import("{\"_type\": \"export\"}").is_err());
+        assert!(import("not json").is_err());
+    }
+}
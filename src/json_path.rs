@@ -0,0 +1,82 @@
+//! A small subset of JSONPath, supporting child keys and array indexes like
+//! `$.data.items[3].id` or `$['key with spaces']`. The leading `$` is optional.
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+pub fn parse(path: &str) -> Option<Vec<Segment>> {
+    let path = path.trim();
+    let mut rest = path.strip_prefix('$').unwrap_or(path);
+    let mut segments = Vec::new();
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']')?;
+            let inner = after[..end].trim();
+            let quoted = inner
+                .strip_prefix('\'')
+                .and_then(|inner| inner.strip_suffix('\''))
+                .or_else(|| {
+                    inner
+                        .strip_prefix('"')
+                        .and_then(|inner| inner.strip_suffix('"'))
+                });
+            match quoted {
+                Some(key) => segments.push(Segment::Key(key.to_string())),
+                None => segments.push(Segment::Index(inner.parse().ok()?)),
+            }
+            rest = &after[end + 1..];
+        } else {
+            let after = rest.strip_prefix('.').unwrap_or(rest);
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            if end == 0 {
+                return None;
+            }
+            segments.push(Segment::Key(after[..end].to_string()));
+            rest = &after[end..];
+        }
+    }
+    Some(segments)
+}
+
+/// Get the value at the path in a document.
+pub fn get<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    parse(path)?
+        .iter()
+        .try_fold(value, |value, segment| match segment {
+            Segment::Key(key) => value.get(key.as_str()),
+            Segment::Index(index) => value.get(index),
+        })
+}
+
+/// Format a path from segments, for instance `$.data.items[3].id`.
+pub fn format(segments: &[Segment]) -> String {
+    let mut res = "$".to_string();
+    for segment in segments {
+        match segment {
+            Segment::Key(key) => {
+                if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    res.push('.');
+                    res.push_str(key);
+                } else {
+                    res.push_str(format!("['{:}']", key).as_str());
+                }
+            }
+            Segment::Index(index) => res.push_str(format!("[{:}]", index).as_str()),
+        }
+    }
+    res
+}
+
+/// The text of a value, strings are not quoted.
+pub fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
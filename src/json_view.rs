@@ -0,0 +1,368 @@
+//! Line transforms for the formatted JSON shown in the response body.
+
+use crate::history::now;
+use crate::json_path::{self, Segment};
+use crate::paragraph_with_state::LineTransform;
+use crate::settings::Settings;
+
+/// Shorten string literals in a line of formatted JSON which are longer than `max` characters,
+/// noting how many characters were hidden.
+pub fn elide_strings(line: &str, max: usize) -> String {
+    let mut res = String::with_capacity(line.len());
+    let mut chars = line.char_indices();
+    while let Some((start, c)) = chars.next() {
+        if c != '"' {
+            res.push(c);
+            continue;
+        }
+        let mut end = None;
+        let mut escaped = false;
+        for (index, c) in chars.by_ref() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => {
+                    end = Some(index);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let content = match end {
+            Some(end) => &line[start + 1..end],
+            None => &line[start + 1..],
+        };
+        let length = content.chars().count();
+        res.push('"');
+        if length > max {
+            res.extend(content.chars().take(max));
+            res.push_str(format!("... [+{:} chars]", length - max).as_str());
+        } else {
+            res.push_str(content);
+        }
+        if end.is_some() {
+            res.push('"');
+        }
+    }
+    res
+}
+
+/// The transform applied to the lines of a response body with the given content type.
+pub fn line_transform(content_type: &str, settings: &Settings) -> Option<LineTransform> {
+    if !content_type.contains("json") {
+        return None;
+    }
+    let elide_over = settings.elide_strings_over;
+    let humanize = settings.humanize_timestamps;
+    let number_hints = settings.number_hints;
+    if elide_over.is_none() && !humanize && !number_hints {
+        return None;
+    }
+    let now = now();
+    Some(Box::new(move |line| {
+        // Annotations are found before eliding, which could shorten a date string.
+        let annotation = if humanize {
+            timestamp_annotation(line, now)
+        } else {
+            None
+        };
+        let annotation = match annotation {
+            None if number_hints => number_annotation(line),
+            annotation => annotation,
+        };
+        let mut line = match elide_over {
+            Some(max) => elide_strings(line, max),
+            None => line.to_string(),
+        };
+        if let Some(annotation) = annotation {
+            line.push_str(format!("  ({:})", annotation).as_str());
+        }
+        line
+    }))
+}
+
+/// A relative time for a line of formatted JSON whose value is a timestamp. Numbers are taken as
+/// Unix timestamps in seconds or milliseconds, and strings as ISO 8601 dates.
+pub fn timestamp_annotation(line: &str, now: u64) -> Option<String> {
+    let line = line.trim();
+    let line = line.strip_suffix(',').unwrap_or(line);
+    let value = match split_key(line) {
+        Some((_, value)) => value,
+        None => line,
+    };
+
+    let timestamp = match value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        Some(string) => parse_iso8601(string)?,
+        None if value.bytes().all(|byte| byte.is_ascii_digit()) => match value.len() {
+            10 => value.parse().ok()?,
+            13 => value.parse::<u64>().ok()? / 1000,
+            _ => return None,
+        },
+        None => return None,
+    };
+    Some(format_relative(timestamp, now))
+}
+
+/// A readable form of a large number, or of a number of bytes, on a line of formatted JSON.
+/// Members whose name mentions bytes or a size are taken to be in bytes.
+pub fn number_annotation(line: &str) -> Option<String> {
+    const MIN_GROUPED: f64 = 10000.0;
+
+    let line = line.trim();
+    let line = line.strip_suffix(',').unwrap_or(line);
+    let (key, value) = match split_key(line) {
+        Some((key, value)) => (Some(key.to_lowercase()), value),
+        None => (None, line),
+    };
+    let number: f64 = value.parse().ok()?;
+    if !value.starts_with(|c: char| c == '-' || c.is_ascii_digit()) {
+        return None;
+    }
+
+    let is_bytes = key.is_some_and(|key| key.contains("byte") || key.contains("size"));
+    if is_bytes && number >= 1024.0 {
+        return Some(format_bytes(number));
+    }
+    if number.abs() < MIN_GROUPED {
+        return None;
+    }
+    Some(group_thousands(value))
+}
+
+/// A size in bytes with a binary unit, like `1.5 MiB`.
+pub fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {:}", value, UNITS[unit])
+}
+
+/// Separate the thousands of the integer part of a number with commas.
+fn group_thousands(number: &str) -> String {
+    let (sign, number) = match number.strip_prefix('-') {
+        Some(number) => ("-", number),
+        None => ("", number),
+    };
+    let end = number.find(['.', 'e', 'E']).unwrap_or(number.len());
+    let (integer, fraction) = number.split_at(end);
+    let mut grouped = String::with_capacity(integer.len() + integer.len() / 3);
+    for (index, digit) in integer.chars().enumerate() {
+        if index > 0 && (integer.len() - index) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    format!("{:}{:}{:}", sign, grouped, fraction)
+}
+
+fn format_relative(timestamp: u64, now: u64) -> String {
+    let (elapsed, future) = if timestamp > now {
+        (timestamp - now, true)
+    } else {
+        (now - timestamp, false)
+    };
+    let (amount, unit) = match elapsed {
+        0..=59 => (elapsed, "second"),
+        60..=3599 => (elapsed / 60, "minute"),
+        3600..=86399 => (elapsed / 3600, "hour"),
+        86400..=2591999 => (elapsed / 86400, "day"),
+        2592000..=31535999 => (elapsed / 2592000, "month"),
+        _ => (elapsed / 31536000, "year"),
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+    if future {
+        format!("in {:} {:}{:}", amount, unit, plural)
+    } else {
+        format!("{:} {:}{:} ago", amount, unit, plural)
+    }
+}
+
+/// Parse dates like `2022-01-31`, `2022-01-31T12:30:00Z`, or `2022-01-31T12:30:00.123+01:00`
+/// into a Unix timestamp. Times without an offset are taken as UTC.
+fn parse_iso8601(text: &str) -> Option<u64> {
+    let number = |range: std::ops::Range<usize>| -> Option<i64> {
+        let part = text.get(range)?;
+        if !part.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        part.parse().ok()
+    };
+    if text.get(4..5)? != "-" || text.get(7..8)? != "-" {
+        return None;
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let mut seconds = 0;
+    let mut rest = &text[10..];
+    if !rest.is_empty() {
+        let time = rest.strip_prefix('T').or_else(|| rest.strip_prefix(' '))?;
+        if time.get(2..3)? != ":" || time.get(5..6)? != ":" {
+            return None;
+        }
+        let part = |range: std::ops::Range<usize>| -> Option<i64> {
+            let part = time.get(range)?;
+            if !part.bytes().all(|byte| byte.is_ascii_digit()) {
+                return None;
+            }
+            part.parse().ok()
+        };
+        seconds = part(0..2)? * 3600 + part(3..5)? * 60 + part(6..8)?;
+        rest = time[8..].trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+        match rest {
+            "" | "Z" | "z" => {}
+            _ => {
+                let sign = match rest.get(0..1)? {
+                    "+" => 1,
+                    "-" => -1,
+                    _ => return None,
+                };
+                let offset = rest[1..].replace(':', "");
+                if offset.len() != 4 || !offset.bytes().all(|byte| byte.is_ascii_digit()) {
+                    return None;
+                }
+                let hours: i64 = offset[..2].parse().ok()?;
+                let minutes: i64 = offset[2..].parse().ok()?;
+                seconds -= sign * (hours * 3600 + minutes * 60);
+            }
+        }
+    }
+
+    let timestamp = days_from_civil(year, month, day) * 86400 + seconds;
+    u64::try_from(timestamp).ok()
+}
+
+/// Days since the Unix epoch of a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+enum Container {
+    Object,
+    /// An array, with the index of its next element.
+    Array(usize),
+}
+
+/// Walk the lines of JSON formatted with one value or key per line, as done by
+/// `jsonxf::pretty_print`, calling `visit` with the index and path of each line until it returns
+/// true. A line closing an object or array gives the path of that container, and is visited with
+/// `closing` set. Returns `None` if a line isn't formatted JSON.
+fn walk_paths(text: &str, mut visit: impl FnMut(usize, &[Segment], bool) -> bool) -> Option<()> {
+    let mut containers: Vec<Container> = Vec::new();
+    let mut path: Vec<Segment> = Vec::new();
+
+    for (index, line) in text.split('\n').enumerate() {
+        let line = line.trim();
+        let line = line.strip_suffix(',').unwrap_or(line);
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('}') || line.starts_with(']') {
+            if visit(index, &path, true) {
+                return Some(());
+            }
+            containers.pop()?;
+            if !containers.is_empty() {
+                path.pop();
+            }
+            continue;
+        }
+
+        let (segment, value) = match containers.last_mut() {
+            Some(Container::Object) => {
+                let (key, value) = split_key(line)?;
+                (Some(Segment::Key(key)), value)
+            }
+            Some(Container::Array(next)) => {
+                *next += 1;
+                (Some(Segment::Index(*next - 1)), line)
+            }
+            None => (None, line),
+        };
+
+        let opens = match value {
+            "{" => Some(Container::Object),
+            "[" => Some(Container::Array(0)),
+            _ => None,
+        };
+        let has_segment = segment.is_some();
+        path.extend(segment);
+        if visit(index, &path, false) {
+            return Some(());
+        }
+        match opens {
+            Some(container) => containers.push(container),
+            None if has_segment => {
+                path.pop();
+            }
+            None => {}
+        }
+    }
+    Some(())
+}
+
+/// The path of the value on a line of JSON formatted with one value or key per line, as done by
+/// `jsonxf::pretty_print`. A line closing an object or array gives the path of that container.
+pub fn path_at_line(text: &str, target: usize) -> Option<Vec<Segment>> {
+    let mut res = None;
+    walk_paths(text, |index, path, _| {
+        if index == target {
+            res = Some(path.to_vec());
+        }
+        index >= target
+    });
+    res
+}
+
+/// The path of every object member in formatted JSON, with the line it starts on, in the order
+/// they appear.
+pub fn key_paths(text: &str) -> Vec<(String, usize)> {
+    let mut res = Vec::new();
+    walk_paths(text, |index, path, closing| {
+        if !closing && matches!(path.last(), Some(Segment::Key(_))) {
+            res.push((json_path::format(path), index));
+        }
+        false
+    });
+    res
+}
+
+/// Split an object member like `"key": value` into the key and the value text.
+fn split_key(line: &str) -> Option<(String, &str)> {
+    let mut escaped = false;
+    let end = line
+        .char_indices()
+        .skip(1)
+        .find(|(_, c)| match c {
+            _ if escaped => {
+                escaped = false;
+                false
+            }
+            '\\' => {
+                escaped = true;
+                false
+            }
+            '"' => true,
+            _ => false,
+        })?
+        .0;
+    let key: String = serde_json::from_str(&line[..=end]).ok()?;
+    let value = line[end + 1..].trim_start().strip_prefix(':')?.trim_start();
+    Some((key, value))
+}
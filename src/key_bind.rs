@@ -1,5 +1,16 @@
+use crate::error::{Error, Result};
+use crate::script::parse_key;
 use crate::Operation;
 use crossterm::event::{KeyCode, KeyModifiers};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Spell out keys with ASCII names instead of Unicode symbols.
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+pub fn set_ascii(ascii: bool) {
+    ASCII.store(ascii, Ordering::SeqCst);
+}
 
 #[derive(Clone, Debug)]
 pub struct KeyBind {
@@ -11,6 +22,19 @@ pub struct KeyBind {
 pub fn get_modifier_symbol(modifier: KeyModifiers) -> String {
     let mut res = String::new();
 
+    if ASCII.load(Ordering::SeqCst) {
+        if modifier.contains(KeyModifiers::ALT) {
+            res.push_str("Alt+");
+        }
+        if modifier.contains(KeyModifiers::CONTROL) {
+            res.push_str("Ctrl+");
+        }
+        if modifier.contains(KeyModifiers::SHIFT) {
+            res.push_str("Shift+");
+        }
+        return res;
+    }
+
     if modifier.contains(KeyModifiers::ALT) {
         res.push('⎇');
     }
@@ -24,6 +48,13 @@ pub fn get_modifier_symbol(modifier: KeyModifiers) -> String {
 }
 
 pub fn get_key_symbol(key: KeyCode) -> String {
+    if ASCII.load(Ordering::SeqCst) {
+        return get_key_name(key);
+    }
+    get_key_symbol_unicode(key)
+}
+
+fn get_key_symbol_unicode(key: KeyCode) -> String {
     match key {
         KeyCode::Backspace => "⌫".to_string(),
         KeyCode::Enter => "⏎".to_string(),
@@ -39,14 +70,32 @@ pub fn get_key_symbol(key: KeyCode) -> String {
         KeyCode::BackTab => "⇤".to_string(),
         KeyCode::Delete => "⌦".to_string(),
         KeyCode::Insert => "Ins".to_string(),
-        KeyCode::F(_) => "".to_string(),
+        KeyCode::F(number) => format!("F{:}", number),
         KeyCode::Char(c) => c.to_string(),
         KeyCode::Null => "".to_string(),
         KeyCode::Esc => "Esc".to_string(),
+        // The remaining keys are only reported with keyboard enhancements, which aren't enabled.
+        _ => "".to_string(),
     }
 }
 
-pub fn get_help(label: &str, operation: Operation, key_binds: &Vec<KeyBind>) -> String {
+fn get_key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Backspace => "Backspace".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Left => "Left".to_string(),
+        KeyCode::Right => "Right".to_string(),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        // Back tab is bound with shift, which is already spelled out.
+        KeyCode::Tab | KeyCode::BackTab => "Tab".to_string(),
+        KeyCode::Delete => "Del".to_string(),
+        key => get_key_symbol_unicode(key),
+    }
+}
+
+pub fn get_help(label: &str, operation: Operation, key_binds: &[KeyBind]) -> String {
     let key_bind = key_binds
         .iter()
         .find(|key_bind| key_bind.operation == operation);
@@ -61,3 +110,200 @@ pub fn get_help(label: &str, operation: Operation, key_binds: &Vec<KeyBind>) ->
     }
     label.to_string()
 }
+
+/// Build a help line from a list of labeled operations using their current key binds.
+pub fn get_hints(hints: &[(&str, Operation)], key_binds: &[KeyBind]) -> String {
+    let hints: Vec<String> = hints
+        .iter()
+        .map(|(label, operation)| get_help(label, *operation, key_binds))
+        .collect();
+    hints.join(" ")
+}
+
+/// Keys which are typed into the editors, so they can't run an operation.
+pub fn is_typing_key(modifiers: KeyModifiers, key: KeyCode) -> bool {
+    !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        && !matches!(
+            key,
+            KeyCode::F(_) | KeyCode::Tab | KeyCode::BackTab | KeyCode::Insert
+        )
+}
+
+/// The name of an operation as it is written in the settings and scripts, like `SendRequest`.
+pub fn operation_key(operation: Operation) -> String {
+    format!("{:?}", operation)
+}
+
+/// The name of an operation for people, like `Send Request`.
+pub fn operation_name(operation: Operation) -> String {
+    let mut res = String::new();
+    for c in operation_key(operation).chars() {
+        if c.is_uppercase() && !res.is_empty() {
+            res.push(' ');
+        }
+        res.push(c);
+    }
+    res
+}
+
+/// The operation bound to the keys.
+pub fn bound_to(key_binds: &[KeyBind], modifiers: KeyModifiers, key: KeyCode) -> Option<Operation> {
+    key_binds
+        .iter()
+        .find(|key_bind| key_bind.key == key && key_bind.modifiers == modifiers)
+        .map(|key_bind| key_bind.operation)
+}
+
+/// Change key binds to the keys in the settings, which are written like keys in scripts and
+/// named after their operations. Keys which can't be read, or are already bound to another
+/// operation, are left as they were and reported.
+pub fn apply_overrides(
+    key_binds: &mut [KeyBind],
+    overrides: &BTreeMap<String, String>,
+) -> Result<()> {
+    let mut problems = Vec::new();
+    for (name, text) in overrides {
+        let index = match key_binds
+            .iter()
+            .position(|key_bind| &operation_key(key_bind.operation) == name)
+        {
+            Some(index) => index,
+            None => {
+                problems.push(format!("{:} isn't an operation", name));
+                continue;
+            }
+        };
+        let key = match parse_key(text) {
+            Some(key) => key,
+            None => {
+                problems.push(format!("{:} isn't a key, for {:}", text, name));
+                continue;
+            }
+        };
+        match bound_to(key_binds, key.modifiers, key.code) {
+            Some(other) if other != key_binds[index].operation => problems.push(format!(
+                "{:} is already bound to {:}, for {:}",
+                text,
+                operation_key(other),
+                name
+            )),
+            _ => {
+                key_binds[index].modifiers = key.modifiers;
+                key_binds[index].key = key.code;
+            }
+        }
+    }
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(Error::Parse(format!(
+        "Some key binds in the settings weren't used: {:}",
+        problems.join("; ")
+    )))
+}
+
+/// The categories of the help, in the order they are shown.
+pub const CATEGORIES: [&str; 8] = [
+    "Navigation",
+    "Request",
+    "Editing",
+    "Response",
+    "Collection",
+    "Import and Export",
+    "Tools",
+    "Application",
+];
+
+/// The category an operation is listed under in the help.
+pub fn category(operation: Operation) -> &'static str {
+    match operation {
+        Operation::GotoUrl
+        | Operation::GotoRequestBody
+        | Operation::GotoRequestHeaders
+        | Operation::GotoRequestParams
+        | Operation::GotoResponseBody
+        | Operation::GotoResponseHeaders
+        | Operation::GotoRequestView
+        | Operation::GotoResponseView
+        | Operation::GotoResultsView
+        | Operation::FocusLeft
+        | Operation::FocusRight
+        | Operation::FocusUp
+        | Operation::FocusDown
+        | Operation::NextMode
+        | Operation::PreviousMode
+        | Operation::ToggleResponseSplit
+        | Operation::ToggleSentRequest
+        | Operation::NextTab
+        | Operation::PreviousTab => "Navigation",
+        Operation::SendRequest
+        | Operation::NextMethod
+        | Operation::ToggleSendBody
+        | Operation::NextBodyType
+        | Operation::SetTimeout
+        | Operation::SetProxy
+        | Operation::EditPrompts
+        | Operation::ToggleProxyBypass
+        | Operation::AlternateRequest
+        | Operation::NewTab
+        | Operation::CloseTab
+        | Operation::EditAssertions
+        | Operation::EditCaptures => "Request",
+        Operation::InsertFile
+        | Operation::DuplicateLine
+        | Operation::MoveLineUp
+        | Operation::MoveLineDown
+        | Operation::ToggleComment
+        | Operation::EditBodyExternally
+        | Operation::Paste => "Editing",
+        Operation::SaveResponse
+        | Operation::PostProcess
+        | Operation::Download
+        | Operation::ExportHar
+        | Operation::CopyResponse => "Response",
+        Operation::LoadRequest
+        | Operation::SaveRequest
+        | Operation::ShowHistory
+        | Operation::SelectEnvironment
+        | Operation::RunCollection => "Collection",
+        Operation::ExportAuditLog
+        | Operation::ExportSession
+        | Operation::ImportSession
+        | Operation::ImportCurl
+        | Operation::ImportCookies
+        | Operation::ImportOpenApi
+        | Operation::ImportInsomnia => "Import and Export",
+        Operation::ProbeOptions
+        | Operation::ProbeWellKnown
+        | Operation::SetupOidc
+        | Operation::RecordMacro
+        | Operation::PlayMacro
+        | Operation::ToggleOffline => "Tools",
+        Operation::ToggleProfiler
+        | Operation::Suspend
+        | Operation::EditKeyBinds
+        | Operation::ShowHelp
+        | Operation::Quit => "Application",
+    }
+}
+
+/// Every operation with its keys, by category.
+pub fn help_sections(key_binds: &[KeyBind]) -> Vec<(&'static str, Vec<String>)> {
+    CATEGORIES
+        .iter()
+        .map(|category_name| {
+            let lines = key_binds
+                .iter()
+                .filter(|key_bind| category(key_bind.operation) == *category_name)
+                .map(|key_bind| {
+                    get_help(
+                        operation_name(key_bind.operation).as_str(),
+                        key_bind.operation,
+                        key_binds,
+                    )
+                })
+                .collect();
+            (*category_name, lines)
+        })
+        .collect()
+}
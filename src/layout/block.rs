@@ -1,7 +1,7 @@
 use tui::style::{Color, Style};
-use tui::widgets::{Block, Borders, BorderType};
+use tui::widgets::{Block, BorderType, Borders};
 
-pub fn block(title: &str, active: bool) -> Block {
+pub fn block(title: &str, active: bool) -> Block<'_> {
     Block::default()
         .borders(Borders::ALL)
         .style(Style::default().fg(Color::White))
@@ -11,4 +11,4 @@ pub fn block(title: &str, active: bool) -> Block {
         } else {
             BorderType::Plain
         })
-}
\ No newline at end of file
+}
@@ -1 +1 @@
-pub mod block;
\ No newline at end of file
+pub mod block;
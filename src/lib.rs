@@ -2,52 +2,214 @@ use bytes::Bytes;
 use reqwest::header::HeaderMap;
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use strum_macros::IntoStaticStr;
 use tokio::sync::mpsc;
 #[macro_use]
 extern crate log;
 
+pub mod annotate;
 pub mod app;
+pub mod assertion;
+pub mod audit;
+pub mod body;
+pub mod capture;
+pub mod clipboard;
+pub mod command;
+pub mod content_type;
+pub mod cookies;
+pub mod crypto;
+pub mod curl;
 pub mod default_key_binds;
+pub mod download;
+pub mod environment;
+pub mod error;
+pub mod external_editor;
+pub mod formatters;
+pub mod frame_stats;
+pub mod guard;
+pub mod har;
+pub mod highlight;
+pub mod history;
+pub mod hooks;
+pub mod http_file;
+pub mod insomnia;
+pub mod json_path;
+pub mod json_view;
 pub mod key_bind;
 pub mod layout;
+pub mod metrics;
+pub mod oauth2;
+pub mod oidc;
+pub mod openapi;
+pub mod output;
 pub mod paragraph_with_state;
+pub mod paths;
 pub mod persistence;
+pub mod prepare;
+pub mod preview;
+pub mod probe;
+pub mod proxy;
+pub mod query;
+pub mod redact;
+pub mod redirect;
+pub mod run;
+pub mod runner;
+pub mod script;
+pub mod session;
+pub mod settings;
+pub mod suggest;
+pub mod token_refresh;
 pub mod ui;
+pub mod waterfall;
 pub mod web_request_handler;
 
 pub type Responder<T> = mpsc::Sender<T>;
 
-#[derive(Copy, Clone, PartialEq, IntoStaticStr, Debug, Serialize, Deserialize)]
+/// Methods are saved by name, so a custom one is saved the same way as the others.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum Method {
     GET,
     POST,
     PUT,
     DELETE,
     PATCH,
+    HEAD,
+    OPTIONS,
+    /// Any other method, like `PROPFIND` or `PURGE`, in upper case.
+    Custom(String),
+}
+
+impl Method {
+    /// The methods cycled through in the method selector.
+    pub const CYCLE: [Method; 7] = [
+        Method::GET,
+        Method::POST,
+        Method::PUT,
+        Method::DELETE,
+        Method::PATCH,
+        Method::HEAD,
+        Method::OPTIONS,
+    ];
+
+    /// The method named by a string, which is a custom method unless it is one of the others.
+    pub fn parse(method: &str) -> Method {
+        let method = method.trim().to_ascii_uppercase();
+        Method::CYCLE
+            .iter()
+            .find(|known| known.as_str() == method)
+            .cloned()
+            .unwrap_or(Method::Custom(method))
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Method::GET => "GET",
+            Method::POST => "POST",
+            Method::PUT => "PUT",
+            Method::DELETE => "DELETE",
+            Method::PATCH => "PATCH",
+            Method::HEAD => "HEAD",
+            Method::OPTIONS => "OPTIONS",
+            Method::Custom(method) => method.as_str(),
+        }
+    }
+
+    /// Methods which conventionally change state on the server. Custom methods are assumed to.
+    pub fn is_mutating(&self) -> bool {
+        !matches!(self, Method::GET | Method::HEAD | Method::OPTIONS)
+    }
+
+    /// Methods which conventionally send a body.
+    pub fn has_body(&self) -> bool {
+        !matches!(self, Method::GET | Method::HEAD | Method::OPTIONS)
+    }
+}
+
+impl From<String> for Method {
+    fn from(method: String) -> Self {
+        Method::parse(method.as_str())
+    }
+}
+
+impl From<Method> for String {
+    fn from(method: Method) -> Self {
+        method.as_str().to_string()
+    }
+}
+
+/// How the request body editor is sent.
+#[derive(Copy, Clone, PartialEq, IntoStaticStr, Debug, Serialize, Deserialize, Default)]
+pub enum BodyType {
+    /// As it is typed.
+    #[default]
+    Raw,
+    /// `name=value` lines encoded as `application/x-www-form-urlencoded`.
+    Form,
+    /// `name=value` lines sent as the parts of a `multipart/form-data` body. A value like
+    /// `@path` sends the file.
+    Multipart,
+}
+
+impl BodyType {
+    pub fn is_raw(&self) -> bool {
+        *self == BodyType::Raw
+    }
+
+    pub fn next(self) -> BodyType {
+        match self {
+            BodyType::Raw => BodyType::Form,
+            BodyType::Form => BodyType::Multipart,
+            BodyType::Multipart => BodyType::Raw,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum Response {
     Status(StatusCode),
+    /// The proxy the request was sent through, if any.
+    Proxy(Option<String>),
+    /// The redirects followed before the response, sent before its status if there were any.
+    Redirects(Vec<redirect::Redirect>),
     Headers(HeaderMap),
     Body(Bytes),
-    Failure,
+    /// Bytes of the body written to the download file so far, sent instead of the body.
+    Progress(u64),
+    Failure(error::Error),
+    /// The request was cancelled before its response was complete.
+    Cancelled,
 }
 
 #[derive(Debug)]
 pub struct Request {
+    /// Identifies the request to cancel it, from `web_request_handler::next_request_id`.
+    pub id: u64,
     pub method: Method,
     pub url: String,
     pub headers: String,
     pub body: String,
+    /// Send these parts as a multipart form, instead of the body.
+    pub multipart: Option<Vec<body::Part>>,
+    /// Stream the file at this path as the body, instead of the body.
+    pub body_file: Option<String>,
+    /// Write the response body to the file at this path, instead of sending it back.
+    pub download: Option<String>,
+    pub proxy: proxy::ProxyChoice,
+    /// How long to wait for the whole response before giving up.
+    pub timeout: Option<Duration>,
+    pub redirects: redirect::RedirectPolicy,
     pub resp: Responder<Response>,
 }
 
 #[derive(Debug)]
 pub enum WebRequest {
-    Request(Request),
-    Cancel,
+    Request(Box<Request>),
+    /// Cancel the request with the id, if it is still in flight.
+    Cancel(u64),
+    /// Stop the handler, abandoning any response still being received.
+    Shutdown,
 }
 
 #[derive(Debug)]
@@ -61,6 +223,7 @@ pub enum Operation {
     GotoUrl,
     GotoRequestBody,
     GotoRequestHeaders,
+    GotoRequestParams,
     GotoResponseBody,
     GotoResponseHeaders,
     NextMethod,
@@ -70,5 +233,58 @@ pub enum Operation {
     GotoRequestView,
     GotoResponseView,
     SendRequest,
+    InsertFile,
+    DuplicateLine,
+    MoveLineUp,
+    MoveLineDown,
+    ToggleComment,
+    FocusLeft,
+    FocusRight,
+    FocusUp,
+    FocusDown,
+    NextMode,
+    PreviousMode,
+    ShowHistory,
+    ToggleProxyBypass,
+    SelectEnvironment,
+    ExportAuditLog,
+    ToggleOffline,
+    ToggleResponseSplit,
+    RecordMacro,
+    PlayMacro,
+    AlternateRequest,
+    Suspend,
+    ToggleProfiler,
+    ExportSession,
+    PostProcess,
+    ImportSession,
+    ImportCurl,
+    ImportCookies,
+    ToggleSendBody,
+    NextBodyType,
+    Download,
+    SetTimeout,
+    SetProxy,
+    ImportOpenApi,
+    ExportHar,
+    ImportInsomnia,
+    RunCollection,
+    GotoResultsView,
+    EditAssertions,
+    EditCaptures,
+    EditKeyBinds,
+    ShowHelp,
+    NewTab,
+    CloseTab,
+    NextTab,
+    PreviousTab,
+    EditBodyExternally,
+    CopyResponse,
+    Paste,
+    ProbeOptions,
+    ProbeWellKnown,
+    SetupOidc,
+    ToggleSentRequest,
+    EditPrompts,
     Quit,
 }
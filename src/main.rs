@@ -1,68 +1,173 @@
-#[macro_use]
-extern crate log;
 extern crate simplelog;
 
 use crossterm::{
-    event::{self, DisableMouseCapture, Event},
+    cursor::Show,
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, KeyModifiers,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, SetTitle,
+    },
 };
-use log::LevelFilter;
+use log::{info, LevelFilter};
+use rester::annotate::Source;
 use rester::app::{App, Modal, Mode, View};
-use rester::key_bind::get_help;
+use rester::body::file_reference;
+use rester::content_type::short_name;
+use rester::highlight::highlighter;
+use rester::history::format_age;
+use rester::key_bind::{
+    get_help, get_hints, get_key_symbol, get_modifier_symbol, help_sections, operation_key,
+    operation_name,
+};
 use rester::layout::block::block;
+use rester::paragraph_with_state::ParagraphWithState;
+use rester::paths::{self, Paths};
+use rester::preview::preview;
+use rester::query;
+use rester::run::{self, Run};
+use rester::script::{Recorder, Script};
+use rester::settings::Settings;
+use rester::ui::ascii_borders::AsciiBorders;
 use rester::ui::centered_rect;
-use rester::ui::paragraph::{paragraph, paragraph_color};
+use rester::ui::json_tree::JsonTree;
+use rester::ui::paragraph::{
+    highlight_line, highlight_matches, highlighted_paragraph, paragraph, paragraph_color,
+    WrappedCache,
+};
 use rester::ui::text_area::TextArea;
-use rester::{web_request_handler, Operation};
+use rester::{crypto, waterfall, web_request_handler, BodyType, Operation};
 use simplelog::{CombinedLogger, Config, WriteLogger};
 use std::fs::File;
 use std::io;
+use std::io::Write;
+use std::path::PathBuf;
 use std::str;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use std::thread::sleep;
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tui::style::Modifier;
-use tui::widgets::{Clear, List, ListItem};
+use tui::widgets::{Clear, List, ListItem, ListState, Tabs};
 use tui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Alignment, Constraint, Direction, Layout},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Style},
-    widgets::{Block, BorderType, Borders, Paragraph},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, Paragraph, Wrap},
     Frame, Terminal,
 };
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{:}\n\n{:}", message, USAGE);
+            std::process::exit(run::EXIT_USAGE);
+        }
+    };
+    if args.help {
+        println!("A terminal client for REST APIs.\n\n{:}", USAGE);
+        return Ok(());
+    }
+
     CombinedLogger::init(vec![WriteLogger::new(
         LevelFilter::Info,
         Config::default(),
-        File::create("rester.log").unwrap(),
+        File::create(&args.log)?,
     )])
     .unwrap();
+    if let Some(project) = &args.paths.project {
+        info!("Using project directory {:?}", project);
+    }
+    paths::set(args.paths);
+
+    // Errors loading the settings are shown once the app has started.
+    let settings = Settings::load().unwrap_or_default();
+    if settings.encrypt_at_rest || crypto::has_encrypted_files() {
+        // Nothing has been encrypted yet, so the passphrase is being chosen. It's asked for
+        // twice, since a mistyped one would lock the files away.
+        let first_setup = !crypto::has_encrypted_files();
+        let passphrase = rpassword::prompt_password("Passphrase: ")?;
+        if first_setup && rpassword::prompt_password("Confirm passphrase: ")? != passphrase {
+            eprintln!("The passphrases don't match, nothing was encrypted");
+            std::process::exit(run::EXIT_FAILURE);
+        }
+        let res = crypto::unlock(passphrase.as_str(), settings.encrypt_at_rest)
+            .and_then(|_| crypto::verify())
+            .and_then(|_| crypto::rewrite_protected_files());
+        if let Err(err) = res {
+            eprintln!("{:}", err);
+            std::process::exit(run::EXIT_FAILURE);
+        }
+    }
+
+    if let Some(run) = args.run {
+        let code = match run::run(&run).await {
+            Ok(code) => code,
+            Err(err) => {
+                eprintln!("{:}", err);
+                run::EXIT_FAILURE
+            }
+        };
+        std::process::exit(code);
+    }
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, DisableMouseCapture)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        DisableMouseCapture,
+        EnableBracketedPaste
+    )?;
+    // Save the window title on the terminal's title stack, so it can be restored on exit.
+    write!(stdout, "\x1b[22;0t")?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     let (sender, receiver) = mpsc::channel(10);
-    let app = App::new(sender);
+    let mut app = App::new(sender);
+    if app.settings.mouse {
+        execute!(terminal.backend_mut(), EnableMouseCapture)?;
+    }
+    let script = args
+        .script
+        .and_then(|path| match Script::load(&path, &app.key_binds) {
+            Ok(script) => Some(script),
+            Err(err) => {
+                app.report(err);
+                None
+            }
+        });
+    let mut recorder = args.record_script.as_ref().map(|_| Recorder::default());
 
-    web_request_handler::web_request_handler(receiver);
+    let handler = web_request_handler::web_request_handler(receiver);
 
-    let res = run_app(&mut terminal, app);
+    let res = run_app(&mut terminal, &mut app, script, &mut recorder);
 
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
+    write!(terminal.backend_mut(), "\x1b[23;0t")?;
     terminal.show_cursor()?;
 
+    app.shutdown(handler).await;
+
+    if let (Some(recorder), Some(path)) = (recorder, args.record_script) {
+        if let Err(err) = recorder.save(&path) {
+            println!("Error saving the script {:}", err);
+        }
+    }
+
     if let Err(err) = res {
         println!("{:?}", err)
     }
@@ -70,48 +175,328 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
+const USAGE: &str = "Usage: rester [OPTIONS]
+       rester run <REQUEST> [--env <NAME>] [--var <NAME=VALUE>]... [--allow-unsafe] [OPTIONS]
+
+Commands:
+    run <REQUEST>        Send a saved request without the UI, writing its response body to
+                         stdout. The exit code is 0 for a status below 400, 4 for a 4xx status,
+                         5 for a 5xx status, and 1 if the request failed. For a request with
+                         assertions it is 0 if they all passed and 2 if one failed. A request
+                         which would need confirming in the UI, like a POST while a read only
+                         environment is active, isn't sent.
+
+Run options:
+    --env <NAME>         Environment to use instead of the active one
+    --var <NAME=VALUE>   Set a variable, overriding the environment. Can be repeated.
+    --allow-unsafe       Send the request even when it would need confirming in the UI
+
+Options:
+    --collection <FILE>  Request collection to use [default: requests.json]
+    --history <FILE>     History file to use [default: history.json]
+    --config <FILE>      Settings file to use [default: settings.json]
+    --env-file <FILE>    Environments file to use [default: environments.json]
+    --script <FILE>      Replay the key presses in a script
+    --record-script <FILE>
+                         Record key presses to a script which can be replayed with --script
+    --log <FILE>         Log file to write [default: rester.log in the temporary directory]
+    -h, --help           Print this help
+
+The collection and environments default to the ones in the nearest .rester directory, found by
+searching from the current directory upwards. An invalid command line exits with code 64.";
+
+struct Args {
+    paths: Paths,
+    script: Option<PathBuf>,
+    record_script: Option<PathBuf>,
+    log: PathBuf,
+    /// Send a saved request instead of starting the UI.
+    run: Option<Run>,
+    help: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    // The run options can come before run, so they are collected until the end.
+    let mut run = Run::default();
+    let mut is_run = false;
+    let mut run_option = None;
+    let mut res = Args {
+        paths: Paths::discover(),
+        script: None,
+        record_script: None,
+        log: std::env::temp_dir().join("rester.log"),
+        run: None,
+        help: false,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = |what: &str| {
+            args.next()
+                .ok_or_else(|| format!("{:} requires {:}", arg, what))
+        };
+        match arg.as_str() {
+            "--collection" => res.paths.collection = value("a file")?.into(),
+            "--history" => res.paths.history = value("a file")?.into(),
+            "--config" => res.paths.settings = value("a file")?.into(),
+            "--env-file" => res.paths.environments = value("a file")?.into(),
+            "--script" => res.script = Some(value("a file")?.into()),
+            "--record-script" => res.record_script = Some(value("a file")?.into()),
+            "--log" => res.log = value("a file")?.into(),
+            "--env" => {
+                run.environment = Some(value("the name of an environment")?);
+                run_option = Some(arg);
+            }
+            "--var" => {
+                let variable = value("a variable like name=value")?;
+                let (name, variable_value) = variable
+                    .split_once('=')
+                    .ok_or_else(|| format!("--var {:} isn't like name=value", variable))?;
+                run.variables
+                    .push((name.to_string(), variable_value.to_string()));
+                run_option = Some(arg);
+            }
+            "--allow-unsafe" => {
+                run.allow_unsafe = true;
+                run_option = Some(arg);
+            }
+            "run" if !is_run => is_run = true,
+            "-h" | "--help" => {
+                res.help = true;
+                return Ok(res);
+            }
+            _ if is_run && run.request.is_empty() && !arg.starts_with('-') => run.request = arg,
+            _ => return Err(format!("Unknown argument {:}", arg)),
+        };
+    }
+    match (is_run, run_option) {
+        (true, _) if run.request.is_empty() => {
+            Err("run requires the name of a saved request".to_string())
+        }
+        (true, _) => {
+            res.run = Some(run);
+            Ok(res)
+        }
+        (false, Some(option)) => Err(format!("{:} is only used with run", option)),
+        (false, None) => Ok(res),
+    }
+}
+
+const LOW_BANDWIDTH_RENDER_INTERVAL_MS: u64 = 250;
+
+/// Format a number of seconds like `42s` or `3m 05s`.
+fn format_elapsed(seconds: u64) -> String {
+    if seconds < 60 {
+        format!("{:}s", seconds)
+    } else {
+        format!("{:}m {:02}s", seconds / 60, seconds % 60)
+    }
+}
+
+/// The query of a paragraph's search and the match shown, for its title.
+fn search_label(paragraph: &ParagraphWithState) -> String {
+    match paragraph.search() {
+        Some((query, _, 0)) => format!(" [/{:} no matches, Esc to end]", query),
+        Some((query, current, count)) => {
+            format!(" [/{:} {:}/{:}, n/N next/previous]", query, current, count)
+        }
+        None => "".to_string(),
+    }
+}
+
+fn highlight_search<B: Backend>(
+    rect: &mut Frame<B>,
+    area: Rect,
+    paragraph: &ParagraphWithState,
+    (scroll, cache): &(u16, Arc<WrappedCache>),
+) {
+    if let Some((query, _, _)) = paragraph.search() {
+        highlight_matches(rect, area, *scroll, cache, query, paragraph.current_match());
+    }
+}
+
+fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    mut script: Option<Script>,
+    recorder: &mut Option<Recorder>,
+) -> io::Result<()> {
     let mut needs_render = true;
+    let mut window_title = String::new();
+    let mut input_start: Option<Instant> = None;
+    let mut last_render = Instant::now();
     loop {
+        let poll_interval = Duration::from_millis(app.settings.poll_interval_ms);
         if needs_render {
-            terminal.draw(|f| ui(f, &mut app))?;
+            let start = Instant::now();
+            terminal.draw(|f| ui(f, app))?;
             needs_render = false;
+            last_render = Instant::now();
+            app.frame_stats
+                .record_render(start.elapsed(), poll_interval);
+            if let Some(input_start) = input_start.take() {
+                app.frame_stats.record_input(input_start.elapsed());
+            }
+
+            let title = app.window_title();
+            if title != window_title {
+                execute!(io::stdout(), SetTitle(title.as_str()))?;
+                window_title = title;
+            }
         }
 
         // Poll with a timeout used a lot more CPU than expected.
-        // So, for now, it just sleeps for 16ms, then checks for any stimulus.
-        sleep(Duration::from_millis(16));
-        loop {
-            if let Ok(present) = event::poll(Duration::from_millis(0)) {
-                if present {
-                    let start = Instant::now();
-                    if let Event::Key(key) = event::read()? {
-                        if app.handle_input(key) {
-                            return Ok(());
+        // So, for now, it sleeps for the poll interval, then checks for any stimulus.
+        sleep(poll_interval);
+        // Scripted keys are pressed one per loop, so each is drawn as it would be when typed.
+        if let Some(key) = script.as_mut().and_then(Script::next_key) {
+            if app.handle_input(key) {
+                return Ok(());
+            }
+            needs_render = true;
+        }
+        while let Ok(true) = event::poll(Duration::from_millis(0)) {
+            input_start.get_or_insert_with(Instant::now);
+            match event::read()? {
+                Event::Key(key) => {
+                    if handle_key(terminal, app, recorder, key)? {
+                        return Ok(());
+                    }
+                }
+                Event::Resize(width, height) => {
+                    app.handle_resize();
+                    // Resizing clears the terminal, so the next draw repaints everything.
+                    terminal.resize(Rect::new(0, 0, width, height))?;
+                }
+                Event::Mouse(mouse) => app.handle_mouse(mouse),
+                Event::Paste(text) => {
+                    if let Some(recorder) = recorder.as_mut() {
+                        pasted_keys(text.as_str()).for_each(|key| recorder.record(key));
+                    }
+                    // Where text can't be pasted, like in a list, it is typed instead.
+                    if !app.paste(text.as_str()) {
+                        for key in pasted_keys(text.as_str()) {
+                            if handle_key(terminal, app, &mut None, key)? {
+                                return Ok(());
+                            }
                         }
                     }
-
-                    let duration = start.elapsed();
-
-                    info!("Time elapsed input handling is: {:?}", duration);
-                    needs_render = true;
-                } else {
-                    break;
                 }
-            } else {
-                break;
+                Event::FocusGained | Event::FocusLost => {}
             }
+            needs_render = true;
         }
 
-        if app.dirty.swap(false, Ordering::SeqCst) {
+        app.tick();
+        // Over a slow connection, streamed response updates are batched rather than drawn as
+        // each chunk arrives.
+        let throttled = app.settings.low_bandwidth
+            && last_render.elapsed() < Duration::from_millis(LOW_BANDWIDTH_RENDER_INTERVAL_MS);
+        if !throttled && app.dirty.swap(false, Ordering::SeqCst) {
             needs_render = true;
         }
     }
 }
 
+/// Handle a key, then anything it asked of the terminal. Returns true to quit.
+fn handle_key<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    recorder: &mut Option<Recorder>,
+    key: KeyEvent,
+) -> io::Result<bool> {
+    if let (Some(recorder), false) = (recorder.as_mut(), app.modal == Modal::Unlock) {
+        recorder.record(key);
+    }
+    if app.handle_input(key) {
+        return Ok(true);
+    }
+    if app.suspend_requested {
+        app.suspend_requested = false;
+        suspend(terminal, app.settings.mouse)?;
+    }
+    if app.external_edit_requested {
+        app.external_edit_requested = false;
+        leave_terminal()?;
+        app.edit_body_externally();
+        enter_terminal(terminal, app.settings.mouse)?;
+    }
+    Ok(false)
+}
+
+/// The keys which would type the pasted text.
+fn pasted_keys(text: &str) -> impl Iterator<Item = KeyEvent> + '_ {
+    text.chars().filter_map(|c| {
+        let code = match c {
+            '\n' => KeyCode::Enter,
+            '\t' => KeyCode::Tab,
+            '\r' => return None,
+            c => KeyCode::Char(c),
+        };
+        Some(KeyEvent::new(code, KeyModifiers::NONE))
+    })
+}
+
+/// Restore the terminal and stop the process, as Ctrl+Z would outside of raw mode. Once the
+/// shell resumes it the terminal is set up again and fully redrawn.
+#[cfg(unix)]
+fn suspend<B: Backend>(terminal: &mut Terminal<B>, mouse: bool) -> io::Result<()> {
+    leave_terminal()?;
+    unsafe {
+        libc::raise(libc::SIGTSTP);
+    }
+    enter_terminal(terminal, mouse)
+}
+
+#[cfg(not(unix))]
+fn suspend<B: Backend>(_terminal: &mut Terminal<B>, _mouse: bool) -> io::Result<()> {
+    Ok(())
+}
+
+/// Give the terminal back, as it was before the interface started, so another program can use it.
+fn leave_terminal() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        Show
+    )
+}
+
+/// Set the terminal up for the interface again, which is fully redrawn.
+fn enter_terminal<B: Backend>(terminal: &mut Terminal<B>, mouse: bool) -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste)?;
+    if mouse {
+        execute!(io::stdout(), EnableMouseCapture)?;
+    }
+    terminal.clear()
+}
+
 fn ui<B: Backend>(rect: &mut Frame<B>, app: &mut App) {
-    let start = Instant::now();
     let size = rect.size();
+    app.pane_areas.clear();
+
+    // With more than one request open, a row of tabs goes above the method and URL.
+    let tab_labels = app.tab_labels();
+    let size = if tab_labels.len() > 1 {
+        let tab_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(1)].as_ref())
+            .split(size);
+        let tabs = Tabs::new(tab_labels.into_iter().map(Spans::from).collect())
+            .select(app.current_tab)
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            );
+        rect.render_widget(tabs, tab_chunks[0]);
+        tab_chunks[1]
+    } else {
+        size
+    };
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(0)
@@ -119,7 +504,7 @@ fn ui<B: Backend>(rect: &mut Frame<B>, app: &mut App) {
             [
                 Constraint::Length(3),
                 Constraint::Min(2),
-                Constraint::Length(3),
+                Constraint::Length(4),
             ]
             .as_ref(),
         )
@@ -131,98 +516,398 @@ fn ui<B: Backend>(rect: &mut Frame<B>, app: &mut App) {
         .constraints([Constraint::Length(11), Constraint::Min(11)].as_ref())
         .split(chunks[0]);
 
+    // A request to a host the environment doesn't expect gets a banner above everything else, so
+    // a production URL sent with a staging environment is hard to miss.
+    let main_area = match app.host_warning() {
+        Some(warning) => {
+            let banner_chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(1)].as_ref())
+                .split(chunks[1]);
+            let banner = Paragraph::new(format!("WARNING: {:}", warning))
+                .style(
+                    Style::default()
+                        .fg(Color::White)
+                        .bg(Color::Red)
+                        .add_modifier(Modifier::BOLD),
+                )
+                .alignment(Alignment::Center);
+            rect.render_widget(banner, banner_chunks[0]);
+            banner_chunks[1]
+        }
+        None => chunks[1],
+    };
+
     let main_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Percentage(75), Constraint::Percentage(25)].as_ref())
-        .split(chunks[1]);
+        .split(main_area);
 
     if app.view == View::Response {
         let mut header_response_paragraph = app.response_header_paragraph.lock().unwrap();
         let status = app.status.load(Ordering::SeqCst);
-        let status_string = if status != 0 {
+        let mut status_string = if status != 0 {
             format!("Response Headers (Status {:})", status)
         } else {
             "Response Headers".to_string()
         };
+        if let Some(proxy) = app.proxy.lock().unwrap().as_ref() {
+            status_string.push_str(format!(" via {:}", proxy).as_str());
+        }
 
-        let header_updates = paragraph(
-            rect,
-            main_chunks[1],
-            get_help(
+        // A send which took more than one request shows the time each took next to the headers.
+        let steps = app.steps.lock().unwrap();
+        let header_chunks = if steps.len() > 1 {
+            Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                .split(main_chunks[1])
+        } else {
+            vec![main_chunks[1]]
+        };
+        if let Some(area) = header_chunks.get(1) {
+            let text = waterfall::render(
+                &steps,
+                area.width.saturating_sub(2) as usize,
+                app.settings.glyphs.use_ascii() || app.settings.low_bandwidth,
+            );
+            paragraph(rect, *area, "Timing", text.as_str(), false, 0, None);
+        }
+        drop(steps);
+
+        let sent_request = app.sent_request.lock().unwrap();
+        if app.show_sent_request && !sent_request.is_empty() {
+            let title = get_help(
+                "Sent Request (variables yellow, cookies magenta, OAuth2 green, pre-send hook cyan)",
+                Operation::ToggleSentRequest,
+                &app.key_binds,
+            );
+            let text: Vec<Spans> = sent_request
+                .iter()
+                .map(|line| {
+                    Spans::from(
+                        line.iter()
+                            .map(|segment| {
+                                let color = match segment.source {
+                                    Source::Typed => Color::White,
+                                    Source::Variable => Color::Yellow,
+                                    Source::Cookie => Color::Magenta,
+                                    Source::Auth => Color::Green,
+                                    Source::Hook => Color::Cyan,
+                                };
+                                Span::styled(segment.display(), Style::default().fg(color))
+                            })
+                            .collect::<Vec<Span>>(),
+                    )
+                })
+                .collect();
+            rect.render_widget(
+                Paragraph::new(text)
+                    .wrap(Wrap { trim: false })
+                    .block(block(title.as_str(), app.mode == Mode::ResponseHeaders)),
+                header_chunks[0],
+            );
+        } else {
+            let mut title = get_help(
                 status_string.as_str(),
                 Operation::GotoResponseHeaders,
                 &app.key_binds,
-            )
-            .as_str(),
-            header_response_paragraph.as_str(),
-            app.mode == Mode::ResponseHeaders,
-            header_response_paragraph.scroll,
-            header_response_paragraph.cache.clone(),
-        );
+            );
+            title.push_str(search_label(&header_response_paragraph).as_str());
+            let header_updates = paragraph(
+                rect,
+                header_chunks[0],
+                title.as_str(),
+                header_response_paragraph.as_str(),
+                app.mode == Mode::ResponseHeaders,
+                header_response_paragraph.scroll,
+                header_response_paragraph.cache.clone(),
+            );
+            highlight_search(
+                rect,
+                header_chunks[0],
+                &header_response_paragraph,
+                &header_updates,
+            );
 
-        header_response_paragraph.update(header_updates);
+            header_response_paragraph.update(header_updates);
+        }
+        app.pane_areas
+            .push((Mode::ResponseHeaders, header_chunks[0]));
+        drop(sent_request);
 
         let mut response_paragraph = app.response_paragraph.lock().unwrap();
 
-        let res = paragraph(
-            rect,
-            main_chunks[0],
-            get_help("Response Body", Operation::GotoResponseBody, &app.key_binds).as_str(),
-            response_paragraph.as_str(),
-            app.mode == Mode::ResponseBody,
-            response_paragraph.scroll,
-            response_paragraph.cache.clone(),
+        let body_chunks = if app.response_split {
+            Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                .split(main_chunks[0])
+        } else {
+            vec![main_chunks[0]]
+        };
+
+        let mut body_title = get_help("Response Body", Operation::GotoResponseBody, &app.key_binds);
+        if app.in_flight.load(Ordering::SeqCst) {
+            let elapsed = app.request_started.elapsed().as_secs();
+            body_title.push_str(format!(" (in flight {:}", format_elapsed(elapsed)).as_str());
+            // The timeout applies until the whole body has been received.
+            if let Some(timeout) = app.in_flight_timeout_secs {
+                let remaining = timeout.saturating_sub(elapsed);
+                body_title
+                    .push_str(format!(", times out in {:}", format_elapsed(remaining)).as_str());
+            }
+            body_title.push(')');
+            if !response_paragraph.follow {
+                body_title.push_str(" [paused, F to follow]");
+            }
+        }
+        if app.handler_busy.load(Ordering::SeqCst) {
+            body_title.push_str(" (handler busy)");
+        }
+        if let Some(download) = app.download.lock().unwrap().as_ref() {
+            body_title.push_str(format!(" [{:}]", download.label()).as_str());
+        }
+        {
+            let outcomes = app.assertion_outcomes.lock().unwrap();
+            if !outcomes.is_empty() {
+                let passed = outcomes.iter().filter(|outcome| outcome.passed).count();
+                body_title.push_str(
+                    format!(" [{:}/{:} assertions passed]", passed, outcomes.len()).as_str(),
+                );
+            }
+        }
+        if !app.post_process.trim().is_empty() {
+            body_title.push_str(format!(" [| {:}]", app.post_process.trim()).as_str());
+        }
+        {
+            let content_type = app.content_type.lock().unwrap();
+            if app.content_type_overridden {
+                body_title.push_str(format!(" [as {:}]", short_name(&content_type)).as_str());
+            } else if app.content_type_detected.load(Ordering::SeqCst) {
+                body_title.push_str(format!(" [detected {:}]", short_name(&content_type)).as_str());
+            }
+        }
+        if !app.response_bookmarks.is_empty() {
+            let numbers: Vec<String> = (1..=app.response_bookmarks.len())
+                .map(|number| number.to_string())
+                .collect();
+            body_title.push_str(format!(" [bookmarks {:}]", numbers.join(" ")).as_str());
+        }
+        app.pane_areas.push((Mode::ResponseBody, body_chunks[0]));
+        if app.show_response_tree {
+            app.response_tree.update(response_paragraph.as_str());
+        }
+        if app.show_response_tree && app.response_tree.is_json() {
+            body_title.push_str(" [tree, J for text]");
+            rect.render_stateful_widget(
+                JsonTree::default()
+                    .block(block(body_title.as_str(), app.mode == Mode::ResponseBody))
+                    .active(app.mode == Mode::ResponseBody),
+                body_chunks[0],
+                &mut app.response_tree,
+            );
+        } else {
+            body_title.push_str(search_label(&response_paragraph).as_str());
+            let highlighter = app
+                .settings
+                .syntax_highlighting
+                .then(|| highlighter(app.content_type.lock().unwrap().as_str()))
+                .flatten();
+            let res = match highlighter {
+                Some(highlighter) => highlighted_paragraph(
+                    rect,
+                    body_chunks[0],
+                    body_title.as_str(),
+                    response_paragraph.display_str(),
+                    app.mode == Mode::ResponseBody,
+                    response_paragraph.scroll,
+                    response_paragraph.cache.clone(),
+                    highlighter,
+                ),
+                None => paragraph(
+                    rect,
+                    body_chunks[0],
+                    body_title.as_str(),
+                    response_paragraph.display_str(),
+                    app.mode == Mode::ResponseBody,
+                    response_paragraph.scroll,
+                    response_paragraph.cache.clone(),
+                ),
+            };
+            if let (Some(line), true) = (
+                response_paragraph.cursor_line(),
+                app.mode == Mode::ResponseBody,
+            ) {
+                highlight_line(rect, body_chunks[0], res.0, &res.1, line);
+            }
+            highlight_search(rect, body_chunks[0], &response_paragraph, &res);
+            response_paragraph.update(res);
+        }
+
+        if app.response_split {
+            let body = response_paragraph.as_str();
+            if app.response_preview_len != Some(body.len()) {
+                let content_type = app.content_type.lock().unwrap();
+                app.response_preview.set_value(preview(
+                    body,
+                    content_type.as_str(),
+                    app.metrics_filter.as_str(),
+                ));
+                app.response_preview_len = Some(body.len());
+            }
+
+            // The preview scrolls with the body.
+            let res = paragraph(
+                rect,
+                body_chunks[1],
+                "Preview",
+                app.response_preview.as_str(),
+                false,
+                response_paragraph.scroll,
+                app.response_preview.cache.clone(),
+            );
+            app.response_preview.update(res);
+        }
+    }
+
+    if app.view == View::Results {
+        let collection_run = app.collection_run.lock().unwrap();
+        let items: Vec<ListItem> = collection_run
+            .results
+            .iter()
+            .map(|result| {
+                let color = if result.passed() {
+                    Color::Green
+                } else {
+                    Color::Red
+                };
+                ListItem::new(result.row()).style(Style::default().fg(color))
+            })
+            .collect();
+        let title = format!(
+            "{:} ({:}, {:} response, r run again)",
+            get_help("Results", Operation::GotoResultsView, &app.key_binds),
+            collection_run.summary(),
+            get_key_symbol(KeyCode::Enter)
         );
-        response_paragraph.update(res);
+        drop(collection_run);
+        let results = List::new(items)
+            .block(block(title.as_str(), app.mode == Mode::Results))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol(">> ");
+        rect.render_stateful_widget(results, main_area, &mut app.results_selection_state);
+        app.pane_areas.push((Mode::Results, main_area));
     }
 
     if app.view == View::Request {
+        let mut body_title = get_help("Request Body", Operation::GotoRequestBody, &app.key_binds);
+        if app.body_skipped() {
+            let method = app.method.as_str();
+            body_title.push_str(
+                format!(
+                    " (not sent with {:}, {:})",
+                    method,
+                    get_help("send anyway", Operation::ToggleSendBody, &app.key_binds)
+                )
+                .as_str(),
+            );
+        } else if app.send_body && !app.method.has_body() {
+            body_title.push_str(" (sent anyway)");
+        }
+        let body_type = match app.body_type {
+            BodyType::Raw if file_reference(app.body.as_str()).is_some() => {
+                Some("streamed from the file")
+            }
+            BodyType::Raw => None,
+            BodyType::Form => Some("form, name=value per line"),
+            BodyType::Multipart => Some("multipart, name=value or name=@file per line"),
+        };
+        if let Some(body_type) = body_type {
+            body_title.push_str(format!(" [{:}]", body_type).as_str());
+        }
         rect.render_stateful_widget(
             TextArea::default()
                 .block(
                     Block::default()
                         .borders(Borders::ALL)
-                        .style(Style::default().fg(Color::White))
-                        .title(get_help(
-                            "Request Body",
-                            Operation::GotoRequestBody,
-                            &app.key_binds,
-                        ))
+                        .style(Style::default().fg(if app.body_skipped() {
+                            Color::DarkGray
+                        } else {
+                            Color::White
+                        }))
+                        .title(body_title)
                         .border_type(if app.mode == Mode::RequestBody {
                             BorderType::Double
                         } else {
                             BorderType::Plain
                         }),
                 )
-                .active(app.mode == Mode::RequestBody),
+                .active(app.mode == Mode::RequestBody)
+                .placeholder("{ }"),
             main_chunks[0],
             &mut app.body,
         );
+        app.pane_areas.push((Mode::RequestBody, main_chunks[0]));
+
+        // The query params are next to the headers.
+        let request_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+            .split(main_chunks[1]);
 
+        let mut headers_title = get_help(
+            "Request Headers",
+            Operation::GotoRequestHeaders,
+            &app.key_binds,
+        );
+        let cookies = app.cookies.lock().unwrap().cookies.len();
+        if cookies > 0 {
+            headers_title.push_str(format!(" ({:} imported cookies)", cookies).as_str());
+        }
         rect.render_stateful_widget(
             TextArea::default()
                 .block(
                     Block::default()
                         .borders(Borders::ALL)
                         .style(Style::default().fg(Color::White))
-                        .title(get_help(
-                            "Request Headers",
-                            Operation::GotoRequestHeaders,
-                            &app.key_binds,
-                        ))
+                        .title(headers_title)
                         .border_type(if app.mode == Mode::RequestHeaders {
                             BorderType::Double
                         } else {
                             BorderType::Plain
                         }),
                 )
-                .active(app.mode == Mode::RequestHeaders),
-            main_chunks[1],
+                .active(app.mode == Mode::RequestHeaders)
+                .placeholder("Header-Name: value"),
+            request_chunks[0],
             &mut app.headers,
         );
+        app.pane_areas
+            .push((Mode::RequestHeaders, request_chunks[0]));
+
+        let mut params_title =
+            get_help("Query Params", Operation::GotoRequestParams, &app.key_binds);
+        let params = query::parse(app.params.as_str()).len();
+        if params > 0 {
+            params_title.push_str(format!(" ({:})", params).as_str());
+        }
+        rect.render_stateful_widget(
+            TextArea::default()
+                .block(block(
+                    params_title.as_str(),
+                    app.mode == Mode::RequestParams,
+                ))
+                .active(app.mode == Mode::RequestParams)
+                .placeholder("name=value"),
+            request_chunks[1],
+            &mut app.params,
+        );
+        app.pane_areas
+            .push((Mode::RequestParams, request_chunks[1]));
     }
 
-    let method_str: &'static str = app.method.into();
+    let method_str = app.method.as_str();
 
     paragraph(
         rect,
@@ -234,77 +919,431 @@ fn ui<B: Backend>(rect: &mut Frame<B>, app: &mut App) {
         None,
     );
 
+    let mut url_title = "Url".to_string();
+    if app.bypass_proxy {
+        url_title.push_str(" (No Proxy)");
+    }
+    if app.request_proxy.is_some() {
+        url_title.push_str(" (Proxy)");
+    }
+    if let Some(timeout) = app.timeout_secs {
+        url_title.push_str(format!(" (Timeout {:}s)", timeout).as_str());
+    }
     rect.render_stateful_widget(
         TextArea::default()
             .block(block(
-                get_help("Url", Operation::GotoUrl, &app.key_binds).as_str(),
+                get_help(url_title.as_str(), Operation::GotoUrl, &app.key_binds).as_str(),
                 app.mode == Mode::Url,
             ))
-            .active(app.mode == Mode::Url),
+            .active(app.mode == Mode::Url)
+            .placeholder("https://api.example.com/v1/…"),
         header_chunks[1],
         &mut app.url,
     );
+    app.pane_areas.push((Mode::Method, header_chunks[0]));
+    app.pane_areas.push((Mode::Url, header_chunks[1]));
+
+    if !app.suggestions.is_empty() && app.view == View::Request {
+        let height = app.suggestions.len() as u16 + 2;
+        // URL suggestions drop down below it, header suggestions sit above the headers pane so
+        // they don't cover the line being typed.
+        let area = match app.mode {
+            Mode::Url => Rect::new(
+                header_chunks[1].x,
+                header_chunks[1].bottom(),
+                header_chunks[1].width,
+                height,
+            ),
+            _ => Rect::new(
+                main_chunks[1].x,
+                main_chunks[1].y.saturating_sub(height),
+                main_chunks[1].width,
+                height,
+            ),
+        };
+        suggestions_popup(rect, area.intersection(size), &app.suggestions);
+    }
 
     let help_string = format!(
-        "{:} {:} {:} {:} {:} {:}",
-        get_help("Req", Operation::GotoResponseView, &app.key_binds),
-        get_help("Res", Operation::GotoRequestView, &app.key_binds),
-        get_help("Load", Operation::LoadRequest, &app.key_binds),
-        get_help("Save", Operation::SaveRequest, &app.key_binds),
-        get_help("Quit", Operation::Quit, &app.key_binds),
-        if app.mode != Mode::Url {
-            get_help("Send", Operation::SendRequest, &app.key_binds)
-        } else {
-            "Send ⏎".to_string()
-        }
+        "{:}\n{:}",
+        app.mode_hints(),
+        get_hints(
+            &[
+                ("Req", Operation::GotoRequestView),
+                ("Res", Operation::GotoResponseView),
+                ("Load", Operation::LoadRequest),
+                ("Save", Operation::SaveRequest),
+                ("Env", Operation::SelectEnvironment),
+                ("Next", Operation::NextMode),
+                ("Prev", Operation::PreviousMode),
+                ("Tab", Operation::NewTab),
+                ("Help", Operation::ShowHelp),
+                ("Quit", Operation::Quit),
+            ],
+            &app.key_binds
+        )
     );
 
+    let environments = app.environments.lock().unwrap();
+    let mut help_title = match environments.active() {
+        Some(environment) if environment.read_only => {
+            format!("Help [{:} (read only)]", environment.name)
+        }
+        Some(environment) => format!("Help [{:}]", environment.name),
+        None => "Help".to_string(),
+    };
+    drop(environments);
+    if let Some(project) = paths::get().project_name() {
+        help_title.push_str(format!(" [project: {:}]", project).as_str());
+    }
+    if let Some(expiry) = app.token_expiry_label() {
+        help_title.push_str(format!(" [{:}]", expiry).as_str());
+    }
+    if app.settings.offline {
+        help_title.push_str(" [OFFLINE]");
+    }
+    if app.recording.is_some() {
+        help_title.push_str(" [REC]");
+    }
     let status_help = Paragraph::new(help_string.as_str())
         .style(Style::default().fg(Color::LightCyan))
         .alignment(Alignment::Center)
-        .block(block("Help", false));
-
-    if app.modal == Modal::Requests {
-        let block = Block::default().style(Style::default().bg(Color::Blue));
-        rect.render_widget(block.clone(), chunks[1]);
-        rect.render_widget(block.clone(), chunks[0]);
-        rect.render_widget(block, chunks[2]);
+        .block(block(help_title.as_str(), false));
 
-        let area = centered_rect(60, 60, size);
-        rect.render_widget(Clear, area);
+    // Dimming repaints the whole screen, which is slow over a low bandwidth connection.
+    let dim_chunks: &[Rect] = if app.settings.low_bandwidth {
+        &[]
+    } else {
+        &chunks
+    };
 
+    if app.modal == Modal::Requests {
         let items: Vec<ListItem> = app
             .request_collection
             .requests
             .iter()
             .map(|i| ListItem::new(i.key.as_str()))
             .collect();
-        let items = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Requests"))
-            .highlight_style(
-                Style::default()
-                    .bg(Color::Cyan)
-                    .add_modifier(Modifier::BOLD)
-                    .fg(Color::Black),
-            )
-            .highlight_symbol(">> ");
+        let title = format!(
+            "Requests ({:}/{:}) {:} load, s send, {:} delete, r retarget, i import .http, e export .http",
+            app.request_selection_state
+                .selected()
+                .map_or(0, |selected| selected + 1)
+                .min(items.len()),
+            items.len(),
+            get_key_symbol(KeyCode::Enter),
+            get_key_symbol(KeyCode::Delete)
+        );
+
+        let area = list_modal(
+            rect,
+            dim_chunks,
+            title.as_str(),
+            items,
+            &mut app.request_selection_state,
+        );
+        // Leave room for the borders.
+        app.request_page_size = area.height.saturating_sub(2).max(1) as usize;
+    }
+
+    if app.modal == Modal::History {
+        let history = app.history.lock().unwrap();
+        let items: Vec<ListItem> = history
+            .entries
+            .iter()
+            .map(|entry| {
+                let method = entry.method.as_str();
+                ListItem::new(format!(
+                    "{:>8} {:<6} {:>3} {:}{:}{:}",
+                    format_age(entry.timestamp),
+                    method,
+                    entry.status,
+                    entry.url,
+                    entry
+                        .proxy
+                        .as_ref()
+                        .map_or("".to_string(), |proxy| format!(" via {:}", proxy)),
+                    if entry.response_body.is_some() {
+                        ""
+                    } else {
+                        " (no response)"
+                    }
+                ))
+            })
+            .collect();
+        let title = format!(
+            "History ({:} entries, {:} KiB) {:} view, {:} delete, r delete response, p prune, h export HAR, c clear",
+            items.len(),
+            history.disk_usage() / 1024,
+            get_key_symbol(KeyCode::Enter),
+            get_key_symbol(KeyCode::Delete)
+        );
+        drop(history);
 
-        rect.render_stateful_widget(items, area, &mut app.request_selection_state);
+        list_modal(
+            rect,
+            dim_chunks,
+            title.as_str(),
+            items,
+            &mut app.history_selection_state,
+        );
+    }
+
+    if app.modal == Modal::KeyBinds {
+        let items: Vec<ListItem> = app
+            .key_binds
+            .iter()
+            .map(|key_bind| {
+                let changed = app
+                    .settings
+                    .key_binds
+                    .contains_key(&operation_key(key_bind.operation));
+                ListItem::new(format!(
+                    "{:<28} {:}{:}{:}",
+                    operation_name(key_bind.operation),
+                    get_modifier_symbol(key_bind.modifiers),
+                    get_key_symbol(key_bind.key),
+                    if changed { " (changed)" } else { "" }
+                ))
+            })
+            .collect();
+        let title = match app.key_binds_selection_state.selected() {
+            Some(selected) if app.rebinding => format!(
+                "Press the Keys for {:} (Esc to Cancel)",
+                operation_name(app.key_binds[selected].operation)
+            ),
+            _ => format!(
+                "Key Binds {:} rebind, {:} reset to default",
+                get_key_symbol(KeyCode::Enter),
+                get_key_symbol(KeyCode::Delete)
+            ),
+        };
+        list_modal(
+            rect,
+            dim_chunks,
+            title.as_str(),
+            items,
+            &mut app.key_binds_selection_state,
+        );
+    }
+
+    if app.modal == Modal::Help {
+        help_modal(rect, app);
     }
 
     if app.modal == Modal::Save {
-        let block = Block::default().style(Style::default().bg(Color::Blue));
-        rect.render_widget(block.clone(), chunks[1]);
-        rect.render_widget(block.clone(), chunks[0]);
-        rect.render_widget(block, chunks[2]);
+        input_modal(rect, dim_chunks, "Request Name", app.request_name.as_str());
+    }
+
+    if app.modal == Modal::SaveResponseSecrets {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Response Contains Secrets",
+            "r save redacted, s save as is, Esc cancel",
+        );
+    }
+
+    if app.modal == Modal::CopyResponseSecrets {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Response Contains Secrets",
+            "r copy redacted, c copy as is, Esc cancel",
+        );
+    }
+
+    if app.modal == Modal::Environments {
+        let mut items = vec![ListItem::new("No Environment")];
+        items.extend(
+            app.environments
+                .lock()
+                .unwrap()
+                .environments
+                .iter()
+                .map(|environment| {
+                    ListItem::new(if environment.read_only {
+                        format!("{:} (read only)", environment.name)
+                    } else {
+                        environment.name.clone()
+                    })
+                }),
+        );
+        let title = format!(
+            "Environments {:} select, x export, i import",
+            get_key_symbol(KeyCode::Enter)
+        );
+        list_modal(
+            rect,
+            dim_chunks,
+            title.as_str(),
+            items,
+            &mut app.environment_selection_state,
+        );
+    }
+
+    if app.modal == Modal::ConfirmSend {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Confirm Send (y to send, Esc to cancel)",
+            app.confirm_reason.as_str(),
+        );
+    }
+
+    if app.modal == Modal::Prompt {
+        input_modal(
+            rect,
+            dim_chunks,
+            app.prompt_name.as_str(),
+            app.prompt_value.as_str(),
+        );
+    }
+
+    if app.modal == Modal::Unlock {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Passphrase for Encrypted Variables",
+            "*".repeat(app.passphrase.chars().count()).as_str(),
+        );
+    }
+
+    if app.modal == Modal::Error {
+        let message = app
+            .errors
+            .lock()
+            .unwrap()
+            .first()
+            .map(|err| err.to_string())
+            .unwrap_or_default();
+        input_modal(
+            rect,
+            dim_chunks,
+            "Error (Enter to dismiss)",
+            message.as_str(),
+        );
+    }
+
+    if app.modal == Modal::Notice {
+        let message = app.notices.first().cloned().unwrap_or_default();
+        input_modal(
+            rect,
+            dim_chunks,
+            "Done (Enter to dismiss)",
+            message.as_str(),
+        );
+    }
 
-        let area = centered_rect(60, 20, size);
+    if app.modal == Modal::ExtractVariable {
+        let title = format!("Save {:} to Variable", app.extract_value);
+        input_modal(
+            rect,
+            dim_chunks,
+            title.as_str(),
+            app.extract_variable.as_str(),
+        );
+    }
+
+    if app.modal == Modal::ImportEnvironments {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Import Environments",
+            app.import_environments_path.as_str(),
+        );
+    }
+
+    if app.modal == Modal::PostProcess {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Pipe the Response Body Through",
+            app.post_process.as_str(),
+        );
+    }
+
+    if app.modal == Modal::GoToKey {
+        let items: Vec<ListItem> = app
+            .key_matches
+            .iter()
+            .map(|(path, _)| ListItem::new(path.as_str()))
+            .collect();
+        let title = format!("Go to Key: {:}", app.key_query);
+        list_modal(
+            rect,
+            dim_chunks,
+            title.as_str(),
+            items,
+            &mut app.key_match_state,
+        );
+    }
+
+    if app.modal == Modal::MetricsFilter {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Filter Metrics by Name",
+            app.metrics_filter.as_str(),
+        );
+    }
+
+    if app.modal == Modal::ImportCurl {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Import curl Command",
+            app.import_curl.as_str(),
+        );
+    }
+
+    if app.modal == Modal::ImportSession {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Import Session",
+            app.import_session_path.as_str(),
+        );
+    }
+
+    if app.modal == Modal::Retarget {
+        let mut text = app.retarget.clone();
+        text.push_str("\n\n");
+        match app.retarget_bases() {
+            Some((from, to)) => {
+                let changes = app.request_collection.retarget_preview(from, to);
+                if changes.is_empty() {
+                    text.push_str(format!("No request URLs start with {:}", from).as_str());
+                }
+                for change in changes {
+                    text.push_str(
+                        format!(
+                            "{:}\n  - {:}\n  + {:}\n",
+                            change.key, change.from, change.to
+                        )
+                        .as_str(),
+                    );
+                }
+            }
+            None => text.push_str(
+                "Type the base URL to replace, a space, then its replacement, like {{base_url}}",
+            ),
+        }
+        let block = Block::default().style(Style::default().bg(Color::Blue));
+        for chunk in dim_chunks {
+            rect.render_widget(block.clone(), *chunk);
+        }
+        let area = centered_rect(60, 60, rect.size());
         rect.render_widget(Clear, area);
+        let title = format!(
+            "Retarget Requests ({:} to apply)",
+            get_key_symbol(KeyCode::Enter)
+        );
         paragraph_color(
             rect,
             area,
-            "Request Name",
-            app.request_name.as_str(),
+            title.as_str(),
+            text.as_str(),
             true,
             0,
             Color::Cyan,
@@ -312,8 +1351,256 @@ fn ui<B: Backend>(rect: &mut Frame<B>, app: &mut App) {
         );
     }
 
+    if app.modal == Modal::Download {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Send and Download the Response To",
+            app.download_path.as_str(),
+        );
+    }
+
+    if app.modal == Modal::Prompts {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Variables Asked for When Sending if Unset, Like order_id, user_id",
+            app.prompts_input.as_str(),
+        );
+    }
+
+    if app.modal == Modal::Timeout {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Timeout in Seconds, Empty for the Default",
+            app.timeout_input.as_str(),
+        );
+    }
+
+    if app.modal == Modal::Proxy {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Proxy, Like socks5://proxy:1080, Empty for the Default",
+            app.proxy_input.as_str(),
+        );
+    }
+
+    if app.modal == Modal::CustomMethod {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Custom Method, Like PROPFIND",
+            app.method_input.as_str(),
+        );
+    }
+
+    if app.modal == Modal::Assertions {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Assertions, Like status 200, header Name: value, json $.id == 1 or json $.name contains a (Enter on an Empty Line to Finish)",
+            app.assertions_input.as_str(),
+        );
+    }
+
+    if app.modal == Modal::Captures {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Captures, Like auth_token = $.token (Enter on an Empty Line to Finish)",
+            app.captures_input.as_str(),
+        );
+    }
+
+    if app.modal == Modal::RunCollection {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Run the Saved Requests Starting With (Empty for All)",
+            app.run_prefix.as_str(),
+        );
+    }
+
+    if app.modal == Modal::ImportHttpFile {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Path of the .http File to Import",
+            app.import_http_path.as_str(),
+        );
+    }
+
+    if app.modal == Modal::ImportInsomnia {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Path of the Insomnia Export to Import",
+            app.import_insomnia_path.as_str(),
+        );
+    }
+
+    if app.modal == Modal::ImportOpenApi {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Path or URL of the OpenAPI Document to Import",
+            app.import_openapi_source.as_str(),
+        );
+    }
+
+    if app.modal == Modal::OidcIssuer {
+        input_modal(
+            rect,
+            dim_chunks,
+            "OpenID Connect Issuer to Set Up OAuth2 From",
+            app.oidc_issuer.as_str(),
+        );
+    }
+
+    if app.modal == Modal::ImportCookies {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Import Cookies (Cookie header or cookies.txt path, empty to clear)",
+            app.import_cookies.as_str(),
+        );
+    }
+
+    if app.modal == Modal::InsertFile {
+        input_modal(
+            rect,
+            dim_chunks,
+            "Insert File",
+            app.insert_file_path.as_str(),
+        );
+    }
+
     rect.render_widget(status_help, chunks[2]);
-    let duration = start.elapsed();
 
-    info!("Time elapsed rendering ui is: {:?}", duration);
+    if app.show_profiler {
+        let width = 24.min(size.width);
+        let area = Rect::new(size.width - width, 0, width, 5.min(size.height));
+        rect.render_widget(Clear, area);
+        rect.render_widget(
+            Paragraph::new(app.frame_stats.lines())
+                .style(Style::default().fg(Color::Yellow))
+                .block(block("Profiler", false)),
+            area,
+        );
+    }
+
+    if app.settings.low_bandwidth {
+        rect.render_widget(AsciiBorders, size);
+    }
+}
+
+/// Render a selectable list modal over the dimmed application. Returns the area of the list.
+fn list_modal<B: Backend>(
+    rect: &mut Frame<B>,
+    chunks: &[Rect],
+    title: &str,
+    items: Vec<ListItem>,
+    state: &mut ListState,
+) -> Rect {
+    let block = Block::default().style(Style::default().bg(Color::Blue));
+    for chunk in chunks {
+        rect.render_widget(block.clone(), *chunk);
+    }
+
+    let area = centered_rect(60, 60, rect.size());
+    rect.render_widget(Clear, area);
+
+    let items = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(
+            Style::default()
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD)
+                .fg(Color::Black),
+        )
+        .highlight_symbol(">> ");
+
+    rect.render_stateful_widget(items, area, state);
+    area
+}
+
+/// Render suggestions for the text being typed, with the first, which is accepted by pressing
+/// right, highlighted.
+fn suggestions_popup<B: Backend>(rect: &mut Frame<B>, area: Rect, suggestions: &[String]) {
+    let items: Vec<ListItem> = suggestions
+        .iter()
+        .enumerate()
+        .map(|(index, suggestion)| {
+            let style = if index == 0 {
+                Style::default().add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            ListItem::new(suggestion.as_str()).style(style)
+        })
+        .collect();
+    let title = format!(
+        "Suggestions ({:} to accept)",
+        get_key_symbol(KeyCode::Right)
+    );
+    rect.render_widget(Clear, area);
+    rect.render_widget(
+        List::new(items).block(Block::default().borders(Borders::ALL).title(title)),
+        area,
+    );
+}
+
+/// Render a single line input modal over the dimmed application.
+/// Every operation and its keys, in columns by category, over the whole screen.
+fn help_modal<B: Backend>(rect: &mut Frame<B>, app: &App) {
+    let area = rect.size();
+    rect.render_widget(Clear, area);
+    let title = format!(
+        "Help ({:}{:} scroll, Esc close)",
+        get_key_symbol(KeyCode::Up),
+        get_key_symbol(KeyCode::Down)
+    );
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let inner = block.inner(area);
+    rect.render_widget(block, area);
+
+    let sections = help_sections(&app.key_binds);
+    let total: usize = sections.iter().map(|(_, lines)| lines.len() + 2).sum();
+    const COLUMNS: usize = 3;
+    let per_column = total.div_ceil(COLUMNS);
+    let mut column_lines: Vec<Vec<Spans>> = vec![Vec::new()];
+    for (category, lines) in sections {
+        if column_lines.last().unwrap().len() >= per_column && column_lines.len() < COLUMNS {
+            column_lines.push(Vec::new());
+        }
+        let column = column_lines.last_mut().unwrap();
+        column.push(Spans::from(Span::styled(
+            category,
+            Style::default().add_modifier(Modifier::BOLD),
+        )));
+        column.extend(lines.into_iter().map(Spans::from));
+        column.push(Spans::from(""));
+    }
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Ratio(1, COLUMNS as u32); COLUMNS])
+        .split(inner);
+    for (lines, chunk) in column_lines.into_iter().zip(chunks) {
+        let paragraph = Paragraph::new(lines).scroll((app.help_scroll, 0));
+        rect.render_widget(paragraph, chunk);
+    }
+}
+
+fn input_modal<B: Backend>(rect: &mut Frame<B>, chunks: &[Rect], title: &str, value: &str) {
+    let block = Block::default().style(Style::default().bg(Color::Blue));
+    for chunk in chunks {
+        rect.render_widget(block.clone(), *chunk);
+    }
+
+    let area = centered_rect(60, 20, rect.size());
+    rect.render_widget(Clear, area);
+    paragraph_color(rect, area, title, value, true, 0, Color::Cyan, None);
 }
@@ -0,0 +1,175 @@
+//! A readable view of metrics in the Prometheus text format, as served by `/metrics` endpoints.
+
+/// The content type given to responses detected as metrics.
+pub const METRICS_CONTENT_TYPE: &str = "application/openmetrics-text";
+
+/// Whether a content type is one of the ones used for the Prometheus text format.
+pub fn is_metrics_content_type(content_type: &str) -> bool {
+    content_type.contains("openmetrics") || content_type.contains("version=0.0.4")
+}
+
+struct Sample<'a> {
+    name: &'a str,
+    labels: &'a str,
+    value: &'a str,
+}
+
+/// A metric with its samples. Histograms and summaries have samples with suffixed names, like
+/// `_bucket`, which are grouped with the metric.
+struct Family<'a> {
+    name: &'a str,
+    kind: Option<&'a str>,
+    help: Option<&'a str>,
+    samples: Vec<Sample<'a>>,
+}
+
+/// Split a line like `http_requests_total{method="get",code="200"} 1027 1395066363000`.
+fn parse_sample(line: &str) -> Option<Sample<'_>> {
+    let name_end = line
+        .find(|c: char| c == '{' || c.is_whitespace())
+        .unwrap_or(line.len());
+    let name = &line[..name_end];
+    if name.is_empty() {
+        return None;
+    }
+    let mut rest = &line[name_end..];
+    let mut labels = "";
+    if rest.starts_with('{') {
+        let mut quoted = false;
+        let mut escaped = false;
+        let end = rest.char_indices().find(|(_, c)| {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => quoted = !quoted,
+                '}' if !quoted => return true,
+                _ => {}
+            }
+            false
+        })?;
+        labels = &rest[..=end.0];
+        rest = &rest[end.0 + 1..];
+    }
+    let value = rest.split_whitespace().next()?;
+    Some(Sample {
+        name,
+        labels,
+        value,
+    })
+}
+
+/// The body has `# HELP` or `# TYPE` comments, and its first samples parse.
+pub fn is_metrics(body: &str) -> bool {
+    const SAMPLE_LINES: usize = 20;
+
+    let mut has_comments = false;
+    let mut lines = body
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty());
+    for line in lines.by_ref().take(SAMPLE_LINES) {
+        if line.starts_with("# HELP ") || line.starts_with("# TYPE ") {
+            has_comments = true;
+        } else if !line.starts_with('#') && parse_sample(line).is_none() {
+            return false;
+        }
+    }
+    has_comments
+}
+
+/// Render metrics grouped by name, with the values of each metric's samples aligned. Only
+/// metrics whose name contains `filter` are included.
+pub fn metrics_view(body: &str, filter: &str) -> String {
+    let mut families: Vec<Family> = Vec::new();
+    for line in body.lines().map(|line| line.trim()) {
+        if let Some(comment) = line.strip_prefix('#') {
+            let mut parts = comment.trim_start().splitn(3, ' ');
+            let (keyword, name, text) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(keyword), Some(name), text) => (keyword, name, text.map(str::trim)),
+                _ => continue,
+            };
+            let family = family(&mut families, name);
+            match keyword {
+                "HELP" => family.help = text,
+                "TYPE" => family.kind = text,
+                _ => {}
+            }
+            continue;
+        }
+        if let Some(sample) = parse_sample(line) {
+            let name = families
+                .iter()
+                .rev()
+                .find(|family| belongs_to(sample.name, family.name))
+                .map_or(sample.name, |family| family.name);
+            family(&mut families, name).samples.push(sample);
+        }
+    }
+
+    let mut res = String::new();
+    for family in families
+        .iter()
+        .filter(|family| family.name.contains(filter))
+    {
+        // A metric with a single unlabeled sample fits on one line.
+        let single = match family.samples.as_slice() {
+            [sample] if sample.name == family.name && sample.labels.is_empty() => Some(sample),
+            _ => None,
+        };
+        res.push_str(family.name);
+        if let Some(sample) = single {
+            res.push_str(format!("  {:}", sample.value).as_str());
+        }
+        if let Some(kind) = family.kind {
+            res.push_str(format!(" ({:})", kind).as_str());
+        }
+        if let Some(help) = family.help {
+            res.push_str(format!("  {:}", help).as_str());
+        }
+        res.push('\n');
+        if single.is_some() {
+            continue;
+        }
+
+        let keys: Vec<String> = family
+            .samples
+            .iter()
+            .map(|sample| format!("{:}{:}", &sample.name[family.name.len()..], sample.labels))
+            .collect();
+        let width = keys
+            .iter()
+            .map(|key| key.chars().count())
+            .max()
+            .unwrap_or(0);
+        for (key, sample) in keys.iter().zip(family.samples.iter()) {
+            let line = format!("  {:width$}  {:}", key, sample.value, width = width);
+            res.push_str(line.trim_end());
+            res.push('\n');
+        }
+    }
+    res
+}
+
+/// The family with the name, added if there isn't one yet.
+fn family<'a, 'b>(families: &'b mut Vec<Family<'a>>, name: &'a str) -> &'b mut Family<'a> {
+    match families.iter().position(|family| family.name == name) {
+        Some(index) => &mut families[index],
+        None => {
+            families.push(Family {
+                name,
+                kind: None,
+                help: None,
+                samples: Vec::new(),
+            });
+            families.last_mut().unwrap()
+        }
+    }
+}
+
+fn belongs_to(sample: &str, family: &str) -> bool {
+    const SUFFIXES: [&str; 6] = ["", "_bucket", "_sum", "_count", "_total", "_created"];
+
+    sample
+        .strip_prefix(family)
+        .is_some_and(|suffix| SUFFIXES.contains(&suffix))
+}
@@ -0,0 +1,224 @@
+//! OAuth2 configured per environment. Tokens are fetched from the token endpoint with the client
+//! credentials grant, or with the refresh token when the endpoint gave one, and cached in the
+//! environment's variables. They are sent as a Bearer token, and fetched again when they are
+//! about to expire or a request is rejected as unauthorized.
+
+use crate::environment::{substitute, Environment, Environments};
+use crate::history::now;
+use crate::proxy::ProxyChoice;
+use crate::query::encode;
+use crate::settings::Settings;
+use crate::token_refresh::jwt_expiry;
+use crate::web_request_handler::{next_request_id, queue};
+use crate::{Method, Request, Response, WebRequest};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Variables the tokens are cached in.
+pub const ACCESS_TOKEN: &str = "oauth2_access_token";
+pub const REFRESH_TOKEN: &str = "oauth2_refresh_token";
+/// When the access token expires, in seconds since the unix epoch, if the endpoint said.
+const EXPIRES_AT: &str = "oauth2_expires_at";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OAuth2 {
+    /// The fields can reference variables, like `{{client_secret}}`, which keeps the secret with
+    /// the other secrets of the environment.
+    pub token_endpoint: String,
+    pub client_id: String,
+    pub client_secret: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+}
+
+/// The variables holding the cached tokens, which are treated as secrets.
+pub fn is_token_variable(name: &str) -> bool {
+    name == ACCESS_TOKEN || name == REFRESH_TOKEN
+}
+
+fn cached<'a>(environment: &'a Environment, variable: &str) -> Option<&'a str> {
+    environment.get(variable).filter(|value| !value.is_empty())
+}
+
+/// When the cached access token expires, from its `exp` claim if it is a JWT, otherwise as the
+/// token endpoint said.
+pub fn token_expiry(environment: &Environment) -> Option<u64> {
+    let token = cached(environment, ACCESS_TOKEN)?;
+    jwt_expiry(token).or_else(|| cached(environment, EXPIRES_AT)?.parse().ok())
+}
+
+/// A token needs fetching when there isn't one, or it expires within `window` seconds.
+pub fn needs_token(environment: &Environment, window: u64) -> bool {
+    if environment.oauth2.is_none() {
+        return false;
+    }
+    match cached(environment, ACCESS_TOKEN) {
+        None => true,
+        Some(_) => token_expiry(environment).is_some_and(|expiry| expiry <= now() + window),
+    }
+}
+
+/// The header sending the cached token, unless the headers already authorize the request.
+pub fn authorization(headers: &str, environment: Option<&Environment>) -> Option<String> {
+    let environment = environment.filter(|environment| environment.oauth2.is_some())?;
+    let token = cached(environment, ACCESS_TOKEN)?;
+    let authorized = headers.lines().any(|line| {
+        line.split_once(':')
+            .is_some_and(|(name, _)| name.trim().eq_ignore_ascii_case("authorization"))
+    });
+    (!authorized).then(|| format!("Authorization: Bearer {:}", token))
+}
+
+/// The form body of a token request, using the refresh token grant when one is given.
+fn token_form(oauth2: &OAuth2, refresh_token: Option<&str>, environment: &Environment) -> String {
+    let mut params = match refresh_token {
+        Some(refresh_token) => vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", refresh_token.to_string()),
+        ],
+        None => vec![("grant_type", "client_credentials".to_string())],
+    };
+    params.push((
+        "client_id",
+        substitute(oauth2.client_id.as_str(), Some(environment)),
+    ));
+    params.push((
+        "client_secret",
+        substitute(oauth2.client_secret.as_str(), Some(environment)),
+    ));
+    if !oauth2.scopes.is_empty() {
+        params.push(("scope", oauth2.scopes.join(" ")));
+    }
+    let params: Vec<String> = params
+        .iter()
+        .map(|(name, value)| format!("{:}={:}", name, encode(value)))
+        .collect();
+    params.join("&")
+}
+
+/// Send a token request, returning the status, 0 if there was no response, and the token
+/// response if the request succeeded.
+async fn request_token(
+    sender: &mpsc::Sender<WebRequest>,
+    busy: &AtomicBool,
+    url: String,
+    body: String,
+    settings: &Settings,
+) -> (u16, Option<Value>) {
+    let (tx, mut rx) = mpsc::channel(10);
+    let request = WebRequest::Request(Box::new(Request {
+        id: next_request_id(),
+        method: Method::POST,
+        url,
+        headers: "Content-Type: application/x-www-form-urlencoded\nAccept: application/json"
+            .to_string(),
+        body,
+        multipart: None,
+        body_file: None,
+        download: None,
+        proxy: ProxyChoice::new(false, settings.proxy.clone()),
+        timeout: settings.request_timeout(),
+        redirects: settings.redirects,
+        resp: tx,
+    }));
+    if let Err(err) = queue(sender, request, busy).await {
+        error!("Error fetching OAuth2 token {:}", err);
+        return (0, None);
+    }
+
+    let mut status = 0;
+    let mut success = false;
+    let mut received: Vec<u8> = Vec::new();
+    while let Some(res) = rx.recv().await {
+        match res {
+            Response::Status(res) => {
+                status = res.as_u16();
+                success = res.is_success();
+            }
+            Response::Body(bytes) => received.extend_from_slice(&bytes),
+            Response::Failure(err) => {
+                error!("Error fetching OAuth2 token {:}", err);
+                return (status, None);
+            }
+            _ => {}
+        }
+    }
+    if !success {
+        return (status, None);
+    }
+    (status, serde_json::from_slice(&received).ok())
+}
+
+/// Fetch a token for the active environment and cache it. The refresh token is used when there
+/// is one, falling back to the client credentials if it is rejected. Returns the status of the
+/// token response, 0 if there wasn't one, and whether a token was cached.
+pub async fn fetch_token(
+    sender: &mpsc::Sender<WebRequest>,
+    busy: &AtomicBool,
+    environments: &Arc<Mutex<Environments>>,
+    settings: &Settings,
+) -> (u16, bool) {
+    let (oauth2, environment) = {
+        let environments = environments.lock().unwrap();
+        match environments.active() {
+            Some(environment) => match &environment.oauth2 {
+                Some(oauth2) => (oauth2.clone(), environment.clone()),
+                None => return (0, false),
+            },
+            None => return (0, false),
+        }
+    };
+    let url = substitute(oauth2.token_endpoint.as_str(), Some(&environment));
+    let mut refresh_token = cached(&environment, REFRESH_TOKEN);
+
+    loop {
+        let body = token_form(&oauth2, refresh_token, &environment);
+        let (status, response) = request_token(sender, busy, url.clone(), body, settings).await;
+        let token = response
+            .as_ref()
+            .and_then(|response| response.get("access_token"))
+            .and_then(Value::as_str);
+        match (token, response.as_ref()) {
+            (Some(token), Some(response)) => {
+                store_token(environments, token, response);
+                return (status, true);
+            }
+            _ if refresh_token.is_some() => {
+                info!(
+                    "Refresh token rejected with {:}, using the client credentials",
+                    status
+                );
+                refresh_token = None;
+            }
+            _ => {
+                error!("OAuth2 token request to {:} failed with {:}", url, status);
+                return (status, false);
+            }
+        }
+    }
+}
+
+fn store_token(environments: &Arc<Mutex<Environments>>, token: &str, response: &Value) {
+    let mut environments = environments.lock().unwrap();
+    let environment = match environments.active_mut() {
+        Some(environment) => environment,
+        None => return,
+    };
+    environment.set(ACCESS_TOKEN, token);
+    // Endpoints which rotate refresh tokens send a new one, others keep the old one valid.
+    if let Some(refresh_token) = response.get("refresh_token").and_then(Value::as_str) {
+        environment.set(REFRESH_TOKEN, refresh_token);
+    }
+    let expires_at = response
+        .get("expires_in")
+        .and_then(Value::as_u64)
+        .map(|expires_in| (now() + expires_in).to_string());
+    environment.set(EXPIRES_AT, expires_at.as_deref().unwrap_or(""));
+    if let Err(err) = environments.save() {
+        error!("Error saving OAuth2 token {:}", err);
+    }
+}
@@ -0,0 +1,111 @@
+//! Setting up OAuth2 for an environment from the discovery document of an OpenID Connect issuer.
+//! The token is fetched with the client credentials grant by a saved request, which the
+//! environment's token refresh sends when a request is rejected as unauthorized.
+
+use crate::environment::Environment;
+use crate::persistence::{Request, RequestBuilder};
+use crate::token_refresh::TokenRefresh;
+use crate::Method;
+
+use serde::Deserialize;
+
+const DISCOVERY_PATH: &str = "/.well-known/openid-configuration";
+
+/// The parts of the discovery document used to configure OAuth2.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Discovery {
+    pub issuer: String,
+    pub authorization_endpoint: Option<String>,
+    pub token_endpoint: String,
+    #[serde(default)]
+    pub scopes_supported: Vec<String>,
+}
+
+/// The URL of the discovery document for an issuer, which may already be the full URL.
+pub fn discovery_url(issuer: &str) -> String {
+    let issuer = issuer.trim().trim_end_matches('/');
+    if issuer.ends_with(DISCOVERY_PATH) {
+        issuer.to_string()
+    } else {
+        format!("{:}{:}", issuer, DISCOVERY_PATH)
+    }
+}
+
+/// Name of the saved request which fetches the token for an environment.
+pub fn token_request_name(environment: &str) -> String {
+    format!("{:} token", environment)
+}
+
+/// The request for a token with the client credentials grant, using the environment's variables.
+pub fn token_request(environment: &str) -> Request {
+    let mut builder = RequestBuilder::new(token_request_name(environment).as_str());
+    builder.method(Method::POST);
+    builder.url("{{oauth_token_endpoint}}");
+    builder.headers("Content-Type: application/x-www-form-urlencoded");
+    builder.body(
+        "grant_type=client_credentials&client_id={{client_id}}&client_secret={{client_secret}}\
+         &scope={{oauth_scope}}",
+    );
+    builder.build()
+}
+
+/// Set the endpoint variables and the token refresh of an environment. The client credentials
+/// and scope are only added if they aren't set yet, the scope defaulting to all of the supported
+/// scopes.
+pub fn configure(environment: &mut Environment, discovery: &Discovery) {
+    environment.set("oauth_issuer", discovery.issuer.as_str());
+    environment.set("oauth_token_endpoint", discovery.token_endpoint.as_str());
+    if let Some(endpoint) = &discovery.authorization_endpoint {
+        environment.set("oauth_authorization_endpoint", endpoint.as_str());
+    }
+    let scope = discovery.scopes_supported.join(" ");
+    for (name, value) in [
+        ("client_id", ""),
+        ("client_secret", ""),
+        ("oauth_scope", scope.as_str()),
+    ] {
+        if environment.get(name).is_none() {
+            environment.set(name, value);
+        }
+    }
+    if !environment.is_secret("client_secret") {
+        environment.secrets.push("client_secret".to_string());
+    }
+    environment.token_refresh = Some(TokenRefresh {
+        request: token_request_name(environment.name.as_str()),
+        variable: "access_token".to_string(),
+        path: "$.access_token".to_string(),
+    });
+}
+
+/// What was set up, and what is left to do.
+pub fn report(environment: &str, discovery: &Discovery) -> String {
+    let mut res = format!(
+        "Set up OAuth2 for the {:} environment from {:}\n\n",
+        environment, discovery.issuer
+    );
+    res.push_str(format!("Token endpoint: {:}\n", discovery.token_endpoint).as_str());
+    if let Some(endpoint) = &discovery.authorization_endpoint {
+        res.push_str(format!("Authorization endpoint: {:}\n", endpoint).as_str());
+    }
+    if !discovery.scopes_supported.is_empty() {
+        res.push_str(
+            format!(
+                "Supported scopes: {:}\n",
+                discovery.scopes_supported.join(" ")
+            )
+            .as_str(),
+        );
+    }
+    res.push_str(
+        format!(
+            "\nSet client_id and client_secret in the environment, and trim oauth_scope to the \
+             scopes needed. Then send requests with the header\n\n\
+             Authorization: Bearer {{{{access_token}}}}\n\n\
+             A token is fetched with the \"{:}\" request when one is rejected as unauthorized.\n",
+            token_request_name(environment)
+        )
+        .as_str(),
+    );
+    res
+}
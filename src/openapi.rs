@@ -0,0 +1,599 @@
+//! Importing the operations of an OpenAPI 3 or Swagger 2 document as saved requests. Each path
+//! and method becomes a request whose URL starts with `{{base_url}}`, with path parameters as
+//! variables, required query parameters and headers filled in, and an example body built from
+//! the schema when the document doesn't give one.
+
+use crate::error::{Error, Result};
+use crate::persistence::{Request, RequestBuilder};
+use crate::{BodyType, Method};
+use reqwest::Url;
+use serde_json::{Map, Value};
+
+/// The variable the URLs of the imported requests start with.
+pub const BASE_URL: &str = "base_url";
+
+/// Schemas nested deeper than this are left out of example bodies.
+const MAX_DEPTH: usize = 8;
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// The requests of a document, and the URL of its first server.
+pub struct Import {
+    pub title: String,
+    pub server: Option<String>,
+    pub requests: Vec<Request>,
+}
+
+/// Parse a document, which can be JSON or YAML.
+pub fn parse(text: &str) -> Result<Value> {
+    match serde_json::from_str(text) {
+        Ok(document) => Ok(document),
+        Err(_) => serde_yaml::from_str(text).map_err(|err| {
+            Error::Parse(format!("The OpenAPI document isn't JSON or YAML: {:}", err))
+        }),
+    }
+}
+
+/// The requests for each operation of a document. The source, if it is a URL, is used for the
+/// server when the document's is relative or missing.
+pub fn import(document: &Value, source: Option<&str>) -> Result<Import> {
+    let version = document
+        .get("openapi")
+        .or_else(|| document.get("swagger"))
+        .and_then(Value::as_str)
+        .ok_or_else(|| {
+            Error::Parse("The document has no openapi or swagger version".to_string())
+        })?;
+    if !version.starts_with('3') && !version.starts_with('2') {
+        return Err(Error::Parse(format!(
+            "OpenAPI version {:} isn't supported",
+            version
+        )));
+    }
+    let title = document
+        .pointer("/info/title")
+        .and_then(Value::as_str)
+        .unwrap_or("API")
+        .to_string();
+    let source = source
+        .and_then(|source| Url::parse(source).ok())
+        .filter(|source| matches!(source.scheme(), "http" | "https"));
+    let server = if version.starts_with('3') {
+        server_url(document, source.as_ref())
+    } else {
+        swagger_server_url(document, source.as_ref())
+    };
+    let paths = document
+        .get("paths")
+        .and_then(Value::as_object)
+        .ok_or_else(|| Error::Parse("The document has no paths".to_string()))?;
+
+    let mut requests = Vec::new();
+    for (path, item) in paths {
+        let item = resolve(document, item);
+        let shared = parameters(document, item);
+        for method in METHODS {
+            if let Some(operation) = item.get(method) {
+                requests.push(request(
+                    document,
+                    title.as_str(),
+                    path.as_str(),
+                    method,
+                    operation,
+                    &shared,
+                ));
+            }
+        }
+    }
+    Ok(Import {
+        title,
+        server,
+        requests,
+    })
+}
+
+/// A summary of an import, shown in place of a response.
+pub fn report(import: &Import) -> String {
+    let mut res = format!(
+        "Imported {:} requests from {:}\n\n",
+        import.requests.len(),
+        import.title
+    );
+    for request in &import.requests {
+        res.push_str(format!("{:}\n", request.key).as_str());
+    }
+    match &import.server {
+        Some(server) => res.push_str(
+            format!(
+                "\nThe URLs start with {{{{{:}}}}}, which is set to {:} in the active \
+                 environment if it isn't set yet.\n",
+                BASE_URL, server
+            )
+            .as_str(),
+        ),
+        None => res.push_str(
+            format!(
+                "\nThe URLs start with {{{{{:}}}}}, set it to the URL of the API in an \
+                 environment.\n",
+                BASE_URL
+            )
+            .as_str(),
+        ),
+    }
+    res
+}
+
+/// Follow a `$ref` to a definition elsewhere in the document. References to other documents
+/// aren't followed.
+fn resolve<'a>(document: &'a Value, value: &'a Value) -> &'a Value {
+    let mut value = value;
+    for _ in 0..MAX_DEPTH {
+        match value
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|reference| reference.strip_prefix('#'))
+            .and_then(|pointer| document.pointer(pointer))
+        {
+            Some(target) => value = target,
+            None => break,
+        }
+    }
+    value
+}
+
+/// The first server of an OpenAPI 3 document, with its variables set to their defaults.
+fn server_url(document: &Value, source: Option<&Url>) -> Option<String> {
+    let server = document.pointer("/servers/0");
+    let mut url = server
+        .and_then(|server| server.get("url"))
+        .and_then(Value::as_str)
+        .unwrap_or("/")
+        .to_string();
+    if let Some(variables) = server
+        .and_then(|server| server.get("variables"))
+        .and_then(Value::as_object)
+    {
+        for (name, variable) in variables {
+            if let Some(default) = variable.get("default").and_then(Value::as_str) {
+                url = url.replace(format!("{{{:}}}", name).as_str(), default);
+            }
+        }
+    }
+    if Url::parse(url.as_str()).is_err() {
+        url = source?.join(url.as_str()).ok()?.to_string();
+    }
+    Some(url.trim_end_matches('/').to_string())
+}
+
+/// The server of a Swagger 2 document, from its scheme, host and base path.
+fn swagger_server_url(document: &Value, source: Option<&Url>) -> Option<String> {
+    let host = match document.get("host").and_then(Value::as_str) {
+        Some(host) => host.to_string(),
+        None => {
+            let source = source?;
+            match source.port() {
+                Some(port) => format!("{:}:{:}", source.host_str()?, port),
+                None => source.host_str()?.to_string(),
+            }
+        }
+    };
+    let scheme = document
+        .pointer("/schemes/0")
+        .and_then(Value::as_str)
+        .or_else(|| source.map(Url::scheme))
+        .unwrap_or("https");
+    let base_path = document
+        .get("basePath")
+        .and_then(Value::as_str)
+        .unwrap_or("");
+    Some(
+        format!("{:}://{:}{:}", scheme, host, base_path)
+            .trim_end_matches('/')
+            .to_string(),
+    )
+}
+
+/// The parameters of a path or operation, with references resolved.
+fn parameters<'a>(document: &'a Value, item: &'a Value) -> Vec<&'a Value> {
+    item.get("parameters")
+        .and_then(Value::as_array)
+        .map(|parameters| {
+            parameters
+                .iter()
+                .map(|parameter| resolve(document, parameter))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn request(
+    document: &Value,
+    title: &str,
+    path: &str,
+    method: &str,
+    operation: &Value,
+    shared: &[&Value],
+) -> Request {
+    let method = Method::parse(method);
+    let mut builder =
+        RequestBuilder::new(format!("{:}: {:} {:}", title, method.as_str(), path).as_str());
+    // Path templates like `/pets/{id}` become variables like `/pets/{{id}}`.
+    builder.url(
+        format!(
+            "{{{{{:}}}}}{:}",
+            BASE_URL,
+            path.replace('{', "{{").replace('}', "}}")
+        )
+        .as_str(),
+    );
+
+    // Parameters of the operation override those of the path with the same name and location.
+    let own = parameters(document, operation);
+    let key = |parameter: &Value| (parameter.get("name").cloned(), parameter.get("in").cloned());
+    let parameters: Vec<&Value> = shared
+        .iter()
+        .copied()
+        .filter(|parameter| own.iter().all(|other| key(other) != key(parameter)))
+        .chain(own.iter().copied())
+        .collect();
+
+    let mut headers = Vec::new();
+    let mut params = Vec::new();
+    let mut form = Vec::new();
+    let mut body = None;
+    for parameter in parameters {
+        let name = parameter.get("name").and_then(Value::as_str).unwrap_or("");
+        let required = parameter.get("required").and_then(Value::as_bool) == Some(true);
+        let location = parameter.get("in").and_then(Value::as_str).unwrap_or("");
+        match location {
+            "query" if required => params.push(format!(
+                "{:}={:}",
+                name,
+                parameter_example(document, parameter)
+            )),
+            "header" if required => headers.push(format!(
+                "{:}: {:}",
+                name,
+                parameter_example(document, parameter)
+            )),
+            // Swagger 2 describes the body and form fields as parameters.
+            "body" => {
+                body = parameter
+                    .get("schema")
+                    .map(|schema| example(document, schema))
+            }
+            "formData" => form.push(format!(
+                "{:}={:}",
+                name,
+                parameter_example(document, parameter)
+            )),
+            _ => {}
+        }
+    }
+
+    let consumes = operation
+        .pointer("/consumes/0")
+        .or_else(|| document.pointer("/consumes/0"))
+        .and_then(Value::as_str);
+    let content = match operation.get("requestBody") {
+        Some(request_body) => request_body_example(document, resolve(document, request_body)),
+        None if !form.is_empty() => Some((
+            consumes
+                .unwrap_or("application/x-www-form-urlencoded")
+                .to_string(),
+            Value::Null,
+        )),
+        None => body.map(|body| (consumes.unwrap_or("application/json").to_string(), body)),
+    };
+    if let Some((content_type, value)) = content {
+        headers.push(format!("Content-Type: {:}", content_type));
+        let body_type = if content_type.starts_with("multipart/form-data") {
+            BodyType::Multipart
+        } else if content_type.starts_with("application/x-www-form-urlencoded") {
+            BodyType::Form
+        } else {
+            BodyType::Raw
+        };
+        if !body_type.is_raw() {
+            // Form fields are edited as `name=value` lines. The multipart content type is set
+            // with its boundary when sending.
+            if let Value::Object(fields) = &value {
+                form.extend(
+                    fields
+                        .iter()
+                        .map(|(name, value)| format!("{:}={:}", name, plain(value))),
+                );
+            }
+            if body_type == BodyType::Multipart {
+                headers.pop();
+            }
+            builder.body(form.join("\n").as_str());
+        } else if let Value::String(text) = &value {
+            builder.body(text.as_str());
+        } else if !value.is_null() {
+            builder.body(
+                serde_json::to_string_pretty(&value)
+                    .unwrap_or_default()
+                    .as_str(),
+            );
+        }
+        builder.body_type(body_type);
+    }
+
+    builder.method(method);
+    builder.headers(headers.join("\n").as_str());
+    builder.params(params.join("\n").as_str());
+    builder.build()
+}
+
+/// The content type and example of an OpenAPI 3 request body, preferring JSON.
+fn request_body_example(document: &Value, request_body: &Value) -> Option<(String, Value)> {
+    let content = request_body.get("content").and_then(Value::as_object)?;
+    let (content_type, media) = content
+        .iter()
+        .find(|(content_type, _)| content_type.contains("json"))
+        .or_else(|| content.iter().next())?;
+    let value = match media.get("example") {
+        Some(example) => example.clone(),
+        None => match media
+            .get("examples")
+            .and_then(Value::as_object)
+            .and_then(|examples| examples.values().next())
+        {
+            Some(example) => resolve(document, example)
+                .get("value")
+                .cloned()
+                .unwrap_or(Value::Null),
+            None => media
+                .get("schema")
+                .map_or(Value::Null, |schema| example(document, schema)),
+        },
+    };
+    Some((content_type.clone(), value))
+}
+
+/// An example value of a parameter, as it is written in a URL or header.
+fn parameter_example(document: &Value, parameter: &Value) -> String {
+    let value = match parameter.get("example") {
+        Some(example) => example.clone(),
+        // Swagger 2 parameters have their type directly, instead of in a schema.
+        None => example(document, parameter.get("schema").unwrap_or(parameter)),
+    };
+    plain(&value)
+}
+
+/// A value without the quotes of a JSON string.
+fn plain(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => "".to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// An example of a value matching a schema, from its example, default or first allowed value,
+/// otherwise made up from its type.
+fn example(document: &Value, schema: &Value) -> Value {
+    schema_example(document, schema, &mut Vec::new()).unwrap_or(Value::Null)
+}
+
+/// An example of a schema within its parents, or nothing if it is one of them, so recursive
+/// schemas stop at the first repeat.
+fn schema_example<'a>(
+    document: &'a Value,
+    schema: &'a Value,
+    parents: &mut Vec<&'a Value>,
+) -> Option<Value> {
+    let schema = resolve(document, schema);
+    if parents.len() > MAX_DEPTH || parents.iter().any(|parent| std::ptr::eq(*parent, schema)) {
+        return None;
+    }
+    if let Some(value) = schema
+        .get("example")
+        .or_else(|| schema.get("default"))
+        .or_else(|| schema.pointer("/enum/0"))
+    {
+        return Some(value.clone());
+    }
+
+    parents.push(schema);
+    let value = if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+        let mut merged = Map::new();
+        for part in all {
+            if let Some(Value::Object(fields)) = schema_example(document, part, parents) {
+                merged.extend(fields);
+            }
+        }
+        Some(Value::Object(merged))
+    } else if let Some(first) = schema
+        .pointer("/oneOf/0")
+        .or_else(|| schema.pointer("/anyOf/0"))
+    {
+        schema_example(document, first, parents)
+    } else {
+        Some(type_example(document, schema, parents))
+    };
+    parents.pop();
+    value
+}
+
+/// An example made up from the type of a schema.
+fn type_example<'a>(document: &'a Value, schema: &'a Value, parents: &mut Vec<&'a Value>) -> Value {
+    let kind = schema.get("type").and_then(Value::as_str).unwrap_or(
+        if schema.get("properties").is_some() {
+            "object"
+        } else {
+            ""
+        },
+    );
+    match kind {
+        "object" => {
+            let mut fields = Map::new();
+            if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+                for (name, property) in properties {
+                    if let Some(value) = schema_example(document, property, parents) {
+                        fields.insert(name.clone(), value);
+                    }
+                }
+            }
+            Value::Object(fields)
+        }
+        "array" => Value::Array(
+            schema
+                .get("items")
+                .and_then(|items| schema_example(document, items, parents))
+                .into_iter()
+                .collect(),
+        ),
+        "string" => Value::from(match schema.get("format").and_then(Value::as_str) {
+            Some("date-time") => "2024-01-01T00:00:00Z",
+            Some("date") => "2024-01-01",
+            Some("uuid") => "00000000-0000-0000-0000-000000000000",
+            Some("email") => "user@example.com",
+            Some("uri") | Some("url") => "https://example.com",
+            _ => "string",
+        }),
+        "integer" | "number" => Value::from(0),
+        "boolean" => Value::from(false),
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::RequestTemplate;
+
+    const DOCUMENT: &str = r#"
+openapi: 3.0.0
+info:
+  title: Pets
+servers:
+  - url: https://{region}.example.com/v1/
+    variables:
+      region:
+        default: eu
+paths:
+  /pets/{id}:
+    parameters:
+      - name: id
+        in: path
+        required: true
+    get:
+      parameters:
+        - name: fields
+          in: query
+          required: true
+          example: name
+        - name: page
+          in: query
+        - name: X-Trace
+          in: header
+          required: true
+          schema:
+            type: string
+    put:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              $ref: '#/components/schemas/Pet'
+components:
+  schemas:
+    Pet:
+      type: object
+      properties:
+        name:
+          type: string
+        born:
+          type: string
+          format: date
+        tags:
+          type: array
+          items:
+            type: string
+        parent:
+          $ref: '#/components/schemas/Pet'
+"#;
+
+    #[test]
+    fn imports_an_openapi_document() {
+        let import = import(&parse(DOCUMENT).unwrap(), None).unwrap();
+        assert_eq!(import.title, "Pets");
+        assert_eq!(import.server.as_deref(), Some("https://eu.example.com/v1"));
+        assert_eq!(import.requests.len(), 2);
+
+        let get = &import.requests[0];
+        assert_eq!(get.key, "Pets: GET /pets/{id}");
+        let template = RequestTemplate::from(get);
+        assert_eq!(template.url, "{{base_url}}/pets/{{id}}");
+        assert_eq!(template.params, "fields=name");
+        assert_eq!(template.headers, "X-Trace:string");
+
+        let put = RequestTemplate::from(&import.requests[1]);
+        assert_eq!(put.method, Method::PUT);
+        assert_eq!(put.headers, "Content-Type:application/json");
+        // The recursive parent is left out.
+        let body: Value = serde_json::from_str(put.body.as_str()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"born": "2024-01-01", "name": "string", "tags": ["string"]})
+        );
+    }
+
+    #[test]
+    fn imports_a_swagger_document() {
+        let document = serde_json::json!({
+            "swagger": "2.0",
+            "basePath": "/api/",
+            "paths": {
+                "/login": {
+                    "post": {
+                        "parameters": [
+                            {"name": "user", "in": "formData", "type": "string"},
+                            {"name": "remember", "in": "formData", "type": "boolean"}
+                        ]
+                    }
+                }
+            }
+        });
+        let import = import(&document, Some("http://localhost:8080/swagger.json")).unwrap();
+        assert_eq!(import.title, "API");
+        assert_eq!(import.server.as_deref(), Some("http://localhost:8080/api"));
+        let login = RequestTemplate::from(&import.requests[0]);
+        assert_eq!(login.body_type, BodyType::Form);
+        assert_eq!(login.body, "user=string\nremember=false");
+        assert_eq!(
+            login.headers,
+            "Content-Type:application/x-www-form-urlencoded"
+        );
+    }
+
+    #[test]
+    fn relative_servers_use_the_source() {
+        let document = serde_json::json!({
+            "openapi": "3.1.0",
+            "servers": [{"url": "/v2"}],
+            "paths": {}
+        });
+        let imported = import(&document, Some("https://example.com/docs/openapi.json")).unwrap();
+        assert_eq!(imported.server.as_deref(), Some("https://example.com/v2"));
+        assert_eq!(import(&document, None).unwrap().server, None);
+        assert_eq!(
+            import(&document, Some("file:///tmp/a.json"))
+                .unwrap()
+                .server,
+            None
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse("a: [").is_err());
+        assert!(import(&serde_json::json!({"paths": {}}), None).is_err());
+        assert!(import(&serde_json::json!({"openapi": "4.0", "paths": {}}), None).is_err());
+        assert!(import(&serde_json::json!({"openapi": "3.0.0"}), None).is_err());
+    }
+}
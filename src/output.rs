@@ -0,0 +1,30 @@
+//! Naming of the files rester writes for the user, like saved responses and exports.
+
+use std::path::PathBuf;
+
+/// Long names, like URLs with big query strings, are shortened to this many characters.
+const MAX_NAME_LEN: usize = 100;
+
+/// A path in the current directory for a new file named after `name`, which can be a URL or
+/// other text that isn't a valid file name. The name is made valid on every platform, including
+/// Windows, shortened when very long, and numbered when a file with the name already exists.
+pub fn output_path(name: &str, extension: &str) -> PathBuf {
+    let options = sanitize_filename::Options {
+        windows: true,
+        truncate: true,
+        replacement: "_",
+    };
+    let name = sanitize_filename::sanitize_with_options(name.replace("://", "_"), options);
+    let name: String = name.chars().take(MAX_NAME_LEN).collect();
+    // Windows doesn't allow names ending with a dot or space, which truncating can leave.
+    let name = name.trim_end_matches(['.', ' ']);
+    let name = if name.is_empty() { "output" } else { name };
+
+    let mut path = PathBuf::from(format!("{:}.{:}", name, extension));
+    let mut number = 1;
+    while path.exists() {
+        path = PathBuf::from(format!("{:}-{:}.{:}", name, number, extension));
+        number += 1;
+    }
+    path
+}
@@ -4,12 +4,44 @@ use crossterm::event::{KeyCode, KeyEvent};
 
 use std::sync::Arc;
 
+/// Changes how a line is displayed, without changing the value.
+pub type LineTransform = Box<dyn Fn(&str) -> String + Send>;
+
+/// Text searched for in the displayed text.
+struct Search {
+    query: String,
+    /// The query is being typed, so keys add to it.
+    typing: bool,
+    /// Line of the displayed text the search started from.
+    origin: usize,
+    /// Line of the displayed text and byte offset in it of each match.
+    matches: Vec<(usize, usize)>,
+    current: usize,
+}
+
 pub struct ParagraphWithState {
     value: String,
     pub cache: Option<Arc<WrappedCache>>,
     pub scroll: u16,
+    /// Keep the end of the text in view as it is appended to, like `less +F`. Scrolling up stops
+    /// following.
+    pub follow: bool,
     supports_scroll: bool,
     supports_editing: bool,
+    supports_cursor: bool,
+    /// Line of the value the cursor is on.
+    cursor: usize,
+    /// Line of the value where a selection was started.
+    mark: Option<usize>,
+    /// Inclusive ranges of lines of the value which are collapsed into a placeholder.
+    folds: Vec<(usize, usize)>,
+    line_transform: Option<LineTransform>,
+    /// Lines of the value which are displayed without the line transform.
+    expanded: Vec<usize>,
+    /// The text displayed when it differs from the value, with the line of the value each of its
+    /// lines came from.
+    display: Option<(String, Vec<usize>)>,
+    search: Option<Search>,
 }
 
 impl ParagraphWithState {
@@ -18,14 +50,41 @@ impl ParagraphWithState {
             value: init_value,
             cache: None,
             scroll: 0,
+            follow: true,
             supports_scroll,
             supports_editing,
+            supports_cursor: false,
+            cursor: 0,
+            mark: None,
+            folds: Vec::new(),
+            line_transform: None,
+            expanded: Vec::new(),
+            display: None,
+            search: None,
         }
     }
 
+    /// Move through the text with a line cursor, instead of scrolling it.
+    pub fn with_cursor(mut self) -> Self {
+        self.supports_cursor = true;
+        self
+    }
+
     pub fn reset(&mut self) {
         self.scroll = 0;
         self.value = "".to_string();
+        self.cursor = 0;
+        self.mark = None;
+        self.folds.clear();
+        self.expanded.clear();
+        self.refresh_display();
+        self.search = None;
+    }
+
+    pub fn set_line_transform(&mut self, line_transform: Option<LineTransform>) {
+        self.line_transform = line_transform;
+        self.refresh_display();
+        self.cache = None;
     }
 
     pub fn is_empty(&self) -> bool {
@@ -36,21 +95,276 @@ impl ParagraphWithState {
         self.value.as_str()
     }
 
+    /// The text to render, with folded lines collapsed.
+    pub fn display_str(&self) -> &str {
+        match &self.display {
+            Some((display, _)) => display.as_str(),
+            None => self.value.as_str(),
+        }
+    }
+
+    /// The line of the value the cursor is on.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// The line of the displayed text the cursor is on, if the cursor is shown.
+    pub fn cursor_line(&self) -> Option<usize> {
+        if !self.supports_cursor {
+            return None;
+        }
+        Some(self.display_line(self.cursor))
+    }
+
     pub fn set_value(&mut self, value: String) {
         self.value = value;
+        self.cursor = 0;
+        self.mark = None;
+        self.folds.clear();
+        self.expanded.clear();
+        self.refresh_display();
+        self.cache = None;
+    }
+
+    /// Drop the wrapped text, so it is wrapped again at the current width on the next render.
+    pub fn invalidate_cache(&mut self) {
         self.cache = None;
     }
 
     pub fn append_value(&mut self, value: String) {
         self.value.push_str(value.as_str());
+        if self.display.is_some() {
+            self.refresh_display();
+        } else {
+            self.find_matches();
+        }
         self.cache = None;
-        self.scroll = u16::MAX;
+        if self.follow {
+            self.scroll = u16::MAX;
+        }
+    }
+
+    /// Move the cursor to a line and scroll it to the top, unfolding it if it is folded.
+    pub fn go_to_line(&mut self, line: usize) {
+        self.follow = false;
+        self.folds
+            .retain(|(start, end)| !(*start..=*end).contains(&line));
+        self.refresh_display();
+        self.cursor = line;
+        let display_line = self.display_line(line);
+        self.scroll = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.rows_of(display_line))
+            .map_or(display_line as u16, |(row, _)| row);
+        self.cache = None;
+    }
+
+    /// Start following the end of the text, or stop.
+    pub fn toggle_follow(&mut self) {
+        self.follow = !self.follow;
+        if self.follow {
+            self.scroll = u16::MAX;
+            self.cursor = self.line_count().saturating_sub(1);
+        }
+    }
+
+    /// The search query, with the number of the current match and how many there are, while
+    /// there is a search.
+    pub fn search(&self) -> Option<(&str, usize, usize)> {
+        let search = self.search.as_ref()?;
+        let current = if search.matches.is_empty() {
+            0
+        } else {
+            search.current + 1
+        };
+        Some((search.query.as_str(), current, search.matches.len()))
+    }
+
+    /// The line of the displayed text with the current match, and which of the matches on the
+    /// line it is.
+    pub fn current_match(&self) -> Option<(usize, usize)> {
+        let search = self.search.as_ref()?;
+        let (line, _) = *search.matches.get(search.current)?;
+        let nth = search.matches[..search.current]
+            .iter()
+            .filter(|(match_line, _)| *match_line == line)
+            .count();
+        Some((line, nth))
+    }
+
+    /// Stop searching, returning whether there was a search.
+    pub fn end_search(&mut self) -> bool {
+        self.search.take().is_some()
+    }
+
+    /// Search the displayed text. `/` starts typing a query, which is searched for as it is
+    /// typed, and Enter finishes it. `n` and `N` then move to the next and previous match, and Esc
+    /// ends the search. Returns whether the key was used by the search.
+    pub fn handle_search_input(&mut self, key: KeyEvent) -> bool {
+        if self.supports_editing {
+            return false;
+        }
+        if key.code == KeyCode::Esc {
+            return self.end_search();
+        }
+        let search = match &mut self.search {
+            Some(search) => search,
+            None if key.code == KeyCode::Char('/') => {
+                self.start_search();
+                return true;
+            }
+            None => return false,
+        };
+        if search.typing {
+            match key.code {
+                KeyCode::Char(c) => search.query.push(c),
+                KeyCode::Backspace => {
+                    search.query.pop();
+                }
+                KeyCode::Enter if search.query.is_empty() => {
+                    self.search = None;
+                    return true;
+                }
+                KeyCode::Enter => {
+                    search.typing = false;
+                    return true;
+                }
+                _ => return true,
+            }
+            let origin = search.origin;
+            self.find_matches();
+            self.select_match_from(origin);
+            return true;
+        }
+        match key.code {
+            KeyCode::Char('/') => self.start_search(),
+            KeyCode::Char('n') => self.step_match(ScrollDirection::Down),
+            KeyCode::Char('N') => self.step_match(ScrollDirection::Up),
+            _ => return false,
+        }
+        true
+    }
+
+    fn start_search(&mut self) {
+        let origin = if self.supports_cursor {
+            self.display_line(self.cursor)
+        } else {
+            self.cache
+                .as_ref()
+                .and_then(|cache| cache.line_of_row(self.scroll))
+                .unwrap_or(0)
+        };
+        self.search = Some(Search {
+            query: String::new(),
+            typing: true,
+            origin,
+            matches: Vec::new(),
+            current: 0,
+        });
+    }
+
+    /// Find the query in the displayed text, ignoring ASCII case.
+    fn find_matches(&mut self) {
+        let search = match &mut self.search {
+            Some(search) => search,
+            None => return,
+        };
+        search.matches.clear();
+        let query = search.query.to_ascii_lowercase();
+        if !query.is_empty() {
+            let display = match &self.display {
+                Some((display, _)) => display.as_str(),
+                None => self.value.as_str(),
+            };
+            for (index, line) in display.split('\n').enumerate() {
+                let line = line.to_ascii_lowercase();
+                search.matches.extend(
+                    line.match_indices(query.as_str())
+                        .map(|(start, _)| (index, start)),
+                );
+            }
+        }
+        search.current = search.current.min(search.matches.len().saturating_sub(1));
+    }
+
+    /// Move to the first match at or after a line of the displayed text, wrapping to the top.
+    fn select_match_from(&mut self, line: usize) {
+        if let Some(search) = &mut self.search {
+            search.current = search
+                .matches
+                .iter()
+                .position(|(match_line, _)| *match_line >= line)
+                .unwrap_or(0);
+        }
+        self.show_match();
+    }
+
+    fn step_match(&mut self, direction: ScrollDirection) {
+        if let Some(search) = &mut self.search {
+            let count = search.matches.len();
+            if count == 0 {
+                return;
+            }
+            search.current = match direction {
+                ScrollDirection::Down => (search.current + 1) % count,
+                ScrollDirection::Up => (search.current + count - 1) % count,
+            };
+        }
+        self.show_match();
+    }
+
+    /// Scroll the current match into view, and move the cursor to it.
+    fn show_match(&mut self) {
+        let line = match self.current_match() {
+            Some((line, _)) => line,
+            None => return,
+        };
+        self.follow = false;
+        if self.supports_cursor {
+            self.cursor = self.value_line(line);
+        }
+        match self.cache.as_ref() {
+            Some(cache) => {
+                if let Some((row, rows)) = cache.rows_of(line) {
+                    let height = cache.height().max(1);
+                    if row < self.scroll || row + rows > self.scroll.saturating_add(height) {
+                        // Leave some of the text before the match in view.
+                        self.scroll = row.saturating_sub(height / 3);
+                    }
+                }
+            }
+            None => self.scroll = line as u16,
+        }
+    }
+
+    /// Scroll the text without moving the cursor, like with the mouse wheel. Scrolling up stops
+    /// following.
+    pub fn scroll_view(&mut self, direction: ScrollDirection) {
+        if let ScrollDirection::Up = direction {
+            self.follow = false;
+        }
+        self.scroll(direction);
     }
 
     pub fn handle_input(&mut self, key: KeyEvent) {
+        if self.handle_search_input(key) {
+            return;
+        }
         match key.code {
-            KeyCode::Up => self.scroll(ScrollDirection::Up),
+            KeyCode::Up if self.supports_cursor => {
+                self.follow = false;
+                self.move_cursor(ScrollDirection::Up)
+            }
+            KeyCode::Down if self.supports_cursor => self.move_cursor(ScrollDirection::Down),
+            KeyCode::Up => {
+                self.follow = false;
+                self.scroll(ScrollDirection::Up)
+            }
             KeyCode::Down => self.scroll(ScrollDirection::Down),
+            KeyCode::Char('v') if self.supports_cursor => self.mark = Some(self.cursor),
+            KeyCode::Char('z') if self.supports_cursor => self.toggle_fold(),
+            KeyCode::Char('e') if self.supports_cursor => self.toggle_expanded(),
             KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Enter => {
                 self.edit(key.code);
             }
@@ -101,6 +415,146 @@ impl ParagraphWithState {
         };
     }
 
+    fn line_count(&self) -> usize {
+        self.value.split('\n').count()
+    }
+
+    fn display_line(&self, line: usize) -> usize {
+        match &self.display {
+            Some((_, lines)) => lines
+                .iter()
+                .rposition(|display_line| *display_line <= line)
+                .unwrap_or(0),
+            None => line,
+        }
+    }
+
+    fn value_line(&self, display_line: usize) -> usize {
+        match &self.display {
+            Some((_, lines)) => lines.get(display_line).copied().unwrap_or(0),
+            None => display_line,
+        }
+    }
+
+    fn display_line_count(&self) -> usize {
+        match &self.display {
+            Some((_, lines)) => lines.len(),
+            None => self.line_count(),
+        }
+    }
+
+    fn move_cursor(&mut self, direction: ScrollDirection) {
+        let display_line = self.display_line(self.cursor);
+        let display_line = match direction {
+            ScrollDirection::Up => display_line.saturating_sub(1),
+            ScrollDirection::Down => {
+                (display_line + 1).min(self.display_line_count().saturating_sub(1))
+            }
+        };
+        self.cursor = self.value_line(display_line);
+
+        // Keep the cursor on screen, using the wrapping from the last render.
+        if let Some(cache) = &self.cache {
+            if let Some((row, rows)) = cache.rows_of(display_line) {
+                let height = cache.height().max(1);
+                if row < self.scroll {
+                    self.scroll = row;
+                } else if row + rows > self.scroll.saturating_add(height) {
+                    self.scroll = (row + rows).saturating_sub(height);
+                }
+            }
+        }
+    }
+
+    /// The lines of the value from the mark to the cursor, if there is a mark, which is cleared.
+    pub fn take_selection(&mut self) -> Option<String> {
+        let mark = self.mark.take()?;
+        let (start, end) = (mark.min(self.cursor), mark.max(self.cursor));
+        let lines: Vec<&str> = self
+            .value
+            .lines()
+            .skip(start)
+            .take(end - start + 1)
+            .collect();
+        Some(lines.join("\n"))
+    }
+
+    /// Unfold the fold under the cursor, or fold the lines from the mark to the cursor.
+    fn toggle_fold(&mut self) {
+        let cursor = self.cursor;
+        if let Some(index) = self
+            .folds
+            .iter()
+            .position(|(start, end)| (*start..=*end).contains(&cursor))
+        {
+            self.folds.remove(index);
+        } else if let Some(mark) = self.mark.take() {
+            let (start, end) = (mark.min(cursor), mark.max(cursor));
+            if start == end {
+                return;
+            }
+            self.folds
+                .retain(|(fold_start, fold_end)| *fold_end < start || *fold_start > end);
+            self.folds.push((start, end));
+            self.folds.sort_unstable();
+            self.cursor = start;
+        }
+        self.refresh_display();
+        self.cache = None;
+    }
+
+    /// Show the line under the cursor without the line transform, or restore it.
+    fn toggle_expanded(&mut self) {
+        match self.expanded.iter().position(|line| *line == self.cursor) {
+            Some(index) => {
+                self.expanded.remove(index);
+            }
+            None => self.expanded.push(self.cursor),
+        }
+        self.refresh_display();
+        self.cache = None;
+    }
+
+    fn refresh_display(&mut self) {
+        if self.folds.is_empty() && self.line_transform.is_none() {
+            self.display = None;
+            self.find_matches();
+            return;
+        }
+        let mut display = String::with_capacity(self.value.len());
+        let mut lines = Vec::new();
+        let mut folds = self.folds.iter().peekable();
+        let mut skip_until = None;
+        for (index, line) in self.value.split('\n').enumerate() {
+            if let Some(end) = skip_until {
+                if index <= end {
+                    continue;
+                }
+                skip_until = None;
+            }
+            if !lines.is_empty() {
+                display.push('\n');
+            }
+            lines.push(index);
+            match folds.peek() {
+                Some((start, end)) if *start == index => {
+                    display
+                        .push_str(format!("[... {:} lines folded ...]", end - start + 1).as_str());
+                    skip_until = Some(*end);
+                    folds.next();
+                }
+                _ => match &self.line_transform {
+                    Some(transform) if !self.expanded.contains(&index) => {
+                        display.push_str(transform(line).as_str())
+                    }
+                    _ => display.push_str(line),
+                },
+            }
+        }
+        self.display = Some((display, lines));
+        self.find_matches();
+    }
+
     pub fn update(&mut self, update: (u16, Arc<WrappedCache>)) {
         self.scroll = update.0;
         self.cache = Some(update.1);
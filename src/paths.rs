@@ -0,0 +1,101 @@
+//! Locations of the files rester reads and writes. They default to the current directory and
+//! can be changed once at startup, for example by command line flags.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+static PATHS: OnceLock<Paths> = OnceLock::new();
+
+/// Name of the directory holding a project's own collection and environments.
+const PROJECT_DIR: &str = ".rester";
+
+#[derive(Debug, Clone)]
+pub struct Paths {
+    pub collection: PathBuf,
+    pub history: PathBuf,
+    pub settings: PathBuf,
+    pub environments: PathBuf,
+    pub audit: PathBuf,
+    /// Project directory the collection and environments were found in, if any.
+    pub project: Option<PathBuf>,
+}
+
+impl Default for Paths {
+    fn default() -> Self {
+        Paths {
+            collection: PathBuf::from("requests.json"),
+            history: PathBuf::from("history.json"),
+            settings: PathBuf::from("settings.json"),
+            environments: PathBuf::from("environments.json"),
+            audit: PathBuf::from("audit.jsonl"),
+            project: None,
+        }
+    }
+}
+
+impl Paths {
+    /// The default paths, using the collection and environments of the nearest project
+    /// directory when there is one.
+    pub fn discover() -> Self {
+        let mut paths = Paths::default();
+        let project = std::env::current_dir()
+            .ok()
+            .and_then(|dir| find_project_dir(&dir));
+        if let Some(project) = project {
+            paths.collection = project.join("requests.json");
+            paths.environments = project.join("environments.json");
+            paths.project = Some(project);
+        }
+        paths
+    }
+
+    /// A short name for the project, taken from the directory containing it.
+    pub fn project_name(&self) -> Option<String> {
+        let project = self.project.as_ref()?;
+        let name = project.parent().and_then(|parent| parent.file_name())?;
+        Some(name.to_string_lossy().to_string())
+    }
+
+    /// Files which contain user data.
+    pub fn data_files(&self) -> [&Path; 4] {
+        [
+            self.collection.as_path(),
+            self.history.as_path(),
+            self.environments.as_path(),
+            self.audit.as_path(),
+        ]
+    }
+
+    /// Data files which are encrypted when encryption is enabled. Files in a project directory
+    /// are shared through version control, so they are left in plain text.
+    pub fn protected_files(&self) -> Vec<&Path> {
+        self.data_files()
+            .into_iter()
+            .filter(|path| {
+                !self
+                    .project
+                    .as_ref()
+                    .is_some_and(|project| path.starts_with(project))
+            })
+            .collect()
+    }
+}
+
+/// Walk up from `start` looking for a project directory, like git does for `.git`.
+fn find_project_dir(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_DIR))
+        .find(|dir| dir.is_dir())
+}
+
+/// Set the paths for this session. Must be called before anything is loaded.
+pub fn set(paths: Paths) {
+    if PATHS.set(paths).is_err() {
+        error!("Paths were already set");
+    }
+}
+
+pub fn get() -> &'static Paths {
+    PATHS.get_or_init(Paths::default)
+}
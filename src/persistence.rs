@@ -1,24 +1,23 @@
-use crate::Method;
+use crate::assertion::Assertion;
+use crate::capture::Capture;
+use crate::error::Result;
+use crate::{crypto, paths, query, BodyType, Method};
 
 use serde::{Deserialize, Serialize};
 
-use std::fs::File;
-use std::io::{BufReader, Write};
-use std::path::Path;
-
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct KeyValuePair {
     pub key: String,
     pub value: String,
 }
 
-impl KeyValuePair {
-    pub fn to_string(&self) -> String {
-        format!("{:}:{:}", self.key, self.value)
+impl std::fmt::Display for KeyValuePair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:}:{:}", self.key, self.value)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Request {
     pub key: String,
     pub method: Method,
@@ -28,7 +27,36 @@ pub struct Request {
     // re-order.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub headers: Option<Vec<KeyValuePair>>,
+    /// Query parameters appended to the URL when the request is sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Vec<KeyValuePair>>,
     pub body: Option<String>,
+    #[serde(default, skip_serializing_if = "BodyType::is_raw")]
+    pub body_type: BodyType,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bypass_proxy: bool,
+    /// Seconds to wait for the response to start, instead of the timeout in the settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// Proxy to send the request through, instead of the one in the settings. Variables are
+    /// substituted, so its credentials can be kept in an environment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// Send the body even with a method which conventionally has none, like GET.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub send_body: bool,
+    /// Variables which are prompted for when sending, unless they are already set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prompts: Vec<String>,
+    /// Command the response body is piped through before it is shown, like `jq '.items[]'`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_process: Option<String>,
+    /// Checks of the response, evaluated each time the request is sent.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assertions: Vec<Assertion>,
+    /// Values of the response set as variables of the active environment.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub captures: Vec<Capture>,
 }
 
 impl Request {
@@ -37,7 +65,21 @@ impl Request {
             None => "".to_string(),
             Some(headers) => {
                 let strings: Vec<String> = headers.iter().map(|i| i.to_string()).collect();
-                strings.join("\r\n")
+                strings.join("\n")
+            }
+        }
+    }
+
+    /// The query parameters in the form they are edited, one `name=value` per line.
+    pub fn params_to_string(&self) -> String {
+        match &self.params {
+            None => "".to_string(),
+            Some(params) => {
+                let strings: Vec<String> = params
+                    .iter()
+                    .map(|param| format!("{:}={:}", param.key, param.value))
+                    .collect();
+                strings.join("\n")
             }
         }
     }
@@ -48,7 +90,17 @@ pub struct RequestBuilder {
     method: Method,
     url: Option<String>,
     body: Option<String>,
+    body_type: BodyType,
     headers: Option<String>,
+    params: Option<String>,
+    bypass_proxy: bool,
+    timeout_secs: Option<u64>,
+    proxy: Option<String>,
+    send_body: bool,
+    prompts: Vec<String>,
+    post_process: Option<String>,
+    assertions: Vec<Assertion>,
+    captures: Vec<Capture>,
 }
 
 impl RequestBuilder {
@@ -58,7 +110,17 @@ impl RequestBuilder {
             method: Method::GET,
             url: None,
             headers: None,
+            params: None,
             body: None,
+            body_type: BodyType::Raw,
+            bypass_proxy: false,
+            timeout_secs: None,
+            proxy: None,
+            send_body: false,
+            prompts: Vec::new(),
+            post_process: None,
+            assertions: Vec::new(),
+            captures: Vec::new(),
         }
     }
 
@@ -67,6 +129,11 @@ impl RequestBuilder {
         self
     }
 
+    pub fn params(&mut self, params: &str) -> &Self {
+        self.params = Some(params.to_string());
+        self
+    }
+
     pub fn url(&mut self, url: &str) -> &Self {
         self.url = Some(url.to_string());
         self
@@ -82,14 +149,60 @@ impl RequestBuilder {
         self
     }
 
+    pub fn body_type(&mut self, body_type: BodyType) -> &Self {
+        self.body_type = body_type;
+        self
+    }
+
+    pub fn bypass_proxy(&mut self, bypass_proxy: bool) -> &Self {
+        self.bypass_proxy = bypass_proxy;
+        self
+    }
+
+    pub fn timeout_secs(&mut self, timeout_secs: Option<u64>) -> &Self {
+        self.timeout_secs = timeout_secs;
+        self
+    }
+
+    pub fn proxy(&mut self, proxy: Option<String>) -> &Self {
+        self.proxy = proxy;
+        self
+    }
+
+    pub fn send_body(&mut self, send_body: bool) -> &Self {
+        self.send_body = send_body;
+        self
+    }
+
+    pub fn prompts(&mut self, prompts: &[String]) -> &Self {
+        self.prompts = prompts.to_vec();
+        self
+    }
+
+    pub fn assertions(&mut self, assertions: &[Assertion]) -> &Self {
+        self.assertions = assertions.to_vec();
+        self
+    }
+
+    pub fn captures(&mut self, captures: &[Capture]) -> &Self {
+        self.captures = captures.to_vec();
+        self
+    }
+
+    /// An empty command leaves the response as it is.
+    pub fn post_process(&mut self, command: &str) -> &Self {
+        let command = command.trim();
+        self.post_process = (!command.is_empty()).then(|| command.to_string());
+        self
+    }
+
     pub fn build(self) -> Request {
         let headers = match self.headers {
             None => None,
             Some(header_string) => {
-                let headers_strings: Vec<&str> = header_string.split("\r\n").collect();
                 let mut parsed_headers: Vec<KeyValuePair> = Vec::new();
 
-                for entry in headers_strings {
+                for entry in header_string.lines() {
                     if let Some((key, value)) = entry.split_once(":") {
                         parsed_headers.push(KeyValuePair {
                             key: key.trim().to_string(),
@@ -105,26 +218,51 @@ impl RequestBuilder {
             }
         };
 
+        let params = self
+            .params
+            .map(|params| {
+                query::parse(params.as_str())
+                    .into_iter()
+                    .map(|(key, value)| KeyValuePair { key, value })
+                    .collect::<Vec<KeyValuePair>>()
+            })
+            .filter(|params| !params.is_empty());
+
         Request {
             key: self.key,
             method: self.method,
             url: self.url.expect("Must set URL."),
             headers,
+            params,
             body: self.body,
+            body_type: self.body_type,
+            bypass_proxy: self.bypass_proxy,
+            timeout_secs: self.timeout_secs,
+            proxy: self.proxy.clone(),
+            send_body: self.send_body,
+            prompts: self.prompts,
+            post_process: self.post_process,
+            assertions: self.assertions,
+            captures: self.captures,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A request whose URL changes when the collection is retargeted.
+pub struct Retarget {
+    pub key: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct RequestCollection {
     pub requests: Vec<Request>,
 }
 
 impl RequestCollection {
     pub fn new() -> Self {
-        RequestCollection {
-            requests: Vec::new(),
-        }
+        Self::default()
     }
 
     pub fn add_request(&mut self, request: Request) {
@@ -145,36 +283,62 @@ impl RequestCollection {
         }
     }
 
-    pub fn save(&self) {
-        let serialized = serde_json::to_string_pretty(&self.requests);
-        info!("Serialized: {:?}", serialized);
-        let file = File::create("requests.json");
-        if let Ok(mut file) = file {
-            if let Err(err) = file.write_all(serialized.unwrap().as_bytes()) {
-                error!("Error writing file {:?}", err);
+    /// The requests which would change by replacing the base URL `from` with `to`, which can be a
+    /// variable like `{{base_url}}`.
+    pub fn retarget_preview(&self, from: &str, to: &str) -> Vec<Retarget> {
+        self.requests
+            .iter()
+            .filter_map(|request| {
+                Some(Retarget {
+                    key: request.key.clone(),
+                    from: request.url.clone(),
+                    to: retarget_url(request.url.as_str(), from, to)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Replace the base URL `from` with `to` in every request. Returns the number of requests
+    /// changed.
+    pub fn retarget(&mut self, from: &str, to: &str) -> usize {
+        let mut count = 0;
+        for request in self.requests.iter_mut() {
+            if let Some(url) = retarget_url(request.url.as_str(), from, to) {
+                request.url = url;
+                count += 1;
             }
         }
+        count
     }
 
-    pub fn load() -> Self {
-        if Path::new("requests.json").exists() {
-            match File::open("requests.json") {
-                Ok(file) => {
-                    let reader = BufReader::new(file);
-
-                    // Read the JSON contents of the file as an instance of `User`.
-                    match serde_json::from_reader(reader) {
-                        Ok(collection) => {
-                            return Self {
-                                requests: collection,
-                            };
-                        }
-                        _ => {}
-                    }
-                }
-                Err(_) => {}
-            }
+    pub fn save(&self) -> Result<()> {
+        let serialized = serde_json::to_string_pretty(&self.requests)?;
+        info!("Saving {:} requests", self.requests.len());
+        crypto::write(&paths::get().collection, serialized.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn load() -> Result<Self> {
+        if !paths::get().collection.exists() {
+            return Ok(Self::new());
         }
-        Self::new()
+        let contents = crypto::read(&paths::get().collection)?;
+        Ok(Self {
+            requests: serde_json::from_slice(&contents)?,
+        })
+    }
+}
+
+fn retarget_url(url: &str, from: &str, to: &str) -> Option<String> {
+    let from = from.trim_end_matches('/');
+    if from.is_empty() {
+        return None;
+    }
+    let rest = url.strip_prefix(from)?;
+    // Only match whole parts of the URL, so `https://example.com` doesn't change
+    // `https://example.company`.
+    if !(rest.is_empty() || rest.starts_with(['/', '?', '#'])) {
+        return None;
     }
+    Some(format!("{:}{:}", to.trim_end_matches('/'), rest))
 }
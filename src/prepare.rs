@@ -0,0 +1,159 @@
+//! Turning a request template into the request which is sent. Variables are substituted, the
+//! params are added to the URL, the body is prepared and encoded, auth and cookie headers are
+//! added, and the pre-send hook is run. Requests sent from the editor and by the runner are both
+//! prepared here, so they follow the same rules.
+
+use crate::annotate::Source;
+use crate::app::RequestTemplate;
+use crate::body::{self, Part};
+use crate::cookies::CookieJar;
+use crate::environment::{substitute, Environment};
+use crate::error::Result;
+use crate::guard::changed_request_reason;
+use crate::history::{now, HistoryEntry};
+use crate::hooks::{self, HookRequest};
+use crate::proxy::ProxyChoice;
+use crate::redact::redact_headers;
+use crate::settings::Settings;
+use crate::{oauth2, query, BodyType, Method};
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A request ready to be sent.
+pub struct Prepared {
+    pub method: Method,
+    pub url: String,
+    pub headers: String,
+    pub body: String,
+    pub multipart: Option<Vec<Part>>,
+    pub body_file: Option<String>,
+    pub proxy: ProxyChoice,
+    pub timeout: Option<Duration>,
+    /// Headers added to the template's own, like an OAuth2 token or a cookie, and where each
+    /// came from.
+    pub added: Vec<(String, Source)>,
+    /// The request as it is kept in the history, before its response is received.
+    pub entry: HistoryEntry,
+}
+
+/// The URL the template is sent to, with variables substituted and the params added.
+pub fn request_url(template: &RequestTemplate, environment: Option<&Environment>) -> String {
+    query::append(
+        substitute(template.url.as_str(), environment).as_str(),
+        substitute(template.params.as_str(), environment).as_str(),
+    )
+}
+
+/// The proxy the template is sent through, from its own proxy or else the settings.
+pub fn proxy_choice(
+    template: &RequestTemplate,
+    environment: Option<&Environment>,
+    settings: &Settings,
+) -> ProxyChoice {
+    let configured = match &template.proxy {
+        Some(proxy) => Some(substitute(proxy.as_str(), environment)),
+        None => settings.proxy.clone(),
+    };
+    ProxyChoice::new(template.bypass_proxy, configured)
+}
+
+/// Prepare the template to be sent with the environment's variables, adding the cookies from
+/// the jar when one is given. A request the pre-send hook changes into one which would need
+/// confirmation is refused.
+pub async fn prepare_request(
+    template: &RequestTemplate,
+    environment: Option<&Environment>,
+    settings: &Settings,
+    cookies: Option<&Mutex<CookieJar>>,
+) -> Result<Prepared> {
+    let url = request_url(template, environment);
+    let headers = substitute(template.headers.as_str(), environment);
+    let body = body::prepare_body(
+        substitute(template.body.as_str(), environment).as_str(),
+        settings,
+    );
+    let (headers, body) = match template.body_type {
+        BodyType::Form => body::encode_form(headers, body.as_str()),
+        BodyType::Raw | BodyType::Multipart => (headers, body),
+    };
+
+    let mut added = Vec::new();
+    let headers = match oauth2::authorization(headers.as_str(), environment) {
+        Some(authorization) => {
+            added.push((authorization.clone(), Source::Auth));
+            if headers.trim().is_empty() {
+                authorization
+            } else {
+                format!("{:}\n{:}", headers.trim_end(), authorization)
+            }
+        }
+        None => headers,
+    };
+    let headers = match cookies {
+        Some(cookies) => {
+            let with_cookie = cookies
+                .lock()
+                .unwrap()
+                .add_to(headers.clone(), url.as_str());
+            if with_cookie != headers {
+                if let Some(cookie) = with_cookie.lines().last() {
+                    added.push((cookie.to_string(), Source::Cookie));
+                }
+            }
+            with_cookie
+        }
+        None => headers,
+    };
+
+    let request = HookRequest {
+        method: template.method.clone(),
+        url: url.clone(),
+        headers,
+        body,
+    };
+    let request = match &settings.pre_send_hook {
+        Some(hook) => hooks::pre_send(hook, request).await?,
+        None => request,
+    };
+    if let Some(reason) = changed_request_reason(
+        (&template.method, url.as_str()),
+        (&request.method, request.url.as_str()),
+        settings,
+        environment,
+    ) {
+        return Err(hooks::refusal(reason));
+    }
+
+    let multipart = (template.body_type == BodyType::Multipart)
+        .then(|| body::multipart_parts(request.body.as_str()));
+    let body_file = match template.body_type {
+        BodyType::Raw => body::file_reference(request.body.as_str()).map(str::to_string),
+        BodyType::Form | BodyType::Multipart => None,
+    };
+    let entry = HistoryEntry {
+        timestamp: now(),
+        method: request.method.clone(),
+        url: request.url.clone(),
+        headers: redact_headers(request.headers.as_str(), settings),
+        body: request.body.clone(),
+        status: 0,
+        response_headers: "".to_string(),
+        response_body: None,
+        proxy: None,
+        wait_ms: None,
+        elapsed_ms: None,
+    };
+    Ok(Prepared {
+        method: request.method,
+        url: request.url,
+        headers: request.headers,
+        body: request.body,
+        multipart,
+        body_file,
+        proxy: proxy_choice(template, environment, settings),
+        timeout: template.timeout(settings),
+        added,
+        entry,
+    })
+}
@@ -0,0 +1,263 @@
+//! Readable previews of response bodies, shown next to the raw body.
+
+use crate::content_type::split_csv_line;
+use crate::metrics::{is_metrics_content_type, metrics_view};
+use serde_json::Value;
+
+/// Render a preview of a response body based on its content type. Metrics are filtered to those
+/// whose name contains `metrics_filter`.
+pub fn preview(body: &str, content_type: &str, metrics_filter: &str) -> String {
+    if content_type.starts_with("image/") {
+        return format!(
+            "Image ({:}), images can't be previewed in the terminal.",
+            content_type
+        );
+    }
+    if content_type.contains("html") {
+        return html_to_text(body);
+    }
+    if content_type.contains("csv") {
+        return csv_table(body);
+    }
+    if is_metrics_content_type(content_type) {
+        return metrics_view(body, metrics_filter);
+    }
+    match serde_json::from_str::<Value>(body) {
+        Ok(value) => {
+            let mut res = String::new();
+            json_outline(&value, "$", 0, &mut res);
+            res
+        }
+        Err(_) => body.to_string(),
+    }
+}
+
+fn summary(value: &Value) -> String {
+    match value {
+        Value::Object(map) => format!("{{}} {:} keys", map.len()),
+        Value::Array(items) => format!("[] {:} items", items.len()),
+        value => value.to_string(),
+    }
+}
+
+/// Outline of a JSON document with one line per value, indented by depth.
+pub fn json_outline(value: &Value, label: &str, depth: usize, res: &mut String) {
+    res.push_str("  ".repeat(depth).as_str());
+    res.push_str(label);
+    res.push_str(": ");
+    res.push_str(summary(value).as_str());
+    res.push('\n');
+
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                json_outline(value, key, depth + 1, res);
+            }
+        }
+        Value::Array(items) => {
+            for (index, value) in items.iter().enumerate() {
+                json_outline(value, format!("[{:}]", index).as_str(), depth + 1, res);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Align the columns of a CSV document.
+pub fn csv_table(body: &str) -> String {
+    let rows: Vec<Vec<String>> = body.lines().map(split_csv_line).collect();
+    let mut widths: Vec<usize> = Vec::new();
+    for row in rows.iter() {
+        for (index, field) in row.iter().enumerate() {
+            let width = field.chars().count();
+            match widths.get_mut(index) {
+                Some(max) => *max = (*max).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+
+    let mut res = String::new();
+    for row in rows {
+        let fields: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(index, field)| format!("{:width$}", field, width = widths[index]))
+            .collect();
+        res.push_str(fields.join(" | ").trim_end());
+        res.push('\n');
+    }
+    res
+}
+
+/// Render an HTML document as readable text. Block level elements start new lines, headings are
+/// marked with `#` by level, list items with `-` or their number, and links are followed by their
+/// target.
+pub fn html_to_text(html: &str) -> String {
+    const BLOCK_TAGS: [&str; 20] = [
+        "p",
+        "div",
+        "br",
+        "tr",
+        "ul",
+        "ol",
+        "table",
+        "section",
+        "article",
+        "header",
+        "footer",
+        "nav",
+        "main",
+        "pre",
+        "blockquote",
+        "dl",
+        "dt",
+        "dd",
+        "hr",
+        "title",
+    ];
+
+    let mut res = String::new();
+    let mut rest = html;
+    let mut skip_until: Option<&str> = None;
+    // The open lists, with the next number of ordered lists.
+    let mut lists: Vec<Option<usize>> = Vec::new();
+    let mut link: Option<String> = None;
+
+    while let Some(start) = rest.find('<') {
+        if skip_until.is_none() {
+            push_text(&mut res, &rest[..start]);
+        }
+        let after = &rest[start + 1..];
+        let end = match after.find('>') {
+            Some(end) => end,
+            None => break,
+        };
+        let tag = after[..end].trim();
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        rest = &after[end + 1..];
+
+        if let Some(until) = skip_until {
+            if closing && name == until {
+                skip_until = None;
+            }
+            continue;
+        }
+        match name.as_str() {
+            "script" | "style" if !closing => {
+                skip_until = Some(if name == "script" { "script" } else { "style" })
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                res.push_str("\n\n");
+                if !closing {
+                    let level = name[1..].parse().unwrap_or(1);
+                    res.push_str(format!("{:} ", "#".repeat(level)).as_str());
+                }
+            }
+            "ul" | "ol" => {
+                res.push('\n');
+                if closing {
+                    lists.pop();
+                } else {
+                    lists.push((name == "ol").then_some(1));
+                }
+            }
+            "li" if !closing => {
+                res.push('\n');
+                res.push_str("  ".repeat(lists.len().saturating_sub(1)).as_str());
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        res.push_str(format!("{:}. ", number).as_str());
+                        *number += 1;
+                    }
+                    _ => res.push_str("- "),
+                }
+            }
+            "a" if closing => {
+                // Links within the page aren't useful in a text rendering.
+                if let Some(href) = link.take().filter(|href| !href.starts_with('#')) {
+                    res.push_str(format!(" ({:})", href).as_str());
+                }
+            }
+            "a" => link = attribute(tag, "href"),
+            name if BLOCK_TAGS.contains(&name) => res.push('\n'),
+            _ => {}
+        }
+    }
+    if skip_until.is_none() {
+        push_text(&mut res, rest);
+    }
+
+    // Headings are separated from the text around them by a blank line, other blank lines are
+    // dropped.
+    let text = decode_entities(res.as_str());
+    let mut lines: Vec<&str> = Vec::new();
+    let mut blank = false;
+    for line in text.lines().map(|line| line.trim_end()) {
+        if line.trim().is_empty() {
+            blank = lines.last().is_some_and(|last| last.starts_with('#'));
+            continue;
+        }
+        if (blank || line.starts_with('#')) && !lines.is_empty() {
+            lines.push("");
+        }
+        blank = false;
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+/// Add text from an HTML document with its runs of whitespace collapsed, as a browser would.
+fn push_text(res: &mut String, text: &str) {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut space = res.is_empty() || res.ends_with(char::is_whitespace);
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !space {
+                collapsed.push(' ');
+            }
+            space = true;
+        } else {
+            collapsed.push(c);
+            space = false;
+        }
+    }
+    res.push_str(collapsed.as_str());
+}
+
+/// The value of an attribute in the text of a tag, like `a href="/page"`.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut search = 0;
+    while let Some(found) = lower[search..].find(name) {
+        let start = search + found;
+        search = start + name.len();
+        let preceded = lower[..start].ends_with(char::is_whitespace);
+        let value = tag[search..].trim_start();
+        let value = match value.strip_prefix('=') {
+            Some(value) if preceded => value.trim_start(),
+            _ => continue,
+        };
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or(""),
+            _ => value.split(char::is_whitespace).next().unwrap_or(""),
+        };
+        return Some(value.to_string());
+    }
+    None
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
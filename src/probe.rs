@@ -0,0 +1,326 @@
+//! Quick requests for exploring an unfamiliar API, whose responses are summarized instead of
+//! shown as they are.
+
+use crate::audit::{self, AuditRecord};
+use crate::environment::Environment;
+use crate::error::{Error, Result};
+use crate::guard::confirmation_reason;
+use crate::history::now;
+use crate::proxy::ProxyChoice;
+use crate::redirect::RedirectPolicy;
+use crate::settings::Settings;
+use crate::web_request_handler::{next_request_id, queue};
+use crate::{Method, Request, Response, WebRequest};
+use reqwest::header::HeaderMap;
+use reqwest::{StatusCode, Url};
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Holds probes to the same rules as the requests sent from the editor: nothing is sent while
+/// offline or without the confirmation a safety rule asks for, and each request sent is audited.
+pub struct Gate {
+    settings: Settings,
+    environment: Option<Environment>,
+}
+
+impl Gate {
+    pub fn new(settings: &Settings, environment: Option<&Environment>) -> Self {
+        Gate {
+            settings: settings.clone(),
+            environment: environment.cloned(),
+        }
+    }
+
+    fn check(&self, method: &Method, url: &str) -> Result<()> {
+        if self.settings.offline {
+            return Err(Error::Refused(
+                "Requests aren't sent while offline".to_string(),
+            ));
+        }
+        let environment = self.environment.as_ref();
+        match confirmation_reason(method, url, &self.settings, environment) {
+            Some(reason) => Err(Error::Refused(format!(
+                "Not sent without confirmation: {:}",
+                reason
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn audit(&self, method: Method, url: String, status: u16) {
+        if self.settings.audit_log {
+            audit::append(
+                AuditRecord {
+                    timestamp: now(),
+                    user: audit::current_user(),
+                    method,
+                    url,
+                    environment: self
+                        .environment
+                        .as_ref()
+                        .map(|environment| environment.name.clone()),
+                    status,
+                },
+                &self.settings,
+            );
+        }
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.settings.request_timeout()
+    }
+}
+
+/// Send a request through the request handler, returning its id and the receiver for its
+/// response.
+async fn send(
+    gate: &Gate,
+    sender: &mpsc::Sender<WebRequest>,
+    busy: &AtomicBool,
+    method: Method,
+    url: String,
+    headers: String,
+    proxy: ProxyChoice,
+) -> Result<(u64, mpsc::Receiver<Response>)> {
+    let timeout = gate.timeout();
+    let (tx, rx) = mpsc::channel(10);
+    let id = next_request_id();
+    let request = WebRequest::Request(Box::new(Request {
+        id,
+        method,
+        url,
+        headers,
+        body: "".to_string(),
+        multipart: None,
+        body_file: None,
+        download: None,
+        proxy,
+        timeout,
+        redirects: RedirectPolicy::default(),
+        resp: tx,
+    }));
+    queue(sender, request, busy).await?;
+    Ok((id, rx))
+}
+
+/// Send a request and wait for the status and headers of its response. The body isn't read.
+pub async fn fetch_headers(
+    gate: &Gate,
+    sender: &mpsc::Sender<WebRequest>,
+    busy: &AtomicBool,
+    method: Method,
+    url: String,
+    headers: String,
+    proxy: ProxyChoice,
+) -> Result<(StatusCode, HeaderMap)> {
+    gate.check(&method, url.as_str())?;
+    let res = receive_headers(
+        gate,
+        sender,
+        busy,
+        method.clone(),
+        url.clone(),
+        headers,
+        proxy,
+    )
+    .await;
+    let status = res.as_ref().map_or(0, |(status, _)| status.as_u16());
+    gate.audit(method, url, status);
+    res
+}
+
+async fn receive_headers(
+    gate: &Gate,
+    sender: &mpsc::Sender<WebRequest>,
+    busy: &AtomicBool,
+    method: Method,
+    url: String,
+    headers: String,
+    proxy: ProxyChoice,
+) -> Result<(StatusCode, HeaderMap)> {
+    let (id, mut rx) = send(gate, sender, busy, method, url, headers, proxy).await?;
+    let mut status = None;
+    while let Some(res) = rx.recv().await {
+        match res {
+            Response::Status(res) => status = Some(res),
+            Response::Headers(headers) => {
+                if let Some(status) = status {
+                    // The body isn't needed, so it isn't streamed.
+                    queue(sender, WebRequest::Cancel(id), busy).await?;
+                    return Ok((status, headers));
+                }
+            }
+            Response::Failure(err) => return Err(err),
+            _ => {}
+        }
+    }
+    Err(Error::Handler("The request ended without a response"))
+}
+
+/// GET a URL and wait for the whole body of the response.
+pub async fn fetch(
+    gate: &Gate,
+    sender: &mpsc::Sender<WebRequest>,
+    busy: &AtomicBool,
+    url: String,
+    proxy: ProxyChoice,
+) -> Result<(StatusCode, Vec<u8>)> {
+    gate.check(&Method::GET, url.as_str())?;
+    let res = receive_body(gate, sender, busy, url.clone(), proxy).await;
+    let status = res.as_ref().map_or(0, |(status, _)| status.as_u16());
+    gate.audit(Method::GET, url, status);
+    res
+}
+
+async fn receive_body(
+    gate: &Gate,
+    sender: &mpsc::Sender<WebRequest>,
+    busy: &AtomicBool,
+    url: String,
+    proxy: ProxyChoice,
+) -> Result<(StatusCode, Vec<u8>)> {
+    let headers = "".to_string();
+    let (_, mut rx) = send(gate, sender, busy, Method::GET, url, headers, proxy).await?;
+    let mut status = None;
+    let mut body = Vec::new();
+    while let Some(res) = rx.recv().await {
+        match res {
+            Response::Status(res) => status = Some(res),
+            Response::Body(bytes) => body.extend_from_slice(&bytes),
+            Response::Failure(err) => return Err(err),
+            _ => {}
+        }
+    }
+    match status {
+        Some(status) => Ok((status, body)),
+        None => Err(Error::Handler("The request ended without a response")),
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// Summarize the `Allow` and CORS headers of a response to an OPTIONS request, starting with a
+/// line like `Supports GET, POST; CORS allows origin https://example.com`.
+pub fn capability_report(
+    url: &str,
+    status: StatusCode,
+    headers: &HeaderMap,
+    has_origin: bool,
+) -> String {
+    const CORS_HEADERS: [(&str, &str); 5] = [
+        ("access-control-allow-methods", "Methods"),
+        ("access-control-allow-headers", "Headers"),
+        ("access-control-allow-credentials", "Credentials"),
+        ("access-control-expose-headers", "Exposed headers"),
+        ("access-control-max-age", "Max age (seconds)"),
+    ];
+
+    let allow = header(headers, "allow");
+    let origin = header(headers, "access-control-allow-origin");
+
+    let mut summary = Vec::new();
+    match allow {
+        Some(allow) => summary.push(format!("Supports {:}", allow)),
+        None => summary.push("Methods not listed".to_string()),
+    }
+    match origin {
+        Some(origin) => summary.push(format!("CORS allows origin {:}", origin)),
+        None => summary.push("no CORS headers".to_string()),
+    }
+
+    let mut res = format!(
+        "{:}\n\nOPTIONS {:} returned {:}\n",
+        summary.join("; "),
+        url,
+        status
+    );
+    if allow.is_none() {
+        res.push_str("There is no Allow header listing the supported methods.\n");
+    }
+    if let Some(origin) = origin {
+        res.push_str("\nCORS\n");
+        res.push_str(format!("  Origin: {:}\n", origin).as_str());
+        for (name, label) in CORS_HEADERS {
+            if let Some(value) = header(headers, name) {
+                res.push_str(format!("  {:}: {:}\n", label, value).as_str());
+            }
+        }
+    } else if !has_origin {
+        res.push_str(
+            "Servers often only answer with CORS headers for a preflight, add an Origin header \
+             to the request to check one.\n",
+        );
+    }
+    res
+}
+
+/// Standard paths which describe a service, checked on the host of the current URL.
+pub const WELL_KNOWN_PATHS: [&str; 8] = [
+    "/robots.txt",
+    "/.well-known/openid-configuration",
+    "/.well-known/security.txt",
+    "/healthz",
+    "/health",
+    "/openapi.json",
+    "/swagger.json",
+    "/v3/api-docs",
+];
+
+/// The scheme, host and port of a URL, like `https://api.example.com:8443`.
+pub fn origin(url: &str) -> Result<String> {
+    let url =
+        Url::parse(url).map_err(|err| Error::Parse(format!("Invalid URL {:}: {:}", url, err)))?;
+    Ok(url.origin().ascii_serialization())
+}
+
+/// One line per path, like `200  /healthz  application/json`, with the ones which responded
+/// successfully first.
+pub fn well_known_report(
+    origin: &str,
+    results: &[(&str, Result<(StatusCode, HeaderMap)>)],
+) -> String {
+    let width = results
+        .iter()
+        .map(|(path, _)| path.len())
+        .max()
+        .unwrap_or(0);
+    let found = results
+        .iter()
+        .filter(|(_, res)| matches!(res, Ok((status, _)) if status.is_success()))
+        .count();
+    let mut res = format!(
+        "{:} of {:} well known paths respond on {:}\n\n",
+        found,
+        results.len(),
+        origin
+    );
+    let mut lines: Vec<(bool, String)> = results
+        .iter()
+        .map(|(path, result)| match result {
+            Ok((status, headers)) => (
+                status.is_success(),
+                format!(
+                    "{:}  {:width$}  {:}",
+                    status.as_u16(),
+                    path,
+                    header(headers, "content-type").unwrap_or(""),
+                    width = width
+                ),
+            ),
+            Err(err) => (
+                false,
+                format!("---  {:width$}  {:}", path, err, width = width),
+            ),
+        })
+        .collect();
+    // Sorting is stable, so the paths keep their order within each group.
+    lines.sort_by_key(|(success, _)| !success);
+    for (_, line) in lines {
+        res.push_str(line.trim_end());
+        res.push('\n');
+    }
+    res
+}
@@ -0,0 +1,109 @@
+//! Query parameters edited apart from the URL, one `name=value` per line, which are encoded and
+//! appended to the URL when the request is sent.
+
+/// The name and value on each line which isn't blank. A line without `=` is a name with an empty
+/// value.
+pub fn parse(params: &str) -> Vec<(String, String)> {
+    params
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| match line.split_once('=') {
+            Some((name, value)) => (name.trim().to_string(), value.trim().to_string()),
+            None => (line.trim().to_string(), "".to_string()),
+        })
+        .collect()
+}
+
+/// Percent encode everything but the unreserved characters.
+pub fn encode(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                res.push(byte as char)
+            }
+            _ => res.push_str(format!("%{:02X}", byte).as_str()),
+        }
+    }
+    res
+}
+
+/// Encode the `name=value` lines as a query string, which is also the form of an
+/// `application/x-www-form-urlencoded` body.
+pub fn encode_params(params: &str) -> String {
+    let params: Vec<String> = parse(params)
+        .iter()
+        .map(|(name, value)| {
+            if value.is_empty() {
+                encode(name)
+            } else {
+                format!("{:}={:}", encode(name), encode(value))
+            }
+        })
+        .collect();
+    params.join("&")
+}
+
+/// Append the encoded parameters to the query of a URL, keeping any fragment at the end.
+pub fn append(url: &str, params: &str) -> String {
+    let query = encode_params(params);
+    if query.is_empty() {
+        return url.to_string();
+    }
+    let (base, fragment) = match url.find('#') {
+        Some(index) => url.split_at(index),
+        None => (url, ""),
+    };
+    let separator = if !base.contains('?') {
+        "?"
+    } else if base.ends_with('?') || base.ends_with('&') {
+        ""
+    } else {
+        "&"
+    };
+    format!("{:}{:}{:}{:}", base, separator, query, fragment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_lines() {
+        assert_eq!(
+            parse("a = 1\n\n  flag\nb=x=y\n"),
+            vec![
+                ("a".to_string(), "1".to_string()),
+                ("flag".to_string(), "".to_string()),
+                ("b".to_string(), "x=y".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn encodes_params() {
+        assert_eq!(encode("a b/é~"), "a%20b%2F%C3%A9~");
+        assert_eq!(encode_params("q=a&b\nflag\nx="), "q=a%26b&flag&x");
+    }
+
+    #[test]
+    fn appends_to_the_url() {
+        assert_eq!(append("https://example.com", ""), "https://example.com");
+        assert_eq!(
+            append("https://example.com/a", "q=1"),
+            "https://example.com/a?q=1"
+        );
+        assert_eq!(
+            append("https://example.com/a?x=1", "q=1"),
+            "https://example.com/a?x=1&q=1"
+        );
+        assert_eq!(
+            append("https://example.com/a?", "q=1"),
+            "https://example.com/a?q=1"
+        );
+        assert_eq!(
+            append("https://example.com/a#top", "q=1"),
+            "https://example.com/a?q=1#top"
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! Which redirects are followed, and the record of those that were, so each hop of a flow like
+//! an OAuth login can be inspected.
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// How redirect responses are handled.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum RedirectPolicy {
+    /// Follow up to 10 redirects.
+    #[default]
+    Follow,
+    /// Show the redirect response itself.
+    DontFollow,
+    /// Follow up to this many redirects.
+    Max(usize),
+}
+
+/// Redirects beyond this are an error when following them.
+const DEFAULT_MAX: usize = 10;
+
+/// A redirect which was followed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Redirect {
+    pub status: u16,
+    pub from: String,
+    pub to: String,
+}
+
+impl RedirectPolicy {
+    fn max(&self) -> usize {
+        match self {
+            RedirectPolicy::Follow => DEFAULT_MAX,
+            RedirectPolicy::DontFollow => 0,
+            RedirectPolicy::Max(max) => *max,
+        }
+    }
+
+    /// The policy for a client, recording the redirects it follows in `chain`.
+    pub fn policy(&self, chain: Arc<Mutex<Vec<Redirect>>>) -> reqwest::redirect::Policy {
+        let max = self.max();
+        reqwest::redirect::Policy::custom(move |attempt| {
+            // The previous URLs include the original one, so this is the number of redirects
+            // followed before this one.
+            let followed = attempt.previous().len().saturating_sub(1);
+            if max == 0 {
+                return attempt.stop();
+            }
+            if followed >= max {
+                return attempt.error(format!("Stopped after {:} redirects", max));
+            }
+            if let Some(from) = attempt.previous().last() {
+                chain.lock().unwrap().push(Redirect {
+                    status: attempt.status().as_u16(),
+                    from: from.to_string(),
+                    to: attempt.url().to_string(),
+                });
+            }
+            attempt.follow()
+        })
+    }
+}
+
+/// The redirects as lines to show above the response headers, or nothing if there were none.
+pub fn describe(chain: &[Redirect]) -> String {
+    if chain.is_empty() {
+        return "".to_string();
+    }
+    let mut description = "Redirects:\n".to_string();
+    for redirect in chain {
+        description.push_str(
+            format!(
+                "{:} {:} -> {:}\n",
+                redirect.status, redirect.from, redirect.to
+            )
+            .as_str(),
+        );
+    }
+    description.push('\n');
+    description
+}
@@ -0,0 +1,151 @@
+//! Sending a saved request without the terminal UI, like `rester run "List items" --env staging`,
+//! so the collection can be used from shell scripts and CI. The response body is written to
+//! stdout, and the exit code tells how the request went.
+
+use crate::assertion;
+use crate::crypto;
+use crate::environment::{Environment, Environments};
+use crate::error::{Error, Result};
+use crate::persistence::RequestCollection;
+use crate::runner;
+use crate::settings::Settings;
+use crate::web_request_handler::{queue, web_request_handler};
+use crate::WebRequest;
+use std::io::Write;
+use std::sync::atomic::AtomicBool;
+use tokio::sync::mpsc;
+
+/// The exit code for a response with a status below 400.
+pub const EXIT_SUCCESS: i32 = 0;
+/// The exit code when the request couldn't be sent, or its response failed.
+pub const EXIT_FAILURE: i32 = 1;
+/// The exit code when an assertion of the request failed.
+pub const EXIT_ASSERTION_FAILED: i32 = 2;
+/// The exit code for a 4xx response.
+pub const EXIT_CLIENT_ERROR: i32 = 4;
+/// The exit code for a 5xx response.
+pub const EXIT_SERVER_ERROR: i32 = 5;
+/// The exit code for an invalid command line, as in the BSD `sysexits.h`.
+pub const EXIT_USAGE: i32 = 64;
+
+/// What to run, from the command line.
+#[derive(Debug, Default)]
+pub struct Run {
+    /// Key of the saved request.
+    pub request: String,
+    /// Environment to use instead of the active one.
+    pub environment: Option<String>,
+    /// Variables set for this run, overriding those of the environment.
+    pub variables: Vec<(String, String)>,
+    /// Send the request even when it matches a safety rule, which would otherwise need to be
+    /// confirmed in the UI.
+    pub allow_unsafe: bool,
+}
+
+/// Send the request, writing its response body to stdout. Returns the exit code for its
+/// assertions if it has any, and otherwise for the status of the response. Failed assertions are
+/// written to stderr.
+pub async fn run(run: &Run) -> Result<i32> {
+    let settings = Settings::load()?;
+    let collection = RequestCollection::load()?;
+    let request = find(&collection, run.request.as_str())?;
+    let environment = environment(run)?;
+
+    let (sender, receiver) = mpsc::channel(10);
+    let handler = web_request_handler(receiver);
+    let mut stdout = std::io::stdout();
+    let res = runner::send(
+        &sender,
+        request,
+        &environment,
+        &settings,
+        run.allow_unsafe,
+        &mut |bytes| Ok(stdout.write_all(bytes)?),
+    )
+    .await;
+    std::io::stdout().flush()?;
+    let _ = queue(&sender, WebRequest::Shutdown, &AtomicBool::new(false)).await;
+    let _ = handler.await;
+
+    let exchange = res?;
+    if !request.assertions.is_empty() {
+        let outcomes = assertion::evaluate(
+            &request.assertions,
+            exchange.status,
+            &exchange.headers,
+            &exchange.body,
+        );
+        let failed: Vec<_> = outcomes.iter().filter(|outcome| !outcome.passed).collect();
+        for outcome in &failed {
+            eprintln!("{:}", assertion::describe_outcome(outcome));
+        }
+        return Ok(if failed.is_empty() {
+            EXIT_SUCCESS
+        } else {
+            EXIT_ASSERTION_FAILED
+        });
+    }
+    Ok(match exchange.status {
+        500..=599 => EXIT_SERVER_ERROR,
+        400..=499 => EXIT_CLIENT_ERROR,
+        _ => EXIT_SUCCESS,
+    })
+}
+
+/// The request with the key, or the only one whose key matches ignoring case.
+fn find<'a>(
+    collection: &'a RequestCollection,
+    key: &str,
+) -> Result<&'a crate::persistence::Request> {
+    if let Some(request) = collection
+        .requests
+        .iter()
+        .find(|request| request.key == key)
+    {
+        return Ok(request);
+    }
+    let matches: Vec<_> = collection
+        .requests
+        .iter()
+        .filter(|request| request.key.eq_ignore_ascii_case(key))
+        .collect();
+    match matches.as_slice() {
+        [request] => Ok(request),
+        [] => Err(Error::NotFound(format!(
+            "There is no saved request named {:}",
+            key
+        ))),
+        _ => Err(Error::NotFound(format!(
+            "More than one saved request is named {:}",
+            key
+        ))),
+    }
+}
+
+/// The environment named on the command line, or the active one, with the variables given on
+/// the command line. The passphrase is asked for when it has encrypted variables.
+fn environment(run: &Run) -> Result<Environment> {
+    let mut environments = Environments::load()?;
+    if selected(&environments, run)?.is_some_and(Environment::needs_passphrase) {
+        let passphrase = rpassword::prompt_password("Passphrase for encrypted variables: ")?;
+        crypto::unlock_values(passphrase.as_str(), environments.encrypted_sample())?;
+        environments.decrypt_variables()?;
+    }
+    let mut environment = selected(&environments, run)?.cloned().unwrap_or_default();
+    for (name, value) in &run.variables {
+        environment.set(name.as_str(), value.as_str());
+    }
+    Ok(environment)
+}
+
+fn selected<'a>(environments: &'a Environments, run: &Run) -> Result<Option<&'a Environment>> {
+    match &run.environment {
+        Some(name) => environments
+            .environments
+            .iter()
+            .find(|environment| &environment.name == name)
+            .map(Some)
+            .ok_or_else(|| Error::NotFound(format!("There is no environment named {:}", name))),
+        None => Ok(environments.active()),
+    }
+}
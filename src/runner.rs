@@ -0,0 +1,303 @@
+//! Sending saved requests outside of the editor, one after another, for the collection runner and
+//! the `run` command. These use the variables of an environment, without prompting, cookies or
+//! token refreshes. Variables captured during a run are kept for the rest of the run only.
+//!
+//! The same safety rules apply as when sending from the editor. Requests needing confirmation
+//! aren't sent unless it was given up front, nothing is sent while offline, and every request sent
+//! is written to the audit log.
+
+use crate::app::RequestTemplate;
+use crate::assertion;
+use crate::audit;
+use crate::capture;
+use crate::environment::Environment;
+use crate::error::{Error, Result};
+use crate::guard::confirmation_reason;
+use crate::history::HistoryEntry;
+use crate::persistence;
+use crate::prepare::{prepare_request, request_url};
+use crate::redact::redact_headers;
+use crate::settings::Settings;
+use crate::web_request_handler::{next_request_id, queue};
+use crate::{Request, Response, WebRequest};
+use reqwest::header::HeaderMap;
+use std::sync::atomic::AtomicBool;
+use std::time::Instant;
+use tokio::sync::mpsc::{self, Sender};
+
+/// A request which was sent, and its response.
+pub struct Exchange {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    /// The exchange as it is kept in the history.
+    pub entry: HistoryEntry,
+}
+
+/// Send a saved request through the request handler, passing each chunk of the response body to
+/// `on_body` as it arrives. A request which matches a safety rule is only sent when `confirmed`.
+pub async fn send(
+    sender: &Sender<WebRequest>,
+    request: &persistence::Request,
+    environment: &Environment,
+    settings: &Settings,
+    confirmed: bool,
+    on_body: &mut (dyn FnMut(&[u8]) -> Result<()> + Send),
+) -> Result<Exchange> {
+    if settings.offline {
+        return Err(Error::Refused(
+            "Requests aren't sent while offline".to_string(),
+        ));
+    }
+    if environment.needs_passphrase() {
+        return Err(Error::Refused(format!(
+            "The environment {:} has encrypted variables, which need the passphrase",
+            environment.name
+        )));
+    }
+    if let Some(prompt) = request
+        .prompts
+        .iter()
+        .find(|prompt| environment.get(prompt.as_str()).is_none())
+    {
+        return Err(Error::NotFound(format!(
+            "The request prompts for {:}, which isn't set",
+            prompt
+        )));
+    }
+
+    let template = RequestTemplate::from(request);
+    let environment = Some(environment);
+    if let (Some(reason), false) = (
+        confirmation_reason(
+            &template.method,
+            request_url(&template, environment).as_str(),
+            settings,
+            environment,
+        ),
+        confirmed,
+    ) {
+        return Err(Error::Refused(format!(
+            "Not sent without confirmation: {:}",
+            reason
+        )));
+    }
+    let prepared = prepare_request(&template, environment, settings, None).await?;
+    let mut entry = prepared.entry;
+    let (tx, mut rx) = mpsc::channel(10);
+    let request = WebRequest::Request(Box::new(Request {
+        id: next_request_id(),
+        method: prepared.method,
+        url: prepared.url,
+        headers: prepared.headers,
+        body: prepared.body,
+        multipart: prepared.multipart,
+        body_file: prepared.body_file,
+        download: None,
+        proxy: prepared.proxy,
+        timeout: prepared.timeout,
+        redirects: settings.redirects,
+        resp: tx,
+    }));
+    let start = Instant::now();
+    queue(sender, request, &AtomicBool::new(false)).await?;
+
+    let mut headers = HeaderMap::new();
+    let mut received = Vec::new();
+    let mut failure = None;
+    while let Some(response) = rx.recv().await {
+        match response {
+            Response::Status(status) => {
+                entry.status = status.as_u16();
+                entry.wait_ms = Some(start.elapsed().as_millis() as u64);
+            }
+            Response::Proxy(proxy) => entry.proxy = proxy,
+            Response::Headers(map) => {
+                if let Ok(formatted) = jsonxf::pretty_print(format!("{:?}", map).as_str()) {
+                    entry.response_headers = redact_headers(formatted.as_str(), settings);
+                }
+                headers = map;
+            }
+            Response::Body(bytes) => {
+                if let Err(err) = on_body(&bytes) {
+                    failure = Some(err);
+                    break;
+                }
+                received.extend_from_slice(&bytes);
+            }
+            Response::Failure(err) => {
+                failure = Some(err);
+                break;
+            }
+            Response::Cancelled => {
+                failure = Some(Error::Handler("The request was cancelled"));
+                break;
+            }
+            Response::Redirects(_) | Response::Progress(_) => {}
+        }
+    }
+    entry.elapsed_ms = Some(start.elapsed().as_millis() as u64);
+    let environment_name = environment
+        .map(|environment| environment.name.clone())
+        .filter(|name| !name.is_empty());
+    audit::record_sent(&entry, environment_name, settings);
+    if let Some(err) = failure {
+        return Err(err);
+    }
+    if entry.status == 0 {
+        return Err(Error::Handler("The request was cancelled"));
+    }
+    entry.response_body = Some(String::from_utf8_lossy(&received).to_string());
+    Ok(Exchange {
+        status: entry.status,
+        headers,
+        body: received,
+        entry,
+    })
+}
+
+/// The result of one request of a collection run.
+pub struct RunResult {
+    pub key: String,
+    pub status: u16,
+    pub duration_ms: u64,
+    /// Why the request failed, like an error sending it or an error status.
+    pub failures: Vec<String>,
+    /// The exchange, unless the request couldn't be sent.
+    pub entry: Option<HistoryEntry>,
+}
+
+impl RunResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// A row of the results table.
+    pub fn row(&self) -> String {
+        let status = match self.status {
+            0 => "---".to_string(),
+            status => status.to_string(),
+        };
+        let mut row = format!(
+            "{:} {:} {:>7}ms  {:}",
+            if self.passed() { "PASS" } else { "FAIL" },
+            status,
+            self.duration_ms,
+            self.key
+        );
+        if let Some(failure) = self.failures.first() {
+            row.push_str(format!("  ({:})", failure).as_str());
+        }
+        row
+    }
+}
+
+/// The saved requests whose keys start with `prefix`, which is like a folder for requests named
+/// like `API: Orders / Create`. Every request is run for an empty prefix.
+pub fn select<'a>(
+    requests: &'a [persistence::Request],
+    prefix: &str,
+) -> Vec<&'a persistence::Request> {
+    requests
+        .iter()
+        .filter(|request| {
+            request
+                .key
+                .to_lowercase()
+                .starts_with(prefix.to_lowercase().as_str())
+        })
+        .collect()
+}
+
+/// Send a request of a run. It passes if its assertions do, or without assertions unless it fails
+/// to send or gets an error status. Its captures are set in the environment of the run, for the
+/// requests after it, and fail it when they aren't found.
+pub async fn run_one(
+    sender: &Sender<WebRequest>,
+    request: &persistence::Request,
+    environment: &mut Environment,
+    settings: &Settings,
+    confirmed: bool,
+) -> RunResult {
+    let start = Instant::now();
+    let res = send(
+        sender,
+        request,
+        environment,
+        settings,
+        confirmed,
+        &mut |_| Ok(()),
+    )
+    .await;
+    let duration_ms = start.elapsed().as_millis() as u64;
+    match res {
+        Ok(exchange) => {
+            let mut failures: Vec<String> = if request.assertions.is_empty() {
+                (exchange.status >= 400)
+                    .then(|| format!("status {:}", exchange.status))
+                    .into_iter()
+                    .collect()
+            } else {
+                assertion::evaluate(
+                    &request.assertions,
+                    exchange.status,
+                    &exchange.headers,
+                    &exchange.body,
+                )
+                .iter()
+                .filter(|outcome| !outcome.passed)
+                .map(assertion::describe_outcome)
+                .collect()
+            };
+            for missing in capture::apply(
+                &request.captures,
+                exchange.status,
+                &exchange.body,
+                environment,
+            ) {
+                failures.push(format!("nothing captured at {:}", missing.path));
+            }
+            RunResult {
+                key: request.key.clone(),
+                status: exchange.status,
+                duration_ms,
+                failures,
+                entry: Some(exchange.entry),
+            }
+        }
+        Err(err) => RunResult {
+            key: request.key.clone(),
+            status: 0,
+            duration_ms,
+            failures: vec![err.to_string()],
+            entry: None,
+        },
+    }
+}
+
+/// The progress and results of a collection run.
+#[derive(Default)]
+pub struct CollectionRun {
+    /// Prefix of the keys of the requests run.
+    pub prefix: String,
+    pub total: usize,
+    pub results: Vec<RunResult>,
+    pub done: bool,
+}
+
+impl CollectionRun {
+    /// Like `12/20 run, 11 passed, 1 failed in 3.2s`.
+    pub fn summary(&self) -> String {
+        let passed = self.results.iter().filter(|result| result.passed()).count();
+        let duration: u64 = self.results.iter().map(|result| result.duration_ms).sum();
+        format!(
+            "{:}/{:} run{:}, {:} passed, {:} failed in {:.1}s",
+            self.results.len(),
+            self.total,
+            if self.done { "" } else { " so far" },
+            passed,
+            self.results.len() - passed,
+            duration as f64 / 1000.0
+        )
+    }
+}
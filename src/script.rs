@@ -0,0 +1,201 @@
+//! Scripts of key presses replayed into the interface, for producing demos and checking key bind
+//! flows. A script has one step per line, and lines starting with `#` are comments:
+//!
+//! ```text
+//! # Pause for 500 milliseconds.
+//! wait 500
+//! # Press a key, named as in the ASCII help.
+//! key Ctrl+s
+//! # Press the key for each character.
+//! type example.com
+//! # Press the key bound to an operation.
+//! op SendRequest
+//! ```
+
+use crate::error::{Error, Result};
+use crate::key_bind::KeyBind;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+enum Step {
+    Wait(Duration),
+    Key(KeyEvent),
+}
+
+/// A script being played back.
+pub struct Script {
+    steps: VecDeque<Step>,
+    resume_at: Instant,
+}
+
+impl Script {
+    pub fn load(path: &Path, key_binds: &[KeyBind]) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut steps = VecDeque::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |message: &str| {
+                Error::Parse(format!("{:} on line {:} of the script", message, index + 1))
+            };
+            let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+            let argument = argument.trim();
+            match command {
+                "wait" => {
+                    let millis = argument
+                        .parse()
+                        .map_err(|_| invalid("Expected a number of milliseconds"))?;
+                    steps.push_back(Step::Wait(Duration::from_millis(millis)));
+                }
+                "key" => {
+                    let key = parse_key(argument).ok_or_else(|| invalid("Unknown key"))?;
+                    steps.push_back(Step::Key(key));
+                }
+                "type" => steps.extend(
+                    argument
+                        .chars()
+                        .map(|c| Step::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))),
+                ),
+                "op" => {
+                    let key_bind = key_binds
+                        .iter()
+                        .find(|key_bind| format!("{:?}", key_bind.operation) == argument)
+                        .ok_or_else(|| invalid("Unknown operation"))?;
+                    steps.push_back(Step::Key(KeyEvent::new(key_bind.key, key_bind.modifiers)));
+                }
+                _ => return Err(invalid("Unknown command")),
+            }
+        }
+        Ok(Script {
+            steps,
+            resume_at: Instant::now(),
+        })
+    }
+
+    /// The next key to press, once any wait before it has passed.
+    pub fn next_key(&mut self) -> Option<KeyEvent> {
+        while Instant::now() >= self.resume_at {
+            match self.steps.pop_front()? {
+                Step::Wait(duration) => self.resume_at = Instant::now() + duration,
+                Step::Key(key) => return Some(key),
+            }
+        }
+        None
+    }
+}
+
+/// Records keys as they are pressed, with the time between them, into a script.
+pub struct Recorder {
+    lines: Vec<String>,
+    last_key: Instant,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Recorder {
+            lines: Vec::new(),
+            last_key: Instant::now(),
+        }
+    }
+}
+
+impl Recorder {
+    pub fn record(&mut self, key: KeyEvent) {
+        let elapsed = self.last_key.elapsed().as_millis();
+        self.last_key = Instant::now();
+        if elapsed > 0 {
+            self.lines.push(format!("wait {:}", elapsed));
+        }
+        self.lines.push(format!("key {:}", format_key(key)));
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut contents = self.lines.join("\n");
+        contents.push('\n');
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+const KEY_NAMES: [(&str, KeyCode); 15] = [
+    ("Backspace", KeyCode::Backspace),
+    ("Enter", KeyCode::Enter),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PgUp", KeyCode::PageUp),
+    ("PgDn", KeyCode::PageDown),
+    ("Tab", KeyCode::Tab),
+    ("BackTab", KeyCode::BackTab),
+    ("Del", KeyCode::Delete),
+    ("Ins", KeyCode::Insert),
+    ("Esc", KeyCode::Esc),
+];
+
+/// Parse a key like `Ctrl+Shift+Tab`, `Alt+x`, `F5`, or `Space`.
+pub fn parse_key(text: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = text;
+    loop {
+        if let Some(stripped) = rest.strip_prefix("Ctrl+") {
+            modifiers |= KeyModifiers::CONTROL;
+            rest = stripped;
+        } else if let Some(stripped) = rest.strip_prefix("Alt+") {
+            modifiers |= KeyModifiers::ALT;
+            rest = stripped;
+        } else if let Some(stripped) = rest.strip_prefix("Shift+") {
+            modifiers |= KeyModifiers::SHIFT;
+            rest = stripped;
+        } else {
+            break;
+        }
+    }
+
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ if rest == "Space" => KeyCode::Char(' '),
+        _ => match KEY_NAMES.iter().find(|(name, _)| *name == rest) {
+            Some((_, code)) => *code,
+            None => KeyCode::F(rest.strip_prefix('F')?.parse().ok()?),
+        },
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
+/// The name of a key as understood by `parse_key`.
+pub fn format_key(key: KeyEvent) -> String {
+    let mut res = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        res.push_str("Ctrl+");
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        res.push_str("Alt+");
+    }
+    if key.modifiers.contains(KeyModifiers::SHIFT) {
+        res.push_str("Shift+");
+    }
+    match key.code {
+        KeyCode::Char(' ') => res.push_str("Space"),
+        KeyCode::Char(c) => res.push(c),
+        KeyCode::F(number) => res.push_str(format!("F{:}", number).as_str()),
+        code => {
+            let name = KEY_NAMES
+                .iter()
+                .find(|(_, key_code)| *key_code == code)
+                .map_or("Null", |(name, _)| name);
+            res.push_str(name);
+        }
+    }
+    res
+}
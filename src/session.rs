@@ -0,0 +1,47 @@
+//! A request, the environment it was sent with, and the response received, bundled into a single
+//! file. Importing the file shows a teammate exactly what was seen when it was exported.
+
+use crate::environment::Environment;
+use crate::error::Result;
+use crate::history::{now, HistoryEntry};
+use crate::output::output_path;
+use crate::persistence::Request;
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Session {
+    /// Seconds since the unix epoch when the session was exported.
+    pub exported: u64,
+    /// The request as written in the editor, before variables were substituted.
+    pub request: Request,
+    /// The environment the request was sent with. Secret values are blanked.
+    pub environment: Option<Environment>,
+    /// The request as it was sent, and the response. Sensitive headers, and their values found
+    /// in the response body, are masked.
+    pub exchange: HistoryEntry,
+}
+
+impl Session {
+    pub fn new(request: Request, environment: Option<Environment>, exchange: HistoryEntry) -> Self {
+        Session {
+            exported: now(),
+            request,
+            environment,
+            exchange,
+        }
+    }
+
+    /// Write the session to a new file. Returns the name of the file.
+    pub fn export(&self) -> Result<String> {
+        let path = output_path(format!("session-{:}", self.exchange.url).as_str(), "json");
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path.display().to_string())
+    }
+
+    pub fn import(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+}
@@ -0,0 +1,88 @@
+//! Suggestions for the URL and header values being typed, from the requests in the history, and
+//! for the key paths of a JSON response.
+
+use crate::history::History;
+use crate::redact::is_sensitive;
+use crate::settings::Settings;
+
+const MAX_SUGGESTIONS: usize = 5;
+const MAX_KEY_PATHS: usize = 20;
+
+/// URLs sent before which start with the typed text, most recent first.
+pub fn urls(history: &History, typed: &str) -> Vec<String> {
+    if typed.is_empty() {
+        return Vec::new();
+    }
+    let urls = history.entries.iter().rev().map(|entry| entry.url.as_str());
+    matching(urls, typed)
+}
+
+/// Values sent before for the header on a line like `Accept: app`, which start with the value
+/// typed so far. Sensitive headers, like `Authorization`, never have suggestions.
+pub fn header_values(history: &History, line: &str, settings: &Settings) -> Vec<String> {
+    let (name, typed) = match line.split_once(':') {
+        Some((name, typed)) => (name.trim(), typed.trim_start()),
+        None => return Vec::new(),
+    };
+    if name.is_empty() || is_sensitive(name, &settings.redacted_headers) {
+        return Vec::new();
+    }
+    let values = history.entries.iter().rev().flat_map(|entry| {
+        entry.headers.lines().filter_map(|header| {
+            let (key, value) = header.split_once(':')?;
+            key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    });
+    matching(values, typed)
+}
+
+/// The first distinct items which start with, and are longer than, the typed text.
+fn matching<'a>(items: impl Iterator<Item = &'a str>, typed: &str) -> Vec<String> {
+    let mut res: Vec<String> = Vec::new();
+    for item in items {
+        if item.len() > typed.len() && item.starts_with(typed) && !res.iter().any(|s| s == item) {
+            res.push(item.to_string());
+            if res.len() == MAX_SUGGESTIONS {
+                break;
+            }
+        }
+    }
+    res
+}
+
+/// Score an item when the typed characters appear in it in order, ignoring case. Characters which
+/// follow the previous match, or start a segment of a path, score higher.
+fn fuzzy_score(typed: &str, item: &str) -> Option<i64> {
+    let item: Vec<char> = item.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for c in typed.to_lowercase().chars() {
+        let found = position + item[position..].iter().position(|item_c| *item_c == c)?;
+        score += 1;
+        if previous == Some(found.wrapping_sub(1)) {
+            score += 5;
+        }
+        if found == 0 || matches!(item[found - 1], '.' | '[' | '\'' | '$') {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+/// The key paths which best match the typed text, best first. Equally good matches are shorter
+/// paths first, then in the order they appear.
+pub fn key_paths(paths: &[(String, usize)], typed: &str) -> Vec<(String, usize)> {
+    let mut matches: Vec<(i64, &(String, usize))> = paths
+        .iter()
+        .filter_map(|path| fuzzy_score(typed, path.0.as_str()).map(|score| (score, path)))
+        .collect();
+    matches.sort_by_key(|(score, (path, line))| (-score, path.len(), *line));
+    matches
+        .into_iter()
+        .take(MAX_KEY_PATHS)
+        .map(|(_, path)| path.clone())
+        .collect()
+}
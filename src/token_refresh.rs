@@ -0,0 +1,187 @@
+use crate::environment::{substitute, Environment, Environments};
+use crate::history::now;
+use crate::persistence;
+use crate::proxy::ProxyChoice;
+use crate::settings::Settings;
+use crate::web_request_handler::{next_request_id, queue};
+use crate::{json_path, Request, Response, WebRequest};
+
+use serde::{Deserialize, Serialize};
+
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// When a request is rejected with a 401 the `request` is sent, and the value at `path` in its
+/// response is stored in `variable`. The original request is then retried once.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenRefresh {
+    /// Name of the saved request which fetches a new token.
+    pub request: String,
+    /// Variable in the active environment to store the token in.
+    pub variable: String,
+    /// JSON path of the token in the response, like `$.access_token`.
+    pub path: String,
+}
+
+/// Send the refresh request and update the token variable. Returns the status of the refresh
+/// response, 0 if there wasn't one, and whether the token was updated.
+pub async fn refresh_token(
+    sender: &mpsc::Sender<WebRequest>,
+    busy: &AtomicBool,
+    refresh: &TokenRefresh,
+    request: &persistence::Request,
+    environments: &Arc<Mutex<Environments>>,
+    settings: &Settings,
+) -> (u16, bool) {
+    let (url, headers, body, proxy) = {
+        let environments = environments.lock().unwrap();
+        let environment = environments.active();
+        let proxy = request
+            .proxy
+            .as_deref()
+            .map(|proxy| substitute(proxy, environment))
+            .or_else(|| settings.proxy.clone());
+        (
+            substitute(request.url.as_str(), environment),
+            substitute(request.headers_to_string().as_str(), environment),
+            crate::body::prepare_body(
+                substitute(request.body.as_deref().unwrap_or(""), environment).as_str(),
+                settings,
+            ),
+            ProxyChoice::new(request.bypass_proxy, proxy),
+        )
+    };
+
+    let (tx, mut rx) = mpsc::channel(10);
+    let request = WebRequest::Request(Box::new(Request {
+        id: next_request_id(),
+        method: request.method.clone(),
+        url,
+        headers,
+        body,
+        multipart: None,
+        body_file: None,
+        download: None,
+        proxy,
+        timeout: settings.request_timeout(),
+        redirects: settings.redirects,
+        resp: tx,
+    }));
+    if let Err(err) = queue(sender, request, busy).await {
+        error!("Error refreshing token {:}", err);
+        return (0, false);
+    }
+
+    let mut status = 0;
+    let mut success = false;
+    let mut received: Vec<u8> = Vec::new();
+    while let Some(res) = rx.recv().await {
+        match res {
+            Response::Status(res) => {
+                status = res.as_u16();
+                success = res.is_success();
+            }
+            Response::Body(bytes) => received.extend_from_slice(&bytes),
+            Response::Failure(err) => {
+                error!("Error refreshing token {:}", err);
+                return (status, false);
+            }
+            _ => {}
+        }
+    }
+    if !success {
+        error!("Token refresh request {:} failed", refresh.request);
+        return (status, false);
+    }
+
+    let value: Option<serde_json::Value> = serde_json::from_slice(&received).ok();
+    let token = value.as_ref().and_then(|value| {
+        json_path::get(value, refresh.path.as_str()).map(json_path::value_to_string)
+    });
+    // OAuth2 token responses say how long the token lasts, which is needed for tokens which
+    // aren't JWTs.
+    let expires_in = value
+        .as_ref()
+        .and_then(|value| value.get("expires_in"))
+        .and_then(|expires_in| expires_in.as_u64());
+    match token {
+        Some(token) => {
+            let mut environments = environments.lock().unwrap();
+            match environments.active_mut() {
+                Some(environment) => {
+                    environment.set(refresh.variable.as_str(), token.as_str());
+                    let variable = expires_at_variable(refresh);
+                    match expires_in {
+                        Some(expires_in) => environment
+                            .set(variable.as_str(), (now() + expires_in).to_string().as_str()),
+                        // Clear the expiry of the previous token.
+                        None if environment.get(variable.as_str()).is_some() => {
+                            environment.set(variable.as_str(), "")
+                        }
+                        None => {}
+                    }
+                }
+                None => return (status, false),
+            }
+            if let Err(err) = environments.save() {
+                error!("Error saving refreshed token {:}", err);
+            }
+            (status, true)
+        }
+        None => {
+            error!(
+                "Token not found at {:} in the refresh response",
+                refresh.path
+            );
+            (status, false)
+        }
+    }
+}
+
+/// Variable the expiry of the token is stored in, in seconds since the unix epoch, when the
+/// refresh response gives one.
+fn expires_at_variable(refresh: &TokenRefresh) -> String {
+    format!("{:}_expires_at", refresh.variable)
+}
+
+/// When the managed token of an environment expires, in seconds since the unix epoch. This is
+/// the `exp` claim of a JWT, or the expiry given with the token when it was refreshed.
+pub fn token_expiry(environment: &Environment) -> Option<u64> {
+    if environment.oauth2.is_some() {
+        return crate::oauth2::token_expiry(environment);
+    }
+    let refresh = environment.token_refresh.as_ref()?;
+    let token = environment.get(refresh.variable.as_str())?;
+    jwt_expiry(token).or_else(|| {
+        environment
+            .get(expires_at_variable(refresh).as_str())
+            .and_then(|expires_at| expires_at.parse().ok())
+    })
+}
+
+pub fn jwt_expiry(token: &str) -> Option<u64> {
+    let token = token.trim().trim_start_matches("Bearer ");
+    let payload = token.split('.').nth(1)?;
+    let payload =
+        base64::decode_config(payload.trim_end_matches('='), base64::URL_SAFE_NO_PAD).ok()?;
+    let claims: serde_json::Value = serde_json::from_slice(&payload).ok()?;
+    claims.get("exp")?.as_u64()
+}
+
+/// A short description of when a token expires, like `token expires in 4m`.
+pub fn expiry_label(expiry: u64) -> String {
+    let now = now();
+    if expiry <= now {
+        return "token expired".to_string();
+    }
+    let remaining = expiry - now;
+    let remaining = if remaining < 60 {
+        format!("{:}s", remaining)
+    } else if remaining < 60 * 60 {
+        format!("{:}m", remaining / 60)
+    } else {
+        format!("{:}h {:}m", remaining / (60 * 60), remaining / 60 % 60)
+    };
+    format!("token expires in {:}", remaining)
+}
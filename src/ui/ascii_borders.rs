@@ -0,0 +1,30 @@
+use tui::buffer::Buffer;
+use tui::layout::Rect;
+use tui::widgets::Widget;
+
+/// Replaces the box drawing characters already rendered in an area with ASCII equivalents.
+/// Rendered last, so it applies to every border on the screen.
+pub struct AsciiBorders;
+
+impl Widget for AsciiBorders {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = buf.get_mut(x, y);
+                if let Some(ascii) = ascii_symbol(cell.symbol.as_str()) {
+                    cell.set_symbol(ascii);
+                }
+            }
+        }
+    }
+}
+
+fn ascii_symbol(symbol: &str) -> Option<&'static str> {
+    match symbol {
+        "─" | "━" | "═" => Some("-"),
+        "│" | "┃" | "║" => Some("|"),
+        "┌" | "┐" | "└" | "┘" | "╭" | "╮" | "╰" | "╯" | "┏" | "┓" | "┗" | "┛" | "╔" | "╗" | "╚"
+        | "╝" => Some("+"),
+        _ => None,
+    }
+}
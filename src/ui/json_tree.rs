@@ -0,0 +1,245 @@
+use crate::json_path::{format, Segment};
+use crate::ScrollDirection;
+use crossterm::event::{KeyCode, KeyEvent};
+use serde_json::Value;
+use std::collections::HashSet;
+use tui::buffer::Buffer;
+use tui::layout::Rect;
+use tui::style::{Color, Style};
+use tui::widgets::{Block, StatefulWidget, Widget};
+
+/// A JSON response parsed into a tree of collapsible nodes. Only the root is expanded at first,
+/// so large documents can be explored a level at a time. Object keys are shown sorted.
+#[derive(Default)]
+pub struct JsonTreeState {
+    value: Option<Value>,
+    /// Length of the body the value was parsed from, to parse it again when more arrives.
+    parsed_len: Option<usize>,
+    /// Paths of the expanded nodes, like `$.data.items`.
+    expanded: HashSet<String>,
+    /// Row of the selected node.
+    selected: usize,
+    scroll: usize,
+}
+
+/// A node of the tree shown on a row.
+struct TreeRow {
+    depth: usize,
+    path: Vec<Segment>,
+    text: String,
+    /// Whether the node is expanded, for objects and arrays.
+    expanded: Option<bool>,
+}
+
+impl JsonTreeState {
+    pub fn reset(&mut self) {
+        *self = JsonTreeState::default();
+    }
+
+    /// Parse the body if it has changed since it was last parsed.
+    pub fn update(&mut self, body: &str) {
+        if self.parsed_len == Some(body.len()) {
+            return;
+        }
+        self.parsed_len = Some(body.len());
+        self.value = serde_json::from_str(body).ok();
+        if self.expanded.is_empty() {
+            self.expanded.insert(format(&[]));
+        }
+    }
+
+    /// The body parsed as JSON, otherwise there is no tree to show.
+    pub fn is_json(&self) -> bool {
+        self.value.is_some()
+    }
+
+    /// The path of the selected node, like `$.data.items[3]`.
+    pub fn selected_path(&self) -> Option<String> {
+        self.rows()
+            .get(self.selected)
+            .map(|row| format(row.path.as_slice()))
+    }
+
+    pub fn handle_input(&mut self, key: KeyEvent) {
+        let rows = self.rows();
+        let row = match rows.get(self.selected) {
+            Some(row) => row,
+            None => return,
+        };
+        match key.code {
+            KeyCode::Up => self.move_selection(ScrollDirection::Up, rows.len()),
+            KeyCode::Down => self.move_selection(ScrollDirection::Down, rows.len()),
+            KeyCode::Enter if row.expanded.is_some() => self.toggle(&row.path),
+            // Expand a collapsed node, or step into an expanded one.
+            KeyCode::Right => match row.expanded {
+                Some(false) => self.toggle(&row.path),
+                Some(true) => self.move_selection(ScrollDirection::Down, rows.len()),
+                None => {}
+            },
+            // Collapse an expanded node, or step out to its parent.
+            KeyCode::Left => match row.expanded {
+                Some(true) if row.depth > 0 => self.toggle(&row.path),
+                _ => {
+                    if let Some(parent) = rows[..self.selected]
+                        .iter()
+                        .rposition(|parent| parent.depth < row.depth)
+                    {
+                        self.selected = parent;
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn move_selection(&mut self, direction: ScrollDirection, count: usize) {
+        self.selected = match direction {
+            ScrollDirection::Up => self.selected.saturating_sub(1),
+            ScrollDirection::Down => (self.selected + 1).min(count.saturating_sub(1)),
+        };
+    }
+
+    fn toggle(&mut self, path: &[Segment]) {
+        let path = format(path);
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+    }
+
+    fn rows(&self) -> Vec<TreeRow> {
+        let mut rows = Vec::new();
+        if let Some(value) = &self.value {
+            self.add_rows(&mut rows, None, value, &mut Vec::new());
+        }
+        rows
+    }
+
+    fn add_rows(
+        &self,
+        rows: &mut Vec<TreeRow>,
+        label: Option<String>,
+        value: &Value,
+        path: &mut Vec<Segment>,
+    ) {
+        let expanded = match value {
+            Value::Object(_) | Value::Array(_) => Some(self.expanded.contains(&format(path))),
+            _ => None,
+        };
+        let summary = match value {
+            Value::Object(map) if expanded == Some(false) => format!("{{ {:} keys }}", map.len()),
+            Value::Array(items) if expanded == Some(false) => {
+                format!("[ {:} items ]", items.len())
+            }
+            Value::Object(_) => "{".to_string(),
+            Value::Array(_) => "[".to_string(),
+            value => value.to_string(),
+        };
+        rows.push(TreeRow {
+            depth: path.len(),
+            path: path.clone(),
+            text: match label {
+                Some(label) => format!("{:}: {:}", label, summary),
+                None => summary,
+            },
+            expanded,
+        });
+        if expanded != Some(true) {
+            return;
+        }
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    path.push(Segment::Key(key.clone()));
+                    self.add_rows(rows, Some(format!("{:?}", key)), value, path);
+                    path.pop();
+                }
+            }
+            Value::Array(items) => {
+                for (index, value) in items.iter().enumerate() {
+                    path.push(Segment::Index(index));
+                    self.add_rows(rows, Some(index.to_string()), value, path);
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct JsonTree<'a> {
+    block: Option<Block<'a>>,
+    active: bool,
+}
+
+impl<'a> JsonTree<'a> {
+    pub fn block(mut self, block: Block<'a>) -> JsonTree<'a> {
+        self.block = Some(block);
+        self
+    }
+
+    pub fn active(mut self, active: bool) -> JsonTree<'a> {
+        self.active = active;
+        self
+    }
+}
+
+impl<'a> StatefulWidget for JsonTree<'a> {
+    type State = JsonTreeState;
+
+    fn render(mut self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let area = match self.block.take() {
+            Some(block) => {
+                let inner_area = block.inner(area);
+                block.render(area, buf);
+                inner_area
+            }
+            None => area,
+        };
+        if area.height == 0 || area.width == 0 {
+            return;
+        }
+
+        let rows = state.rows();
+        state.selected = state.selected.min(rows.len().saturating_sub(1));
+        // Keep the selected node in view.
+        let height = area.height as usize;
+        if state.selected < state.scroll {
+            state.scroll = state.selected;
+        } else if state.selected >= state.scroll + height {
+            state.scroll = state.selected + 1 - height;
+        }
+
+        for (offset, (index, row)) in rows
+            .iter()
+            .enumerate()
+            .skip(state.scroll)
+            .take(height)
+            .enumerate()
+        {
+            let marker = match row.expanded {
+                Some(true) => "- ",
+                Some(false) => "+ ",
+                None => "  ",
+            };
+            let style = if index == state.selected && self.active {
+                Style::default().bg(Color::DarkGray)
+            } else {
+                Style::default()
+            };
+            let y = area.y + offset as u16;
+            buf.set_style(Rect::new(area.x, y, area.width, 1), style);
+            buf.set_stringn(
+                area.x,
+                y,
+                format!("{:}{:}{:}", "  ".repeat(row.depth), marker, row.text),
+                area.width as usize,
+                style.fg(if row.expanded.is_some() {
+                    Color::LightCyan
+                } else {
+                    Color::White
+                }),
+            );
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use tui::layout::{Constraint, Direction, Layout, Rect};
 
+pub mod ascii_borders;
 mod cursor;
+pub mod json_tree;
 pub mod paragraph;
 pub mod text_area;
 
@@ -1,24 +1,52 @@
+use std::ops::Range;
 use std::sync::Arc;
 
+use crate::highlight::{Highlighter, Token, Tokens};
+use crate::layout::block::block;
 use crate::ui::count_newlines;
 use tui::backend::Backend;
 use tui::layout::{Alignment, Rect};
 use tui::style::{Color, Style};
-use tui::widgets::{Paragraph};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Paragraph};
 use tui::Frame;
-use crate::layout::block::block;
 
 pub struct WrappedCache {
     id: usize,
     width: u16,
+    height: u16,
     wrapped: String,
     lines: u16,
+    /// The line of the unwrapped text each wrapped row belongs to.
+    row_lines: Vec<usize>,
+    /// Byte offset of each wrapped row in its line of the unwrapped text.
+    row_starts: Vec<usize>,
 }
 
 impl WrappedCache {
     pub fn get_lines(&self) -> u16 {
         self.lines
     }
+
+    /// Number of rows visible in the area the text was wrapped for.
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// The first wrapped row of a line of the unwrapped text, and how many rows it wraps onto.
+    pub fn rows_of(&self, line: usize) -> Option<(u16, u16)> {
+        let first = self.row_lines.iter().position(|row| *row == line)?;
+        let count = self.row_lines[first..]
+            .iter()
+            .take_while(|row| **row == line)
+            .count();
+        Some((first as u16, count as u16))
+    }
+
+    /// The line of the unwrapped text a wrapped row belongs to.
+    pub fn line_of_row(&self, row: u16) -> Option<usize> {
+        self.row_lines.get(row as usize).copied()
+    }
 }
 
 pub fn paragraph<B: Backend>(
@@ -42,6 +70,7 @@ pub fn paragraph<B: Backend>(
     )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn paragraph_color<B: Backend>(
     app_rect: &mut Frame<B>,
     rect: Rect,
@@ -54,29 +83,8 @@ pub fn paragraph_color<B: Backend>(
 ) -> (u16, Arc<WrappedCache>) {
     let block = block(title, active);
     let inner_rect = block.inner(rect);
-
-    let cur_cache = match cache {
-        None => make_cache(text, inner_rect),
-        Some(cache) => {
-            if cache.id != text.as_ptr() as *const _ as usize || cache.width != inner_rect.width {
-                make_cache(text, inner_rect)
-            } else {
-                cache
-            }
-        }
-    };
-
-    let height_adjusted_lines = if cur_cache.lines >= inner_rect.height {
-        (cur_cache.lines - inner_rect.height) + 1
-    } else {
-        0
-    };
-
-    let capped_scroll = if scroll > height_adjusted_lines {
-        height_adjusted_lines
-    } else {
-        scroll
-    };
+    let cur_cache = current_cache(text, inner_rect, cache);
+    let capped_scroll = cap_scroll(&cur_cache, inner_rect, scroll);
 
     let response_body = Paragraph::new(cur_cache.wrapped.as_str())
         .alignment(Alignment::Left)
@@ -88,15 +96,243 @@ pub fn paragraph_color<B: Backend>(
     (capped_scroll, cur_cache)
 }
 
+/// Render text like `paragraph`, colouring the tokens found by the highlighter. Only the visible
+/// rows are tokenized.
+#[allow(clippy::too_many_arguments)]
+pub fn highlighted_paragraph<B: Backend>(
+    app_rect: &mut Frame<B>,
+    rect: Rect,
+    title: &str,
+    text: &str,
+    active: bool,
+    scroll: u16,
+    cache: Option<Arc<WrappedCache>>,
+    highlighter: Highlighter,
+) -> (u16, Arc<WrappedCache>) {
+    let block = block(title, active);
+    let inner_rect = block.inner(rect);
+    let cur_cache = current_cache(text, inner_rect, cache);
+    let capped_scroll = cap_scroll(&cur_cache, inner_rect, scroll);
+
+    let first = capped_scroll as usize;
+    let rows: Vec<&str> = cur_cache
+        .wrapped
+        .split('\n')
+        .skip(first)
+        .take(inner_rect.height as usize)
+        .collect();
+    let first_line = cur_cache.row_lines.get(first).copied().unwrap_or(0);
+    let mut lines = text.split('\n').skip(first_line);
+    // The line and its tokens which the current row belongs to.
+    let mut current: Option<(usize, String, Tokens)> = None;
+    let mut spans = Vec::with_capacity(rows.len());
+    for (offset, row) in rows.iter().enumerate() {
+        let line_index = cur_cache.row_lines[first + offset];
+        while current
+            .as_ref()
+            .is_none_or(|(index, _, _)| *index < line_index)
+        {
+            let next = current
+                .as_ref()
+                .map_or(first_line, |(index, _, _)| index + 1);
+            let line = lines.next().unwrap_or("").replace('\r', "");
+            let tokens = highlighter(line.as_str());
+            current = Some((next, line, tokens));
+        }
+        let (_, line, tokens) = current.as_ref().unwrap();
+        let start = cur_cache.row_starts[first + offset];
+        spans.push(highlight_row(row, line, start, tokens));
+    }
+
+    let response_body = Paragraph::new(spans)
+        .alignment(Alignment::Left)
+        .style(Style::default().fg(Color::White))
+        .block(block);
+    app_rect.render_widget(response_body, rect);
+    (capped_scroll, cur_cache)
+}
+
+/// The spans of a wrapped row which starts at `start` in `line`.
+fn highlight_row<'a>(
+    row: &'a str,
+    line: &str,
+    start: usize,
+    tokens: &[(Range<usize>, Token)],
+) -> Spans<'a> {
+    let end = start + row.len();
+    // The row isn't a slice of the line if wrapping changed it.
+    if line.get(start..end) != Some(row) {
+        return Spans::from(row);
+    }
+    let mut spans = Vec::new();
+    let mut position = start;
+    for (range, token) in tokens {
+        if range.end <= position || range.start >= end {
+            continue;
+        }
+        let token_start = range.start.max(position);
+        let token_end = range.end.min(end);
+        if token_start > position {
+            spans.push(Span::raw(&row[position - start..token_start - start]));
+        }
+        spans.push(Span::styled(
+            &row[token_start - start..token_end - start],
+            Style::default().fg(token_color(*token)),
+        ));
+        position = token_end;
+    }
+    if position < end {
+        spans.push(Span::raw(&row[position - start..]));
+    }
+    Spans::from(spans)
+}
+
+fn token_color(token: Token) -> Color {
+    match token {
+        Token::Key => Color::LightBlue,
+        Token::String => Color::Green,
+        Token::Number => Color::Yellow,
+        Token::Literal => Color::Magenta,
+        Token::Tag => Color::LightBlue,
+        Token::Attribute => Color::Yellow,
+        Token::Comment | Token::Annotation => Color::DarkGray,
+    }
+}
+
+/// The cache of the text wrapped for the area, wrapping it again if it has changed.
+fn current_cache(
+    text: &str,
+    inner_rect: Rect,
+    cache: Option<Arc<WrappedCache>>,
+) -> Arc<WrappedCache> {
+    match cache {
+        Some(cache)
+            if cache.id == text.as_ptr() as *const _ as usize
+                && cache.width == inner_rect.width
+                && cache.height == inner_rect.height =>
+        {
+            cache
+        }
+        _ => make_cache(text, inner_rect),
+    }
+}
+
+/// Keep the scroll position from going past the end of the text.
+fn cap_scroll(cache: &WrappedCache, inner_rect: Rect, scroll: u16) -> u16 {
+    let height_adjusted_lines = if cache.lines >= inner_rect.height {
+        (cache.lines - inner_rect.height) + 1
+    } else {
+        0
+    };
+    scroll.min(height_adjusted_lines)
+}
+
+/// Highlight the rows of a line of the text rendered by `paragraph` in `rect`.
+pub fn highlight_line<B: Backend>(
+    app_rect: &mut Frame<B>,
+    rect: Rect,
+    scroll: u16,
+    cache: &WrappedCache,
+    line: usize,
+) {
+    let inner_rect = block("", false).inner(rect);
+    if let Some((first, count)) = cache.rows_of(line) {
+        for row in first..first + count {
+            if row < scroll || row - scroll >= inner_rect.height {
+                continue;
+            }
+            let area = Rect::new(
+                inner_rect.x,
+                inner_rect.y + row - scroll,
+                inner_rect.width,
+                1,
+            );
+            app_rect.render_widget(
+                Block::default().style(Style::default().bg(Color::DarkGray)),
+                area,
+            );
+        }
+    }
+}
+
+/// Highlight where `query` appears in the visible rows of the text rendered by `paragraph` in
+/// `rect`, ignoring ASCII case. `current` is the line of the unwrapped text with the current
+/// match, and which of the matches on that line it is. Matches split by wrapping aren't shown.
+pub fn highlight_matches<B: Backend>(
+    app_rect: &mut Frame<B>,
+    rect: Rect,
+    scroll: u16,
+    cache: &WrappedCache,
+    query: &str,
+    current: Option<(usize, usize)>,
+) {
+    let query = query.to_ascii_lowercase();
+    if query.is_empty() {
+        return;
+    }
+    let inner_rect = block("", false).inner(rect);
+    let mut previous_line = None;
+    let mut nth = 0;
+    for (row, (text, line)) in cache.wrapped.split('\n').zip(&cache.row_lines).enumerate() {
+        if previous_line != Some(*line) {
+            previous_line = Some(*line);
+            nth = 0;
+        }
+        let text = text.to_ascii_lowercase();
+        for (start, _) in text.match_indices(query.as_str()) {
+            let is_current = current == Some((*line, nth));
+            nth += 1;
+            let row = row as u16;
+            if row < scroll || row - scroll >= inner_rect.height {
+                continue;
+            }
+            let column = text[..start].chars().count() as u16;
+            if column >= inner_rect.width {
+                continue;
+            }
+            let width = (query.chars().count() as u16).min(inner_rect.width - column);
+            let area = Rect::new(inner_rect.x + column, inner_rect.y + row - scroll, width, 1);
+            let style = if is_current {
+                Style::default().bg(Color::Yellow).fg(Color::Black)
+            } else {
+                Style::default().bg(Color::Blue)
+            };
+            app_rect.render_widget(Block::default().style(style), area);
+        }
+    }
+}
+
 fn make_cache(text: &str, inner_rect: Rect) -> Arc<WrappedCache> {
-    let wrapped = textwrap::fill(text, inner_rect.width as usize);
+    let mut wrapped = String::with_capacity(text.len());
+    let mut row_lines = Vec::new();
+    let mut row_starts = Vec::new();
+    // Carriage returns would move the terminal cursor while drawing.
+    for (index, line) in text.replace('\r', "").split('\n').enumerate() {
+        let mut offset = 0;
+        for row in textwrap::wrap(line, inner_rect.width as usize) {
+            if !row_lines.is_empty() {
+                wrapped.push('\n');
+            }
+            wrapped.push_str(&row);
+            row_lines.push(index);
+            // Wrapping drops the whitespace between rows.
+            let start = line[offset..]
+                .find(row.as_ref())
+                .map_or(offset, |found| offset + found);
+            row_starts.push(start);
+            offset = (start + row.len()).min(line.len());
+        }
+    }
     let lines = count_newlines(wrapped.as_str());
 
     let cache = WrappedCache {
         id: text.as_ptr() as *const _ as usize,
         width: inner_rect.width,
+        height: inner_rect.height,
         wrapped,
         lines,
+        row_lines,
+        row_starts,
     };
     Arc::new(cache)
 }
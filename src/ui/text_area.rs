@@ -1,7 +1,7 @@
 use crate::ui::cursor::Cursor;
 use tui::buffer::Buffer;
 use tui::layout::Rect;
-use tui::style::Style;
+use tui::style::{Color, Style};
 use tui::widgets::{Block, Paragraph, StatefulWidget, Widget};
 
 pub struct EditState {
@@ -17,6 +17,11 @@ pub enum EditCommand {
     BackwardCursor,
     UpCursor,
     DownCursor,
+    DuplicateLine,
+    MoveLineUp,
+    MoveLineDown,
+    /// Add or remove a `//` comment prefix on the current line.
+    ToggleComment,
 }
 
 pub struct Row {
@@ -33,6 +38,8 @@ pub struct TextArea<'a> {
     style: Style,
     /// Flag indicating if this component should render as active.
     active: bool,
+    /// Dim hint text displayed while the buffer is empty.
+    placeholder: Option<&'a str>,
 }
 
 impl<'a> TextArea<'a> {
@@ -50,6 +57,11 @@ impl<'a> TextArea<'a> {
         self.active = active;
         self
     }
+
+    pub fn placeholder(mut self, placeholder: &'a str) -> TextArea<'a> {
+        self.placeholder = Some(placeholder);
+        self
+    }
 }
 
 impl<'a> StatefulWidget for TextArea<'a> {
@@ -77,18 +89,30 @@ impl<'a> StatefulWidget for TextArea<'a> {
         let mut x_scroll = 0;
 
         if row > (text_area.height as usize - 1) {
-            y_scroll = ((text_area.height - 1) as i32 - row as i32).abs() as u16
+            y_scroll = ((text_area.height - 1) as i32 - row as i32).unsigned_abs() as u16
         }
 
         if pos_in_row > (text_area.width as usize - 1) {
-            x_scroll = ((text_area.width - 1) as i32 - pos_in_row as i32).abs() as u16
+            x_scroll = ((text_area.width - 1) as i32 - pos_in_row as i32).unsigned_abs() as u16
         }
 
-        let paragraph = Paragraph::new(state.buffer.as_str())
-            // .block(block)
-            .scroll((y_scroll, x_scroll));
-
-        paragraph.render(text_area, buf);
+        match self.placeholder {
+            Some(placeholder) if state.buffer.is_empty() => {
+                Paragraph::new(placeholder)
+                    .style(Style::default().fg(Color::DarkGray))
+                    .render(text_area, buf);
+            }
+            _ => {
+                // A carriage return written to the terminal moves the cursor, which corrupts the
+                // screen on Windows consoles. A space keeps the cursor columns lined up.
+                let text = state.buffer.replace('\r', " ");
+                let paragraph = Paragraph::new(text.as_str())
+                    // .block(block)
+                    .scroll((y_scroll, x_scroll));
+
+                paragraph.render(text_area, buf);
+            }
+        }
         if self.active {
             let cursor = Cursor::default()
                 .position((state.pos - before) as u16, row as u16)
@@ -107,7 +131,7 @@ pub fn row_topology(s: &str, pos: usize) -> (Vec<Row>, usize) {
 
     let mut row_start = 0;
     for (index, item) in s.as_bytes().iter().enumerate() {
-        if index >= pos as usize && !row_found {
+        if index >= pos && !row_found {
             row_found = true;
             current_row = topology.len()
         }
@@ -119,7 +143,7 @@ pub fn row_topology(s: &str, pos: usize) -> (Vec<Row>, usize) {
                 end: bytes_line + row_start - 1,
                 size: bytes_line,
             });
-            row_start = bytes_line + row_start;
+            row_start += bytes_line;
             bytes_line = 0;
         }
     }
@@ -128,7 +152,7 @@ pub fn row_topology(s: &str, pos: usize) -> (Vec<Row>, usize) {
         end: bytes_line + row_start,
         size: bytes_line,
     });
-    if pos >= s.len() && topology.len() > 0 {
+    if pos >= s.len() && !topology.is_empty() {
         current_row = topology.len() - 1
     }
     (topology, current_row)
@@ -139,7 +163,7 @@ pub fn count_newlines(s: &str, pos: usize) -> (usize, usize, usize) {
     let mut bytes_before = 0;
     let mut bytes_line = 0;
     for (index, item) in s.as_bytes().iter().enumerate() {
-        if index >= pos as usize {
+        if index >= pos {
             break;
         }
 
@@ -179,7 +203,7 @@ impl EditState {
                 }
             }
             EditCommand::ForwardCursor => {
-                if self.buffer.len() > 0 && self.pos < self.buffer.len() {
+                if !self.buffer.is_empty() && self.pos < self.buffer.len() {
                     self.pos += 1
                 }
             }
@@ -224,6 +248,79 @@ impl EditState {
 
                 self.pos = new_pos;
             }
+            EditCommand::DuplicateLine => {
+                let (topology, row) = row_topology(self.buffer.as_str(), self.pos);
+                let current = &topology[row];
+                let line = self.buffer[current.start..current.end].to_string();
+
+                self.buffer
+                    .insert_str(current.end, format!("\n{:}", line).as_str());
+                self.pos += line.len() + 1;
+            }
+            EditCommand::MoveLineUp => {
+                let (topology, row) = row_topology(self.buffer.as_str(), self.pos);
+                if row == 0 {
+                    return;
+                }
+                let previous = &topology[row - 1];
+                let current = &topology[row];
+                let pos_in_row = self.pos - current.start;
+
+                let swapped = format!(
+                    "{:}\n{:}",
+                    &self.buffer[current.start..current.end],
+                    &self.buffer[previous.start..previous.end]
+                );
+                self.buffer
+                    .replace_range(previous.start..current.end, swapped.as_str());
+                self.pos = previous.start + pos_in_row;
+            }
+            EditCommand::MoveLineDown => {
+                let (topology, row) = row_topology(self.buffer.as_str(), self.pos);
+                if row == topology.len() - 1 {
+                    return;
+                }
+                let current = &topology[row];
+                let next = &topology[row + 1];
+                let pos_in_row = self.pos - current.start;
+                let next_len = next.end - next.start;
+
+                let swapped = format!(
+                    "{:}\n{:}",
+                    &self.buffer[next.start..next.end],
+                    &self.buffer[current.start..current.end]
+                );
+                self.buffer
+                    .replace_range(current.start..next.end, swapped.as_str());
+                self.pos = current.start + next_len + 1 + pos_in_row;
+            }
+            EditCommand::ToggleComment => {
+                let (topology, row) = row_topology(self.buffer.as_str(), self.pos);
+                let current = &topology[row];
+                let line = &self.buffer[current.start..current.end];
+                let indent = line.len() - line.trim_start().len();
+                let comment_start = current.start + indent;
+
+                if line.trim_start().starts_with("//") {
+                    let remove = if line.trim_start().starts_with("// ") {
+                        3
+                    } else {
+                        2
+                    };
+                    self.buffer
+                        .replace_range(comment_start..comment_start + remove, "");
+                    if self.pos >= comment_start + remove {
+                        self.pos -= remove;
+                    } else if self.pos > comment_start {
+                        self.pos = comment_start;
+                    }
+                } else {
+                    self.buffer.insert_str(comment_start, "// ");
+                    if self.pos >= comment_start {
+                        self.pos += 3;
+                    }
+                }
+            }
         };
     }
 
@@ -239,4 +336,42 @@ impl EditState {
         self.pos = value.len();
         self.buffer = value;
     }
+
+    /// The text of the current line before the cursor. This is empty while the cursor is within
+    /// a multi-byte character, like after typing `é`, since the cursor moves a byte at a time.
+    pub fn line_before_cursor(&self) -> &str {
+        let before = self.buffer.get(..self.pos).unwrap_or_default();
+        let start = before.rfind('\n').map_or(0, |index| index + 1);
+        &before[start..]
+    }
+
+    pub fn at_line_end(&self) -> bool {
+        self.buffer
+            .get(self.pos..)
+            .is_some_and(|after| after.is_empty() || after.starts_with('\n'))
+    }
+
+    /// Move the cursor to a position in a text area of `width` by `height`, counted from its top
+    /// left, as it is currently scrolled.
+    pub fn move_to(&mut self, width: u16, height: u16, column: u16, row: u16) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        // The text area scrolls just enough to show the cursor.
+        let (cursor_row, _, pos_in_row) = count_newlines(self.buffer.as_str(), self.pos);
+        let y_scroll = cursor_row.saturating_sub(height as usize - 1);
+        let x_scroll = pos_in_row.saturating_sub(width as usize - 1);
+        let (rows, _) = row_topology(self.buffer.as_str(), self.pos);
+        let row = &rows[(row as usize + y_scroll).min(rows.len() - 1)];
+        let mut pos = (row.start + column as usize + x_scroll).min(row.end);
+        while !self.buffer.is_char_boundary(pos) {
+            pos -= 1;
+        }
+        self.pos = pos;
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        self.buffer.insert_str(self.pos, text);
+        self.pos += text.len();
+    }
 }
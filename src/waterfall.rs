@@ -0,0 +1,92 @@
+//! Timings of the requests made for a single send, like the retry after a token refresh, drawn as
+//! a waterfall so the slow step stands out.
+
+use std::time::{Duration, Instant};
+
+pub struct Step {
+    pub label: String,
+    /// When the step started, from the start of the first step.
+    pub start: Duration,
+    pub duration: Duration,
+    /// The response status, 0 if there was no response.
+    pub status: u16,
+}
+
+impl Step {
+    /// A step which started at `step_start` and has just finished.
+    pub fn finished(label: &str, chain_start: Instant, step_start: Instant, status: u16) -> Self {
+        Step {
+            label: label.to_string(),
+            start: step_start.duration_since(chain_start),
+            duration: step_start.elapsed(),
+            status,
+        }
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration.as_millis() < 1000 {
+        format!("{:}ms", duration.as_millis())
+    } else {
+        format!("{:.2}s", duration.as_secs_f64())
+    }
+}
+
+/// One line per step, like `refresh  200 ···████·····  340ms`, with bars positioned on a shared
+/// time scale which fits in `width`. The bars are drawn with `#` and `.` when `ascii` is set.
+pub fn render(steps: &[Step], width: usize, ascii: bool) -> String {
+    let (fill, empty) = if ascii { ("#", ".") } else { ("█", "·") };
+    let total = steps
+        .iter()
+        .map(|step| step.start + step.duration)
+        .max()
+        .unwrap_or_default()
+        .as_secs_f64();
+    let label_width = steps
+        .iter()
+        .map(|step| step.label.chars().count())
+        .max()
+        .unwrap_or(0);
+    let durations: Vec<String> = steps
+        .iter()
+        .map(|step| format_duration(step.duration))
+        .collect();
+    let duration_width = durations.iter().map(|d| d.len()).max().unwrap_or(0);
+    // The label, status and duration are separated by two spaces.
+    let bar_width = width
+        .saturating_sub(label_width + 2 + 3 + 2 + 2 + duration_width)
+        .max(1);
+
+    let mut res = String::new();
+    for (step, duration) in steps.iter().zip(durations) {
+        let (offset, length) = if total > 0.0 {
+            let scale = bar_width as f64 / total;
+            let offset = ((step.start.as_secs_f64() * scale) as usize).min(bar_width - 1);
+            let length = ((step.duration.as_secs_f64() * scale).round() as usize)
+                .clamp(1, bar_width - offset);
+            (offset, length)
+        } else {
+            (0, 1)
+        };
+        let status = if step.status == 0 {
+            "---".to_string()
+        } else {
+            step.status.to_string()
+        };
+        res.push_str(
+            format!(
+                "{:label_width$}  {:>3}  {:}{:}{:}  {:>duration_width$}\n",
+                step.label,
+                status,
+                empty.repeat(offset),
+                fill.repeat(length),
+                empty.repeat(bar_width - offset - length),
+                duration,
+                label_width = label_width,
+                duration_width = duration_width,
+            )
+            .as_str(),
+        );
+    }
+    res
+}
@@ -1,90 +1,313 @@
-use crate::WebRequest::{Cancel, Request};
-use crate::{Method, Response, WebRequest};
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
-use std::str;
+use crate::body::Part;
+use crate::error::{Error, Result};
+use crate::proxy::display;
+use crate::WebRequest::{Cancel, Request, Shutdown};
+use crate::{Response, WebRequest};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
+use reqwest::multipart::{self, Form};
+use reqwest::Proxy;
+use std::collections::HashMap;
+use std::path::Path;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
 use tokio::select;
-use tokio::sync::mpsc::Receiver;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
 
-pub fn web_request_handler(mut receiver: Receiver<WebRequest>) {
+/// How long to wait for room in a full queue before giving up on sending a request.
+const QUEUE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Queue a message for the request handler. While the queue is full `busy` is set, and if it
+/// stays full for too long an error is returned instead of waiting forever.
+pub async fn queue(
+    sender: &Sender<WebRequest>,
+    message: WebRequest,
+    busy: &AtomicBool,
+) -> Result<()> {
+    let permit = match sender.try_reserve() {
+        Ok(permit) => permit,
+        Err(TrySendError::Full(())) => {
+            busy.store(true, Ordering::SeqCst);
+            let permit = tokio::time::timeout(QUEUE_TIMEOUT, sender.reserve()).await;
+            busy.store(false, Ordering::SeqCst);
+            match permit {
+                Ok(Ok(permit)) => permit,
+                Ok(Err(_)) => return Err(Error::Handler("The request handler has stopped")),
+                Err(_) => return Err(Error::Handler("The request handler is busy, try again")),
+            }
+        }
+        Err(TrySendError::Closed(())) => {
+            return Err(Error::Handler("The request handler has stopped"))
+        }
+    };
+    permit.send(message);
+    Ok(())
+}
+
+/// Timeouts are told apart from other failures, so the user knows to wait longer.
+fn request_error(err: reqwest::Error, timeout: Option<Duration>) -> Error {
+    match timeout {
+        Some(timeout) if err.is_timeout() => Error::Timeout(timeout),
+        _ => err.into(),
+    }
+}
+
+/// An error reading a file to send, naming the file.
+fn file_error(path: &str, err: std::io::Error) -> Error {
+    std::io::Error::new(err.kind(), format!("{:} {:}", path, err)).into()
+}
+
+/// Open a file to stream as the body, with its length.
+async fn open_body_file(path: &str) -> Result<(File, u64)> {
+    let file = File::open(path)
+        .await
+        .map_err(|err| file_error(path, err))?;
+    let metadata = file.metadata().await.map_err(|err| file_error(path, err))?;
+    Ok((file, metadata.len()))
+}
+
+/// Build a multipart form, reading the files of its file parts.
+async fn multipart_form(parts: Vec<Part>) -> Result<Form> {
+    let mut form = Form::new();
+    for part in parts {
+        form = match part {
+            Part::Text { name, value } => form.text(name, value),
+            Part::File {
+                name,
+                path,
+                content_type,
+            } => {
+                let bytes = tokio::fs::read(path.as_str())
+                    .await
+                    .map_err(|err| file_error(path.as_str(), err))?;
+                let file_name = Path::new(path.as_str())
+                    .file_name()
+                    .map(|file_name| file_name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let mut part = multipart::Part::bytes(bytes).file_name(file_name);
+                if let Some(content_type) = content_type {
+                    part = part.mime_str(content_type.as_str())?;
+                }
+                form.part(name, part)
+            }
+        };
+    }
+    Ok(form)
+}
+
+/// A new id for a request, which is unique while the program runs, so it can be cancelled.
+pub fn next_request_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    NEXT_ID.fetch_add(1, Ordering::SeqCst)
+}
+
+/// Receive requests and send each in its own task, so several can be in flight at once. A
+/// cancelled request gets `Response::Cancelled` instead of the rest of its response. Stopping
+/// the handler cancels the requests still in flight.
+pub fn web_request_handler(mut receiver: Receiver<WebRequest>) -> JoinHandle<()> {
     tokio::spawn(async move {
+        let mut in_flight: HashMap<u64, oneshot::Sender<()>> = HashMap::new();
         loop {
-            let client = reqwest::Client::new();
-            let req = receiver.recv().await;
-            match req {
+            match receiver.recv().await {
                 Some(Request(req)) => {
-                    info!("Request present");
-                    let mut header_map = HeaderMap::new();
-                    let headers: Vec<&str> = req.headers.split("\n").collect();
-
-                    for entry in headers {
-                        if let Some((key, value)) = entry.split_once(":") {
-                            if let Ok(value) = HeaderValue::from_str(value.trim()) {
-                                if let Ok(key) = HeaderName::from_str(key.trim()) {
-                                    header_map.append(key, value);
-                                }
+                    // The receivers of the requests which have finished are gone.
+                    in_flight.retain(|_, cancel| !cancel.is_closed());
+                    let (cancel, cancelled) = oneshot::channel();
+                    in_flight.insert(req.id, cancel);
+                    tokio::spawn(async move {
+                        let resp = req.resp.clone();
+                        select! {
+                            _ = send(*req) => {}
+                            _ = cancelled => {
+                                let _ = resp.send(Response::Cancelled).await;
                             }
                         }
+                    });
+                }
+                Some(Cancel(id)) => {
+                    if let Some(cancel) = in_flight.remove(&id) {
+                        let _ = cancel.send(());
                     }
+                }
+                Some(Shutdown) | None => {
+                    break;
+                }
+            };
+        }
+    })
+}
 
-                    let mut req_builder = match req.method {
-                        Method::GET => client.get(req.url).headers(header_map),
-                        Method::POST => client.post(req.url).headers(header_map),
-                        Method::PUT => client.put(req.url).headers(header_map),
-                        Method::DELETE => client.delete(req.url).headers(header_map),
-                        Method::PATCH => client.patch(req.url).headers(header_map),
-                    };
+/// Send a request, replying with its response as it is received.
+async fn send(req: crate::Request) {
+    info!("Request present");
+    let proxy = req.proxy.resolve(req.url.as_str());
+    let redirects = Arc::new(Mutex::new(Vec::new()));
+    let mut client_builder = reqwest::Client::builder()
+        .no_proxy()
+        .redirect(req.redirects.policy(redirects.clone()));
+    // The timeout covers the whole response, including a body still streaming in.
+    if let Some(timeout) = req.timeout {
+        client_builder = client_builder.timeout(timeout);
+    }
+    // Credentials in the proxy URL are used to authenticate with the proxy.
+    if let Some(proxy) = &proxy {
+        match Proxy::all(proxy.as_str()) {
+            Ok(proxy) => client_builder = client_builder.proxy(proxy),
+            Err(err) => {
+                error!("Invalid proxy {:} {:?}", display(proxy), err);
+                let _ = req.resp.send(Response::Failure(err.into())).await;
+                return;
+            }
+        }
+    }
+    let client = match client_builder.build() {
+        Ok(client) => client,
+        Err(err) => {
+            error!("Error building client {:?}", err);
+            let _ = req.resp.send(Response::Failure(err.into())).await;
+            return;
+        }
+    };
+    let _ = req
+        .resp
+        .send(Response::Proxy(proxy.as_deref().map(display)))
+        .await;
+    let mut header_map = HeaderMap::new();
+    for entry in req.headers.lines() {
+        if let Some((key, value)) = entry.split_once(":") {
+            if let Ok(value) = HeaderValue::from_str(value.trim()) {
+                if let Ok(key) = HeaderName::from_str(key.trim()) {
+                    header_map.append(key, value);
+                }
+            }
+        }
+    }
 
-                    if !req.body.is_empty() {
-                        req_builder = req_builder.body(req.body)
-                    }
-                    let res = req_builder.send().await;
-                    match res {
-                        Ok(mut res) => {
-                            let _ = req.resp.send(Response::Status(res.status())).await;
-                            let _ = req
-                                .resp
-                                .send(Response::Headers(res.headers().clone()))
-                                .await;
+    // The multipart content type is set with the boundary between the parts.
+    let form = match req.multipart {
+        Some(parts) => match multipart_form(parts).await {
+            Ok(form) => {
+                header_map.remove(CONTENT_TYPE);
+                Some(form)
+            }
+            Err(err) => {
+                let _ = req.resp.send(Response::Failure(err)).await;
+                return;
+            }
+        },
+        None => None,
+    };
+    let body_file = match &req.body_file {
+        Some(path) => match open_body_file(path.as_str()).await {
+            Ok((file, len)) => {
+                // Without a length the file would be sent chunked, which some
+                // servers don't accept for uploads.
+                if !header_map.contains_key(CONTENT_LENGTH) {
+                    header_map.insert(CONTENT_LENGTH, HeaderValue::from(len));
+                }
+                Some(file)
+            }
+            Err(err) => {
+                let _ = req.resp.send(Response::Failure(err)).await;
+                return;
+            }
+        },
+        None => None,
+    };
 
-                            loop {
-                                let bytes_future = res.chunk();
-                                let request_op = receiver.recv();
+    let method = match reqwest::Method::from_bytes(req.method.as_str().as_bytes()) {
+        Ok(method) => method,
+        Err(_) => {
+            let err = Error::Parse(format!("{:} isn't a valid method", req.method.as_str()));
+            let _ = req.resp.send(Response::Failure(err)).await;
+            return;
+        }
+    };
+    let mut req_builder = client.request(method, req.url).headers(header_map);
+
+    match (form, body_file) {
+        (Some(form), _) => req_builder = req_builder.multipart(form),
+        (None, Some(file)) => req_builder = req_builder.body(file),
+        (None, None) if !req.body.is_empty() => req_builder = req_builder.body(req.body),
+        (None, None) => {}
+    }
+    let res = req_builder
+        .send()
+        .await
+        .map_err(|err| request_error(err, req.timeout));
+    let redirects = std::mem::take(&mut *redirects.lock().unwrap());
+    if !redirects.is_empty() {
+        let _ = req.resp.send(Response::Redirects(redirects)).await;
+    }
+    match res {
+        Ok(mut res) => {
+            let _ = req.resp.send(Response::Status(res.status())).await;
+            let _ = req
+                .resp
+                .send(Response::Headers(res.headers().clone()))
+                .await;
+            let download_path = req.download.as_deref().unwrap_or("");
+            let mut download = match &req.download {
+                Some(path) => match File::create(path).await {
+                    Ok(file) => Some((file, 0)),
+                    Err(err) => {
+                        let err = file_error(path, err);
+                        let _ = req.resp.send(Response::Failure(err)).await;
+                        return;
+                    }
+                },
+                None => None,
+            };
 
-                                select! {
-                                    in_bytes = bytes_future => {
-                                        if let Ok(Some(bytes)) = in_bytes {
-                                            if let Err(err) = req.resp.send(Response::Body(bytes)).await {
-                                                error!("Error replying to request {:?}", err);
-                                                break;
-                                            }
-                                        } else {
-                                            break;
-                                        }
-                                    },
-                                    _request = request_op => {
-                                        // This will likely be a cancel request, but we don't care
-                                        // about the content. The signal is enough to know we need
-                                        // to move on.
-                                        break;
-                                    }
+            loop {
+                match res.chunk().await {
+                    Ok(Some(bytes)) => {
+                        let message = match &mut download {
+                            Some((file, written)) => match file.write_all(&bytes).await {
+                                Ok(()) => {
+                                    *written += bytes.len() as u64;
+                                    Response::Progress(*written)
                                 }
-                            }
+                                Err(err) => {
+                                    let err = file_error(download_path, err);
+                                    let _ = req.resp.send(Response::Failure(err)).await;
+                                    break;
+                                }
+                            },
+                            None => Response::Body(bytes),
+                        };
+                        if let Err(err) = req.resp.send(message).await {
+                            error!("Error replying to request {:?}", err);
+                            break;
                         }
-                        Err(_) => {
-                            if let Err(err) = req.resp.send(Response::Failure).await {
-                                error!("Error replying to request {:?}", err);
+                    }
+                    Ok(None) => {
+                        if let Some((file, _)) = &mut download {
+                            if let Err(err) = file.flush().await {
+                                let err = file_error(download_path, err);
+                                let _ = req.resp.send(Response::Failure(err)).await;
                             }
                         }
-                    };
-                }
-                Some(Cancel) => {
-                    continue;
-                }
-                _ => {
-                    break;
+                        break;
+                    }
+                    Err(err) => {
+                        let err = request_error(err, req.timeout);
+                        let _ = req.resp.send(Response::Failure(err)).await;
+                        break;
+                    }
                 }
-            };
+            }
+        }
+        Err(err) => {
+            if let Err(err) = req.resp.send(Response::Failure(err)).await {
+                error!("Error replying to request {:?}", err);
+            }
         }
-    });
+    };
 }